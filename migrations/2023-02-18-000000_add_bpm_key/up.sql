@@ -0,0 +1,2 @@
+ALTER TABLE songs ADD COLUMN bpm INTEGER;
+ALTER TABLE songs ADD COLUMN initial_key TEXT;
@@ -0,0 +1 @@
+ALTER TABLE directories ADD COLUMN is_compilation INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE directories ADD COLUMN track_count INTEGER NOT NULL DEFAULT 0;
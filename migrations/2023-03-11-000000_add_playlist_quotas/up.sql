@@ -0,0 +1,2 @@
+ALTER TABLE misc_settings ADD COLUMN max_playlists_per_user INTEGER NOT NULL DEFAULT 1000;
+ALTER TABLE misc_settings ADD COLUMN max_songs_per_playlist INTEGER NOT NULL DEFAULT 100000;
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN artwork_precedence TEXT NOT NULL DEFAULT 'folder_first';
@@ -0,0 +1,7 @@
+CREATE TABLE audit_log (
+	id INTEGER PRIMARY KEY NOT NULL,
+	timestamp INTEGER NOT NULL,
+	event TEXT NOT NULL,
+	actor TEXT,
+	details TEXT
+);
@@ -0,0 +1,2 @@
+ALTER TABLE misc_settings ADD COLUMN minimum_client_version TEXT NOT NULL DEFAULT '';
+ALTER TABLE misc_settings ADD COLUMN reject_unversioned_clients INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN max_concurrent_streams_per_user INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+CREATE TABLE play_history (
+	id INTEGER PRIMARY KEY NOT NULL,
+	username TEXT NOT NULL,
+	path TEXT NOT NULL,
+	played_at INTEGER NOT NULL
+);
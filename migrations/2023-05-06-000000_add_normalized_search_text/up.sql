@@ -0,0 +1,2 @@
+ALTER TABLE songs ADD COLUMN search_normalized TEXT NOT NULL DEFAULT '';
+ALTER TABLE directories ADD COLUMN search_normalized TEXT NOT NULL DEFAULT '';
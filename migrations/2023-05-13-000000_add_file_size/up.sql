@@ -0,0 +1 @@
+ALTER TABLE songs ADD COLUMN file_size BIGINT NOT NULL DEFAULT 0;
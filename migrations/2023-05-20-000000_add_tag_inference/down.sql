@@ -0,0 +1,51 @@
+CREATE TEMPORARY TABLE misc_settings_backup(id, auth_secret, index_sleep_duration_seconds, index_album_art_pattern, max_playlists_per_user, max_songs_per_playlist, index_follow_symlinks, artwork_precedence, minimum_client_version, reject_unversioned_clients, max_concurrent_streams_per_user);
+INSERT INTO misc_settings_backup
+SELECT id, auth_secret, index_sleep_duration_seconds, index_album_art_pattern, max_playlists_per_user, max_songs_per_playlist, index_follow_symlinks, artwork_precedence, minimum_client_version, reject_unversioned_clients, max_concurrent_streams_per_user
+FROM misc_settings;
+DROP TABLE misc_settings;
+CREATE TABLE misc_settings (
+	id INTEGER PRIMARY KEY NOT NULL CHECK(id = 0),
+	auth_secret BLOB NOT NULL DEFAULT (randomblob(32)),
+	index_sleep_duration_seconds INTEGER NOT NULL,
+	index_album_art_pattern TEXT NOT NULL,
+	max_playlists_per_user INTEGER NOT NULL DEFAULT 1000,
+	max_songs_per_playlist INTEGER NOT NULL DEFAULT 100000,
+	index_follow_symlinks INTEGER NOT NULL DEFAULT 0,
+	artwork_precedence TEXT NOT NULL DEFAULT 'folder_first',
+	minimum_client_version TEXT NOT NULL DEFAULT '',
+	reject_unversioned_clients INTEGER NOT NULL DEFAULT 0,
+	max_concurrent_streams_per_user INTEGER NOT NULL DEFAULT 0
+);
+INSERT INTO misc_settings SELECT * FROM misc_settings_backup;
+DROP TABLE misc_settings_backup;
+
+CREATE TEMPORARY TABLE songs_backup(id, path, parent, track_number, disc_number, title, artist, album_artist, year, album, artwork, duration, lyricist, composer, genre, label, bpm, initial_key, encoder_delay, encoder_padding, search_normalized, file_size);
+INSERT INTO songs_backup SELECT id, path, parent, track_number, disc_number, title, artist, album_artist, year, album, artwork, duration, lyricist, composer, genre, label, bpm, initial_key, encoder_delay, encoder_padding, search_normalized, file_size FROM songs;
+DROP TABLE songs;
+CREATE TABLE songs (
+	id INTEGER PRIMARY KEY NOT NULL,
+	path TEXT NOT NULL,
+	parent TEXT NOT NULL,
+	track_number INTEGER,
+	disc_number INTEGER,
+	title TEXT,
+	artist TEXT,
+	album_artist TEXT,
+	year INTEGER,
+	album TEXT,
+	artwork TEXT,
+	duration INTEGER,
+	lyricist TEXT,
+	composer TEXT,
+	genre TEXT,
+	label TEXT,
+	bpm INTEGER,
+	initial_key TEXT,
+	encoder_delay INTEGER,
+	encoder_padding INTEGER,
+	search_normalized TEXT NOT NULL DEFAULT '',
+	file_size BIGINT NOT NULL DEFAULT 0,
+	UNIQUE(path) ON CONFLICT REPLACE
+);
+INSERT INTO songs SELECT * FROM songs_backup;
+DROP TABLE songs_backup;
@@ -0,0 +1,2 @@
+ALTER TABLE misc_settings ADD COLUMN index_infer_tags_from_path INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE songs ADD COLUMN tags_inferred INTEGER NOT NULL DEFAULT 0;
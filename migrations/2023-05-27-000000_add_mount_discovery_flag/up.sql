@@ -0,0 +1 @@
+ALTER TABLE mount_points ADD COLUMN include_in_discovery INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1 @@
+DROP TABLE lastfm_scrobbles;
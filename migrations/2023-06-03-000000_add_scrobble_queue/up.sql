@@ -0,0 +1,7 @@
+CREATE TABLE lastfm_scrobbles (
+	id INTEGER PRIMARY KEY NOT NULL,
+	username TEXT NOT NULL,
+	path TEXT NOT NULL,
+	queued_at INTEGER NOT NULL,
+	attempts INTEGER NOT NULL DEFAULT 0
+);
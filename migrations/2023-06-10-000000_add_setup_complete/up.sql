@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN setup_complete INTEGER NOT NULL DEFAULT 0;
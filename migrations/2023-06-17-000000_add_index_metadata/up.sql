@@ -0,0 +1,8 @@
+CREATE TABLE index_metadata (
+	id INTEGER PRIMARY KEY NOT NULL CHECK(id = 0),
+	last_index_start INTEGER NOT NULL,
+	last_index_end INTEGER NOT NULL,
+	file_count INTEGER NOT NULL,
+	error_count INTEGER NOT NULL,
+	UNIQUE(id) ON CONFLICT REPLACE
+);
@@ -0,0 +1 @@
+DROP TABLE mime_overrides;
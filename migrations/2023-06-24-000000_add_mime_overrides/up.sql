@@ -0,0 +1,6 @@
+CREATE TABLE mime_overrides (
+	id INTEGER PRIMARY KEY NOT NULL,
+	extension TEXT NOT NULL,
+	mime TEXT NOT NULL,
+	UNIQUE(extension) ON CONFLICT REPLACE
+);
@@ -0,0 +1,6 @@
+CREATE TABLE password_history (
+	id INTEGER PRIMARY KEY NOT NULL,
+	username TEXT NOT NULL,
+	password_hash TEXT NOT NULL,
+	changed_at INTEGER NOT NULL
+);
@@ -0,0 +1,12 @@
+CREATE TEMPORARY TABLE mount_points_backup(id, source, name, include_in_discovery);
+INSERT INTO mount_points_backup SELECT id, source, name, include_in_discovery FROM mount_points;
+DROP TABLE mount_points;
+CREATE TABLE mount_points (
+	id INTEGER PRIMARY KEY NOT NULL,
+	source TEXT NOT NULL,
+	name TEXT NOT NULL,
+	include_in_discovery INTEGER NOT NULL DEFAULT 1,
+	UNIQUE(name)
+);
+INSERT INTO mount_points SELECT * FROM mount_points_backup;
+DROP TABLE mount_points_backup;
@@ -0,0 +1 @@
+ALTER TABLE mount_points ADD COLUMN max_depth INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,22 @@
+CREATE TEMPORARY TABLE misc_settings_backup(id, auth_secret, index_sleep_duration_seconds, index_album_art_pattern, max_playlists_per_user, max_songs_per_playlist, index_follow_symlinks, artwork_precedence, minimum_client_version, reject_unversioned_clients, max_concurrent_streams_per_user, index_infer_tags_from_path, setup_complete);
+INSERT INTO misc_settings_backup
+SELECT id, auth_secret, index_sleep_duration_seconds, index_album_art_pattern, max_playlists_per_user, max_songs_per_playlist, index_follow_symlinks, artwork_precedence, minimum_client_version, reject_unversioned_clients, max_concurrent_streams_per_user, index_infer_tags_from_path, setup_complete
+FROM misc_settings;
+DROP TABLE misc_settings;
+CREATE TABLE misc_settings (
+	id INTEGER PRIMARY KEY NOT NULL CHECK(id = 0),
+	auth_secret BLOB NOT NULL DEFAULT (randomblob(32)),
+	index_sleep_duration_seconds INTEGER NOT NULL,
+	index_album_art_pattern TEXT NOT NULL,
+	max_playlists_per_user INTEGER NOT NULL DEFAULT 1000,
+	max_songs_per_playlist INTEGER NOT NULL DEFAULT 100000,
+	index_follow_symlinks INTEGER NOT NULL DEFAULT 0,
+	artwork_precedence TEXT NOT NULL DEFAULT 'folder_first',
+	minimum_client_version TEXT NOT NULL DEFAULT '',
+	reject_unversioned_clients INTEGER NOT NULL DEFAULT 0,
+	max_concurrent_streams_per_user INTEGER NOT NULL DEFAULT 0,
+	index_infer_tags_from_path INTEGER NOT NULL DEFAULT 0,
+	setup_complete INTEGER NOT NULL DEFAULT 0
+);
+INSERT INTO misc_settings SELECT * FROM misc_settings_backup;
+DROP TABLE misc_settings_backup;
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN reindex_on_startup INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN duplicate_policy TEXT NOT NULL DEFAULT 'keep_all';
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN tag_parsing TEXT NOT NULL DEFAULT 'lenient';
@@ -0,0 +1,7 @@
+CREATE TABLE favorites (
+	id INTEGER PRIMARY KEY NOT NULL,
+	owner INTEGER NOT NULL,
+	path TEXT NOT NULL,
+	FOREIGN KEY(owner) REFERENCES users(id) ON DELETE CASCADE,
+	UNIQUE(owner, path) ON CONFLICT REPLACE
+);
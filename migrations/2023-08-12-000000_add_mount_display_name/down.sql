@@ -0,0 +1,13 @@
+CREATE TEMPORARY TABLE mount_points_backup(id, source, name, include_in_discovery, max_depth);
+INSERT INTO mount_points_backup SELECT id, source, name, include_in_discovery, max_depth FROM mount_points;
+DROP TABLE mount_points;
+CREATE TABLE mount_points (
+	id INTEGER PRIMARY KEY NOT NULL,
+	source TEXT NOT NULL,
+	name TEXT NOT NULL,
+	include_in_discovery INTEGER NOT NULL DEFAULT 1,
+	max_depth INTEGER NOT NULL DEFAULT 0,
+	UNIQUE(name)
+);
+INSERT INTO mount_points SELECT * FROM mount_points_backup;
+DROP TABLE mount_points_backup;
@@ -0,0 +1 @@
+ALTER TABLE mount_points ADD COLUMN display_name TEXT NOT NULL DEFAULT '';
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN send_security_headers INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+CREATE TABLE index_checkpoint (
+	path TEXT PRIMARY KEY NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN assume_https_behind_proxy INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,7 @@
+CREATE TABLE song_tags (
+	id INTEGER PRIMARY KEY NOT NULL,
+	path TEXT NOT NULL,
+	key TEXT NOT NULL,
+	value TEXT NOT NULL,
+	UNIQUE(path, key) ON CONFLICT REPLACE
+);
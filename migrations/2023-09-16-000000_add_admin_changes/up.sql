@@ -0,0 +1,8 @@
+CREATE TABLE admin_changes (
+	id INTEGER PRIMARY KEY NOT NULL,
+	username TEXT NOT NULL,
+	actor TEXT,
+	old_is_admin INTEGER NOT NULL,
+	new_is_admin INTEGER NOT NULL,
+	changed_at INTEGER NOT NULL
+);
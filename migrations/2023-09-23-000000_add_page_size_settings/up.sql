@@ -0,0 +1,2 @@
+ALTER TABLE misc_settings ADD COLUMN default_page_size INTEGER NOT NULL DEFAULT 100;
+ALTER TABLE misc_settings ADD COLUMN max_page_size INTEGER NOT NULL DEFAULT 1000;
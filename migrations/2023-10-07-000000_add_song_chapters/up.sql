@@ -0,0 +1,8 @@
+CREATE TABLE song_chapters (
+	id INTEGER PRIMARY KEY NOT NULL,
+	path TEXT NOT NULL,
+	ordering INTEGER NOT NULL,
+	title TEXT NOT NULL,
+	start_ms INTEGER NOT NULL,
+	UNIQUE(path, ordering) ON CONFLICT REPLACE
+);
@@ -0,0 +1 @@
+ALTER TABLE misc_settings ADD COLUMN partial_file_policy TEXT NOT NULL DEFAULT 'skip';
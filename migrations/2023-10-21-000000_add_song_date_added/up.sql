@@ -0,0 +1 @@
+ALTER TABLE songs ADD COLUMN date_added INTEGER NOT NULL DEFAULT 0;
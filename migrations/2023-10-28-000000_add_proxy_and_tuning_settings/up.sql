@@ -0,0 +1,9 @@
+ALTER TABLE misc_settings ADD COLUMN trusted_proxies TEXT NOT NULL DEFAULT '';
+ALTER TABLE misc_settings ADD COLUMN forwarded_ip_header TEXT NOT NULL DEFAULT 'x-forwarded-for';
+ALTER TABLE misc_settings ADD COLUMN auth_log_format TEXT NOT NULL DEFAULT 'text';
+ALTER TABLE misc_settings ADD COLUMN old_auth_secrets TEXT NOT NULL DEFAULT '';
+ALTER TABLE misc_settings ADD COLUMN num_traverser_threads INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE misc_settings ADD COLUMN max_results_per_query INTEGER NOT NULL DEFAULT 10000;
+ALTER TABLE misc_settings ADD COLUMN thumbnail_read_timeout_seconds INTEGER NOT NULL DEFAULT 10;
+ALTER TABLE misc_settings ADD COLUMN thumbnail_max_dimension INTEGER NOT NULL DEFAULT 1600;
+ALTER TABLE misc_settings ADD COLUMN thumbnail_stored_max_dimension INTEGER NOT NULL DEFAULT 1280;
@@ -1,18 +1,29 @@
+use base64::prelude::*;
 use std::fs;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
 use crate::db::{self, DB};
 use crate::paths::Paths;
 
+pub mod audit;
 pub mod config;
 pub mod ddns;
+pub mod favorite;
+pub mod files;
+pub mod history;
 pub mod index;
 pub mod lastfm;
+pub mod logs;
+pub mod now_playing;
 pub mod playlist;
 pub mod settings;
+pub mod streams;
 pub mod thumbnail;
 pub mod user;
 pub mod vfs;
+pub mod waveform;
 
 #[cfg(test)]
 pub mod test;
@@ -37,14 +48,22 @@ pub struct App {
 	pub swagger_dir_path: PathBuf,
 	pub db: DB,
 	pub index: index::Index,
+	pub audit_manager: audit::Manager,
 	pub config_manager: config::Manager,
 	pub ddns_manager: ddns::Manager,
+	pub favorite_manager: favorite::Manager,
+	pub files_manager: files::Manager,
+	pub history_manager: history::Manager,
 	pub lastfm_manager: lastfm::Manager,
+	pub logs_manager: logs::Manager,
+	pub now_playing_manager: now_playing::Manager,
 	pub playlist_manager: playlist::Manager,
 	pub settings_manager: settings::Manager,
+	pub streams_manager: streams::Manager,
 	pub thumbnail_manager: thumbnail::Manager,
 	pub user_manager: user::Manager,
 	pub vfs_manager: vfs::Manager,
+	pub waveform_manager: waveform::Manager,
 }
 
 impl App {
@@ -59,21 +78,87 @@ impl App {
 		fs::create_dir_all(&thumbnails_dir_path)
 			.map_err(|e| Error::Io(thumbnails_dir_path.clone(), e))?;
 
+		let waveforms_dir_path = paths.cache_dir_path.join("waveforms");
+		fs::create_dir_all(&waveforms_dir_path)
+			.map_err(|e| Error::Io(waveforms_dir_path.clone(), e))?;
+
 		let vfs_manager = vfs::Manager::new(db.clone());
-		let settings_manager = settings::Manager::new(db.clone());
+		let audit_manager = audit::Manager::new(db.clone());
+		let settings_manager = settings::Manager::new(db.clone())
+			.with_setup_marker_path(paths.db_file_path.with_file_name("setup_complete"));
 		let auth_secret = settings_manager.get_auth_secret()?;
+		// Tuning settings below are applied once here; changing them requires
+		// a restart
+		let startup_settings = settings_manager.read()?;
 		let ddns_manager = ddns::Manager::new(db.clone());
-		let user_manager = user::Manager::new(db.clone(), auth_secret);
-		let index = index::Index::new(db.clone(), vfs_manager.clone(), settings_manager.clone());
+		let favorite_manager = favorite::Manager::new(db.clone(), vfs_manager.clone());
+		let files_manager = files::Manager::new(db.clone(), vfs_manager.clone());
+		let history_manager = history::Manager::new(db.clone());
+		let mut user_manager = user::Manager::new(db.clone(), auth_secret);
+		if !startup_settings.old_auth_secrets.is_empty() {
+			let old_secrets: Vec<settings::AuthSecret> = startup_settings
+				.old_auth_secrets
+				.split(',')
+				.filter_map(|s| BASE64_STANDARD.decode(s.trim()).ok())
+				.filter_map(|bytes| bytes.try_into().ok())
+				.map(|key| settings::AuthSecret { key })
+				.collect();
+			user_manager = user_manager.with_old_auth_secrets(old_secrets);
+		}
+		if let Some(depth) = std::env::var_os("POLARIS_PASSWORD_HISTORY_DEPTH")
+			.and_then(|v| usize::from_str(&v.to_string_lossy()).ok())
+		{
+			user_manager = user_manager.with_password_history_depth(depth);
+		}
+		if let Some(seconds) = std::env::var_os("POLARIS_TOKEN_SKEW_TOLERANCE_SECONDS")
+			.and_then(|v| u32::from_str(&v.to_string_lossy()).ok())
+		{
+			user_manager = user_manager.with_token_skew_tolerance(seconds);
+		}
 		let config_manager = config::Manager::new(
 			settings_manager.clone(),
 			user_manager.clone(),
 			vfs_manager.clone(),
 			ddns_manager.clone(),
 		);
-		let playlist_manager = playlist::Manager::new(db.clone(), vfs_manager.clone());
-		let thumbnail_manager = thumbnail::Manager::new(thumbnails_dir_path);
-		let lastfm_manager = lastfm::Manager::new(index.clone(), user_manager.clone());
+		let playlist_manager =
+			playlist::Manager::new(db.clone(), vfs_manager.clone(), settings_manager.clone());
+		let mut thumbnail_manager = thumbnail::Manager::new(thumbnails_dir_path);
+		if startup_settings.thumbnail_read_timeout_seconds > 0 {
+			thumbnail_manager = thumbnail_manager.with_read_timeout(Duration::from_secs(
+				startup_settings.thumbnail_read_timeout_seconds as u64,
+			));
+		}
+		if startup_settings.thumbnail_max_dimension > 0 {
+			thumbnail_manager = thumbnail_manager
+				.with_max_output_dimension(startup_settings.thumbnail_max_dimension as u32);
+		}
+		if startup_settings.thumbnail_stored_max_dimension > 0 {
+			thumbnail_manager = thumbnail_manager
+				.with_stored_max_dimension(startup_settings.thumbnail_stored_max_dimension as u32);
+		}
+		let waveform_manager = waveform::Manager::new(waveforms_dir_path);
+		let artwork_precache = std::env::var_os("POLARIS_PRECACHE_ARTWORK").is_some();
+		let mut index = index::Index::new(
+			db.clone(),
+			vfs_manager.clone(),
+			settings_manager.clone(),
+			thumbnail_manager.clone(),
+			artwork_precache,
+		);
+		if startup_settings.max_results_per_query > 0 {
+			index =
+				index.with_max_results_per_query(startup_settings.max_results_per_query as usize);
+		}
+		if let Some(tolerance) = std::env::var_os("POLARIS_FUZZY_SEARCH_TOLERANCE")
+			.and_then(|v| usize::from_str(&v.to_string_lossy()).ok())
+		{
+			index = index.with_fuzzy_search_tolerance(tolerance);
+		}
+		let lastfm_manager = lastfm::Manager::new(db.clone(), index.clone(), user_manager.clone());
+		let logs_manager = logs::Manager::new();
+		let now_playing_manager = now_playing::Manager::new();
+		let streams_manager = streams::Manager::new(settings_manager.clone());
 
 		if let Some(config_path) = paths.config_file_path {
 			let config = config::Config::from_path(&config_path)?;
@@ -88,15 +173,30 @@ impl App {
 			web_dir_path: paths.web_dir_path,
 			swagger_dir_path: paths.swagger_dir_path,
 			index,
+			audit_manager,
 			config_manager,
 			ddns_manager,
+			favorite_manager,
+			files_manager,
+			history_manager,
 			lastfm_manager,
+			logs_manager,
+			now_playing_manager,
 			playlist_manager,
 			settings_manager,
+			streams_manager,
 			thumbnail_manager,
 			user_manager,
 			vfs_manager,
+			waveform_manager,
 			db,
 		})
 	}
+
+	// Swaps in the manager already registered with the process-wide logger, so
+	// the streaming endpoint sees the same buffer the log backend writes to
+	pub fn with_logs_manager(mut self, logs_manager: logs::Manager) -> Self {
+		self.logs_manager = logs_manager;
+		self
+	}
 }
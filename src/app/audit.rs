@@ -0,0 +1,116 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::db::{self, audit_log, DB};
+
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error(transparent)]
+	DatabaseConnection(#[from] db::Error),
+	#[error(transparent)]
+	Database(#[from] diesel::result::Error),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+	Login,
+	LoginFailed,
+	SettingsChanged,
+	UserCreated,
+	UserDeleted,
+}
+
+impl Event {
+	pub fn as_event_string(self) -> &'static str {
+		match self {
+			Self::Login => "login",
+			Self::LoginFailed => "login_failed",
+			Self::SettingsChanged => "settings_changed",
+			Self::UserCreated => "user_created",
+			Self::UserDeleted => "user_deleted",
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Queryable)]
+pub struct Entry {
+	pub id: i32,
+	pub timestamp: i32,
+	pub event: String,
+	pub actor: Option<String>,
+	pub details: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_log)]
+struct NewEntry<'a> {
+	timestamp: i32,
+	event: &'a str,
+	actor: Option<&'a str>,
+	details: Option<&'a str>,
+}
+
+#[derive(Debug, Default)]
+pub struct ListOptions {
+	pub offset: i64,
+	pub limit: i64,
+	pub event: Option<Event>,
+	pub actor: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	db: DB,
+}
+
+impl Manager {
+	pub fn new(db: DB) -> Self {
+		Self { db }
+	}
+
+	pub fn record(
+		&self,
+		event: Event,
+		actor: Option<&str>,
+		details: Option<&str>,
+	) -> Result<(), Error> {
+		let timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i32)
+			.unwrap_or_default();
+		let mut connection = self.db.connect()?;
+		diesel::insert_into(audit_log::table)
+			.values(NewEntry {
+				timestamp,
+				event: event.as_event_string(),
+				actor,
+				details,
+			})
+			.execute(&mut connection)?;
+		Ok(())
+	}
+
+	pub fn list(&self, options: &ListOptions) -> Result<Vec<Entry>, Error> {
+		let offset = options.offset.max(0);
+		let limit = options.limit.clamp(0, MAX_PAGE_SIZE);
+
+		let mut connection = self.db.connect()?;
+		let mut query = audit_log::table.into_boxed();
+		if let Some(event) = options.event {
+			query = query.filter(audit_log::event.eq(event.as_event_string()));
+		}
+		if let Some(ref actor) = options.actor {
+			query = query.filter(audit_log::actor.eq(actor));
+		}
+		let entries = query
+			.order((audit_log::timestamp.desc(), audit_log::id.desc()))
+			.offset(offset)
+			.limit(limit)
+			.load(&mut connection)?;
+		Ok(entries)
+	}
+}
@@ -99,7 +99,8 @@ impl Manager {
 			// Update users
 			for user in users {
 				self.user_manager.set_password(&user.name, &user.password)?;
-				self.user_manager.set_is_admin(&user.name, user.admin)?;
+				self.user_manager
+					.set_is_admin(&user.name, user.admin, None)?;
 			}
 		}
 
@@ -121,6 +122,7 @@ mod test {
 			settings: Some(settings::NewSettings {
 				album_art_pattern: Some("🖼️\\.jpg".into()),
 				reindex_every_n_seconds: Some(100),
+				..Default::default()
 			}),
 			..Default::default()
 		};
@@ -146,6 +148,9 @@ mod test {
 			mount_dirs: Some(vec![vfs::MountDir {
 				source: "/home/music".into(),
 				name: "🎵📁".into(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
 			}]),
 			..Default::default()
 		};
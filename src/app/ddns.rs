@@ -9,6 +9,10 @@ use crate::db::{self, ddns_config, DB};
 
 const DDNS_UPDATE_URL: &str = "https://ydns.io/api/v1/update/";
 
+// Sent to clients in place of the stored password. Clients can echo it back
+// unchanged to keep the current password.
+pub const PASSWORD_REDACTED: &str = "***";
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
 	#[error("DDNS update query failed with HTTP status code `{0}`")]
@@ -73,13 +77,24 @@ impl Manager {
 	pub fn set_config(&self, new_config: &Config) -> Result<(), Error> {
 		use crate::db::ddns_config::dsl::*;
 		let mut connection = self.db.connect()?;
-		diesel::update(ddns_config)
-			.set((
-				host.eq(&new_config.host),
-				username.eq(&new_config.username),
-				password.eq(&new_config.password),
-			))
-			.execute(&mut connection)?;
+		if new_config.password == PASSWORD_REDACTED {
+			// The client sent back the redacted placeholder, so the stored
+			// password stays untouched
+			diesel::update(ddns_config)
+				.set((
+					host.eq(&new_config.host),
+					username.eq(&new_config.username),
+				))
+				.execute(&mut connection)?;
+		} else {
+			diesel::update(ddns_config)
+				.set((
+					host.eq(&new_config.host),
+					username.eq(&new_config.username),
+					password.eq(&new_config.password),
+				))
+				.execute(&mut connection)?;
+		}
 		Ok(())
 	}
 
@@ -99,3 +114,35 @@ impl Manager {
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::app::test;
+	use crate::test_name;
+
+	#[test]
+	fn redacted_placeholder_keeps_stored_password() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+
+		ctx.ddns_manager
+			.set_config(&Config {
+				host: "host".to_owned(),
+				username: "username".to_owned(),
+				password: "secret".to_owned(),
+			})
+			.unwrap();
+
+		ctx.ddns_manager
+			.set_config(&Config {
+				host: "new-host".to_owned(),
+				username: "username".to_owned(),
+				password: PASSWORD_REDACTED.to_owned(),
+			})
+			.unwrap();
+
+		let config = ctx.ddns_manager.config().unwrap();
+		assert_eq!(config.host, "new-host");
+		assert_eq!(config.password, "secret");
+	}
+}
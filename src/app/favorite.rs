@@ -0,0 +1,221 @@
+use diesel::prelude::*;
+use diesel::sql_types;
+use diesel::SqliteConnection;
+use std::path::Path;
+
+use crate::app::index::Song;
+use crate::app::vfs;
+use crate::db::{self, favorites, users, DB};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error(transparent)]
+	Database(#[from] diesel::result::Error),
+	#[error(transparent)]
+	DatabaseConnection(#[from] db::Error),
+	#[error("User not found")]
+	UserNotFound,
+	#[error(transparent)]
+	Vfs(#[from] vfs::Error),
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	db: DB,
+	vfs_manager: vfs::Manager,
+}
+
+impl Manager {
+	pub fn new(db: DB, vfs_manager: vfs::Manager) -> Self {
+		Self { db, vfs_manager }
+	}
+
+	pub fn add_favorite(&self, owner: &str, virtual_path: &Path) -> Result<(), Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let real_path_string = real_path.to_string_lossy().into_owned();
+
+		let mut connection = self.db.connect()?;
+		let user_id = self.lookup_user(owner, &mut connection)?;
+
+		// The unique constraint on (owner, path) makes this idempotent
+		diesel::insert_into(favorites::table)
+			.values((
+				favorites::owner.eq(user_id),
+				favorites::path.eq(real_path_string),
+			))
+			.execute(&mut connection)?;
+		Ok(())
+	}
+
+	pub fn remove_favorite(&self, owner: &str, virtual_path: &Path) -> Result<(), Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let real_path_string = real_path.to_string_lossy().into_owned();
+
+		let mut connection = self.db.connect()?;
+		let user_id = self.lookup_user(owner, &mut connection)?;
+
+		diesel::delete(
+			favorites::table
+				.filter(favorites::owner.eq(user_id))
+				.filter(favorites::path.eq(real_path_string)),
+		)
+		.execute(&mut connection)?;
+		Ok(())
+	}
+
+	pub fn list_favorites(&self, owner: &str) -> Result<Vec<Song>, Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let songs: Vec<Song>;
+
+		{
+			let mut connection = self.db.connect()?;
+			let user_id = self.lookup_user(owner, &mut connection)?;
+
+			// Favorites whose file has left the collection are silently skipped
+			// rather than surfaced as holes in the listing
+			let query = diesel::sql_query(
+				r#"
+			SELECT s.id, s.path, s.parent, s.track_number, s.disc_number, s.title, s.artist, s.album_artist, s.year, s.album, s.artwork, s.duration, s.lyricist, s.composer, s.genre, s.label, s.bpm, s.initial_key, s.encoder_delay, s.encoder_padding, s.search_normalized, s.file_size, s.tags_inferred, s.track_total, s.disc_total, s.date_added
+			FROM favorites f
+			JOIN songs s ON f.path = s.path
+			WHERE f.owner = ?
+			ORDER BY s.path COLLATE NOCASE ASC
+		"#,
+			);
+			let query = query.bind::<sql_types::Integer, _>(user_id);
+			songs = query.get_results(&mut connection)?;
+		}
+
+		let virtual_songs = songs
+			.into_iter()
+			.filter_map(|s| s.virtualize(&vfs))
+			.collect();
+
+		Ok(virtual_songs)
+	}
+
+	pub fn is_favorite(&self, owner: &str, virtual_path: &Path) -> Result<bool, Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let real_path_string = real_path.to_string_lossy().into_owned();
+
+		let mut connection = self.db.connect()?;
+		let user_id = self.lookup_user(owner, &mut connection)?;
+
+		let count: i64 = favorites::table
+			.filter(favorites::owner.eq(user_id))
+			.filter(favorites::path.eq(real_path_string))
+			.count()
+			.get_result(&mut connection)?;
+		Ok(count > 0)
+	}
+
+	fn lookup_user(
+		&self,
+		owner: &str,
+		connection: &mut SqliteConnection,
+	) -> Result<i32, Error> {
+		use self::users::dsl::*;
+		let user_id = users
+			.filter(name.eq(owner))
+			.select(id)
+			.first(connection)
+			.optional()?
+			.ok_or(Error::UserNotFound)?;
+		Ok(user_id)
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use std::path::PathBuf;
+
+	use crate::app::test;
+	use crate::test_name;
+
+	const TEST_USER: &str = "test_user";
+	const TEST_PASSWORD: &str = "password";
+	const TEST_MOUNT_NAME: &str = "root";
+
+	fn song_path() -> PathBuf {
+		[
+			TEST_MOUNT_NAME,
+			"Khemmis",
+			"Hunted",
+			"02 - Candlelight.mp3",
+		]
+		.iter()
+		.collect()
+	}
+
+	#[test]
+	fn favorites_golden_path() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build();
+		ctx.index.update().unwrap();
+
+		assert_eq!(ctx.favorite_manager.list_favorites(TEST_USER).unwrap(), vec![]);
+		assert!(!ctx
+			.favorite_manager
+			.is_favorite(TEST_USER, &song_path())
+			.unwrap());
+
+		ctx.favorite_manager
+			.add_favorite(TEST_USER, &song_path())
+			.unwrap();
+
+		let favorites = ctx.favorite_manager.list_favorites(TEST_USER).unwrap();
+		assert_eq!(favorites.len(), 1);
+		assert_eq!(favorites[0].title, Some("Candlelight".to_owned()));
+		assert!(ctx
+			.favorite_manager
+			.is_favorite(TEST_USER, &song_path())
+			.unwrap());
+
+		ctx.favorite_manager
+			.remove_favorite(TEST_USER, &song_path())
+			.unwrap();
+		assert_eq!(ctx.favorite_manager.list_favorites(TEST_USER).unwrap(), vec![]);
+	}
+
+	#[test]
+	fn favorites_are_per_user() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user("alice", TEST_PASSWORD, false)
+			.user("bob", TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build();
+		ctx.index.update().unwrap();
+
+		ctx.favorite_manager
+			.add_favorite("alice", &song_path())
+			.unwrap();
+
+		assert_eq!(ctx.favorite_manager.list_favorites("alice").unwrap().len(), 1);
+		assert_eq!(ctx.favorite_manager.list_favorites("bob").unwrap().len(), 0);
+		assert!(!ctx.favorite_manager.is_favorite("bob", &song_path()).unwrap());
+	}
+
+	#[test]
+	fn favoriting_twice_is_idempotent() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+			.build();
+		ctx.index.update().unwrap();
+
+		ctx.favorite_manager
+			.add_favorite(TEST_USER, &song_path())
+			.unwrap();
+		ctx.favorite_manager
+			.add_favorite(TEST_USER, &song_path())
+			.unwrap();
+
+		assert_eq!(ctx.favorite_manager.list_favorites(TEST_USER).unwrap().len(), 1);
+	}
+}
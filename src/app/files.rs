@@ -0,0 +1,104 @@
+use diesel::prelude::*;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::app::vfs;
+use crate::db::{self, favorites, playlist_songs, song_tags, songs, DB};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("Moving files between mounts is not supported")]
+	CrossMountMove,
+	#[error(transparent)]
+	DatabaseConnection(#[from] db::Error),
+	#[error(transparent)]
+	Database(#[from] diesel::result::Error),
+	#[error("Destination `{0}` already exists")]
+	DestinationExists(PathBuf),
+	#[error("Path `{0}` is not a valid virtual path")]
+	InvalidPath(PathBuf),
+	#[error("Filesystem error for `{0}`: `{1}`")]
+	Io(PathBuf, std::io::Error),
+	#[error("Source file `{0}` does not exist")]
+	SourceNotFound(PathBuf),
+	#[error(transparent)]
+	Vfs(#[from] vfs::Error),
+}
+
+// Virtual paths come straight from clients. Only plain components are allowed
+// so a path cannot traverse out of the mount it names.
+fn validate_virtual_path(path: &Path) -> Result<(), Error> {
+	let is_valid = path.components().count() > 0
+		&& path
+			.components()
+			.all(|c| matches!(c, Component::Normal(_)));
+	match is_valid {
+		true => Ok(()),
+		false => Err(Error::InvalidPath(path.to_owned())),
+	}
+}
+
+fn mount_name(path: &Path) -> Option<std::ffi::OsString> {
+	path.components().next().map(|c| c.as_os_str().to_owned())
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	db: DB,
+	vfs_manager: vfs::Manager,
+}
+
+impl Manager {
+	pub fn new(db: DB, vfs_manager: vfs::Manager) -> Self {
+		Self { db, vfs_manager }
+	}
+
+	pub fn move_file(&self, source: &Path, destination: &Path) -> Result<(), Error> {
+		validate_virtual_path(source)?;
+		validate_virtual_path(destination)?;
+		if mount_name(source) != mount_name(destination) {
+			return Err(Error::CrossMountMove);
+		}
+
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_source = vfs.virtual_to_real(source)?;
+		let real_destination = vfs.virtual_to_real(destination)?;
+
+		if !real_source.is_file() {
+			return Err(Error::SourceNotFound(source.to_owned()));
+		}
+		if real_destination.exists() {
+			return Err(Error::DestinationExists(destination.to_owned()));
+		}
+
+		let source_string = real_source.to_string_lossy().into_owned();
+		let destination_string = real_destination.to_string_lossy().into_owned();
+		let parent_string = real_destination
+			.parent()
+			.map(|p| p.to_string_lossy().into_owned())
+			.unwrap_or_default();
+
+		let mut connection = self.db.connect()?;
+		connection.transaction(|connection| {
+			diesel::update(songs::table.filter(songs::path.eq(&source_string)))
+				.set((
+					songs::path.eq(&destination_string),
+					songs::parent.eq(&parent_string),
+				))
+				.execute(connection)?;
+			diesel::update(playlist_songs::table.filter(playlist_songs::path.eq(&source_string)))
+				.set(playlist_songs::path.eq(&destination_string))
+				.execute(connection)?;
+			diesel::update(favorites::table.filter(favorites::path.eq(&source_string)))
+				.set(favorites::path.eq(&destination_string))
+				.execute(connection)?;
+			diesel::update(song_tags::table.filter(song_tags::path.eq(&source_string)))
+				.set(song_tags::path.eq(&destination_string))
+				.execute(connection)?;
+			// Move the file last so a filesystem failure rolls back the database changes
+			fs::rename(&real_source, &real_destination)
+				.map_err(|e| Error::Io(real_source.clone(), e))?;
+			Ok(())
+		})
+	}
+}
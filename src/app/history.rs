@@ -0,0 +1,166 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::db::{self, play_history, DB};
+
+// Number of recently played songs kept per user
+pub const MAX_HISTORY_LENGTH: i64 = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error(transparent)]
+	DatabaseConnection(#[from] db::Error),
+	#[error(transparent)]
+	Database(#[from] diesel::result::Error),
+}
+
+#[derive(Debug, PartialEq, Eq, Queryable, Serialize, Deserialize)]
+pub struct Entry {
+	pub path: String,
+	pub played_at: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = play_history)]
+struct NewEntry<'a> {
+	username: &'a str,
+	path: &'a str,
+	played_at: i32,
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	db: DB,
+}
+
+impl Manager {
+	pub fn new(db: DB) -> Self {
+		Self { db }
+	}
+
+	pub fn record(&self, username: &str, path: &str) -> Result<(), Error> {
+		let played_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i32)
+			.unwrap_or_default();
+		let mut connection = self.db.connect()?;
+
+		// Playing the same song on repeat refreshes its timestamp instead of
+		// flooding the history
+		let latest: Option<(i32, String)> = play_history::table
+			.filter(play_history::username.eq(username))
+			.order(play_history::id.desc())
+			.select((play_history::id, play_history::path))
+			.first(&mut connection)
+			.optional()?;
+		if let Some((id, ref latest_path)) = latest {
+			if latest_path == path {
+				diesel::update(play_history::table.filter(play_history::id.eq(id)))
+					.set(play_history::played_at.eq(played_at))
+					.execute(&mut connection)?;
+				return Ok(());
+			}
+		}
+
+		diesel::insert_into(play_history::table)
+			.values(NewEntry {
+				username,
+				path,
+				played_at,
+			})
+			.execute(&mut connection)?;
+
+		// Evict the oldest entries beyond the history cap
+		let stale_ids: Vec<i32> = play_history::table
+			.filter(play_history::username.eq(username))
+			.order((play_history::played_at.desc(), play_history::id.desc()))
+			.select(play_history::id)
+			.offset(MAX_HISTORY_LENGTH)
+			.load(&mut connection)?;
+		if !stale_ids.is_empty() {
+			diesel::delete(play_history::table.filter(play_history::id.eq_any(stale_ids)))
+				.execute(&mut connection)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn list(&self, username: &str) -> Result<Vec<Entry>, Error> {
+		let mut connection = self.db.connect()?;
+		let entries = play_history::table
+			.filter(play_history::username.eq(username))
+			.order((play_history::played_at.desc(), play_history::id.desc()))
+			.select((play_history::path, play_history::played_at))
+			.limit(MAX_HISTORY_LENGTH)
+			.load(&mut connection)?;
+		Ok(entries)
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use crate::app::test;
+	use crate::test_name;
+
+	#[test]
+	fn playing_songs_populates_history_in_order() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let manager = Manager::new(ctx.db.clone());
+
+		manager.record("alice", "collection/one.mp3").unwrap();
+		manager.record("alice", "collection/two.mp3").unwrap();
+		manager.record("alice", "collection/three.mp3").unwrap();
+		manager.record("bob", "collection/four.mp3").unwrap();
+
+		let paths: Vec<String> = manager
+			.list("alice")
+			.unwrap()
+			.into_iter()
+			.map(|e| e.path)
+			.collect();
+		assert_eq!(
+			paths,
+			vec![
+				"collection/three.mp3".to_owned(),
+				"collection/two.mp3".to_owned(),
+				"collection/one.mp3".to_owned(),
+			]
+		);
+	}
+
+	#[test]
+	fn consecutive_repeats_are_deduplicated() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let manager = Manager::new(ctx.db.clone());
+
+		manager.record("alice", "collection/one.mp3").unwrap();
+		manager.record("alice", "collection/two.mp3").unwrap();
+		manager.record("alice", "collection/two.mp3").unwrap();
+
+		let entries = manager.list("alice").unwrap();
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].path, "collection/two.mp3");
+	}
+
+	#[test]
+	fn cap_evicts_oldest_entries() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let manager = Manager::new(ctx.db.clone());
+
+		for i in 0..(MAX_HISTORY_LENGTH + 10) {
+			let path = format!("collection/{}.mp3", i);
+			manager.record("alice", &path).unwrap();
+		}
+
+		let entries = manager.list("alice").unwrap();
+		assert_eq!(entries.len(), MAX_HISTORY_LENGTH as usize);
+		assert_eq!(
+			entries[0].path,
+			format!("collection/{}.mp3", MAX_HISTORY_LENGTH + 9)
+		);
+		assert_eq!(entries.last().unwrap().path, "collection/10.mp3");
+	}
+}
@@ -2,11 +2,13 @@ use log::error;
 use std::sync::{Arc, Condvar, Mutex};
 use std::time::Duration;
 
-use crate::app::{settings, vfs};
+use crate::app::{settings, thumbnail, vfs};
 use crate::db::DB;
 
-mod metadata;
+mod chapters;
+pub mod metadata;
 mod query;
+mod tags;
 #[cfg(test)]
 mod test;
 mod types;
@@ -16,26 +18,57 @@ pub use self::query::*;
 pub use self::types::*;
 pub use self::update::*;
 
+// Safety valve against memory exhaustion from unbounded result sets, distinct
+// from any limit a client asks for
+const DEFAULT_MAX_RESULTS_PER_QUERY: usize = 10_000;
+
+// Maximum edit distance between a query word and an indexed word for the two
+// to be considered a match during fuzzy search
+const DEFAULT_FUZZY_SEARCH_TOLERANCE: usize = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReindexTrigger {
+	Scheduled,
+	AlreadyScheduled,
+}
+
+#[derive(Debug, Default)]
+struct ReindexState {
+	pending: bool,
+	full: bool,
+	running: bool,
+}
+
 #[derive(Clone)]
 pub struct Index {
 	db: DB,
 	vfs_manager: vfs::Manager,
 	settings_manager: settings::Manager,
-	pending_reindex: Arc<(Mutex<bool>, Condvar)>,
+	thumbnail_manager: thumbnail::Manager,
+	artwork_precache: bool,
+	max_results_per_query: usize,
+	fuzzy_search_tolerance: usize,
+	pending_reindex: Arc<(Mutex<ReindexState>, Condvar)>,
 }
 
 impl Index {
-	pub fn new(db: DB, vfs_manager: vfs::Manager, settings_manager: settings::Manager) -> Self {
+	pub fn new(
+		db: DB,
+		vfs_manager: vfs::Manager,
+		settings_manager: settings::Manager,
+		thumbnail_manager: thumbnail::Manager,
+		artwork_precache: bool,
+	) -> Self {
 		let index = Self {
 			db,
 			vfs_manager,
 			settings_manager,
+			thumbnail_manager,
+			artwork_precache,
+			max_results_per_query: DEFAULT_MAX_RESULTS_PER_QUERY,
+			fuzzy_search_tolerance: DEFAULT_FUZZY_SEARCH_TOLERANCE,
 
-			pending_reindex: Arc::new((
-				#[allow(clippy::mutex_atomic)]
-				Mutex::new(false),
-				Condvar::new(),
-			)),
+			pending_reindex: Arc::new((Mutex::new(ReindexState::default()), Condvar::new())),
 		};
 
 		let commands_index = index.clone();
@@ -46,11 +79,52 @@ impl Index {
 		index
 	}
 
-	pub fn trigger_reindex(&self) {
+	pub fn with_max_results_per_query(mut self, max_results_per_query: usize) -> Self {
+		self.max_results_per_query = max_results_per_query;
+		self
+	}
+
+	pub fn with_fuzzy_search_tolerance(mut self, fuzzy_search_tolerance: usize) -> Self {
+		self.fuzzy_search_tolerance = fuzzy_search_tolerance;
+		self
+	}
+
+	pub fn trigger_reindex(&self, force: bool) -> ReindexTrigger {
+		self.trigger(force, false)
+	}
+
+	// Like `trigger_reindex`, but the queued scan discards checkpoints from
+	// any interrupted run instead of resuming from them
+	pub fn trigger_full_reindex(&self, force: bool) -> ReindexTrigger {
+		self.trigger(force, true)
+	}
+
+	fn trigger(&self, force: bool, full: bool) -> ReindexTrigger {
 		let (lock, cvar) = &*self.pending_reindex;
-		let mut pending_reindex = lock.lock().unwrap();
-		*pending_reindex = true;
+		let mut state = lock.lock().unwrap();
+		if full {
+			// Upgrades an already queued scan rather than scheduling a new one
+			state.full = true;
+		}
+		if !force && (state.pending || state.running) {
+			return ReindexTrigger::AlreadyScheduled;
+		}
+		state.pending = true;
 		cvar.notify_one();
+		ReindexTrigger::Scheduled
+	}
+
+	// Queues a scan right away when the operator opted into boot-time indexing.
+	// This only flags the worker thread, so startup is never blocked on a scan.
+	pub fn begin_startup_reindex(&self) -> Option<ReindexTrigger> {
+		match self.settings_manager.read() {
+			Ok(settings) if settings.reindex_on_startup => Some(self.trigger_reindex(false)),
+			Ok(_) => None,
+			Err(e) => {
+				error!("Could not read reindex-on-startup setting: {}", e);
+				None
+			}
+		}
 	}
 
 	pub fn begin_periodic_updates(&self) {
@@ -62,23 +136,32 @@ impl Index {
 
 	fn process_commands(&self) {
 		loop {
-			{
+			let full = {
 				let (lock, cvar) = &*self.pending_reindex;
-				let mut pending = lock.lock().unwrap();
-				while !*pending {
-					pending = cvar.wait(pending).unwrap();
+				let mut state = lock.lock().unwrap();
+				while !state.pending {
+					state = cvar.wait(state).unwrap();
 				}
-				*pending = false;
-			}
-			if let Err(e) = self.update() {
+				state.pending = false;
+				state.running = true;
+				std::mem::take(&mut state.full)
+			};
+			let result = match full {
+				true => self.update_force_full(),
+				false => self.update(),
+			};
+			if let Err(e) = result {
 				error!("Error while updating index: {}", e);
 			}
+			{
+				let (lock, _) = &*self.pending_reindex;
+				lock.lock().unwrap().running = false;
+			}
 		}
 	}
 
 	fn automatic_reindex(&self) {
 		loop {
-			self.trigger_reindex();
 			let sleep_duration = self
 				.settings_manager
 				.get_index_sleep_duration()
@@ -87,6 +170,7 @@ impl Index {
 					Duration::from_secs(1800)
 				});
 			std::thread::sleep(sleep_duration);
+			self.trigger_reindex(false);
 		}
 	}
 }
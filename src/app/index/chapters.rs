@@ -0,0 +1,57 @@
+use diesel::prelude::*;
+use std::path::Path;
+
+use crate::app::index::{metadata, Index, QueryError};
+use crate::db::song_chapters;
+use crate::utils::{self, AudioFormat};
+
+// Chapter markers for audiobook files. Markers are parsed from the file on
+// first request and cached in the database, since most of the library will
+// never be asked for them.
+impl Index {
+	pub fn get_chapters(
+		&self,
+		virtual_path: &Path,
+	) -> Result<Vec<metadata::ChapterMarker>, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let real_path_string = real_path.as_path().to_string_lossy().into_owned();
+		let mut connection = self.db.connect()?;
+
+		let cached: Vec<(String, i32)> = song_chapters::table
+			.filter(song_chapters::path.eq(&real_path_string))
+			.order(song_chapters::ordering.asc())
+			.select((song_chapters::title, song_chapters::start_ms))
+			.load(&mut connection)?;
+		if !cached.is_empty() {
+			return Ok(cached
+				.into_iter()
+				.map(|(title, start_ms)| metadata::ChapterMarker { title, start_ms })
+				.collect());
+		}
+
+		// Only the MP4 container carries chapter atoms
+		if utils::get_audio_format(&real_path) != Some(AudioFormat::MP4) {
+			return Ok(Vec::new());
+		}
+
+		let chapters = metadata::read_mp4_chapters(&real_path)?;
+		let rows: Vec<_> = chapters
+			.iter()
+			.enumerate()
+			.map(|(ordering, chapter)| {
+				(
+					song_chapters::path.eq(&real_path_string),
+					song_chapters::ordering.eq(ordering as i32),
+					song_chapters::title.eq(&chapter.title),
+					song_chapters::start_ms.eq(chapter.start_ms),
+				)
+			})
+			.collect();
+		diesel::insert_into(song_chapters::table)
+			.values(&rows)
+			.execute(&mut connection)?;
+
+		Ok(chapters)
+	}
+}
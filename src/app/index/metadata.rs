@@ -2,9 +2,11 @@ use id3::TagLike;
 use lewton::inside_ogg::OggStreamReader;
 use log::error;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::app::settings::TagParsing;
 use crate::utils;
 use crate::utils::AudioFormat;
 
@@ -26,12 +28,16 @@ pub enum Error {
 	Vorbis(#[from] lewton::VorbisError),
 	#[error("Could not find a Vorbis comment within flac file")]
 	VorbisCommentNotFoundInFlacFile,
+	#[error("This file format does not support tag editing: {0}")]
+	UnsupportedFormat(&'static str),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SongTags {
 	pub disc_number: Option<u32>,
+	pub disc_total: Option<u32>,
 	pub track_number: Option<u32>,
+	pub track_total: Option<u32>,
 	pub title: Option<String>,
 	pub duration: Option<u32>,
 	pub artist: Option<String>,
@@ -43,6 +49,11 @@ pub struct SongTags {
 	pub composer: Option<String>,
 	pub genre: Option<String>,
 	pub label: Option<String>,
+	pub bpm: Option<i32>,
+	pub initial_key: Option<String>,
+	pub is_compilation: bool,
+	pub encoder_delay: Option<i32>,
+	pub encoder_padding: Option<i32>,
 }
 
 impl From<id3::Tag> for SongTags {
@@ -53,7 +64,9 @@ impl From<id3::Tag> for SongTags {
 		let title = tag.title().map(|s| s.to_string());
 		let duration = tag.duration();
 		let disc_number = tag.disc();
+		let disc_total = tag.total_discs();
 		let track_number = tag.track();
+		let track_total = tag.total_tracks();
 		let year = tag
 			.year()
 			.or_else(|| tag.date_released().map(|d| d.year))
@@ -64,10 +77,15 @@ impl From<id3::Tag> for SongTags {
 		let composer = tag.get_text("TCOM");
 		let genre = tag.genre().map(|s| s.to_string());
 		let label = tag.get_text("TPUB");
+		let bpm = tag.get_text("TBPM").and_then(|s| s.parse::<i32>().ok());
+		let initial_key = tag.get_text("TKEY");
+		let is_compilation = tag.get_text("TCMP").is_some_and(|v| v != "0");
 
 		SongTags {
 			disc_number,
+			disc_total,
 			track_number,
+			track_total,
 			title,
 			duration,
 			artist,
@@ -79,30 +97,220 @@ impl From<id3::Tag> for SongTags {
 			composer,
 			genre,
 			label,
+			bpm,
+			initial_key,
+			is_compilation,
+			encoder_delay: None,
+			encoder_padding: None,
 		}
 	}
 }
 
-pub fn read(path: &Path) -> Option<SongTags> {
-	let data = match utils::get_audio_format(path) {
-		Some(AudioFormat::AIFF) => read_aiff(path),
-		Some(AudioFormat::APE) => read_ape(path),
-		Some(AudioFormat::FLAC) => read_flac(path),
-		Some(AudioFormat::MP3) => read_mp3(path),
-		Some(AudioFormat::MP4) => read_mp4(path),
-		Some(AudioFormat::MPC) => read_ape(path),
-		Some(AudioFormat::OGG) => read_vorbis(path),
-		Some(AudioFormat::OPUS) => read_opus(path),
-		Some(AudioFormat::WAVE) => read_wave(path),
-		None => return None,
+pub trait TagReader {
+	fn read_tags(&self, path: &Path) -> Result<SongTags, Error>;
+}
+
+impl<F> TagReader for F
+where
+	F: Fn(&Path) -> Result<SongTags, Error>,
+{
+	fn read_tags(&self, path: &Path) -> Result<SongTags, Error> {
+		self(path)
+	}
+}
+
+// Ordered reader chains per format. When the primary reader chokes on a
+// quirky file, the next reader in line gets a chance before we give up. The
+// fallbacks and partial-tag recovery are leniencies, so strict parsing runs a
+// single unforgiving reader per format instead.
+fn format_readers(format: AudioFormat, parsing: TagParsing) -> Vec<&'static dyn TagReader> {
+	match (format, parsing) {
+		(AudioFormat::AIFF, _) => vec![&read_aiff],
+		(AudioFormat::APE, _) => vec![&read_ape],
+		(AudioFormat::DSF, _) => vec![&read_dsf],
+		(AudioFormat::FLAC, _) => vec![&read_flac],
+		// Some rippers write APEv2 tags on MP3s
+		(AudioFormat::MP3, TagParsing::Lenient) => vec![&read_mp3, &read_ape],
+		(AudioFormat::MP3, TagParsing::Strict) => vec![&read_mp3_strict],
+		(AudioFormat::MP4, _) => vec![&read_mp4],
+		(AudioFormat::MPC, _) => vec![&read_ape],
+		(AudioFormat::OGG, _) => vec![&read_vorbis],
+		(AudioFormat::OPUS, _) => vec![&read_opus],
+		(AudioFormat::WAVE, _) => vec![&read_wave],
+	}
+}
+
+fn read_with(path: &Path, readers: &[&dyn TagReader]) -> Result<SongTags, Error> {
+	let mut last_error = None;
+	for reader in readers {
+		match reader.read_tags(path) {
+			Ok(tags) => return Ok(tags),
+			Err(e) => last_error = Some(e),
+		}
+	}
+	Err(last_error.expect("Every format has at least one tag reader"))
+}
+
+// Returns `Ok(None)` for files that are not in a supported audio format.
+// Under lenient parsing, unreadable tags are logged and the file is treated
+// like any other non-audio file; under strict parsing the error is surfaced
+// so callers can record it.
+pub fn read(path: &Path, parsing: TagParsing) -> Result<Option<SongTags>, Error> {
+	let Some(format) = utils::get_audio_format(path) else {
+		return Ok(None);
 	};
-	match data {
-		Ok(d) => Some(d),
-		Err(e) => {
-			error!("Error while reading file metadata for '{:?}': {}", path, e);
-			None
+	match read_with(path, &format_readers(format, parsing)) {
+		Ok(tags) => Ok(Some(tags)),
+		Err(e) => match parsing {
+			TagParsing::Strict => Err(e),
+			TagParsing::Lenient => {
+				error!("Error while reading file metadata for '{:?}': {}", path, e);
+				Ok(None)
+			}
+		},
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagChanges {
+	pub artist: Option<String>,
+	pub album: Option<String>,
+	pub title: Option<String>,
+	pub genre: Option<String>,
+	pub year: Option<i32>,
+}
+
+pub fn update_tags(path: &Path, changes: &TagChanges) -> Result<(), Error> {
+	match utils::get_audio_format(path) {
+		Some(AudioFormat::AIFF) => update_tags_aiff(path, changes),
+		Some(AudioFormat::APE) => update_tags_ape(path, changes),
+		Some(AudioFormat::DSF) => Err(Error::UnsupportedFormat("dsf")),
+		Some(AudioFormat::FLAC) => update_tags_flac(path, changes),
+		Some(AudioFormat::MP3) => update_tags_mp3(path, changes),
+		Some(AudioFormat::MP4) => update_tags_mp4(path, changes),
+		Some(AudioFormat::MPC) => update_tags_ape(path, changes),
+		Some(AudioFormat::OGG) => Err(Error::UnsupportedFormat("ogg")),
+		Some(AudioFormat::OPUS) => Err(Error::UnsupportedFormat("opus")),
+		Some(AudioFormat::WAVE) => update_tags_wave(path, changes),
+		None => Err(Error::UnsupportedFormat("unknown")),
+	}
+}
+
+fn apply_id3_changes(tag: &mut id3::Tag, changes: &TagChanges) {
+	if let Some(ref artist) = changes.artist {
+		tag.set_artist(artist.clone());
+	}
+	if let Some(ref album) = changes.album {
+		tag.set_album(album.clone());
+	}
+	if let Some(ref title) = changes.title {
+		tag.set_title(title.clone());
+	}
+	if let Some(ref genre) = changes.genre {
+		tag.set_genre(genre.clone());
+	}
+	if let Some(year) = changes.year {
+		tag.set_year(year);
+	}
+}
+
+fn read_id3_or_empty(
+	path: &Path,
+	read: impl FnOnce(&Path) -> Result<id3::Tag, id3::Error>,
+) -> Result<id3::Tag, Error> {
+	read(path).or_else(|error| {
+		if let Some(tag) = error.partial_tag {
+			Ok(tag)
+		} else if matches!(error.kind, id3::ErrorKind::NoTag) {
+			Ok(id3::Tag::new())
+		} else {
+			Err(error.into())
 		}
+	})
+}
+
+fn update_tags_mp3(path: &Path, changes: &TagChanges) -> Result<(), Error> {
+	let mut tag = read_id3_or_empty(path, |p| id3::Tag::read_from_path(p))?;
+	apply_id3_changes(&mut tag, changes);
+	tag.write_to_path(path, id3::Version::Id3v24)?;
+	Ok(())
+}
+
+fn update_tags_aiff(path: &Path, changes: &TagChanges) -> Result<(), Error> {
+	let mut tag = read_id3_or_empty(path, |p| id3::Tag::read_from_aiff_path(p))?;
+	apply_id3_changes(&mut tag, changes);
+	tag.write_to_aiff_path(path, id3::Version::Id3v24)?;
+	Ok(())
+}
+
+fn update_tags_wave(path: &Path, changes: &TagChanges) -> Result<(), Error> {
+	let mut tag = read_id3_or_empty(path, |p| id3::Tag::read_from_wav_path(p))?;
+	apply_id3_changes(&mut tag, changes);
+	tag.write_to_wav_path(path, id3::Version::Id3v24)?;
+	Ok(())
+}
+
+fn update_tags_flac(path: &Path, changes: &TagChanges) -> Result<(), Error> {
+	let mut tag = metaflac::Tag::read_from_path(path)?;
+	let comments = tag.vorbis_comments_mut();
+	if let Some(ref artist) = changes.artist {
+		comments.set_artist(vec![artist.clone()]);
+	}
+	if let Some(ref album) = changes.album {
+		comments.set_album(vec![album.clone()]);
+	}
+	if let Some(ref title) = changes.title {
+		comments.set_title(vec![title.clone()]);
 	}
+	if let Some(ref genre) = changes.genre {
+		comments.set("GENRE", vec![genre.clone()]);
+	}
+	if let Some(year) = changes.year {
+		comments.set("DATE", vec![year.to_string()]);
+	}
+	tag.save()?;
+	Ok(())
+}
+
+fn update_tags_mp4(path: &Path, changes: &TagChanges) -> Result<(), Error> {
+	let mut tag = mp4ameta::Tag::read_from_path(path)?;
+	if let Some(ref artist) = changes.artist {
+		tag.set_artist(artist.clone());
+	}
+	if let Some(ref album) = changes.album {
+		tag.set_album(album.clone());
+	}
+	if let Some(ref title) = changes.title {
+		tag.set_title(title.clone());
+	}
+	if let Some(ref genre) = changes.genre {
+		tag.set_genre(genre.clone());
+	}
+	if let Some(year) = changes.year {
+		tag.set_year(year.to_string());
+	}
+	tag.write_to_path(path)?;
+	Ok(())
+}
+
+fn update_tags_ape(path: &Path, changes: &TagChanges) -> Result<(), Error> {
+	let mut tag = ape::read_from_path(path)?;
+	let text_items = [
+		("Artist", &changes.artist),
+		("Album", &changes.album),
+		("Title", &changes.title),
+		("GENRE", &changes.genre),
+	];
+	for (key, value) in text_items {
+		if let Some(value) = value {
+			tag.set_item(ape::Item::from_text(key, value.clone())?);
+		}
+	}
+	if let Some(year) = changes.year {
+		tag.set_item(ape::Item::from_text("Year", year.to_string())?);
+	}
+	ape::write_to_path(&tag, path)?;
+	Ok(())
 }
 
 trait FrameContent {
@@ -129,7 +337,17 @@ fn read_mp3(path: &Path) -> Result<SongTags, Error> {
 			Err(error)
 		}
 	})?;
+	finish_mp3(path, tag)
+}
+
+// Strict variant of `read_mp3`: one malformed frame fails the whole file
+// instead of salvaging the frames that did parse.
+fn read_mp3_strict(path: &Path) -> Result<SongTags, Error> {
+	let tag = id3::Tag::read_from_path(path)?;
+	finish_mp3(path, tag)
+}
 
+fn finish_mp3(path: &Path, tag: id3::Tag) -> Result<SongTags, Error> {
 	let duration = {
 		mp3_duration::from_path(path)
 			.map(|d| d.as_secs() as u32)
@@ -138,9 +356,77 @@ fn read_mp3(path: &Path) -> Result<SongTags, Error> {
 
 	let mut song_tags: SongTags = tag.into();
 	song_tags.duration = duration; // Use duration from mp3_duration instead of from tags.
+	if let Some((delay, padding)) = read_lame_gapless_info(path) {
+		song_tags.encoder_delay = Some(delay);
+		song_tags.encoder_padding = Some(padding);
+	}
 	Ok(song_tags)
 }
 
+// LAME (and compatible encoders) store the encoder delay and padding in the
+// Xing/Info tag of the first MPEG frame. Clients need these values to trim
+// silence for gapless playback. Files without a LAME tag yield None.
+fn read_lame_gapless_info(path: &Path) -> Option<(i32, i32)> {
+	use std::io::Read;
+
+	let mut file = fs::File::open(path).ok()?;
+	let mut header = [0; 10];
+	file.read_exact(&mut header).ok()?;
+
+	// Skip over the ID3v2 tag, if any, to reach the first MPEG frame
+	let mut buffer = Vec::new();
+	if &header[0..3] == b"ID3" {
+		let tag_size = header[6..10]
+			.iter()
+			.fold(0u32, |size, byte| (size << 7) | (byte & 0x7F) as u32);
+		std::io::copy(&mut file.by_ref().take(tag_size as u64), &mut std::io::sink()).ok()?;
+	} else {
+		buffer.extend_from_slice(&header);
+	}
+	file.take(4096).read_to_end(&mut buffer).ok()?;
+
+	// The Xing/Info tag sits at a layout-dependent offset within the frame
+	let xing_offset = buffer
+		.windows(4)
+		.position(|w| w == b"Xing" || w == b"Info")?;
+	let flags = u32::from_be_bytes(buffer.get(xing_offset + 4..xing_offset + 8)?.try_into().ok()?);
+	let mut offset = xing_offset + 8;
+	if flags & 0x1 != 0 {
+		offset += 4; // Frame count
+	}
+	if flags & 0x2 != 0 {
+		offset += 4; // Byte count
+	}
+	if flags & 0x4 != 0 {
+		offset += 100; // Seek table
+	}
+	if flags & 0x8 != 0 {
+		offset += 4; // VBR quality
+	}
+
+	// The LAME extension starts with a 9-byte encoder version string, followed
+	// by 12 bytes of tag data, then delay and padding packed into 12 bits each
+	let encoder = buffer.get(offset..offset + 9)?;
+	if !encoder.iter().all(|b| (0x20..0x7F).contains(b)) {
+		return None; // No LAME extension after the Xing/Info tag
+	}
+	let gapless = buffer.get(offset + 21..offset + 24)?;
+	let delay = ((gapless[0] as i32) << 4) | ((gapless[1] as i32) >> 4);
+	let padding = (((gapless[1] & 0x0F) as i32) << 8) | gapless[2] as i32;
+	Some((delay, padding))
+}
+
+fn read_dsf(path: &Path) -> Result<SongTags, Error> {
+	let tag = utils::read_dsf_id3_tag(path).or_else(|error| {
+		if let Some(tag) = error.partial_tag {
+			Ok(tag)
+		} else {
+			Err(error)
+		}
+	})?;
+	Ok(tag.into())
+}
+
 fn read_aiff(path: &Path) -> Result<SongTags, Error> {
 	let tag = id3::Tag::read_from_aiff_path(path).or_else(|error| {
 		if let Some(tag) = error.partial_tag {
@@ -177,6 +463,18 @@ fn read_ape_i32(item: &ape::Item) -> Option<i32> {
 	}
 }
 
+// Splits combined `2/12` number-of-total values into their two halves. A value
+// with no separator is a bare number with no known total.
+fn parse_x_of_y(value: &str) -> (Option<u32>, Option<u32>) {
+	match value.split_once('/') {
+		Some((number, total)) => (
+			number.trim().parse().ok(),
+			total.trim().parse().ok(),
+		),
+		None => (value.trim().parse().ok(), None),
+	}
+}
+
 fn read_ape_x_of_y(item: &ape::Item) -> Option<u32> {
 	match item.value {
 		ape::ItemValue::Text(ref s) => {
@@ -191,6 +489,13 @@ fn read_ape_x_of_y(item: &ape::Item) -> Option<u32> {
 	}
 }
 
+fn read_ape_x_of_y_total(item: &ape::Item) -> Option<u32> {
+	match item.value {
+		ape::ItemValue::Text(ref s) => parse_x_of_y(s).1,
+		_ => None,
+	}
+}
+
 fn read_ape(path: &Path) -> Result<SongTags, Error> {
 	let tag = ape::read_from_path(path)?;
 	let artist = tag.item("Artist").and_then(read_ape_string);
@@ -199,11 +504,19 @@ fn read_ape(path: &Path) -> Result<SongTags, Error> {
 	let title = tag.item("Title").and_then(read_ape_string);
 	let year = tag.item("Year").and_then(read_ape_i32);
 	let disc_number = tag.item("Disc").and_then(read_ape_x_of_y);
+	let disc_total = tag.item("Disc").and_then(read_ape_x_of_y_total);
 	let track_number = tag.item("Track").and_then(read_ape_x_of_y);
+	let track_total = tag.item("Track").and_then(read_ape_x_of_y_total);
 	let lyricist = tag.item("LYRICIST").and_then(read_ape_string);
 	let composer = tag.item("COMPOSER").and_then(read_ape_string);
 	let genre = tag.item("GENRE").and_then(read_ape_string);
 	let label = tag.item("PUBLISHER").and_then(read_ape_string);
+	let bpm = tag.item("BPM").and_then(read_ape_i32);
+	let initial_key = tag.item("InitialKey").and_then(read_ape_string);
+	let is_compilation = tag
+		.item("Compilation")
+		.and_then(read_ape_string)
+		.is_some_and(|v| v != "0");
 	Ok(SongTags {
 		artist,
 		album_artist,
@@ -211,13 +524,20 @@ fn read_ape(path: &Path) -> Result<SongTags, Error> {
 		title,
 		duration: None,
 		disc_number,
+		disc_total,
 		track_number,
+		track_total,
 		year,
 		has_artwork: false,
 		lyricist,
 		composer,
 		genre,
 		label,
+		bpm,
+		initial_key,
+		is_compilation,
+		encoder_delay: None,
+		encoder_padding: None,
 	})
 }
 
@@ -232,13 +552,20 @@ fn read_vorbis(path: &Path) -> Result<SongTags, Error> {
 		title: None,
 		duration: None,
 		disc_number: None,
+		disc_total: None,
 		track_number: None,
+		track_total: None,
 		year: None,
 		has_artwork: false,
 		lyricist: None,
 		composer: None,
 		genre: None,
 		label: None,
+		bpm: None,
+		initial_key: None,
+		is_compilation: false,
+		encoder_delay: None,
+		encoder_padding: None,
 	};
 
 	for (key, value) in source.comment_hdr.comment_list {
@@ -248,13 +575,28 @@ fn read_vorbis(path: &Path) -> Result<SongTags, Error> {
 				"ALBUM" => tags.album = Some(value),
 				"ARTIST" => tags.artist = Some(value),
 				"ALBUMARTIST" => tags.album_artist = Some(value),
-				"TRACKNUMBER" => tags.track_number = value.parse::<u32>().ok(),
-				"DISCNUMBER" => tags.disc_number = value.parse::<u32>().ok(),
+				"TRACKNUMBER" => {
+					let (number, total) = parse_x_of_y(&value);
+					tags.track_number = number;
+					tags.track_total = total.or(tags.track_total);
+				},
+				"DISCNUMBER" => {
+					let (number, total) = parse_x_of_y(&value);
+					tags.disc_number = number;
+					tags.disc_total = total.or(tags.disc_total);
+				},
+				"TRACKTOTAL" => tags.track_total = value.parse::<u32>().ok().or(tags.track_total),
+				"TOTALTRACKS" => tags.track_total = value.parse::<u32>().ok().or(tags.track_total),
+				"DISCTOTAL" => tags.disc_total = value.parse::<u32>().ok().or(tags.disc_total),
+				"TOTALDISCS" => tags.disc_total = value.parse::<u32>().ok().or(tags.disc_total),
 				"DATE" => tags.year = value.parse::<i32>().ok(),
 				"LYRICIST" => tags.lyricist = Some(value),
 				"COMPOSER" => tags.composer = Some(value),
 				"GENRE" => tags.genre = Some(value),
 				"PUBLISHER" => tags.label = Some(value),
+				"BPM" => tags.bpm = value.parse::<i32>().ok(),
+				"INITIALKEY" => tags.initial_key = Some(value),
+				"COMPILATION" => tags.is_compilation = value != "0",
 				"METADATA_BLOCK_PICTURE" => tags.has_artwork = true,
 				_ => (),
 			}
@@ -267,6 +609,10 @@ fn read_vorbis(path: &Path) -> Result<SongTags, Error> {
 fn read_opus(path: &Path) -> Result<SongTags, Error> {
 	let headers = opus_headers::parse_from_path(path)?;
 
+	// The pre-skip count from the identification header is the number of
+	// samples a decoder must discard for gapless playback
+	let encoder_delay = Some(headers.id.pre_skip as i32);
+
 	let mut tags = SongTags {
 		artist: None,
 		album_artist: None,
@@ -274,13 +620,20 @@ fn read_opus(path: &Path) -> Result<SongTags, Error> {
 		title: None,
 		duration: None,
 		disc_number: None,
+		disc_total: None,
 		track_number: None,
+		track_total: None,
 		year: None,
 		has_artwork: false,
 		lyricist: None,
 		composer: None,
 		genre: None,
 		label: None,
+		bpm: None,
+		initial_key: None,
+		is_compilation: false,
+		encoder_delay,
+		encoder_padding: None,
 	};
 
 	for (key, value) in headers.comments.user_comments {
@@ -290,13 +643,28 @@ fn read_opus(path: &Path) -> Result<SongTags, Error> {
 				"ALBUM" => tags.album = Some(value),
 				"ARTIST" => tags.artist = Some(value),
 				"ALBUMARTIST" => tags.album_artist = Some(value),
-				"TRACKNUMBER" => tags.track_number = value.parse::<u32>().ok(),
-				"DISCNUMBER" => tags.disc_number = value.parse::<u32>().ok(),
+				"TRACKNUMBER" => {
+					let (number, total) = parse_x_of_y(&value);
+					tags.track_number = number;
+					tags.track_total = total.or(tags.track_total);
+				},
+				"DISCNUMBER" => {
+					let (number, total) = parse_x_of_y(&value);
+					tags.disc_number = number;
+					tags.disc_total = total.or(tags.disc_total);
+				},
+				"TRACKTOTAL" => tags.track_total = value.parse::<u32>().ok().or(tags.track_total),
+				"TOTALTRACKS" => tags.track_total = value.parse::<u32>().ok().or(tags.track_total),
+				"DISCTOTAL" => tags.disc_total = value.parse::<u32>().ok().or(tags.disc_total),
+				"TOTALDISCS" => tags.disc_total = value.parse::<u32>().ok().or(tags.disc_total),
 				"DATE" => tags.year = value.parse::<i32>().ok(),
 				"LYRICIST" => tags.lyricist = Some(value),
 				"COMPOSER" => tags.composer = Some(value),
 				"GENRE" => tags.genre = Some(value),
 				"PUBLISHER" => tags.label = Some(value),
+				"BPM" => tags.bpm = value.parse::<i32>().ok(),
+				"INITIALKEY" => tags.initial_key = Some(value),
+				"COMPILATION" => tags.is_compilation = value != "0",
 				"METADATA_BLOCK_PICTURE" => tags.has_artwork = true,
 				_ => (),
 			}
@@ -311,8 +679,18 @@ fn read_flac(path: &Path) -> Result<SongTags, Error> {
 	let vorbis = tag
 		.vorbis_comments()
 		.ok_or(Error::VorbisCommentNotFoundInFlacFile)?;
-	let disc_number = vorbis
+	let (disc_number, disc_total) = vorbis
 		.get("DISCNUMBER")
+		.map_or((None, None), |d| parse_x_of_y(&d[0]));
+	let disc_total = disc_total.or_else(|| {
+		vorbis
+			.get("DISCTOTAL")
+			.or_else(|| vorbis.get("TOTALDISCS"))
+			.and_then(|d| d[0].parse::<u32>().ok())
+	});
+	let track_total = vorbis
+		.get("TRACKTOTAL")
+		.or_else(|| vorbis.get("TOTALTRACKS"))
 		.and_then(|d| d[0].parse::<u32>().ok());
 	let year = vorbis.get("DATE").and_then(|d| d[0].parse::<i32>().ok());
 	let mut streaminfo = tag.get_blocks(metaflac::BlockType::StreamInfo);
@@ -329,19 +707,29 @@ fn read_flac(path: &Path) -> Result<SongTags, Error> {
 		title: vorbis.title().map(|v| v[0].clone()),
 		duration,
 		disc_number,
+		disc_total,
 		track_number: vorbis.track(),
+		track_total,
 		year,
 		has_artwork,
 		lyricist: vorbis.get("LYRICIST").map(|v| v[0].clone()),
 		composer: vorbis.get("COMPOSER").map(|v| v[0].clone()),
 		genre: vorbis.get("GENRE").map(|v| v[0].clone()),
 		label: vorbis.get("PUBLISHER").map(|v| v[0].clone()),
+		bpm: vorbis.get("BPM").and_then(|d| d[0].parse::<i32>().ok()),
+		initial_key: vorbis.get("INITIALKEY").map(|v| v[0].clone()),
+		is_compilation: vorbis
+			.get("COMPILATION")
+			.is_some_and(|v| v[0] != "0"),
+		encoder_delay: None,
+		encoder_padding: None,
 	})
 }
 
 fn read_mp4(path: &Path) -> Result<SongTags, Error> {
 	let mut tag = mp4ameta::Tag::read_from_path(path)?;
 	let label_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "Label");
+	let initial_key_ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", "initialkey");
 
 	Ok(SongTags {
 		artist: tag.take_artist(),
@@ -350,21 +738,123 @@ fn read_mp4(path: &Path) -> Result<SongTags, Error> {
 		title: tag.take_title(),
 		duration: tag.duration().map(|v| v.as_secs() as u32),
 		disc_number: tag.disc_number().map(|d| d as u32),
+		disc_total: tag.total_discs().map(|d| d as u32),
 		track_number: tag.track_number().map(|d| d as u32),
+		track_total: tag.total_tracks().map(|d| d as u32),
 		year: tag.year().and_then(|v| v.parse::<i32>().ok()),
 		has_artwork: tag.artwork().is_some(),
 		lyricist: tag.take_lyricist(),
 		composer: tag.take_composer(),
 		genre: tag.take_genre(),
 		label: tag.take_strings_of(&label_ident).next(),
+		bpm: tag.bpm().map(|n| n as i32),
+		initial_key: tag.take_strings_of(&initial_key_ident).next(),
+		is_compilation: tag.compilation(),
+		encoder_delay: None,
+		encoder_padding: None,
 	})
 }
 
+// Chapter marker parsed from an audiobook file. Start times are offsets from
+// the beginning of the file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChapterMarker {
+	pub title: String,
+	pub start_ms: i32,
+}
+
+// Locates a direct child atom by fourcc between `offset` and `end`, returning
+// the byte range of its body. Seeking over atoms instead of reading them keeps
+// the `mdat` payload of large audiobooks out of memory.
+fn find_mp4_atom(
+	file: &mut fs::File,
+	mut offset: u64,
+	end: u64,
+	fourcc: &[u8; 4],
+) -> std::io::Result<Option<(u64, u64)>> {
+	use std::io::{Read, Seek, SeekFrom};
+
+	while offset + 8 <= end {
+		file.seek(SeekFrom::Start(offset))?;
+		let mut header = [0; 8];
+		file.read_exact(&mut header)?;
+		let mut size = u64::from(u32::from_be_bytes(header[0..4].try_into().unwrap()));
+		let mut body_start = offset + 8;
+		if size == 1 {
+			let mut extended = [0; 8];
+			file.read_exact(&mut extended)?;
+			size = u64::from_be_bytes(extended);
+			body_start = offset + 16;
+		}
+		if size < body_start - offset || size > end - offset {
+			return Ok(None);
+		}
+		if &header[4..8] == fourcc {
+			return Ok(Some((body_start, offset + size)));
+		}
+		offset += size;
+	}
+	Ok(None)
+}
+
+// Reads the Nero-style chapter list (`moov.udta.chpl`), which is the atom most
+// audiobook tooling writes. mp4ameta does not expose it, so this walks the
+// container directly. Files without a chapter list simply yield no markers.
+pub fn read_mp4_chapters(path: &Path) -> Result<Vec<ChapterMarker>, Error> {
+	use std::io::{Read, Seek, SeekFrom};
+
+	let io_error = |e| Error::Io(path.to_owned(), e);
+	let mut file = fs::File::open(path).map_err(io_error)?;
+	let file_size = file.metadata().map_err(io_error)?.len();
+
+	let mut range = (0, file_size);
+	for fourcc in [b"moov", b"udta", b"chpl"] {
+		match find_mp4_atom(&mut file, range.0, range.1, fourcc).map_err(io_error)? {
+			Some(r) => range = r,
+			None => return Ok(Vec::new()),
+		}
+	}
+
+	let mut chpl = vec![0; (range.1 - range.0) as usize];
+	file.seek(SeekFrom::Start(range.0)).map_err(io_error)?;
+	file.read_exact(&mut chpl).map_err(io_error)?;
+
+	// One version byte, three flag bytes, four reserved bytes, then a one byte
+	// chapter count. Each entry is a start time in 100-nanosecond ticks,
+	// followed by a length-prefixed UTF-8 title.
+	if chpl.len() < 9 {
+		return Ok(Vec::new());
+	}
+	let count = chpl[8] as usize;
+	let mut chapters = Vec::with_capacity(count);
+	let mut offset = 9;
+	for _ in 0..count {
+		if offset + 9 > chpl.len() {
+			break;
+		}
+		let start = u64::from_be_bytes(chpl[offset..offset + 8].try_into().unwrap());
+		let title_length = chpl[offset + 8] as usize;
+		offset += 9;
+		if offset + title_length > chpl.len() {
+			break;
+		}
+		let title = String::from_utf8_lossy(&chpl[offset..offset + title_length]).into_owned();
+		offset += title_length;
+		chapters.push(ChapterMarker {
+			title,
+			start_ms: (start / 10_000) as i32,
+		});
+	}
+	Ok(chapters)
+}
+
 #[test]
 fn reads_file_metadata() {
 	let sample_tags = SongTags {
 		disc_number: Some(3),
+		disc_total: Some(4),
 		track_number: Some(1),
+		track_total: Some(2),
 		title: Some("TEST TITLE".into()),
 		artist: Some("TEST ARTIST".into()),
 		album_artist: Some("TEST ALBUM ARTIST".into()),
@@ -376,6 +866,11 @@ fn reads_file_metadata() {
 		composer: Some("TEST COMPOSER".into()),
 		genre: Some("TEST GENRE".into()),
 		label: Some("TEST LABEL".into()),
+		bpm: None,
+		initial_key: None,
+		is_compilation: false,
+		encoder_delay: None,
+		encoder_padding: None,
 	};
 	let flac_sample_tag = SongTags {
 		duration: Some(0),
@@ -383,71 +878,262 @@ fn reads_file_metadata() {
 	};
 	let mp3_sample_tag = SongTags {
 		duration: Some(0),
+		encoder_delay: Some(576),
+		encoder_padding: Some(2089),
 		..sample_tags.clone()
 	};
+	// The m4a sample file does not carry disc or track totals
 	let m4a_sample_tag = SongTags {
 		duration: Some(0),
+		disc_total: None,
+		track_total: None,
+		..sample_tags.clone()
+	};
+	// Neither does the opus one
+	let opus_sample_tag = SongTags {
+		encoder_delay: Some(312),
+		disc_total: None,
+		track_total: None,
 		..sample_tags.clone()
 	};
+	// The m4b audiobook sample shares the m4a tags but embeds a cover
+	let m4b_sample_tag = SongTags {
+		has_artwork: true,
+		..m4a_sample_tag.clone()
+	};
 	assert_eq!(
-		read(Path::new("test-data/formats/sample.aif")).unwrap(),
+		read(Path::new("test-data/formats/sample.aif"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
 		sample_tags
 	);
 	assert_eq!(
-		read(Path::new("test-data/formats/sample.mp3")).unwrap(),
+		read(Path::new("test-data/formats/sample.mp3"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
 		mp3_sample_tag
 	);
 	assert_eq!(
-		read(Path::new("test-data/formats/sample.ogg")).unwrap(),
+		read(Path::new("test-data/formats/sample.ogg"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
 		sample_tags
 	);
 	assert_eq!(
-		read(Path::new("test-data/formats/sample.flac")).unwrap(),
+		read(Path::new("test-data/formats/sample.flac"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
 		flac_sample_tag
 	);
 	assert_eq!(
-		read(Path::new("test-data/formats/sample.m4a")).unwrap(),
+		read(Path::new("test-data/formats/sample.m4a"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
 		m4a_sample_tag
 	);
 	assert_eq!(
-		read(Path::new("test-data/formats/sample.opus")).unwrap(),
+		read(Path::new("test-data/formats/sample.m4b"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
+		m4b_sample_tag
+	);
+	assert_eq!(
+		read(Path::new("test-data/formats/sample.opus"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
+		opus_sample_tag
+	);
+	assert_eq!(
+		read(Path::new("test-data/formats/sample.ape"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
 		sample_tags
 	);
 	assert_eq!(
-		read(Path::new("test-data/formats/sample.ape")).unwrap(),
+		read(Path::new("test-data/formats/sample.dsf"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
 		sample_tags
 	);
 	assert_eq!(
-		read(Path::new("test-data/formats/sample.wav")).unwrap(),
+		read(Path::new("test-data/formats/sample.wav"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap(),
 		sample_tags
 	);
 }
 
+#[test]
+fn reads_bpm_and_key() {
+	use crate::test::prepare_test_directory;
+	use crate::test_name;
+
+	let output_dir = prepare_test_directory(test_name!());
+	let path = output_dir.join("sample.mp3");
+	fs::copy("test-data/formats/sample.mp3", &path).unwrap();
+
+	let mut tag = id3::Tag::read_from_path(&path).unwrap();
+	tag.set_text("TBPM", "175");
+	tag.set_text("TKEY", "Am");
+	tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+	let tags = read(&path, TagParsing::Lenient).unwrap().unwrap();
+	assert_eq!(tags.bpm, Some(175));
+	assert_eq!(tags.initial_key, Some("Am".to_owned()));
+
+	// Garbage BPM values are stored as null rather than failing the read
+	let mut tag = id3::Tag::read_from_path(&path).unwrap();
+	tag.set_text("TBPM", "fast-ish");
+	tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+	assert_eq!(read(&path, TagParsing::Lenient).unwrap().unwrap().bpm, None);
+}
+
+#[test]
+fn parses_combined_disc_and_track_totals() {
+	use crate::test::prepare_test_directory;
+	use crate::test_name;
+
+	let output_dir = prepare_test_directory(test_name!());
+	let path = output_dir.join("sample.mp3");
+	fs::copy("test-data/formats/sample.mp3", &path).unwrap();
+
+	let mut tag = id3::Tag::read_from_path(&path).unwrap();
+	tag.set_text("TPOS", "2/2");
+	tag.set_text("TRCK", "3/10");
+	tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+	let tags = read(&path, TagParsing::Lenient).unwrap().unwrap();
+	assert_eq!(tags.disc_number, Some(2));
+	assert_eq!(tags.disc_total, Some(2));
+	assert_eq!(tags.track_number, Some(3));
+	assert_eq!(tags.track_total, Some(10));
+
+	// Vorbis-style comments use the same combined form
+	assert_eq!(parse_x_of_y("2/2"), (Some(2), Some(2)));
+	assert_eq!(parse_x_of_y("7"), (Some(7), None));
+	assert_eq!(parse_x_of_y("junk"), (None, None));
+}
+
+#[test]
+fn reads_audiobook_chapters() {
+	let chapters = read_mp4_chapters(Path::new("test-data/formats/sample.m4b")).unwrap();
+	assert_eq!(chapters.len(), 2);
+	assert_eq!(
+		chapters[0],
+		ChapterMarker {
+			title: "Chapter 1".to_owned(),
+			start_ms: 0,
+		}
+	);
+	assert_eq!(
+		chapters[1],
+		ChapterMarker {
+			title: "Chapter 2".to_owned(),
+			start_ms: 1500,
+		}
+	);
+
+	// Regular music files have no chapter list
+	let chapters = read_mp4_chapters(Path::new("test-data/formats/sample.m4a")).unwrap();
+	assert!(chapters.is_empty());
+}
+
+#[test]
+fn reads_gapless_playback_info() {
+	// Opus stores its decoder delay as a pre-skip count in the identification header
+	let opus = read(Path::new("test-data/formats/sample.opus"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap();
+	assert_eq!(opus.encoder_delay, Some(312));
+	assert_eq!(opus.encoder_padding, None);
+
+	// MP3 gapless info comes from the LAME extension of the Xing/Info tag
+	let mp3 = read(Path::new("test-data/formats/sample.mp3"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap();
+	assert_eq!(mp3.encoder_delay, Some(576));
+	assert_eq!(mp3.encoder_padding, Some(2089));
+
+	// Formats without encoder delay metadata store null
+	let flac = read(Path::new("test-data/formats/sample.flac"), TagParsing::Lenient)
+			.unwrap()
+			.unwrap();
+	assert_eq!(flac.encoder_delay, None);
+	assert_eq!(flac.encoder_padding, None);
+}
+
 #[test]
 fn reads_embedded_artwork() {
 	assert!(
-		read(Path::new("test-data/artwork/sample.aif"))
+		read(Path::new("test-data/artwork/sample.aif"), TagParsing::Lenient)
+			.unwrap()
 			.unwrap()
 			.has_artwork
 	);
 	assert!(
-		read(Path::new("test-data/artwork/sample.mp3"))
+		read(Path::new("test-data/artwork/sample.mp3"), TagParsing::Lenient)
+			.unwrap()
 			.unwrap()
 			.has_artwork
 	);
 	assert!(
-		read(Path::new("test-data/artwork/sample.flac"))
+		read(Path::new("test-data/artwork/sample.flac"), TagParsing::Lenient)
+			.unwrap()
 			.unwrap()
 			.has_artwork
 	);
 	assert!(
-		read(Path::new("test-data/artwork/sample.m4a"))
+		read(Path::new("test-data/artwork/sample.m4a"), TagParsing::Lenient)
+			.unwrap()
 			.unwrap()
 			.has_artwork
 	);
 	assert!(
-		read(Path::new("test-data/artwork/sample.wav"))
+		read(Path::new("test-data/artwork/sample.wav"), TagParsing::Lenient)
+			.unwrap()
 			.unwrap()
 			.has_artwork
 	);
 }
+
+#[test]
+fn falls_back_to_secondary_reader() {
+	fn broken_reader(_: &Path) -> Result<SongTags, Error> {
+		Err(Error::UnsupportedFormat("broken"))
+	}
+
+	let path = Path::new("test-data/formats/sample.mp3");
+	assert!(read_with(path, &[&broken_reader]).is_err());
+
+	let tags = read_with(path, &[&broken_reader, &read_mp3]).unwrap();
+	assert_eq!(tags.artist, Some("TEST ARTIST".into()));
+}
+
+#[test]
+fn strict_parsing_rejects_malformed_frames() {
+	use crate::test::prepare_test_directory;
+	use crate::test_name;
+
+	let output_dir = prepare_test_directory(test_name!());
+	let path = output_dir.join("sample.mp3");
+	fs::copy("test-data/formats/sample.mp3", &path).unwrap();
+
+	// Clobber the text encoding byte of the TCON frame so that one frame is
+	// malformed while the frames before it remain readable
+	let mut bytes = fs::read(&path).unwrap();
+	let position = bytes
+		.windows(4)
+		.position(|window| window == b"TCON")
+		.unwrap();
+	bytes[position + 10] = 0xFF;
+	fs::write(&path, &bytes).unwrap();
+
+	// Lenient parsing salvages the frames that did parse
+	let tags = read(&path, TagParsing::Lenient).unwrap().unwrap();
+	assert_eq!(tags.title, Some("TEST TITLE".to_owned()));
+	assert_eq!(tags.artist, Some("TEST ARTIST".to_owned()));
+	assert_eq!(tags.genre, None);
+
+	// Strict parsing fails the whole file
+	assert!(read(&path, TagParsing::Strict).is_err());
+}
@@ -1,10 +1,12 @@
 use diesel::dsl::sql;
 use diesel::prelude::*;
 use diesel::sql_types;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use super::*;
-use crate::db::{self, directories, songs};
+use crate::db::{self, directories, song_tags, songs};
+use crate::utils;
 
 #[derive(thiserror::Error, Debug)]
 pub enum QueryError {
@@ -12,8 +14,18 @@ pub enum QueryError {
 	Database(#[from] diesel::result::Error),
 	#[error(transparent)]
 	DatabaseConnection(#[from] db::Error),
+	#[error("Artist was not found: `{0}`")]
+	ArtistNotFound(String),
+	#[error("Directory was not found: `{0}`")]
+	DirectoryNotFound(PathBuf),
+	#[error("Directory id was not found: `{0}`")]
+	DirectoryIdNotFound(i32),
 	#[error("Song was not found: `{0}`")]
 	SongNotFound(PathBuf),
+	#[error("Song id was not found: `{0}`")]
+	SongIdNotFound(i32),
+	#[error(transparent)]
+	Metadata(#[from] metadata::Error),
 	#[error(transparent)]
 	Vfs(#[from] vfs::Error),
 }
@@ -23,8 +35,105 @@ sql_function!(
 	fn random() -> Integer;
 );
 
+// Hard ceiling on recursive browsing, regardless of the depth a client asks
+// for. Total node count is further bounded by `max_results_per_query`.
+pub const TREE_MAX_DEPTH: u32 = 10;
+
+// Ordering of the recent-albums listing: by indexing date, or by the year tag
+// stored on the album
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecentSort {
+	#[default]
+	Added,
+	Released,
+}
+
+// Ordering used when walking the albums under one parent directory
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlbumSort {
+	#[default]
+	Name,
+	Year,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SongQueryOptions {
+	pub path_like: Option<String>,
+	pub search: Option<String>,
+	pub artist: Option<String>,
+	pub genre: Option<String>,
+	pub year: Option<i32>,
+	pub bpm_min: Option<i32>,
+	pub bpm_max: Option<i32>,
+	pub custom_tag: Option<CustomTagFilter>,
+	pub order_by_path: bool,
+	pub limit: Option<i64>,
+}
+
+pub(crate) struct SongQuery {
+	options: SongQueryOptions,
+}
+
+impl SongQuery {
+	pub fn new(options: SongQueryOptions) -> Self {
+		Self { options }
+	}
+
+	pub fn load(
+		&self,
+		connection: &mut diesel::SqliteConnection,
+	) -> Result<Vec<Song>, diesel::result::Error> {
+		use self::songs::dsl::*;
+		let mut query = songs.into_boxed();
+		if let Some(pattern) = &self.options.path_like {
+			query = query.filter(path.like(pattern.clone()));
+		}
+		if let Some(search) = &self.options.search {
+			let like_test = format!("%{}%", utils::normalize_unicode(search));
+			query = query.filter(search_normalized.like(like_test));
+		}
+		if let Some(song_artist) = &self.options.artist {
+			query = query.filter(artist.eq(song_artist.clone()));
+		}
+		if let Some(song_genre) = &self.options.genre {
+			query = query.filter(genre.eq(song_genre.clone()));
+		}
+		if let Some(song_year) = self.options.year {
+			query = query.filter(year.eq(song_year));
+		}
+		if let Some(bpm_min) = self.options.bpm_min {
+			query = query.filter(bpm.ge(bpm_min));
+		}
+		if let Some(bpm_max) = self.options.bpm_max {
+			query = query.filter(bpm.le(bpm_max));
+		}
+		if let Some(custom_tag) = &self.options.custom_tag {
+			let tagged = song_tags::table.filter(song_tags::key.eq(custom_tag.key.clone()));
+			query = match &custom_tag.value {
+				Some(tag_value) => query.filter(path.eq_any(
+					tagged
+						.filter(song_tags::value.eq(tag_value.clone()))
+						.select(song_tags::path),
+				)),
+				None => query.filter(path.eq_any(tagged.select(song_tags::path))),
+			};
+		}
+		if self.options.order_by_path {
+			query = query.order(path);
+		}
+		if let Some(limit) = self.options.limit {
+			query = query.limit(limit);
+		}
+		query.load(connection)
+	}
+}
+
 impl Index {
-	pub fn browse<P>(&self, virtual_path: P) -> Result<Vec<CollectionFile>, QueryError>
+	pub fn browse<P>(
+		&self,
+		virtual_path: P,
+		grouping: BrowseGrouping,
+	) -> Result<Vec<CollectionFile>, QueryError>
 	where
 		P: AsRef<Path>,
 	{
@@ -40,7 +149,7 @@ impl Index {
 			let virtual_directories = real_directories
 				.into_iter()
 				.filter_map(|d| d.virtualize(&vfs));
-			output.extend(virtual_directories.map(CollectionFile::Directory));
+			output.extend(virtual_directories.map(|d| CollectionFile::Directory(Box::new(d))));
 		} else {
 			// Browse sub-directory
 			let real_path = vfs.virtual_to_real(virtual_path)?;
@@ -48,59 +157,383 @@ impl Index {
 
 			let real_directories: Vec<Directory> = directories::table
 				.filter(directories::parent.eq(&real_path_string))
-				.order(sql::<sql_types::Bool>("path COLLATE NOCASE ASC"))
+				.order((directories::search_normalized.asc(), directories::path.asc()))
 				.load(&mut connection)?;
 			let virtual_directories = real_directories
 				.into_iter()
 				.filter_map(|d| d.virtualize(&vfs));
-			output.extend(virtual_directories.map(CollectionFile::Directory));
 
+			// Multi-disc albums must play disc by disc; files without disc or
+			// track tags fall back to their filename ordering
 			let real_songs: Vec<Song> = songs::table
 				.filter(songs::parent.eq(&real_path_string))
-				.order(sql::<sql_types::Bool>("path COLLATE NOCASE ASC"))
+				.order((
+					songs::disc_number.asc(),
+					songs::track_number.asc(),
+					songs::search_normalized.asc(),
+					songs::path.asc(),
+				))
 				.load(&mut connection)?;
 			let virtual_songs = real_songs.into_iter().filter_map(|s| s.virtualize(&vfs));
-			output.extend(virtual_songs.map(CollectionFile::Song));
+
+			match grouping {
+				BrowseGrouping::FoldersFirst => {
+					output.extend(virtual_directories.map(|d| CollectionFile::Directory(Box::new(d))));
+					output.extend(virtual_songs.map(|s| CollectionFile::Song(Box::new(s))));
+				}
+				BrowseGrouping::FilesFirst => {
+					output.extend(virtual_songs.map(|s| CollectionFile::Song(Box::new(s))));
+					output.extend(virtual_directories.map(|d| CollectionFile::Directory(Box::new(d))));
+				}
+				BrowseGrouping::Mixed => {
+					output.extend(virtual_directories.map(|d| CollectionFile::Directory(Box::new(d))));
+					output.extend(virtual_songs.map(|s| CollectionFile::Song(Box::new(s))));
+					output.sort_by(|a, b| {
+						utils::normalize_unicode(a.name()).cmp(&utils::normalize_unicode(b.name()))
+					});
+				}
+			}
 		}
 
 		Ok(output)
 	}
 
-	pub fn flatten<P>(&self, virtual_path: P) -> Result<Vec<Song>, QueryError>
+	pub fn browse_recursive<P>(
+		&self,
+		virtual_path: P,
+		depth: u32,
+	) -> Result<Truncated<TreeEntry>, QueryError>
+	where
+		P: AsRef<Path>,
+	{
+		let depth = depth.clamp(1, TREE_MAX_DEPTH);
+		let mut budget = self.max_results_per_query;
+		let mut truncated = false;
+		let items =
+			self.browse_level(virtual_path.as_ref(), depth, &mut budget, &mut truncated)?;
+		Ok(Truncated { items, truncated })
+	}
+
+	fn browse_level(
+		&self,
+		virtual_path: &Path,
+		depth: u32,
+		budget: &mut usize,
+		truncated: &mut bool,
+	) -> Result<Vec<TreeEntry>, QueryError> {
+		let files = self.browse(virtual_path, BrowseGrouping::default())?;
+		let mut output = Vec::with_capacity(files.len());
+		for file in files {
+			if *budget == 0 {
+				*truncated = true;
+				break;
+			}
+			*budget -= 1;
+			match file {
+				CollectionFile::Directory(directory) => {
+					let children = if depth > 1 {
+						self.browse_level(Path::new(&directory.path), depth - 1, budget, truncated)?
+					} else {
+						Vec::new()
+					};
+					output.push(TreeEntry::Directory {
+						directory,
+						children,
+					});
+				}
+				CollectionFile::Song(song) => output.push(TreeEntry::Song(song)),
+			}
+		}
+		Ok(output)
+	}
+
+	pub fn flatten<P>(
+		&self,
+		virtual_path: P,
+		bpm_min: Option<i32>,
+		bpm_max: Option<i32>,
+		custom_tag: Option<CustomTagFilter>,
+	) -> Result<Truncated<Song>, QueryError>
 	where
 		P: AsRef<Path>,
 	{
-		use self::songs::dsl::*;
 		let vfs = self.vfs_manager.get_vfs()?;
 		let mut connection = self.db.connect()?;
 
-		let real_songs: Vec<Song> = if virtual_path.as_ref().parent().is_some() {
+		let path_like = if virtual_path.as_ref().parent().is_some() {
 			let real_path = vfs.virtual_to_real(virtual_path)?;
-			let song_path_filter = {
-				let mut path_buf = real_path;
-				path_buf.push("%");
-				path_buf.as_path().to_string_lossy().into_owned()
-			};
-			songs
-				.filter(path.like(&song_path_filter))
-				.order(path)
-				.load(&mut connection)?
+			let mut path_buf = real_path;
+			path_buf.push("%");
+			Some(path_buf.as_path().to_string_lossy().into_owned())
 		} else {
-			songs.order(path).load(&mut connection)?
+			None
+		};
+
+		// Fetching one row beyond the cap detects truncation without a second query
+		let real_songs = SongQuery::new(SongQueryOptions {
+			path_like,
+			bpm_min,
+			bpm_max,
+			custom_tag,
+			order_by_path: true,
+			limit: Some(self.max_results_per_query as i64 + 1),
+			..Default::default()
+		})
+		.load(&mut connection)?;
+
+		let virtual_songs = real_songs.into_iter().filter_map(|s| s.virtualize(&vfs));
+		Ok(Truncated::cap(
+			virtual_songs.collect(),
+			self.max_results_per_query,
+		))
+	}
+
+	// Clients use this to estimate download sizes before fetching an entire
+	// directory, so it covers songs in sub-directories too.
+	pub fn get_directory_summary<P>(&self, virtual_path: P) -> Result<DirectorySummary, QueryError>
+	where
+		P: AsRef<Path>,
+	{
+		use diesel::dsl::count_star;
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let mut path_buf = real_path;
+		path_buf.push("%");
+		let path_like = path_buf.as_path().to_string_lossy().into_owned();
+
+		use self::songs::dsl::*;
+		let (song_count, total_size, total_duration): (i64, Option<i64>, Option<i64>) = songs
+			.filter(path.like(&path_like))
+			.select((
+				count_star(),
+				sql::<sql_types::Nullable<sql_types::BigInt>>("SUM(file_size)"),
+				sql::<sql_types::Nullable<sql_types::BigInt>>("SUM(duration)"),
+			))
+			.first(&mut connection)?;
+
+		Ok(DirectorySummary {
+			song_count,
+			total_size: total_size.unwrap_or(0),
+			total_duration: total_duration.unwrap_or(0),
+		})
+	}
+
+	pub fn get_random_albums(
+		&self,
+		count: i64,
+		exclude_compilations: bool,
+	) -> Result<Vec<Directory>, QueryError> {
+		use self::directories::dsl::*;
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+		let mut query = directories.filter(album.is_not_null()).into_boxed();
+		if exclude_compilations {
+			query = query.filter(is_compilation.eq(false));
+		}
+		for mount in vfs.mounts().iter().filter(|m| !m.include_in_discovery) {
+			let mut prefix = mount.source.clone();
+			prefix.push("%");
+			query = query.filter(path.not_like(prefix.to_string_lossy().into_owned()));
+		}
+		let real_directories: Vec<Directory> = query
+			.limit(count)
+			.order((random(), album.asc(), id.asc()))
+			.load(&mut connection)?;
+		let virtual_directories = real_directories
+			.into_iter()
+			.filter_map(|d| d.virtualize(&vfs));
+		Ok(virtual_directories.collect::<Vec<_>>())
+	}
+
+	pub fn get_recent_albums(
+		&self,
+		count: i64,
+		exclude_compilations: bool,
+		sort: RecentSort,
+	) -> Result<Vec<Directory>, QueryError> {
+		use self::directories::dsl::*;
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+		let mut query = directories.filter(album.is_not_null()).into_boxed();
+		if exclude_compilations {
+			query = query.filter(is_compilation.eq(false));
+		}
+		for mount in vfs.mounts().iter().filter(|m| !m.include_in_discovery) {
+			let mut prefix = mount.source.clone();
+			prefix.push("%");
+			query = query.filter(path.not_like(prefix.to_string_lossy().into_owned()));
+		}
+		// Ties on date_added are common after a fresh index; the extra sort keys
+		// keep pagination stable across refreshes. Albums with no year tag sort
+		// last under the released ordering.
+		let real_directories: Vec<Directory> = match sort {
+			RecentSort::Added => query
+				.order((date_added.desc(), album.asc(), id.asc()))
+				.limit(count)
+				.load(&mut connection)?,
+			RecentSort::Released => query
+				.order((year.desc(), date_added.desc(), album.asc(), id.asc()))
+				.limit(count)
+				.load(&mut connection)?,
 		};
+		let virtual_directories = real_directories
+			.into_iter()
+			.filter_map(|d| d.virtualize(&vfs));
+		Ok(virtual_directories.collect::<Vec<_>>())
+	}
 
+	// Song-level counterpart of `get_recent_albums`, so clients can surface
+	// individual tracks added to albums that were indexed long ago
+	pub fn get_recent_songs(&self, count: i64, offset: i64) -> Result<Vec<Song>, QueryError> {
+		use self::songs::dsl::*;
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+		let mut query = songs.into_boxed();
+		for mount in vfs.mounts().iter().filter(|m| !m.include_in_discovery) {
+			let mut prefix = mount.source.clone();
+			prefix.push("%");
+			query = query.filter(path.not_like(prefix.to_string_lossy().into_owned()));
+		}
+		// The extra sort keys keep pagination stable when many songs share a
+		// date, which is the norm after a fresh index
+		let real_songs: Vec<Song> = query
+			.order((date_added.desc(), path.asc(), id.asc()))
+			.offset(offset)
+			.limit(count)
+			.load(&mut connection)?;
 		let virtual_songs = real_songs.into_iter().filter_map(|s| s.virtualize(&vfs));
 		Ok(virtual_songs.collect::<Vec<_>>())
 	}
 
-	pub fn get_random_albums(&self, count: i64) -> Result<Vec<Directory>, QueryError> {
+	// Groups albums by their album-artist tag, falling back to the track
+	// artist only for songs that have none, so compilations with per-track
+	// artists do not flood the listing.
+	pub fn list_album_artists(&self) -> Result<Vec<AlbumArtist>, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+		let album_artists: Vec<AlbumArtist> = diesel::sql_query(
+			r#"
+			SELECT COALESCE(album_artist, artist) AS name,
+			       COUNT(DISTINCT album) AS album_count,
+			       MAX(artwork) AS artwork
+			FROM songs
+			WHERE COALESCE(album_artist, artist) IS NOT NULL
+			GROUP BY COALESCE(album_artist, artist)
+			ORDER BY name COLLATE NOCASE ASC
+		"#,
+		)
+		.load(&mut connection)?;
+		Ok(album_artists
+			.into_iter()
+			.map(|a| a.virtualize(&vfs))
+			.collect())
+	}
+
+	// Aggregates everything we know about one artist. Songs are credited to
+	// their album-artist tag when present, and to their track artist otherwise,
+	// mirroring the grouping used by `list_album_artists`.
+	pub fn get_artist_detail(&self, name: &str) -> Result<ArtistDetail, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+
+		let credited_to_artist = songs::album_artist
+			.eq(name)
+			.or(songs::album_artist.is_null().and(songs::artist.eq(name)));
+
+		let track_count: i64 = songs::table
+			.filter(credited_to_artist)
+			.count()
+			.get_result(&mut connection)?;
+		if track_count == 0 {
+			return Err(QueryError::ArtistNotFound(name.to_owned()));
+		}
+
+		let parents: Vec<String> = songs::table
+			.filter(credited_to_artist)
+			.select(songs::parent)
+			.distinct()
+			.load(&mut connection)?;
+
+		let real_directories: Vec<Directory> = directories::table
+			.filter(directories::path.eq_any(&parents))
+			.filter(directories::album.is_not_null())
+			.order((directories::year.asc(), directories::album.asc()))
+			.load(&mut connection)?;
+		let albums = real_directories
+			.into_iter()
+			.filter_map(|d| d.virtualize(&vfs))
+			.collect();
+
+		Ok(ArtistDetail {
+			name: name.to_owned(),
+			albums,
+			track_count,
+		})
+	}
+
+	// Finds the albums on either side of the given one, among the albums that
+	// share its parent directory, under the requested ordering.
+	pub fn get_adjacent_albums(
+		&self,
+		virtual_path: &Path,
+		sort: AlbumSort,
+	) -> Result<AdjacentAlbums, QueryError> {
+		use self::directories::dsl::*;
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let real_path_string = real_path.to_string_lossy().into_owned();
+
+		let dir_parent: Option<String> = directories
+			.filter(path.eq(&real_path_string))
+			.select(parent)
+			.first(&mut connection)
+			.optional()?
+			.ok_or_else(|| QueryError::DirectoryNotFound(real_path.clone()))?;
+
+		let mut query = directories.filter(album.is_not_null()).into_boxed();
+		query = match dir_parent {
+			Some(p) => query.filter(parent.eq(p)),
+			None => query.filter(parent.is_null()),
+		};
+		let mut siblings: Vec<Directory> = match sort {
+			AlbumSort::Name => query.order((album.asc(), id.asc())).load(&mut connection)?,
+			AlbumSort::Year => query
+				.order((year.asc(), album.asc(), id.asc()))
+				.load(&mut connection)?,
+		};
+
+		let position = siblings
+			.iter()
+			.position(|d| d.path == real_path_string)
+			.ok_or(QueryError::DirectoryNotFound(real_path))?;
+		let next = if position + 1 < siblings.len() {
+			Some(siblings.remove(position + 1))
+		} else {
+			None
+		};
+		let previous = if position > 0 {
+			Some(siblings.remove(position - 1))
+		} else {
+			None
+		};
+
+		Ok(AdjacentAlbums {
+			previous: previous.and_then(|d| d.virtualize(&vfs)),
+			next: next.and_then(|d| d.virtualize(&vfs)),
+		})
+	}
+
+	pub fn get_compilations(&self) -> Result<Vec<Directory>, QueryError> {
 		use self::directories::dsl::*;
 		let vfs = self.vfs_manager.get_vfs()?;
 		let mut connection = self.db.connect()?;
 		let real_directories: Vec<Directory> = directories
-			.filter(album.is_not_null())
-			.limit(count)
-			.order(random())
+			.filter(is_compilation.eq(true))
+			.order(sql::<sql_types::Bool>("path COLLATE NOCASE ASC"))
 			.load(&mut connection)?;
 		let virtual_directories = real_directories
 			.into_iter()
@@ -108,14 +541,23 @@ impl Index {
 		Ok(virtual_directories.collect::<Vec<_>>())
 	}
 
-	pub fn get_recent_albums(&self, count: i64) -> Result<Vec<Directory>, QueryError> {
+	// Albums with no embedded art and no matching sibling cover file end up
+	// with a null artwork column during indexing, so this is a simple scan.
+	pub fn list_directories_without_artwork(
+		&self,
+		offset: i64,
+		limit: i64,
+	) -> Result<Vec<Directory>, QueryError> {
+		const MAX_PAGE_SIZE: i64 = 1000;
 		use self::directories::dsl::*;
 		let vfs = self.vfs_manager.get_vfs()?;
 		let mut connection = self.db.connect()?;
 		let real_directories: Vec<Directory> = directories
 			.filter(album.is_not_null())
-			.order(date_added.desc())
-			.limit(count)
+			.filter(artwork.is_null())
+			.order(sql::<sql_types::Bool>("path COLLATE NOCASE ASC"))
+			.offset(offset)
+			.limit(limit.clamp(0, MAX_PAGE_SIZE))
 			.load(&mut connection)?;
 		let virtual_directories = real_directories
 			.into_iter()
@@ -123,46 +565,162 @@ impl Index {
 		Ok(virtual_directories.collect::<Vec<_>>())
 	}
 
-	pub fn search(&self, query: &str) -> Result<Vec<CollectionFile>, QueryError> {
+	// Groups songs that look like the same recording indexed from multiple
+	// locations, typically because two mounts overlap on disk
+	pub fn list_duplicates(&self) -> Result<Vec<Vec<Song>>, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+		let real_songs: Vec<Song> = songs::table
+			.order(songs::id.asc())
+			.load(&mut connection)?;
+
+		let mut groups: HashMap<_, Vec<Song>> = HashMap::new();
+		for song in real_songs {
+			let Some(key) = song_duplicate_key(
+				song.artist.as_deref(),
+				song.album.as_deref(),
+				song.title.as_deref(),
+				song.track_number,
+			) else {
+				continue;
+			};
+			groups.entry(key).or_default().push(song);
+		}
+
+		let mut duplicates: Vec<Vec<Song>> = groups
+			.into_values()
+			.filter(|group| group.len() > 1)
+			.map(|group| {
+				group
+					.into_iter()
+					.filter_map(|s| s.virtualize(&vfs))
+					.collect::<Vec<_>>()
+			})
+			.filter(|group| group.len() > 1)
+			.collect();
+		duplicates.sort_by(|a, b| a[0].path.cmp(&b[0].path));
+		Ok(duplicates)
+	}
+
+	pub fn search(&self, query: &str, fuzzy: bool) -> Result<Truncated<CollectionFile>, QueryError> {
+		if fuzzy {
+			return self.search_fuzzy(query);
+		}
 		let vfs = self.vfs_manager.get_vfs()?;
 		let mut connection = self.db.connect()?;
-		let like_test = format!("%{}%", query);
+		let normalized_query = utils::normalize_unicode(query);
+		let like_test = format!("%{}%", normalized_query);
+		let parent_matches =
+			|parent: &str| utils::normalize_unicode(parent).contains(&normalized_query);
 		let mut output = Vec::new();
 
 		// Find dirs with matching path and parent not matching
 		{
 			use self::directories::dsl::*;
 			let real_directories: Vec<Directory> = directories
-				.filter(path.like(&like_test))
-				.filter(parent.not_like(&like_test))
+				.filter(search_normalized.like(&like_test))
+				.limit(self.max_results_per_query as i64 + 1)
 				.load(&mut connection)?;
 
 			let virtual_directories = real_directories
 				.into_iter()
+				.filter(|d| !d.parent.as_deref().is_some_and(parent_matches))
 				.filter_map(|d| d.virtualize(&vfs));
 
-			output.extend(virtual_directories.map(CollectionFile::Directory));
+			output.extend(virtual_directories.map(|d| CollectionFile::Directory(Box::new(d))));
 		}
 
 		// Find songs with matching title/album/artist and non-matching parent
 		{
-			use self::songs::dsl::*;
-			let real_songs: Vec<Song> = songs
-				.filter(
-					path.like(&like_test)
-						.or(title.like(&like_test))
-						.or(album.like(&like_test))
-						.or(artist.like(&like_test))
-						.or(album_artist.like(&like_test)),
-				)
-				.filter(parent.not_like(&like_test))
-				.load(&mut connection)?;
+			let real_songs = SongQuery::new(SongQueryOptions {
+				search: Some(query.to_owned()),
+				limit: Some(self.max_results_per_query as i64 + 1),
+				..Default::default()
+			})
+			.load(&mut connection)?;
 
-			let virtual_songs = real_songs.into_iter().filter_map(|d| d.virtualize(&vfs));
+			let virtual_songs = real_songs
+				.into_iter()
+				.filter(|s| !parent_matches(&s.parent))
+				.filter_map(|d| d.virtualize(&vfs));
 
-			output.extend(virtual_songs.map(CollectionFile::Song));
+			output.extend(virtual_songs.map(|s| CollectionFile::Song(Box::new(s))));
 		}
 
+		Ok(Truncated::cap(output, self.max_results_per_query))
+	}
+
+	// Tolerates small typos by comparing query words to indexed words by edit
+	// distance. This scans the whole index in memory, so it is opt-in.
+	fn search_fuzzy(&self, query: &str) -> Result<Truncated<CollectionFile>, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+		let normalized_query = utils::normalize_unicode(query);
+		let query_words: Vec<&str> = normalized_query.split_whitespace().collect();
+		let tolerance = self.fuzzy_search_tolerance;
+		let matches = |text: &str| fuzzy_matches(text, &query_words, tolerance);
+		let parent_matches = |parent: &str| matches(&utils::normalize_unicode(parent));
+		let mut output = Vec::new();
+
+		{
+			use self::directories::dsl::*;
+			let real_directories: Vec<Directory> = directories.load(&mut connection)?;
+
+			let virtual_directories = real_directories
+				.into_iter()
+				.filter(|d| matches(&d.search_normalized))
+				.filter(|d| !d.parent.as_deref().is_some_and(parent_matches))
+				.filter_map(|d| d.virtualize(&vfs));
+
+			output.extend(virtual_directories.map(|d| CollectionFile::Directory(Box::new(d))));
+		}
+
+		{
+			let real_songs: Vec<Song> = songs::table.load(&mut connection)?;
+
+			let virtual_songs = real_songs
+				.into_iter()
+				.filter(|s| matches(&s.search_normalized))
+				.filter(|s| !parent_matches(&s.parent))
+				.filter_map(|s| s.virtualize(&vfs));
+
+			output.extend(virtual_songs.map(|s| CollectionFile::Song(Box::new(s))));
+		}
+
+		Ok(Truncated::cap(output, self.max_results_per_query))
+	}
+
+	pub fn get_songs(&self, virtual_paths: &[PathBuf]) -> Result<Vec<Option<Song>>, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+
+		let real_paths: Vec<Option<String>> = virtual_paths
+			.iter()
+			.map(|p| {
+				vfs.virtual_to_real(p)
+					.ok()
+					.map(|p| p.as_path().to_string_lossy().into_owned())
+			})
+			.collect();
+
+		use self::songs::dsl::*;
+		let real_songs: Vec<Song> = songs
+			.filter(path.eq_any(real_paths.iter().flatten()))
+			.load(&mut connection)?;
+
+		let songs_by_path: HashMap<String, Song> = real_songs
+			.into_iter()
+			.map(|s| (s.path.clone(), s))
+			.collect();
+
+		let output = real_paths
+			.into_iter()
+			.map(|p| {
+				p.and_then(|p| songs_by_path.get(&p).cloned())
+					.and_then(|s| s.virtualize(&vfs))
+			})
+			.collect();
+
 		Ok(output)
 	}
 
@@ -183,4 +741,72 @@ impl Index {
 			None => Err(QueryError::SongNotFound(real_path)),
 		}
 	}
+
+	pub fn get_song_by_id(&self, song_id: i32) -> Result<Song, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+
+		use self::songs::dsl::*;
+		let real_song: Song = songs
+			.filter(id.eq(song_id))
+			.get_result(&mut connection)
+			.optional()?
+			.ok_or(QueryError::SongIdNotFound(song_id))?;
+
+		real_song
+			.virtualize(&vfs)
+			.ok_or(QueryError::SongIdNotFound(song_id))
+	}
+
+	pub fn get_directory_by_id(&self, directory_id: i32) -> Result<Directory, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+
+		use self::directories::dsl::*;
+		let real_directory: Directory = directories
+			.filter(id.eq(directory_id))
+			.get_result(&mut connection)
+			.optional()?
+			.ok_or(QueryError::DirectoryIdNotFound(directory_id))?;
+
+		real_directory
+			.virtualize(&vfs)
+			.ok_or(QueryError::DirectoryIdNotFound(directory_id))
+	}
+}
+
+// Every query word must match some indexed word, either as a substring or
+// within the edit distance tolerance
+fn fuzzy_matches(text: &str, query_words: &[&str], tolerance: usize) -> bool {
+	let text_words: Vec<&str> = text
+		.split(|c: char| !c.is_alphanumeric())
+		.filter(|w| !w.is_empty())
+		.collect();
+	query_words.iter().all(|query_word| {
+		text_words.iter().any(|text_word| {
+			text_word.contains(query_word) || edit_distance(text_word, query_word) <= tolerance
+		})
+	})
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+	let a: Vec<char> = a.chars().collect();
+	let b: Vec<char> = b.chars().collect();
+	let mut distances: Vec<usize> = (0..=b.len()).collect();
+	for (i, a_char) in a.iter().enumerate() {
+		let mut previous_diagonal = distances[0];
+		distances[0] = i + 1;
+		for (j, b_char) in b.iter().enumerate() {
+			let substitution = if a_char == b_char {
+				previous_diagonal
+			} else {
+				previous_diagonal + 1
+			};
+			previous_diagonal = distances[j + 1];
+			distances[j + 1] = substitution
+				.min(distances[j] + 1) // Insertion
+				.min(previous_diagonal + 1); // Deletion
+		}
+	}
+	distances[b.len()]
 }
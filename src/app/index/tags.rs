@@ -0,0 +1,74 @@
+use diesel::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::app::index::{Index, QueryError};
+use crate::db::{song_tags, songs};
+
+// Server-side key/value annotations attached to songs (ratings, moods, custom
+// tags). These live only in the database and never touch the files themselves,
+// so they survive reindexes and work on read-only collections.
+impl Index {
+	pub fn set_custom_tag(
+		&self,
+		virtual_path: &Path,
+		key: &str,
+		value: &str,
+	) -> Result<(), QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let real_path_string = real_path.as_path().to_string_lossy().into_owned();
+		let mut connection = self.db.connect()?;
+
+		// Only indexed songs can be annotated
+		let song_count: i64 = songs::table
+			.filter(songs::path.eq(&real_path_string))
+			.count()
+			.get_result(&mut connection)?;
+		if song_count == 0 {
+			return Err(QueryError::SongNotFound(virtual_path.to_owned()));
+		}
+
+		// The (path, key) pair resolves conflicts with REPLACE, so this covers
+		// both new and updated values
+		diesel::insert_into(song_tags::table)
+			.values((
+				song_tags::path.eq(real_path_string),
+				song_tags::key.eq(key),
+				song_tags::value.eq(value),
+			))
+			.execute(&mut connection)?;
+		Ok(())
+	}
+
+	pub fn get_custom_tags(
+		&self,
+		virtual_path: &Path,
+	) -> Result<HashMap<String, String>, QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let real_path_string = real_path.as_path().to_string_lossy().into_owned();
+		let mut connection = self.db.connect()?;
+
+		let tags: Vec<(String, String)> = song_tags::table
+			.filter(song_tags::path.eq(&real_path_string))
+			.select((song_tags::key, song_tags::value))
+			.load(&mut connection)?;
+		Ok(tags.into_iter().collect())
+	}
+
+	pub fn remove_custom_tag(&self, virtual_path: &Path, key: &str) -> Result<(), QueryError> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let real_path_string = real_path.as_path().to_string_lossy().into_owned();
+		let mut connection = self.db.connect()?;
+
+		diesel::delete(
+			song_tags::table
+				.filter(song_tags::path.eq(&real_path_string))
+				.filter(song_tags::key.eq(key)),
+		)
+		.execute(&mut connection)?;
+		Ok(())
+	}
+}
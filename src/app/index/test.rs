@@ -1,10 +1,11 @@
 use diesel::prelude::*;
+use id3::TagLike;
 use std::default::Default;
 use std::path::{Path, PathBuf};
 
 use super::*;
-use crate::app::test;
-use crate::db::{directories, songs};
+use crate::app::{settings, test, thumbnail};
+use crate::db::{directories, index_checkpoint, songs};
 use crate::test_name;
 
 const TEST_MOUNT_NAME: &str = "root";
@@ -66,6 +67,495 @@ fn update_removes_missing_content() {
 	}
 }
 
+#[test]
+fn interrupted_index_resumes_from_checkpoint() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	// Simulate a scan that was interrupted after completing the Hunted
+	// directory: its checkpoint row exists before the next update runs
+	let hunted_directory: PathBuf = ["test-data", "small-collection", "Khemmis", "Hunted"]
+		.iter()
+		.collect();
+	{
+		let mut connection = ctx.db.connect().unwrap();
+		diesel::insert_into(index_checkpoint::table)
+			.values(index_checkpoint::path.eq(hunted_directory.to_string_lossy()))
+			.execute(&mut connection)
+			.unwrap();
+	}
+
+	// The resumed update walks past the checkpointed directory without
+	// re-parsing its files, so its songs never make it into the index
+	ctx.index.update().unwrap();
+	{
+		let mut connection = ctx.db.connect().unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&mut connection).unwrap();
+		assert_eq!(all_songs.len(), 8);
+		assert!(all_songs
+			.iter()
+			.all(|song| !song.path.contains("Candlelight")));
+	}
+
+	// Completing a scan clears the checkpoint
+	{
+		let mut connection = ctx.db.connect().unwrap();
+		let checkpoints: Vec<String> = index_checkpoint::table
+			.select(index_checkpoint::path)
+			.load(&mut connection)
+			.unwrap();
+		assert_eq!(checkpoints, Vec::<String>::new());
+	}
+
+	// A forced full update re-parses everything
+	ctx.index.update_force_full().unwrap();
+	{
+		let mut connection = ctx.db.connect().unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&mut connection).unwrap();
+		assert_eq!(all_songs.len(), 13);
+	}
+}
+
+#[test]
+fn prune_orphans_removes_rows_from_deleted_mounts() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.mount("extra", "test-data/artwork")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let count_rows = |ctx: &test::Context| {
+		let mut connection = ctx.db.connect().unwrap();
+		let all_directories: Vec<Directory> = directories::table.load(&mut connection).unwrap();
+		let all_songs: Vec<Song> = songs::table.load(&mut connection).unwrap();
+		(all_directories.len(), all_songs.len())
+	};
+
+	let (num_directories, num_songs) = count_rows(&ctx);
+	assert!(num_directories > 6);
+	assert!(num_songs > 13);
+
+	ctx.vfs_manager
+		.set_mount_dirs(&[vfs::MountDir {
+			name: TEST_MOUNT_NAME.to_owned(),
+			source: "test-data/small-collection".to_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
+		}])
+		.unwrap();
+
+	let num_removed = ctx.index.prune_orphans().unwrap();
+	assert_eq!(num_removed, num_directories + num_songs - 6 - 13);
+	assert_eq!(count_rows(&ctx), (6, 13));
+}
+
+#[test]
+fn update_tags_changes_file_and_song() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let song_virtual_path: PathBuf = [
+		TEST_MOUNT_NAME,
+		"Khemmis",
+		"Hunted",
+		"01 - Above The Water.mp3",
+	]
+	.iter()
+	.collect();
+	let song_real_path = test_collection_dir
+		.join("Khemmis")
+		.join("Hunted")
+		.join("01 - Above The Water.mp3");
+
+	let changes = metadata::TagChanges {
+		album: Some("Hunted (Remastered)".to_owned()),
+		..Default::default()
+	};
+	metadata::update_tags(&song_real_path, &changes).unwrap();
+	ctx.index.refresh_song(&song_virtual_path).unwrap();
+
+	let tags = metadata::read(&song_real_path, settings::TagParsing::Lenient)
+		.unwrap()
+		.unwrap();
+	assert_eq!(tags.album, Some("Hunted (Remastered)".to_owned()));
+	assert_eq!(tags.title, Some("Above The Water".to_owned()));
+
+	let song = ctx.index.get_song(&song_virtual_path).unwrap();
+	assert_eq!(song.album, Some("Hunted (Remastered)".to_owned()));
+	assert_eq!(song.title, Some("Above The Water".to_owned()));
+}
+
+#[test]
+fn refresh_song_inserts_new_files_and_removes_vanished_ones() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let hunted_dir = test_collection_dir.join("Khemmis").join("Hunted");
+	let bonus_real_path = hunted_dir.join("99 - Bonus.mp3");
+	std::fs::copy(hunted_dir.join("01 - Above The Water.mp3"), &bonus_real_path).unwrap();
+
+	let bonus_virtual_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "99 - Bonus.mp3"]
+		.iter()
+		.collect();
+	assert!(ctx.index.get_song(&bonus_virtual_path).is_err());
+
+	ctx.index.refresh_song(&bonus_virtual_path).unwrap();
+	let song = ctx.index.get_song(&bonus_virtual_path).unwrap();
+	assert_eq!(song.title, Some("Above The Water".to_owned()));
+	assert_eq!(song.album, Some("Hunted".to_owned()));
+
+	std::fs::remove_file(&bonus_real_path).unwrap();
+	ctx.index.refresh_song(&bonus_virtual_path).unwrap();
+	assert!(ctx.index.get_song(&bonus_virtual_path).is_err());
+}
+
+#[test]
+fn reindexing_preserves_song_and_directory_ids() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let song_virtual_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let song_id = ctx.index.get_song(&song_virtual_path).unwrap().id;
+
+	let directory_ids: Vec<i32> = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default())
+		.unwrap()
+		.into_iter()
+		.filter_map(|f| match f {
+			CollectionFile::Directory(d) => Some(d.id),
+			CollectionFile::Song(_) => None,
+		})
+		.collect();
+	assert!(!directory_ids.is_empty());
+
+	ctx.index.update().unwrap();
+
+	assert_eq!(ctx.index.get_song(&song_virtual_path).unwrap().id, song_id);
+	let new_directory_ids: Vec<i32> = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default())
+		.unwrap()
+		.into_iter()
+		.filter_map(|f| match f {
+			CollectionFile::Directory(d) => Some(d.id),
+			CollectionFile::Song(_) => None,
+		})
+		.collect();
+	assert_eq!(new_directory_ids, directory_ids);
+}
+
+#[test]
+fn songs_and_directories_can_be_looked_up_by_id() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let song_virtual_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let song = ctx.index.get_song(&song_virtual_path).unwrap();
+	assert_eq!(ctx.index.get_song_by_id(song.id).unwrap(), song);
+	assert!(ctx.index.get_song_by_id(-1).is_err());
+
+	let files = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default())
+		.unwrap();
+	let Some(CollectionFile::Directory(directory)) = files.into_iter().next() else {
+		panic!("Expected a directory");
+	};
+	let fetched = ctx.index.get_directory_by_id(directory.id).unwrap();
+	assert_eq!(fetched, *directory);
+	assert!(ctx.index.get_directory_by_id(-1).is_err());
+}
+
+#[test]
+fn verify_thumbnail_cache_removes_stale_entries() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let options = thumbnail::Options::default();
+	let valid_artwork: PathBuf = [
+		"test-data",
+		"small-collection",
+		"Khemmis",
+		"Hunted",
+		"Folder.jpg",
+	]
+	.iter()
+	.collect();
+	let valid_thumbnail = ctx
+		.thumbnail_manager
+		.get_thumbnail(&valid_artwork, &options)
+		.unwrap();
+
+	// Artwork from outside the mounted collection is orphaned as far as the
+	// index is concerned
+	let orphan_artwork: PathBuf = ["test-data", "artwork", "Folder.png"].iter().collect();
+	let orphan_thumbnail = ctx
+		.thumbnail_manager
+		.get_thumbnail(&orphan_artwork, &options)
+		.unwrap();
+
+	let corrupt_artwork: PathBuf = [
+		"test-data",
+		"small-collection",
+		"Tobokegao",
+		"Picnic",
+		"Folder.png",
+	]
+	.iter()
+	.collect();
+	let corrupt_thumbnail = ctx
+		.thumbnail_manager
+		.get_thumbnail(&corrupt_artwork, &options)
+		.unwrap();
+	std::fs::write(&corrupt_thumbnail, b"not an image").unwrap();
+
+	let report = ctx.index.verify_thumbnail_cache().unwrap();
+	assert_eq!(report.removed, 2);
+	assert_eq!(report.kept, 1);
+	assert!(valid_thumbnail.exists());
+	assert!(!orphan_thumbnail.exists());
+	assert!(!corrupt_thumbnail.exists());
+}
+
+#[test]
+fn mount_max_depth_limits_indexing() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let music_dir = builder.test_directory.join("music");
+	let album_dir = music_dir.join("Album");
+	let nested_dir = album_dir.join("Nested");
+	std::fs::create_dir_all(&nested_dir).unwrap();
+	std::fs::copy("test-data/formats/sample.mp3", music_dir.join("shallow.mp3")).unwrap();
+	std::fs::copy("test-data/formats/sample.mp3", album_dir.join("track.mp3")).unwrap();
+	std::fs::copy("test-data/formats/sample.mp3", nested_dir.join("deep.mp3")).unwrap();
+
+	let ctx = builder
+		.mount_with_max_depth("limited", music_dir.to_str().unwrap(), 2)
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let shallow: PathBuf = ["limited", "shallow.mp3"].iter().collect();
+	let track: PathBuf = ["limited", "Album", "track.mp3"].iter().collect();
+	let deep: PathBuf = ["limited", "Album", "Nested", "deep.mp3"].iter().collect();
+	assert!(ctx.index.get_song(&shallow).is_ok());
+	assert!(ctx.index.get_song(&track).is_ok());
+	assert!(ctx.index.get_song(&deep).is_err());
+}
+
+#[test]
+fn browse_grouping_controls_entry_order() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+
+	// Add loose songs next to the `Picnic` albums so that `Tobokegao` holds a
+	// mix of directories and files
+	let tobokegao_dir = test_collection_dir.join("Tobokegao");
+	let source_song = tobokegao_dir
+		.join("Picnic")
+		.join("07 - なぜ (Why).mp3");
+	std::fs::copy(&source_song, tobokegao_dir.join("Aaa.mp3")).unwrap();
+	std::fs::copy(&source_song, tobokegao_dir.join("Zzz.mp3")).unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
+	let is_directory = |f: &CollectionFile| matches!(f, CollectionFile::Directory(_));
+
+	let files = ctx
+		.index
+		.browse(&path, BrowseGrouping::FoldersFirst)
+		.unwrap();
+	assert_eq!(files.len(), 4);
+	assert!(files[..2].iter().all(is_directory));
+	assert!(!files[2..].iter().any(is_directory));
+
+	let files = ctx.index.browse(&path, BrowseGrouping::FilesFirst).unwrap();
+	assert!(!files[..2].iter().any(is_directory));
+	assert!(files[2..].iter().all(is_directory));
+
+	let files = ctx.index.browse(&path, BrowseGrouping::Mixed).unwrap();
+	let names: Vec<&str> = files.iter().map(|f| f.name()).collect();
+	assert_eq!(
+		names,
+		vec!["Aaa.mp3", "Picnic", "Picnic (Remixes)", "Zzz.mp3"]
+	);
+}
+
+#[test]
+fn browse_recursive_depth_one_matches_browse() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let tree = ctx
+		.index
+		.browse_recursive(Path::new(TEST_MOUNT_NAME), 1)
+		.unwrap();
+	assert!(!tree.truncated);
+
+	let browsed = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default())
+		.unwrap();
+	assert_eq!(tree.items.len(), browsed.len());
+	for (entry, file) in tree.items.iter().zip(&browsed) {
+		match (entry, file) {
+			(
+				TreeEntry::Directory {
+					directory,
+					children,
+				},
+				CollectionFile::Directory(d),
+			) => {
+				assert_eq!(directory, d);
+				assert!(children.is_empty());
+			}
+			(TreeEntry::Song(song), CollectionFile::Song(s)) => assert_eq!(song, s),
+			_ => panic!("Mismatched entry kinds"),
+		}
+	}
+}
+
+#[test]
+fn browse_recursive_includes_nested_contents() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let tree = ctx
+		.index
+		.browse_recursive(Path::new(TEST_MOUNT_NAME), 3)
+		.unwrap();
+	assert!(!tree.truncated);
+
+	let khemmis = tree
+		.items
+		.iter()
+		.find_map(|e| match e {
+			TreeEntry::Directory {
+				directory,
+				children,
+			} if directory.path.ends_with("Khemmis") => Some(children),
+			_ => None,
+		})
+		.unwrap();
+	let hunted = khemmis
+		.iter()
+		.find_map(|e| match e {
+			TreeEntry::Directory {
+				directory,
+				children,
+			} if directory.path.ends_with("Hunted") => Some(children),
+			_ => None,
+		})
+		.unwrap();
+	let song_count = hunted
+		.iter()
+		.filter(|e| matches!(e, TreeEntry::Song(_)))
+		.count();
+	assert_eq!(song_count, 5);
+
+	// The node budget bounds the total size of the response
+	let capped_tree = ctx
+		.index
+		.clone()
+		.with_max_results_per_query(3)
+		.browse_recursive(Path::new(TEST_MOUNT_NAME), 3)
+		.unwrap();
+	assert!(capped_tree.truncated);
+}
+
+#[test]
+fn index_status_is_persisted_across_reconstruction() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	assert_eq!(ctx.index.get_index_status().unwrap(), None);
+
+	ctx.index.update().unwrap();
+
+	let status = ctx.index.get_index_status().unwrap().unwrap();
+	assert_eq!(status.file_count, 13);
+	assert_eq!(status.error_count, 0);
+	assert!(status.last_index_start > 0);
+	assert!(status.last_index_end >= status.last_index_start);
+
+	let rebuilt_index = Index::new(
+		ctx.db.clone(),
+		ctx.vfs_manager.clone(),
+		ctx.settings_manager.clone(),
+		ctx.thumbnail_manager.clone(),
+		false,
+	);
+	assert_eq!(rebuilt_index.get_index_status().unwrap(), Some(status));
+}
+
 #[test]
 fn can_browse_top_level() {
 	let ctx = test::ContextBuilder::new(test_name!())
@@ -74,7 +564,7 @@ fn can_browse_top_level() {
 	ctx.index.update().unwrap();
 
 	let root_path = Path::new(TEST_MOUNT_NAME);
-	let files = ctx.index.browse(Path::new("")).unwrap();
+	let files = ctx.index.browse(Path::new(""), BrowseGrouping::default()).unwrap();
 	assert_eq!(files.len(), 1);
 	match files[0] {
 		CollectionFile::Directory(ref d) => assert_eq!(d.path, root_path.to_str().unwrap()),
@@ -90,74 +580,1024 @@ fn can_browse_directory() {
 	let ctx = test::ContextBuilder::new(test_name!())
 		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
 		.build();
-	ctx.index.update().unwrap();
+	ctx.index.update().unwrap();
+
+	let files = ctx.index.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default()).unwrap();
+
+	assert_eq!(files.len(), 2);
+	match files[0] {
+		CollectionFile::Directory(ref d) => assert_eq!(d.path, khemmis_path.to_str().unwrap()),
+		_ => panic!("Expected directory"),
+	}
+
+	match files[1] {
+		CollectionFile::Directory(ref d) => assert_eq!(d.path, tobokegao_path.to_str().unwrap()),
+		_ => panic!("Expected directory"),
+	}
+}
+
+#[test]
+fn browse_sorts_accented_names_next_to_unaccented_forms() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let collection_dir = builder.test_directory.join("collection");
+	for name in ["Avocado", "Étude", "Zebra"] {
+		let artist_dir = collection_dir.join(name);
+		std::fs::create_dir_all(&artist_dir).unwrap();
+		std::fs::copy(
+			"test-data/small-collection/Khemmis/Hunted/01 - Above The Water.mp3",
+			artist_dir.join("song.mp3"),
+		)
+		.unwrap();
+	}
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, collection_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	let files = ctx.index.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default()).unwrap();
+	let names: Vec<String> = files
+		.iter()
+		.filter_map(|f| match f {
+			CollectionFile::Directory(d) => Path::new(&d.path)
+				.file_name()
+				.map(|n| n.to_string_lossy().into_owned()),
+			_ => None,
+		})
+		.collect();
+
+	// "É" sorts between "A" and "Z" instead of after all ASCII names
+	assert_eq!(names, vec!["Avocado", "Étude", "Zebra"]);
+}
+
+#[test]
+fn infers_missing_tags_from_path_when_enabled() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let collection_dir = builder.test_directory.join("collection");
+	let album_dir = collection_dir.join("Some Artist").join("Some Album");
+	std::fs::create_dir_all(&album_dir).unwrap();
+
+	let untagged_path = album_dir.join("untagged.mp3");
+	std::fs::copy("test-data/formats/sample.mp3", &untagged_path).unwrap();
+	id3::Tag::new()
+		.write_to_path(&untagged_path, id3::Version::Id3v24)
+		.unwrap();
+
+	std::fs::copy(
+		"test-data/small-collection/Khemmis/Hunted/01 - Above The Water.mp3",
+		album_dir.join("tagged.mp3"),
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, collection_dir.to_str().unwrap())
+		.build();
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			infer_tags_from_path: Some(true),
+			..Default::default()
+		})
+		.unwrap();
+	ctx.index.update().unwrap();
+
+	let untagged: PathBuf = [TEST_MOUNT_NAME, "Some Artist", "Some Album", "untagged.mp3"]
+		.iter()
+		.collect();
+	let song = ctx.index.get_song(&untagged).unwrap();
+	assert_eq!(song.artist.as_deref(), Some("Some Artist"));
+	assert_eq!(song.album.as_deref(), Some("Some Album"));
+	assert!(song.tags_inferred);
+
+	let tagged: PathBuf = [TEST_MOUNT_NAME, "Some Artist", "Some Album", "tagged.mp3"]
+		.iter()
+		.collect();
+	let song = ctx.index.get_song(&tagged).unwrap();
+	assert_eq!(song.artist.as_deref(), Some("Khemmis"));
+	assert_eq!(song.album.as_deref(), Some("Hunted"));
+	assert!(!song.tags_inferred);
+}
+
+#[test]
+fn dry_run_parses_metadata_without_writing() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let entries = ctx.index.dry_run(&path).unwrap();
+	assert_eq!(entries.len(), 5);
+	assert!(entries
+		.iter()
+		.all(|e| e.tags.artist.as_deref() == Some("Khemmis")));
+	assert!(entries
+		.iter()
+		.any(|e| e.tags.title.as_deref() == Some("Above The Water")));
+
+	let mut connection = ctx.db.connect().unwrap();
+	let all_songs: Vec<Song> = songs::table.load(&mut connection).unwrap();
+	assert!(all_songs.is_empty());
+}
+
+#[test]
+fn browse_accepts_windows_style_paths() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let files = ctx.index.browse(Path::new(r"root\Khemmis\Hunted"), BrowseGrouping::default()).unwrap();
+	assert_eq!(files.len(), 5);
+
+	// Paths served to clients always use forward slashes
+	for file in files {
+		match file {
+			CollectionFile::Song(s) => assert!(!s.path.contains('\\')),
+			CollectionFile::Directory(d) => assert!(!d.path.contains('\\')),
+		}
+	}
+}
+
+#[test]
+fn can_summarize_directory_recursively() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	// All of Khemmis' songs live in the `Hunted` sub-directory
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let summary = ctx.index.get_directory_summary(&path).unwrap();
+
+	let expected_size: i64 = std::fs::read_dir("test-data/small-collection/Khemmis/Hunted")
+		.unwrap()
+		.filter_map(|e| e.ok())
+		.filter(|e| e.path().extension().is_some_and(|x| x == "mp3"))
+		.map(|e| e.metadata().unwrap().len() as i64)
+		.sum();
+
+	let expected_duration: i64 = ctx
+		.index
+		.flatten(&path, None, None, None)
+		.unwrap()
+		.items
+		.iter()
+		.filter_map(|s| s.duration)
+		.map(|d| d as i64)
+		.sum();
+
+	assert_eq!(summary.song_count, 5);
+	assert_eq!(summary.total_size, expected_size);
+	assert_eq!(summary.total_duration, expected_duration);
+
+	let root_summary = ctx.index.get_directory_summary(Path::new(TEST_MOUNT_NAME)).unwrap();
+	assert_eq!(root_summary.song_count, 13);
+}
+
+#[test]
+fn search_ignores_accents_and_case() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+
+	let song_real_path = test_collection_dir
+		.join("Khemmis")
+		.join("Hunted")
+		.join("01 - Above The Water.mp3");
+	let changes = metadata::TagChanges {
+		artist: Some("Björk".to_owned()),
+		..Default::default()
+	};
+	metadata::update_tags(&song_real_path, &changes).unwrap();
+
+	ctx.index.update().unwrap();
+
+	let results = ctx.index.search("bjork", false).unwrap();
+	let found = results.items.iter().any(|f| match f {
+		CollectionFile::Song(s) => s.artist.as_deref() == Some("Björk"),
+		_ => false,
+	});
+	assert!(found);
+}
+
+#[test]
+fn fuzzy_search_tolerates_typos() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let exact = ctx.index.search("picnik", false).unwrap();
+	assert!(exact.items.is_empty());
+
+	let fuzzy = ctx.index.search("picnik", true).unwrap();
+	let found = fuzzy.items.iter().any(|f| match f {
+		CollectionFile::Directory(d) => d.path.ends_with("Picnic"),
+		_ => false,
+	});
+	assert!(found);
+}
+
+#[test]
+fn custom_tags_can_be_set_and_removed() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let song: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	assert!(ctx.index.get_custom_tags(&song).unwrap().is_empty());
+
+	ctx.index.set_custom_tag(&song, "mood", "somber").unwrap();
+	ctx.index.set_custom_tag(&song, "rating", "5").unwrap();
+	let tags = ctx.index.get_custom_tags(&song).unwrap();
+	assert_eq!(tags.len(), 2);
+	assert_eq!(tags.get("mood"), Some(&"somber".to_owned()));
+
+	// Setting an existing key replaces its value
+	ctx.index.set_custom_tag(&song, "rating", "4").unwrap();
+	let tags = ctx.index.get_custom_tags(&song).unwrap();
+	assert_eq!(tags.len(), 2);
+	assert_eq!(tags.get("rating"), Some(&"4".to_owned()));
+
+	ctx.index.remove_custom_tag(&song, "mood").unwrap();
+	let tags = ctx.index.get_custom_tags(&song).unwrap();
+	assert_eq!(tags.len(), 1);
+
+	let missing_song: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "nope.mp3"]
+		.iter()
+		.collect();
+	let error = ctx
+		.index
+		.set_custom_tag(&missing_song, "mood", "somber")
+		.unwrap_err();
+	assert!(matches!(error, QueryError::SongNotFound(_)));
+}
+
+#[test]
+fn can_filter_flatten_by_custom_tag() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let song: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	ctx.index.set_custom_tag(&song, "mood", "somber").unwrap();
+
+	let filter = |key: &str, value: Option<&str>| CustomTagFilter {
+		key: key.to_owned(),
+		value: value.map(str::to_owned),
+	};
+
+	let songs = ctx
+		.index
+		.flatten(Path::new(TEST_MOUNT_NAME), None, None, Some(filter("mood", None)))
+		.unwrap();
+	assert_eq!(songs.items.len(), 1);
+	assert!(songs.items[0].path.ends_with("02 - Candlelight.mp3"));
+
+	let songs = ctx
+		.index
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			None,
+			None,
+			Some(filter("mood", Some("somber"))),
+		)
+		.unwrap();
+	assert_eq!(songs.items.len(), 1);
+
+	let songs = ctx
+		.index
+		.flatten(
+			Path::new(TEST_MOUNT_NAME),
+			None,
+			None,
+			Some(filter("mood", Some("upbeat"))),
+		)
+		.unwrap();
+	assert_eq!(songs.items.len(), 0);
+
+	let songs = ctx
+		.index
+		.flatten(Path::new(TEST_MOUNT_NAME), None, None, Some(filter("rating", None)))
+		.unwrap();
+	assert_eq!(songs.items.len(), 0);
+}
+
+#[test]
+fn partially_synced_files_are_skipped_until_complete() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let album_dir = builder.test_directory.join("Syncing");
+	std::fs::create_dir_all(&album_dir).unwrap();
+	let song_path = album_dir.join("song.mp3");
+	std::fs::write(&song_path, []).unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, album_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	let files = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default())
+		.unwrap();
+	assert!(files.is_empty());
+
+	// Once the sync completes, the next update indexes the file normally
+	std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+	ctx.index.update().unwrap();
+
+	let files = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default())
+		.unwrap();
+	assert_eq!(files.len(), 1);
+	match &files[0] {
+		CollectionFile::Song(s) => assert_eq!(s.title.as_deref(), Some("TEST TITLE")),
+		CollectionFile::Directory(_) => panic!("Expected a song"),
+	}
+}
+
+#[test]
+fn partial_files_can_be_indexed_as_pending() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let album_dir = builder.test_directory.join("Syncing");
+	std::fs::create_dir_all(&album_dir).unwrap();
+	std::fs::write(album_dir.join("song.mp3"), []).unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, album_dir.to_str().unwrap())
+		.build();
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			partial_file_policy: Some(settings::PartialFilePolicy::IndexAsPending),
+			..Default::default()
+		})
+		.unwrap();
+	ctx.index.update().unwrap();
+
+	// The file is listed by path, without any parsed tags
+	let files = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default())
+		.unwrap();
+	assert_eq!(files.len(), 1);
+	match &files[0] {
+		CollectionFile::Song(s) => {
+			assert!(s.path.ends_with("song.mp3"));
+			assert_eq!(s.title, None);
+		}
+		CollectionFile::Directory(_) => panic!("Expected a song"),
+	}
+}
+
+#[test]
+fn audiobook_chapters_are_cached_in_the_database() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let book_dir = builder.test_directory.join("Audiobooks");
+	std::fs::create_dir_all(&book_dir).unwrap();
+	let book_path = book_dir.join("book.m4b");
+	std::fs::copy("test-data/formats/sample.m4b", &book_path).unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, book_dir.to_str().unwrap())
+		.build();
+
+	let virtual_path: PathBuf = [TEST_MOUNT_NAME, "book.m4b"].iter().collect();
+	let chapters = ctx.index.get_chapters(&virtual_path).unwrap();
+	assert_eq!(chapters.len(), 2);
+	assert_eq!(chapters[0].title, "Chapter 1");
+	assert_eq!(chapters[0].start_ms, 0);
+	assert_eq!(chapters[1].title, "Chapter 2");
+	assert_eq!(chapters[1].start_ms, 1500);
+
+	// Subsequent requests are served from the database rather than the file
+	std::fs::remove_file(&book_path).unwrap();
+	let chapters = ctx.index.get_chapters(&virtual_path).unwrap();
+	assert_eq!(chapters.len(), 2);
+}
+
+#[test]
+fn can_flatten_root() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let songs = ctx.index.flatten(Path::new(TEST_MOUNT_NAME), None, None, None).unwrap();
+	assert_eq!(songs.items.len(), 13);
+	assert_eq!(songs.items[0].title, Some("Above The Water".to_owned()));
+}
+
+#[test]
+fn can_flatten_directory() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
+	let songs = ctx.index.flatten(path, None, None, None).unwrap();
+	assert_eq!(songs.items.len(), 8);
+}
+
+#[test]
+fn can_flatten_directory_with_shared_prefix() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic"].iter().collect(); // Prefix of '(Picnic Remixes)'
+	let songs = ctx.index.flatten(path, None, None, None).unwrap();
+	assert_eq!(songs.items.len(), 7);
+}
+
+#[test]
+fn oversized_result_sets_are_truncated_and_flagged() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let capped_index = ctx.index.clone().with_max_results_per_query(5);
+	let songs = capped_index
+		.flatten(Path::new(TEST_MOUNT_NAME), None, None, None)
+		.unwrap();
+	assert_eq!(songs.items.len(), 5);
+	assert!(songs.truncated);
+
+	let songs = ctx
+		.index
+		.flatten(Path::new(TEST_MOUNT_NAME), None, None, None)
+		.unwrap();
+	assert_eq!(songs.items.len(), 13);
+	assert!(!songs.truncated);
+
+	let results = capped_index.search("picnic", false).unwrap();
+	assert!(!results.truncated);
+}
+
+#[test]
+fn song_query_composes_filters() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let mut connection = ctx.db.connect().unwrap();
+
+	let by_path = SongQuery::new(SongQueryOptions {
+		path_like: Some("%Tobokegao%".to_owned()),
+		order_by_path: true,
+		..Default::default()
+	})
+	.load(&mut connection)
+	.unwrap();
+	assert_eq!(by_path.len(), 8);
+	let mut sorted = by_path.clone();
+	sorted.sort_by(|a, b| a.path.cmp(&b.path));
+	assert_eq!(by_path, sorted);
+
+	let by_artist = SongQuery::new(SongQueryOptions {
+		artist: Some("Khemmis".to_owned()),
+		limit: Some(3),
+		..Default::default()
+	})
+	.load(&mut connection)
+	.unwrap();
+	assert_eq!(by_artist.len(), 3);
+	assert!(by_artist
+		.iter()
+		.all(|s| s.artist == Some("Khemmis".to_owned())));
+
+	let by_search = SongQuery::new(SongQueryOptions {
+		search: Some("Above The Water".to_owned()),
+		..Default::default()
+	})
+	.load(&mut connection)
+	.unwrap();
+	assert_eq!(by_search.len(), 1);
+	assert_eq!(by_search[0].title, Some("Above The Water".to_owned()));
+
+	diesel::update(songs::table.filter(songs::artist.eq("Khemmis")))
+		.set(songs::bpm.eq(120))
+		.execute(&mut connection)
+		.unwrap();
+	diesel::update(songs::table.filter(songs::artist.eq("Tobokegao")))
+		.set(songs::bpm.eq(180))
+		.execute(&mut connection)
+		.unwrap();
+	let by_bpm = SongQuery::new(SongQueryOptions {
+		bpm_min: Some(100),
+		bpm_max: Some(150),
+		..Default::default()
+	})
+	.load(&mut connection)
+	.unwrap();
+	assert_eq!(by_bpm.len(), 5);
+	assert!(by_bpm.iter().all(|s| s.bpm == Some(120)));
+
+	let by_year = SongQuery::new(SongQueryOptions {
+		year: Some(2016),
+		..Default::default()
+	})
+	.load(&mut connection)
+	.unwrap();
+	assert!(!by_year.is_empty());
+	assert!(by_year.iter().all(|s| s.year == Some(2016)));
+}
+
+#[test]
+fn can_get_random_albums() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let albums = ctx.index.get_random_albums(1, false).unwrap();
+	assert_eq!(albums.len(), 1);
+}
+
+#[test]
+fn can_get_recent_albums() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+	let albums = ctx.index.get_recent_albums(2, false, RecentSort::Added).unwrap();
+	assert_eq!(albums.len(), 2);
+	assert!(albums[0].date_added >= albums[1].date_added);
+}
+
+#[test]
+fn recent_songs_are_ordered_newest_first() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	// Dates further in the future than any file creation time, so these three
+	// songs outrank the rest of the collection
+	let mut connection = ctx.db.connect().unwrap();
+	for (file_name, date) in [
+		("02 - Candlelight.mp3", 2_000_000_003),
+		("05 - Hunted.mp3", 2_000_000_002),
+		("01 - Above The Water.mp3", 2_000_000_001),
+	] {
+		diesel::update(songs::table.filter(songs::path.like(format!("%{}", file_name))))
+			.set(songs::date_added.eq(date))
+			.execute(&mut connection)
+			.unwrap();
+	}
+
+	let recent = ctx.index.get_recent_songs(3, 0).unwrap();
+	let titles: Vec<Option<String>> = recent.iter().map(|s| s.title.clone()).collect();
+	assert_eq!(
+		titles,
+		vec![
+			Some("Candlelight".to_owned()),
+			Some("Hunted".to_owned()),
+			Some("Above The Water".to_owned())
+		]
+	);
+
+	let page = ctx.index.get_recent_songs(2, 1).unwrap();
+	assert_eq!(page[0].title, Some("Hunted".to_owned()));
+	assert_eq!(page[1].title, Some("Above The Water".to_owned()));
+}
+
+#[test]
+fn recent_songs_can_differ_from_recent_albums() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	// The most recently added album is Hunted, but the most recently added
+	// song is a single dropped into the Picnic album
+	let mut connection = ctx.db.connect().unwrap();
+	diesel::update(directories::table)
+		.set(directories::date_added.eq(1000))
+		.execute(&mut connection)
+		.unwrap();
+	diesel::update(
+		directories::table.filter(directories::album.eq("Hunted")),
+	)
+	.set(directories::date_added.eq(2000))
+	.execute(&mut connection)
+	.unwrap();
+	diesel::update(songs::table)
+		.set(songs::date_added.eq(1000))
+		.execute(&mut connection)
+		.unwrap();
+	diesel::update(songs::table.filter(songs::path.like("%(Why).mp3")))
+		.set(songs::date_added.eq(2000))
+		.execute(&mut connection)
+		.unwrap();
+
+	let albums = ctx.index.get_recent_albums(1, false, RecentSort::Added).unwrap();
+	assert_eq!(albums[0].album, Some("Hunted".to_owned()));
+
+	let songs = ctx.index.get_recent_songs(1, 0).unwrap();
+	assert_eq!(songs[0].album, Some("Picnic".to_owned()));
+}
+
+#[test]
+fn recent_albums_can_sort_by_release_year() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	// Both albums are indexed in the same pass, so their date_added ties and
+	// the added ordering falls back to album name
+	let collection_dir = builder.test_directory.join("collection");
+	for (dir_name, album, year) in [("a", "Archive", 1970), ("b", "Bootleg", 2015)] {
+		let album_dir = collection_dir.join(dir_name);
+		std::fs::create_dir_all(&album_dir).unwrap();
+		let song_path = album_dir.join("track.mp3");
+		std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+		let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+		tag.set_album(album);
+		tag.set_artist("Test Artist");
+		tag.set_year(year);
+		tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+	}
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, collection_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	let albums = |sort| {
+		ctx.index
+			.get_recent_albums(10, false, sort)
+			.unwrap()
+			.into_iter()
+			.map(|d| d.album.unwrap())
+			.collect::<Vec<_>>()
+	};
+
+	assert_eq!(albums(RecentSort::Added), vec!["Archive", "Bootleg"]);
+	assert_eq!(albums(RecentSort::Released), vec!["Bootleg", "Archive"]);
+}
+
+#[test]
+fn discovery_skips_mounts_flagged_as_hidden() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+	ctx.vfs_manager
+		.set_mount_dirs(&[
+			vfs::MountDir {
+				name: "visible".to_owned(),
+				source: "test-data/small-collection/Tobokegao".to_owned(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
+			},
+			vfs::MountDir {
+				name: "hidden".to_owned(),
+				source: "test-data/small-collection/Khemmis".to_owned(),
+				include_in_discovery: false,
+				max_depth: 0,
+				display_name: String::new(),
+			},
+		])
+		.unwrap();
+	ctx.index.update().unwrap();
+
+	let random = ctx.index.get_random_albums(10, false).unwrap();
+	assert!(!random.is_empty());
+	assert!(!random.iter().any(|d| d.album == Some("Hunted".to_owned())));
+
+	let recent = ctx.index.get_recent_albums(10, false, RecentSort::Added).unwrap();
+	assert!(!recent.is_empty());
+	assert!(!recent.iter().any(|d| d.album == Some("Hunted".to_owned())));
+
+	// The mount is still reachable when addressed directly
+	let hunted_path: PathBuf = ["hidden", "Hunted"].iter().collect();
+	let files = ctx.index.browse(&hunted_path, BrowseGrouping::default()).unwrap();
+	assert_eq!(files.len(), 5);
+}
+
+#[test]
+fn browse_reports_directory_aggregates() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let khemmis_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let files = ctx.index.browse(&khemmis_path, BrowseGrouping::default()).unwrap();
+	assert_eq!(files.len(), 1);
+	match &files[0] {
+		CollectionFile::Directory(d) => {
+			assert_eq!(d.album, Some("Hunted".to_owned()));
+			assert_eq!(d.artist, Some("Khemmis".to_owned()));
+			assert_eq!(d.year, Some(2016));
+			assert_eq!(d.track_count, 5);
+		}
+		CollectionFile::Song(_) => panic!("Expected a directory"),
+	}
+}
+
+#[test]
+fn browse_orders_songs_by_disc_then_track() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	// File names deliberately contradict the disc/track ordering
+	let album_dir = builder.test_directory.join("Double Album");
+	std::fs::create_dir_all(&album_dir).unwrap();
+	for (file_name, disc, track) in [
+		("a.mp3", "2/2", "2"),
+		("b.mp3", "1/2", "2"),
+		("c.mp3", "2/2", "1"),
+		("d.mp3", "1/2", "1"),
+	] {
+		let song_path = album_dir.join(file_name);
+		std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+		let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+		tag.set_text("TPOS", disc);
+		tag.set_text("TRCK", track);
+		tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+	}
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, album_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	let files = ctx
+		.index
+		.browse(Path::new(TEST_MOUNT_NAME), BrowseGrouping::default())
+		.unwrap();
+	let ordering: Vec<_> = files
+		.iter()
+		.map(|f| match f {
+			CollectionFile::Song(s) => (s.disc_number, s.track_number, s.disc_total),
+			CollectionFile::Directory(_) => panic!("Expected a song"),
+		})
+		.collect();
+	assert_eq!(
+		ordering,
+		vec![
+			(Some(1), Some(1), Some(2)),
+			(Some(1), Some(2), Some(2)),
+			(Some(2), Some(1), Some(2)),
+			(Some(2), Some(2), Some(2)),
+		]
+	);
+}
+
+#[test]
+fn mixed_metadata_keeps_dominant_value() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let album_dir = builder.test_directory.join("Mostly One Album");
+	std::fs::create_dir_all(&album_dir).unwrap();
+	for (file_name, album) in [
+		("01.mp3", "Dominant"),
+		("02.mp3", "Dominant"),
+		("03.mp3", "Outlier"),
+	] {
+		let song_path = album_dir.join(file_name);
+		std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+		let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+		tag.set_album(album);
+		tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+	}
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, album_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	let mut connection = ctx.db.connect().unwrap();
+	let dirs: Vec<Directory> = directories::table.load(&mut connection).unwrap();
+	assert_eq!(dirs.len(), 1);
+	assert_eq!(dirs[0].album, Some("Dominant".to_owned()));
+	assert_eq!(dirs[0].track_count, 3);
+}
+
+#[test]
+fn lists_directories_without_artwork() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+	ctx.index.update().unwrap();
+
+	let artless = ctx.index.list_directories_without_artwork(0, 100).unwrap();
+	assert_eq!(artless.len(), 1);
+	assert_eq!(artless[0].album, Some("Picnic (Remixes)".to_owned()));
+
+	// Pagination skips past the only result
+	let artless = ctx.index.list_directories_without_artwork(1, 100).unwrap();
+	assert!(artless.is_empty());
+	let artless = ctx.index.list_directories_without_artwork(0, 0).unwrap();
+	assert!(artless.is_empty());
+}
+
+#[test]
+fn recent_albums_order_is_stable_for_identical_dates() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
 
-	let files = ctx.index.browse(Path::new(TEST_MOUNT_NAME)).unwrap();
-
-	assert_eq!(files.len(), 2);
-	match files[0] {
-		CollectionFile::Directory(ref d) => assert_eq!(d.path, khemmis_path.to_str().unwrap()),
-		_ => panic!("Expected directory"),
+	let mut connection = ctx.db.connect().unwrap();
+	for album_name in ["Charlie", "Alpha", "Bravo"] {
+		diesel::insert_into(directories::table)
+			.values((
+				directories::path.eq(format!("test-data/small-collection/{}", album_name)),
+				directories::album.eq(album_name),
+				directories::date_added.eq(1000),
+			))
+			.execute(&mut connection)
+			.unwrap();
 	}
 
-	match files[1] {
-		CollectionFile::Directory(ref d) => assert_eq!(d.path, tobokegao_path.to_str().unwrap()),
-		_ => panic!("Expected directory"),
+	let first_call = ctx.index.get_recent_albums(10, false, RecentSort::Added).unwrap();
+	let albums: Vec<Option<String>> = first_call.iter().map(|d| d.album.clone()).collect();
+	assert_eq!(
+		albums,
+		vec![
+			Some("Alpha".to_owned()),
+			Some("Bravo".to_owned()),
+			Some("Charlie".to_owned())
+		]
+	);
+
+	for _ in 0..5 {
+		assert_eq!(ctx.index.get_recent_albums(10, false, RecentSort::Added).unwrap(), first_call);
 	}
 }
 
 #[test]
-fn can_flatten_root() {
-	let ctx = test::ContextBuilder::new(test_name!())
+fn multi_artist_albums_are_flagged_as_compilations() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let mixtape_dir = builder.test_directory.join("Mixtape");
+	std::fs::create_dir_all(&mixtape_dir).unwrap();
+	for (file_name, artist) in [
+		("01 - one.mp3", "First Artist"),
+		("02 - two.mp3", "Second Artist"),
+	] {
+		let song_path = mixtape_dir.join(file_name);
+		std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+		let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+		tag.set_album("Mixtape");
+		tag.set_artist(artist);
+		tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+	}
+
+	let ctx = builder
 		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.mount("mixtape", mixtape_dir.to_str().unwrap())
 		.build();
 	ctx.index.update().unwrap();
-	let songs = ctx.index.flatten(Path::new(TEST_MOUNT_NAME)).unwrap();
-	assert_eq!(songs.len(), 13);
-	assert_eq!(songs[0].title, Some("Above The Water".to_owned()));
+
+	let compilations = ctx.index.get_compilations().unwrap();
+	assert_eq!(compilations.len(), 1);
+	assert_eq!(compilations[0].album, Some("Mixtape".to_owned()));
+
+	let recent = ctx.index.get_recent_albums(10, false, RecentSort::Added).unwrap();
+	assert!(recent.iter().any(|d| d.is_compilation));
+	let recent = ctx.index.get_recent_albums(10, true, RecentSort::Added).unwrap();
+	assert!(!recent.is_empty());
+	assert!(!recent.iter().any(|d| d.is_compilation));
+	let random = ctx.index.get_random_albums(10, true).unwrap();
+	assert!(!random.iter().any(|d| d.is_compilation));
 }
 
 #[test]
-fn can_flatten_directory() {
+fn album_artists_listing_counts_albums() {
 	let ctx = test::ContextBuilder::new(test_name!())
 		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
 		.build();
 	ctx.index.update().unwrap();
-	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao"].iter().collect();
-	let songs = ctx.index.flatten(path).unwrap();
-	assert_eq!(songs.len(), 8);
+
+	let album_artists = ctx.index.list_album_artists().unwrap();
+	let names: Vec<&str> = album_artists.iter().map(|a| a.name.as_str()).collect();
+	assert_eq!(names, vec!["Khemmis", "Tobokegao"]);
+
+	let khemmis = &album_artists[0];
+	assert_eq!(khemmis.album_count, 1);
+	let artwork_virtual_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+	assert_eq!(
+		khemmis.artwork,
+		Some(artwork_virtual_path.to_string_lossy().into_owned())
+	);
+
+	let tobokegao = &album_artists[1];
+	assert_eq!(tobokegao.album_count, 2);
 }
 
 #[test]
-fn can_flatten_directory_with_shared_prefix() {
-	let ctx = test::ContextBuilder::new(test_name!())
-		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+fn album_artist_tag_shields_track_artists() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let mixtape_dir = builder.test_directory.join("Mixtape");
+	std::fs::create_dir_all(&mixtape_dir).unwrap();
+	for (file_name, artist) in [
+		("01 - one.mp3", "First Artist"),
+		("02 - two.mp3", "Second Artist"),
+	] {
+		let song_path = mixtape_dir.join(file_name);
+		std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+		let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+		tag.set_album("Mixtape");
+		tag.set_artist(artist);
+		tag.set_album_artist("Various Artists");
+		tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+	}
+
+	let ctx = builder
+		.mount("mixtape", mixtape_dir.to_str().unwrap())
 		.build();
 	ctx.index.update().unwrap();
-	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic"].iter().collect(); // Prefix of '(Picnic Remixes)'
-	let songs = ctx.index.flatten(path).unwrap();
-	assert_eq!(songs.len(), 7);
+
+	let album_artists = ctx.index.list_album_artists().unwrap();
+	let names: Vec<&str> = album_artists.iter().map(|a| a.name.as_str()).collect();
+	assert_eq!(names, vec!["Various Artists"]);
+	assert_eq!(album_artists[0].album_count, 1);
+}
+
+fn make_duplicated_mounts(builder: &test::ContextBuilder) {
+	for mount in ["music_a", "music_b"] {
+		let mount_dir = builder.test_directory.join(mount);
+		std::fs::create_dir_all(&mount_dir).unwrap();
+		let song_path = mount_dir.join("track.mp3");
+		std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+		let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+		tag.set_artist("Duplicated Artist");
+		tag.set_album("Duplicated Album");
+		tag.set_title("Duplicated Song");
+		tag.set_track(1);
+		tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+	}
 }
 
 #[test]
-fn can_get_random_albums() {
-	let ctx = test::ContextBuilder::new(test_name!())
-		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+fn duplicate_songs_across_mounts_are_detected() {
+	let builder = test::ContextBuilder::new(test_name!());
+	make_duplicated_mounts(&builder);
+
+	let dir_a = builder.test_directory.join("music_a");
+	let dir_b = builder.test_directory.join("music_b");
+	let ctx = builder
+		.mount("a", dir_a.to_str().unwrap())
+		.mount("b", dir_b.to_str().unwrap())
 		.build();
 	ctx.index.update().unwrap();
-	let albums = ctx.index.get_random_albums(1).unwrap();
-	assert_eq!(albums.len(), 1);
+
+	let duplicates = ctx.index.list_duplicates().unwrap();
+	assert_eq!(duplicates.len(), 1);
+	let paths: Vec<&str> = duplicates[0].iter().map(|s| s.path.as_str()).collect();
+	assert_eq!(paths.len(), 2);
+	assert!(paths.iter().any(|p| Path::new(p).starts_with("a")));
+	assert!(paths.iter().any(|p| Path::new(p).starts_with("b")));
 }
 
 #[test]
-fn can_get_recent_albums() {
-	let ctx = test::ContextBuilder::new(test_name!())
-		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+fn deduplicate_policy_collapses_copies() {
+	let builder = test::ContextBuilder::new(test_name!());
+	make_duplicated_mounts(&builder);
+
+	let dir_a = builder.test_directory.join("music_a");
+	let dir_b = builder.test_directory.join("music_b");
+	let ctx = builder
+		.mount("a", dir_a.to_str().unwrap())
+		.mount("b", dir_b.to_str().unwrap())
 		.build();
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			duplicate_policy: Some(settings::DuplicatePolicy::Deduplicate),
+			..Default::default()
+		})
+		.unwrap();
 	ctx.index.update().unwrap();
-	let albums = ctx.index.get_recent_albums(2).unwrap();
-	assert_eq!(albums.len(), 2);
-	assert!(albums[0].date_added >= albums[1].date_added);
+
+	assert!(ctx.index.list_duplicates().unwrap().is_empty());
+	let songs = ctx.index.flatten(Path::new(""), None, None, None).unwrap();
+	assert_eq!(songs.items.len(), 1);
 }
 
 #[test]
@@ -187,6 +1627,49 @@ fn can_get_a_song() {
 	);
 }
 
+#[test]
+fn can_get_songs_by_paths() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let existing_path: PathBuf = [
+		TEST_MOUNT_NAME,
+		"Khemmis",
+		"Hunted",
+		"01 - Above The Water.mp3",
+	]
+	.iter()
+	.collect();
+	let missing_path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "nope.mp3"]
+		.iter()
+		.collect();
+
+	let songs = ctx
+		.index
+		.get_songs(&[
+			missing_path.clone(),
+			existing_path.clone(),
+			missing_path,
+			existing_path.clone(),
+		])
+		.unwrap();
+
+	assert_eq!(songs.len(), 4);
+	assert_eq!(songs[0], None);
+	assert_eq!(
+		songs[1].as_ref().map(|s| s.path.as_str()),
+		Some(existing_path.to_str().unwrap())
+	);
+	assert_eq!(songs[2], None);
+	assert_eq!(
+		songs[3].as_ref().map(|s| s.path.as_str()),
+		Some(existing_path.to_str().unwrap())
+	);
+}
+
 #[test]
 fn indexes_embedded_artwork() {
 	let ctx = test::ContextBuilder::new(test_name!())
@@ -224,10 +1707,488 @@ fn album_art_pattern_is_case_insensitive() {
 
 		let hunted_virtual_dir: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
 		let artwork_virtual_path = hunted_virtual_dir.join("Folder.jpg");
-		let song = &ctx.index.flatten(&hunted_virtual_dir).unwrap()[0];
+		let song = &ctx.index.flatten(&hunted_virtual_dir, None, None, None).unwrap().items[0];
 		assert_eq!(
 			song.artwork,
 			Some(artwork_virtual_path.to_string_lossy().into_owned())
 		);
 	}
 }
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_indexed_only_when_enabled() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	let collection_dir: PathBuf = ctx.test_directory.join("collection");
+	let outside_dir: PathBuf = ctx.test_directory.join("outside");
+	std::fs::create_dir_all(&collection_dir).unwrap();
+	std::fs::create_dir_all(&outside_dir).unwrap();
+
+	let sample_song: PathBuf = ["test-data", "formats", "sample.mp3"].iter().collect();
+	std::fs::copy(&sample_song, collection_dir.join("direct.mp3")).unwrap();
+	std::fs::copy(&sample_song, outside_dir.join("linked.mp3")).unwrap();
+	std::os::unix::fs::symlink(
+		outside_dir.canonicalize().unwrap(),
+		collection_dir.join("linked"),
+	)
+	.unwrap();
+
+	ctx.config_manager
+		.apply(&crate::app::config::Config {
+			mount_dirs: Some(vec![vfs::MountDir {
+				name: TEST_MOUNT_NAME.into(),
+				source: collection_dir.to_string_lossy().into_owned(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
+			}]),
+			..Default::default()
+		})
+		.unwrap();
+
+	ctx.index.update().unwrap();
+	let mut connection = ctx.db.connect().unwrap();
+	let num_songs: i64 = songs::table.count().get_result(&mut connection).unwrap();
+	assert_eq!(num_songs, 1);
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			follow_symlinks: Some(true),
+			..Default::default()
+		})
+		.unwrap();
+
+	ctx.index.update().unwrap();
+	let num_songs: i64 = songs::table.count().get_result(&mut connection).unwrap();
+	assert_eq!(num_songs, 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_cycles_do_not_hang_the_indexer() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	let collection_dir: PathBuf = ctx.test_directory.join("collection");
+	std::fs::create_dir_all(&collection_dir).unwrap();
+
+	let sample_song: PathBuf = ["test-data", "formats", "sample.mp3"].iter().collect();
+	std::fs::copy(&sample_song, collection_dir.join("song.mp3")).unwrap();
+	std::os::unix::fs::symlink(
+		collection_dir.canonicalize().unwrap(),
+		collection_dir.join("loop"),
+	)
+	.unwrap();
+
+	ctx.config_manager
+		.apply(&crate::app::config::Config {
+			mount_dirs: Some(vec![vfs::MountDir {
+				name: TEST_MOUNT_NAME.into(),
+				source: collection_dir.to_string_lossy().into_owned(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
+			}]),
+			..Default::default()
+		})
+		.unwrap();
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			follow_symlinks: Some(true),
+			..Default::default()
+		})
+		.unwrap();
+
+	ctx.index.update().unwrap();
+
+	let mut connection = ctx.db.connect().unwrap();
+	let num_songs: i64 = songs::table.count().get_result(&mut connection).unwrap();
+	assert_eq!(num_songs, 1);
+}
+
+#[test]
+fn artwork_precedence_picks_configured_source() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	// The Picnic directory has both a Folder.png and a song with embedded artwork
+	let folder_artwork: PathBuf = [
+		"test-data",
+		"small-collection",
+		"Tobokegao",
+		"Picnic",
+		"Folder.png",
+	]
+	.iter()
+	.collect();
+	let embedded_artwork: PathBuf = [
+		"test-data",
+		"small-collection",
+		"Tobokegao",
+		"Picnic",
+		"07 - なぜ (Why).mp3",
+	]
+	.iter()
+	.collect();
+
+	let read_picnic_artwork = || {
+		let mut connection = ctx.db.connect().unwrap();
+		let directory: Directory = directories::table
+			.filter(directories::album.eq("Picnic"))
+			.get_result(&mut connection)
+			.unwrap();
+		directory.artwork.unwrap()
+	};
+
+	ctx.index.update().unwrap();
+	assert_eq!(
+		read_picnic_artwork(),
+		folder_artwork.to_string_lossy().as_ref()
+	);
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			artwork_precedence: Some(settings::ArtworkPrecedence::EmbeddedFirst),
+			..Default::default()
+		})
+		.unwrap();
+
+	ctx.index.update().unwrap();
+	assert_eq!(
+		read_picnic_artwork(),
+		embedded_artwork.to_string_lossy().as_ref()
+	);
+}
+
+#[test]
+fn redundant_reindex_triggers_are_coalesced() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.build();
+
+	// Simulate an in-flight update so triggers cannot race the worker thread
+	{
+		let (lock, _) = &*ctx.index.pending_reindex;
+		lock.lock().unwrap().running = true;
+	}
+
+	assert_eq!(
+		ctx.index.trigger_reindex(false),
+		ReindexTrigger::AlreadyScheduled
+	);
+	assert_eq!(
+		ctx.index.trigger_reindex(false),
+		ReindexTrigger::AlreadyScheduled
+	);
+
+	// No redundant run was queued
+	{
+		let (lock, _) = &*ctx.index.pending_reindex;
+		assert!(!lock.lock().unwrap().pending);
+	}
+
+	assert_eq!(ctx.index.trigger_reindex(true), ReindexTrigger::Scheduled);
+}
+
+#[test]
+fn startup_reindex_honors_setting() {
+	let ctx = test::ContextBuilder::new(test_name!()).build();
+
+	// Boot-time scans are opt-in
+	assert_eq!(ctx.index.begin_startup_reindex(), None);
+
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			reindex_on_startup: Some(true),
+			..Default::default()
+		})
+		.unwrap();
+
+	assert_eq!(
+		ctx.index.begin_startup_reindex(),
+		Some(ReindexTrigger::Scheduled)
+	);
+}
+
+#[test]
+fn precaches_downscaled_artwork_during_indexing() {
+	let ctx = test::ContextBuilder::new(test_name!())
+		.mount(TEST_MOUNT_NAME, "test-data/small-collection")
+		.artwork_precache()
+		.build();
+
+	ctx.index.update().unwrap();
+
+	let cache_dir = ctx.test_directory.join("cache");
+	let cached_covers: Vec<_> = std::fs::read_dir(cache_dir)
+		.unwrap()
+		.flatten()
+		.filter(|entry| {
+			entry
+				.path()
+				.extension()
+				.map(|extension| extension == "jpg" || extension == "png")
+				.unwrap_or(false)
+		})
+		.collect();
+
+	assert!(!cached_covers.is_empty());
+	for entry in cached_covers {
+		let cover = image::open(entry.path()).unwrap();
+		assert!(cover.width() <= 1280);
+		assert!(cover.height() <= 1280);
+	}
+}
+
+#[test]
+fn missing_files_are_reported_then_pruned() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let original_collection_dir: PathBuf = ["test-data", "small-collection"].iter().collect();
+	let test_collection_dir: PathBuf = builder.test_directory.join("small-collection");
+
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy(
+		original_collection_dir,
+		&builder.test_directory,
+		&copy_options,
+	)
+	.unwrap();
+
+	let ctx = builder
+		.mount(TEST_MOUNT_NAME, test_collection_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	assert_eq!(ctx.index.list_missing_files().unwrap(), Vec::<PathBuf>::new());
+
+	let deleted_song: PathBuf = [
+		"Khemmis",
+		"Hunted",
+		"02 - Candlelight.mp3",
+	]
+	.iter()
+	.collect();
+	std::fs::remove_file(test_collection_dir.join(&deleted_song)).unwrap();
+
+	let missing = ctx.index.list_missing_files().unwrap();
+	assert_eq!(
+		missing,
+		vec![Path::new(TEST_MOUNT_NAME).join(&deleted_song)]
+	);
+
+	// Listing is read-only; pruning is what removes the entries
+	assert_eq!(ctx.index.list_missing_files().unwrap().len(), 1);
+	assert_eq!(ctx.index.prune_orphans().unwrap(), 1);
+	assert_eq!(ctx.index.list_missing_files().unwrap(), Vec::<PathBuf>::new());
+}
+
+#[test]
+fn adjacent_albums_returns_both_neighbors() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let collection_dir = builder.test_directory.join("collection");
+	for (album, year) in [("Alpha", 2003), ("Bravo", 2001), ("Charlie", 2002)] {
+		let album_dir = collection_dir.join(album);
+		std::fs::create_dir_all(&album_dir).unwrap();
+		let song_path = album_dir.join("1.mp3");
+		std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+		let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+		tag.set_album(album);
+		tag.set_year(year);
+		tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+	}
+
+	let ctx = builder
+		.mount("collection", collection_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	let album_path = |name: &str| -> PathBuf { ["collection", name].iter().collect() };
+	let neighbors = ctx
+		.index
+		.get_adjacent_albums(&album_path("Bravo"), AlbumSort::Name)
+		.unwrap();
+	assert_eq!(
+		neighbors.previous.and_then(|d| d.album),
+		Some("Alpha".to_owned())
+	);
+	assert_eq!(
+		neighbors.next.and_then(|d| d.album),
+		Some("Charlie".to_owned())
+	);
+}
+
+#[test]
+fn adjacent_albums_respect_requested_sort() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let collection_dir = builder.test_directory.join("collection");
+	for (album, year) in [("Alpha", 2003), ("Bravo", 2001), ("Charlie", 2002)] {
+		let album_dir = collection_dir.join(album);
+		std::fs::create_dir_all(&album_dir).unwrap();
+		let song_path = album_dir.join("1.mp3");
+		std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+		let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+		tag.set_album(album);
+		tag.set_year(year);
+		tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+	}
+
+	let ctx = builder
+		.mount("collection", collection_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	// By year, Charlie (2002) sits between Bravo (2001) and Alpha (2003)
+	let album_path: PathBuf = ["collection", "Charlie"].iter().collect();
+	let neighbors = ctx
+		.index
+		.get_adjacent_albums(&album_path, AlbumSort::Year)
+		.unwrap();
+	assert_eq!(
+		neighbors.previous.and_then(|d| d.album),
+		Some("Bravo".to_owned())
+	);
+	assert_eq!(
+		neighbors.next.and_then(|d| d.album),
+		Some("Alpha".to_owned())
+	);
+
+	// First album by name has no previous neighbor
+	let album_path: PathBuf = ["collection", "Alpha"].iter().collect();
+	let neighbors = ctx
+		.index
+		.get_adjacent_albums(&album_path, AlbumSort::Name)
+		.unwrap();
+	assert_eq!(neighbors.previous, None);
+	assert_eq!(
+		neighbors.next.and_then(|d| d.album),
+		Some("Bravo".to_owned())
+	);
+
+	let missing: PathBuf = ["collection", "Nowhere"].iter().collect();
+	assert!(matches!(
+		ctx.index.get_adjacent_albums(&missing, AlbumSort::Name),
+		Err(QueryError::DirectoryNotFound(_))
+	));
+}
+
+#[test]
+fn artist_detail_aggregates_albums() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let collection_dir = builder.test_directory.join("collection");
+	for (album, year, tracks) in [("First Album", 2001, 2), ("Second Album", 2004, 3)] {
+		let album_dir = collection_dir.join(album);
+		std::fs::create_dir_all(&album_dir).unwrap();
+		for n in 1..=tracks {
+			let song_path = album_dir.join(format!("{}.mp3", n));
+			std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+			let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+			// No album-artist frame, so crediting falls back to the track artist
+			tag.remove("TPE2");
+			tag.set_artist("Detail Artist");
+			tag.set_album(album);
+			tag.set_title(format!("Track {}", n));
+			tag.set_track(n);
+			tag.set_year(year);
+			tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+		}
+	}
+
+	let ctx = builder
+		.mount("collection", collection_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	let detail = ctx.index.get_artist_detail("Detail Artist").unwrap();
+	assert_eq!(detail.name, "Detail Artist");
+	assert_eq!(detail.track_count, 5);
+	let albums: Vec<Option<&str>> = detail.albums.iter().map(|a| a.album.as_deref()).collect();
+	assert_eq!(albums, vec![Some("First Album"), Some("Second Album")]);
+
+	assert!(matches!(
+		ctx.index.get_artist_detail("Nobody"),
+		Err(QueryError::ArtistNotFound(_))
+	));
+}
+
+#[test]
+fn artist_detail_prefers_album_artist_over_track_artist() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let collection_dir = builder.test_directory.join("collection");
+	let album_dir = collection_dir.join("Tribute Album");
+	std::fs::create_dir_all(&album_dir).unwrap();
+	let song_path = album_dir.join("1.mp3");
+	std::fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+	let mut tag = id3::Tag::read_from_path(&song_path).unwrap();
+	tag.set_artist("Guest Artist");
+	tag.set_album_artist("Main Artist");
+	tag.set_album("Tribute Album");
+	tag.set_title("Cover Song");
+	tag.write_to_path(&song_path, id3::Version::Id3v24).unwrap();
+
+	let ctx = builder
+		.mount("collection", collection_dir.to_str().unwrap())
+		.build();
+	ctx.index.update().unwrap();
+
+	// The song is credited to its album-artist, not its track artist
+	let detail = ctx.index.get_artist_detail("Main Artist").unwrap();
+	assert_eq!(detail.track_count, 1);
+	assert!(ctx.index.get_artist_detail("Guest Artist").is_err());
+}
+
+#[test]
+fn strict_tag_parsing_records_parse_errors() {
+	let builder = test::ContextBuilder::new(test_name!());
+
+	let collection_dir = builder.test_directory.join("collection");
+	std::fs::create_dir_all(&collection_dir).unwrap();
+	std::fs::copy("test-data/formats/sample.mp3", collection_dir.join("good.mp3")).unwrap();
+
+	// A file with one malformed frame: clobber the TCON text encoding byte
+	let bad_path = collection_dir.join("bad.mp3");
+	std::fs::copy("test-data/formats/sample.mp3", &bad_path).unwrap();
+	let mut bytes = std::fs::read(&bad_path).unwrap();
+	let position = bytes
+		.windows(4)
+		.position(|window| window == b"TCON")
+		.unwrap();
+	bytes[position + 10] = 0xFF;
+	std::fs::write(&bad_path, &bytes).unwrap();
+
+	let ctx = builder
+		.mount("collection", collection_dir.to_str().unwrap())
+		.build();
+
+	// Strict parsing drops the malformed file and records the failure
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			tag_parsing: Some(settings::TagParsing::Strict),
+			..Default::default()
+		})
+		.unwrap();
+	ctx.index.update().unwrap();
+	let status = ctx.index.get_index_status().unwrap().unwrap();
+	assert_eq!(status.error_count, 1);
+	let songs = ctx.index.flatten(Path::new("collection"), None, None, None).unwrap();
+	assert_eq!(songs.items.len(), 1);
+
+	// Lenient parsing salvages the readable frames instead
+	ctx.settings_manager
+		.amend(&settings::NewSettings {
+			tag_parsing: Some(settings::TagParsing::Lenient),
+			..Default::default()
+		})
+		.unwrap();
+	ctx.index.update().unwrap();
+	let status = ctx.index.get_index_status().unwrap().unwrap();
+	assert_eq!(status.error_count, 0);
+	let songs = ctx.index.flatten(Path::new("collection"), None, None, None).unwrap();
+	assert_eq!(songs.items.len(), 2);
+}
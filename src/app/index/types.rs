@@ -3,18 +3,52 @@ use std::path::Path;
 
 use crate::app::vfs::VFS;
 use crate::db::songs;
+use crate::utils;
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CollectionFile {
-	Directory(Directory),
-	Song(Song),
+	Directory(Box<Directory>),
+	Song(Box<Song>),
 }
 
-#[derive(Debug, PartialEq, Eq, Queryable, QueryableByName, Serialize, Deserialize)]
+impl CollectionFile {
+	// Used to interleave directories and songs when a browse listing is not
+	// grouped by entry kind
+	pub(crate) fn name(&self) -> &str {
+		let path = match self {
+			CollectionFile::Directory(d) => &d.path,
+			CollectionFile::Song(s) => &s.path,
+		};
+		path.rsplit('/').next().unwrap_or(path)
+	}
+}
+
+// Nested variant of `CollectionFile` returned by recursive browsing, so
+// clients can mirror a whole subtree in one request
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TreeEntry {
+	Directory {
+		directory: Box<Directory>,
+		children: Vec<TreeEntry>,
+	},
+	Song(Box<Song>),
+}
+
+// How directory and song entries are ordered within a browse listing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BrowseGrouping {
+	#[default]
+	FoldersFirst,
+	FilesFirst,
+	Mixed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, QueryableByName, Serialize, Deserialize)]
 #[diesel(table_name = songs)]
 pub struct Song {
-	#[serde(skip_serializing, skip_deserializing)]
-	id: i32,
+	// Stable across reindexing, so clients can rely on it as a cache key even
+	// when the file moves
+	pub id: i32,
 	pub path: String,
 	#[serde(skip_serializing, skip_deserializing)]
 	pub parent: String,
@@ -31,17 +65,42 @@ pub struct Song {
 	pub composer: Option<String>,
 	pub genre: Option<String>,
 	pub label: Option<String>,
+	pub bpm: Option<i32>,
+	pub initial_key: Option<String>,
+	pub encoder_delay: Option<i32>,
+	pub encoder_padding: Option<i32>,
+	#[serde(skip_serializing, skip_deserializing)]
+	pub search_normalized: String,
+	pub file_size: i64,
+	pub tags_inferred: bool,
+	pub track_total: Option<i32>,
+	pub disc_total: Option<i32>,
+	pub date_added: i32,
+}
+
+// Songs sharing a key are considered copies of the same recording when looking
+// for duplicates across mounts. Files missing an artist or title never match.
+pub(crate) fn song_duplicate_key(
+	artist: Option<&str>,
+	album: Option<&str>,
+	title: Option<&str>,
+	track_number: Option<i32>,
+) -> Option<(String, String, String, Option<i32>)> {
+	let artist = artist?.trim().to_lowercase();
+	let title = title?.trim().to_lowercase();
+	let album = album.unwrap_or_default().trim().to_lowercase();
+	Some((artist, album, title, track_number))
 }
 
 impl Song {
 	pub fn virtualize(mut self, vfs: &VFS) -> Option<Song> {
 		self.path = match vfs.real_to_virtual(Path::new(&self.path)) {
-			Ok(p) => p.to_string_lossy().into_owned(),
+			Ok(p) => utils::path_to_forward_slashes(p),
 			_ => return None,
 		};
 		if let Some(artwork_path) = self.artwork {
 			self.artwork = match vfs.real_to_virtual(Path::new(&artwork_path)) {
-				Ok(p) => Some(p.to_string_lossy().into_owned()),
+				Ok(p) => Some(utils::path_to_forward_slashes(p)),
 				_ => None,
 			};
 		}
@@ -51,8 +110,7 @@ impl Song {
 
 #[derive(Debug, PartialEq, Eq, Queryable, Serialize, Deserialize)]
 pub struct Directory {
-	#[serde(skip_serializing, skip_deserializing)]
-	id: i32,
+	pub id: i32,
 	pub path: String,
 	#[serde(skip_serializing, skip_deserializing)]
 	pub parent: Option<String>,
@@ -61,17 +119,103 @@ pub struct Directory {
 	pub album: Option<String>,
 	pub artwork: Option<String>,
 	pub date_added: i32,
+	pub is_compilation: bool,
+	pub track_count: i32,
+	#[serde(skip_serializing, skip_deserializing)]
+	pub search_normalized: String,
+}
+
+// Written at the end of each index pass so operators can tell when the
+// library was last scanned, even across restarts
+#[derive(Debug, PartialEq, Eq, Queryable, Serialize, Deserialize)]
+pub struct IndexStatus {
+	pub last_index_start: i32,
+	pub last_index_end: i32,
+	pub file_count: i32,
+	pub error_count: i32,
+}
+
+// One row of the album-artists listing, with a representative cover a client
+// can show in an artists grid
+#[derive(Debug, PartialEq, Eq, QueryableByName, Serialize, Deserialize)]
+pub struct AlbumArtist {
+	#[diesel(sql_type = diesel::sql_types::Text)]
+	pub name: String,
+	#[diesel(sql_type = diesel::sql_types::BigInt)]
+	pub album_count: i64,
+	#[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+	pub artwork: Option<String>,
+}
+
+impl AlbumArtist {
+	pub fn virtualize(mut self, vfs: &VFS) -> AlbumArtist {
+		if let Some(artwork_path) = self.artwork {
+			self.artwork = match vfs.real_to_virtual(Path::new(&artwork_path)) {
+				Ok(p) => Some(utils::path_to_forward_slashes(p)),
+				_ => None,
+			};
+		}
+		self
+	}
+}
+
+// Aggregated view of one artist for the artist-detail endpoint: every album
+// they are credited on, and how many of their tracks are in the collection
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtistDetail {
+	pub name: String,
+	pub albums: Vec<Directory>,
+	pub track_count: i64,
+}
+
+// Neighbors of an album in a sorted listing of the albums under the same
+// parent directory. Boundary albums have no neighbor on that side.
+// Restricts a song query to songs carrying a given custom tag, optionally
+// with a specific value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CustomTagFilter {
+	pub key: String,
+	pub value: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdjacentAlbums {
+	pub previous: Option<Directory>,
+	pub next: Option<Directory>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectorySummary {
+	pub song_count: i64,
+	pub total_size: i64,
+	pub total_duration: i64,
+}
+
+// Result set clamped to the global rows-per-response cap; `truncated` tells
+// callers that more rows matched than were returned
+#[derive(Debug, PartialEq, Eq)]
+pub struct Truncated<T> {
+	pub items: Vec<T>,
+	pub truncated: bool,
+}
+
+impl<T> Truncated<T> {
+	pub fn cap(mut items: Vec<T>, max_results: usize) -> Self {
+		let truncated = items.len() > max_results;
+		items.truncate(max_results);
+		Self { items, truncated }
+	}
 }
 
 impl Directory {
 	pub fn virtualize(mut self, vfs: &VFS) -> Option<Directory> {
 		self.path = match vfs.real_to_virtual(Path::new(&self.path)) {
-			Ok(p) => p.to_string_lossy().into_owned(),
+			Ok(p) => utils::path_to_forward_slashes(p),
 			_ => return None,
 		};
 		if let Some(artwork_path) = self.artwork {
 			self.artwork = match vfs.real_to_virtual(Path::new(&artwork_path)) {
-				Ok(p) => Some(p.to_string_lossy().into_owned()),
+				Ok(p) => Some(utils::path_to_forward_slashes(p)),
 				_ => None,
 			};
 		}
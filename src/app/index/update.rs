@@ -1,20 +1,69 @@
+use diesel::prelude::*;
 use log::{error, info};
-use std::time;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{self, SystemTime, UNIX_EPOCH};
 
 mod cleaner;
 mod collector;
 mod inserter;
 mod traverser;
 
-use crate::app::index::Index;
-use crate::app::vfs;
-use crate::db;
+use crate::app::index::{metadata, types::song_duplicate_key, Index, IndexStatus};
+use crate::app::{settings, thumbnail, vfs};
+use crate::db::{self, directories, index_checkpoint, index_metadata, song_chapters, songs};
+use crate::utils;
 
 use cleaner::Cleaner;
 use collector::Collector;
 use inserter::Inserter;
 use traverser::Traverser;
 
+// Fills in missing artist/album from the common `Artist/Album/song` folder
+// layout. Returns true when any value was inferred.
+fn infer_tags_from_path(path: &Path, tags: &mut metadata::SongTags) -> bool {
+	let album_dir = path.parent();
+	let artist_dir = album_dir.and_then(|p| p.parent());
+	let mut inferred = false;
+	if tags.album.is_none() {
+		if let Some(name) = album_dir.and_then(|p| p.file_name()) {
+			tags.album = Some(name.to_string_lossy().into_owned());
+			inferred = true;
+		}
+	}
+	if tags.artist.is_none() {
+		if let Some(name) = artist_dir.and_then(|p| p.file_name()) {
+			tags.artist = Some(name.to_string_lossy().into_owned());
+			inferred = true;
+		}
+	}
+	inferred
+}
+
+// Normalized text blob matched against search queries. It starts with the song
+// path, so sorting by this column yields accent-insensitive path ordering.
+fn song_search_text(path: &str, tags: &metadata::SongTags) -> String {
+	let fields = [
+		Some(path),
+		tags.title.as_deref(),
+		tags.album.as_deref(),
+		tags.artist.as_deref(),
+		tags.album_artist.as_deref(),
+	];
+	utils::normalize_unicode(&fields.into_iter().flatten().collect::<Vec<_>>().join("\n"))
+}
+
+// Upper bound on the number of files a single dry run will parse, so a request
+// against a huge directory cannot tie up the server.
+pub const DRY_RUN_MAX_FILES: usize = 500;
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DryRunEntry {
+	pub path: String,
+	pub tags: metadata::SongTags,
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
 	#[error(transparent)]
@@ -24,15 +73,48 @@ pub enum Error {
 	#[error(transparent)]
 	DatabaseConnection(#[from] db::Error),
 	#[error(transparent)]
+	Thumbnail(#[from] thumbnail::Error),
+	#[error(transparent)]
 	Vfs(#[from] vfs::Error),
 }
 
 impl Index {
 	pub fn update(&self) -> Result<(), Error> {
 		let start = time::Instant::now();
+		let index_start = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i32)
+			.unwrap_or_default();
+		let mut error_count = 0;
 		info!("Beginning library index update");
 
+		let checkpoint = self.read_checkpoint().unwrap_or_default();
+		if !checkpoint.is_empty() {
+			info!(
+				"Resuming interrupted index update, {} directories already completed",
+				checkpoint.len()
+			);
+		}
+
 		let album_art_pattern = self.settings_manager.get_index_album_art_pattern().ok();
+		let artwork_precedence = self
+			.settings_manager
+			.get_artwork_precedence()
+			.unwrap_or_default();
+		let infer_tags = self
+			.settings_manager
+			.read()
+			.map(|s| s.index_infer_tags_from_path)
+			.unwrap_or(false);
+		let duplicate_policy = self
+			.settings_manager
+			.get_duplicate_policy()
+			.unwrap_or_default();
+		let tag_parsing = self.settings_manager.get_tag_parsing().unwrap_or_default();
+		let partial_file_policy = self
+			.settings_manager
+			.get_partial_file_policy()
+			.unwrap_or_default();
 
 		let cleaner = Cleaner::new(self.db.clone(), self.vfs_manager.clone());
 		cleaner.clean()?;
@@ -46,27 +128,89 @@ impl Index {
 
 		let (collect_sender, collect_receiver) = crossbeam_channel::unbounded();
 		let collector_thread = std::thread::spawn(move || {
-			let collector = Collector::new(collect_receiver, insert_sender, album_art_pattern);
+			let collector = Collector::new(
+				collect_receiver,
+				insert_sender,
+				album_art_pattern,
+				artwork_precedence,
+				infer_tags,
+			);
 			collector.collect();
 		});
 
+		let follow_symlinks = self
+			.settings_manager
+			.read()
+			.map(|s| s.index_follow_symlinks)
+			.unwrap_or(false);
+
+		let num_traverser_threads = self
+			.settings_manager
+			.get_num_traverser_threads()
+			.unwrap_or(0);
+
 		let vfs = self.vfs_manager.get_vfs()?;
 		let traverser_thread = std::thread::spawn(move || {
 			let mounts = vfs.mounts();
-			let traverser = Traverser::new(collect_sender);
-			traverser.traverse(mounts.iter().map(|p| p.source.clone()).collect());
+			let traverser = Traverser::new(
+				collect_sender,
+				follow_symlinks,
+				tag_parsing,
+				partial_file_policy,
+				num_traverser_threads,
+				checkpoint,
+			);
+			traverser.traverse(
+				mounts
+					.iter()
+					.map(|m| traverser::Root {
+						path: m.source.clone(),
+						max_depth: m.max_depth.max(0) as u32,
+					})
+					.collect(),
+			);
+			traverser.parse_error_count()
 		});
 
-		if let Err(e) = traverser_thread.join() {
-			error!("Error joining on traverser thread: {:?}", e);
+		match traverser_thread.join() {
+			Ok(parse_errors) => error_count += parse_errors as i32,
+			Err(e) => {
+				error!("Error joining on traverser thread: {:?}", e);
+				error_count += 1;
+			}
 		}
 
 		if let Err(e) = collector_thread.join() {
 			error!("Error joining on collector thread: {:?}", e);
+			error_count += 1;
 		}
 
 		if let Err(e) = insertion_thread.join() {
 			error!("Error joining on inserter thread: {:?}", e);
+			error_count += 1;
+		}
+
+		// A completed scan invalidates resume data; the next update starts fresh
+		if let Err(e) = self.clear_checkpoint() {
+			error!("Error while clearing index checkpoint: {}", e);
+		}
+
+		if duplicate_policy != settings::DuplicatePolicy::KeepAll {
+			if let Err(e) = self.collapse_duplicates(duplicate_policy) {
+				error!("Error while collapsing duplicate songs: {}", e);
+				error_count += 1;
+			}
+		}
+
+		if self.artwork_precache {
+			if let Err(e) = self.precache_artwork() {
+				error!("Error while pre-caching artwork: {}", e);
+				error_count += 1;
+			}
+		}
+
+		if let Err(e) = self.write_index_metadata(index_start, error_count) {
+			error!("Error while recording index metadata: {}", e);
 		}
 
 		info!(
@@ -76,4 +220,395 @@ impl Index {
 
 		Ok(())
 	}
+
+	// Directories recorded as durably indexed by a scan that did not run to
+	// completion. A non-empty checkpoint makes the next update resume instead
+	// of re-parsing everything already done.
+	fn read_checkpoint(&self) -> Result<HashSet<PathBuf>, Error> {
+		let mut connection = self.db.connect()?;
+		let paths: Vec<String> = index_checkpoint::table
+			.select(index_checkpoint::path)
+			.load(&mut connection)?;
+		Ok(paths.into_iter().map(PathBuf::from).collect())
+	}
+
+	fn clear_checkpoint(&self) -> Result<(), Error> {
+		let mut connection = self.db.connect()?;
+		diesel::delete(index_checkpoint::table).execute(&mut connection)?;
+		Ok(())
+	}
+
+	// Discards resume data from any interrupted scan, so every directory is
+	// re-parsed regardless of checkpoints
+	pub fn update_force_full(&self) -> Result<(), Error> {
+		self.clear_checkpoint()?;
+		self.update()
+	}
+
+	// Persisted so the status survives server restarts
+	fn write_index_metadata(&self, index_start: i32, error_count: i32) -> Result<(), Error> {
+		let index_end = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i32)
+			.unwrap_or_default();
+		let mut connection = self.db.connect()?;
+		let file_count: i64 = songs::table.count().get_result(&mut connection)?;
+		diesel::replace_into(index_metadata::table)
+			.values((
+				index_metadata::id.eq(0),
+				index_metadata::last_index_start.eq(index_start),
+				index_metadata::last_index_end.eq(index_end),
+				index_metadata::file_count.eq(file_count as i32),
+				index_metadata::error_count.eq(error_count),
+			))
+			.execute(&mut connection)?;
+		Ok(())
+	}
+
+	// Returns None when the library has never been indexed
+	pub fn get_index_status(&self) -> Result<Option<IndexStatus>, Error> {
+		let mut connection = self.db.connect()?;
+		let status = index_metadata::table
+			.select((
+				index_metadata::last_index_start,
+				index_metadata::last_index_end,
+				index_metadata::file_count,
+				index_metadata::error_count,
+			))
+			.first(&mut connection)
+			.optional()?;
+		Ok(status)
+	}
+
+	// Drops all but one copy of each duplicated song from the index. Files on
+	// disk are left untouched and remain directly streamable by path.
+	fn collapse_duplicates(&self, policy: settings::DuplicatePolicy) -> Result<(), Error> {
+		let mount_sources: Vec<PathBuf> = self
+			.vfs_manager
+			.get_vfs()?
+			.mounts()
+			.iter()
+			.map(|m| m.source.clone())
+			.collect();
+
+		let mut connection = self.db.connect()?;
+		#[allow(clippy::type_complexity)]
+		let all_songs: Vec<(i32, String, Option<String>, Option<String>, Option<String>, Option<i32>)> =
+			songs::table
+				.select((
+					songs::id,
+					songs::path,
+					songs::artist,
+					songs::album,
+					songs::title,
+					songs::track_number,
+				))
+				.order(songs::id.asc())
+				.load(&mut connection)?;
+
+		let mut groups: HashMap<_, Vec<(i32, String)>> = HashMap::new();
+		for (id, path, artist, album, title, track_number) in all_songs {
+			let Some(key) = song_duplicate_key(
+				artist.as_deref(),
+				album.as_deref(),
+				title.as_deref(),
+				track_number,
+			) else {
+				continue;
+			};
+			groups.entry(key).or_default().push((id, path));
+		}
+
+		let mount_rank = |path: &str| {
+			mount_sources
+				.iter()
+				.position(|source| Path::new(path).starts_with(source))
+				.unwrap_or(usize::MAX)
+		};
+
+		let mut removed_ids = Vec::new();
+		for mut group in groups.into_values() {
+			if group.len() < 2 {
+				continue;
+			}
+			let keeper = match policy {
+				settings::DuplicatePolicy::PreferFirstMount => group
+					.iter()
+					.enumerate()
+					.min_by_key(|(_, (_, path))| mount_rank(path))
+					.map(|(index, _)| index)
+					.unwrap_or(0),
+				_ => 0,
+			};
+			group.swap_remove(keeper);
+			removed_ids.extend(group.into_iter().map(|(id, _)| id));
+		}
+
+		if !removed_ids.is_empty() {
+			info!("Collapsing {} duplicate song(s)", removed_ids.len());
+			diesel::delete(songs::table.filter(songs::id.eq_any(&removed_ids)))
+				.execute(&mut connection)?;
+		}
+
+		Ok(())
+	}
+
+	// Decodes and downscales every cover once during indexing, so the serve
+	// path can work from small cached images instead of the original files
+	fn precache_artwork(&self) -> Result<(), Error> {
+		let mut connection = self.db.connect()?;
+		let mut artwork_paths: Vec<Option<String>> = directories::table
+			.select(directories::artwork)
+			.distinct()
+			.load(&mut connection)?;
+		artwork_paths.extend(
+			songs::table
+				.select(songs::artwork)
+				.distinct()
+				.load::<Option<String>>(&mut connection)?,
+		);
+		for artwork_path in artwork_paths.into_iter().flatten() {
+			if let Err(e) = self.thumbnail_manager.precache_cover(Path::new(&artwork_path)) {
+				error!("Error while pre-caching artwork `{}`: {}", artwork_path, e);
+			}
+		}
+		Ok(())
+	}
+
+	// Clears thumbnail cache entries that no longer match any indexed artwork,
+	// or that were left unreadable by an interrupted write.
+	pub fn verify_thumbnail_cache(&self) -> Result<thumbnail::CacheVerificationReport, Error> {
+		let mut connection = self.db.connect()?;
+		let mut artwork_paths: Vec<Option<String>> = directories::table
+			.select(directories::artwork)
+			.distinct()
+			.load(&mut connection)?;
+		artwork_paths.extend(
+			songs::table
+				.select(songs::artwork)
+				.distinct()
+				.load::<Option<String>>(&mut connection)?,
+		);
+		let valid_sources: Vec<PathBuf> = artwork_paths
+			.into_iter()
+			.flatten()
+			.map(PathBuf::from)
+			.collect();
+		Ok(self.thumbnail_manager.verify_cache(&valid_sources)?)
+	}
+
+	// Parses the audio files under a directory without writing anything to the
+	// database, so admins can preview how a reindex would read their tags.
+	pub fn dry_run(&self, virtual_path: &Path) -> Result<Vec<DryRunEntry>, Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let tag_parsing = self.settings_manager.get_tag_parsing().unwrap_or_default();
+
+		let mut output = Vec::new();
+		let mut directories = vec![real_path];
+		while let Some(directory) = directories.pop() {
+			if output.len() >= DRY_RUN_MAX_FILES {
+				break;
+			}
+			let read_dir = match std::fs::read_dir(&directory) {
+				Ok(read_dir) => read_dir,
+				Err(_) => continue,
+			};
+			for entry in read_dir.filter_map(|e| e.ok()) {
+				let path = entry.path();
+				if path.is_dir() {
+					directories.push(path);
+				} else if let Ok(Some(tags)) = metadata::read(&path, tag_parsing) {
+					let virtual_path = vfs
+						.real_to_virtual(&path)
+						.map(utils::path_to_forward_slashes)
+						.unwrap_or_else(|_| path.to_string_lossy().into_owned());
+					output.push(DryRunEntry {
+						path: virtual_path,
+						tags,
+					});
+					if output.len() >= DRY_RUN_MAX_FILES {
+						break;
+					}
+				}
+			}
+		}
+
+		Ok(output)
+	}
+
+	// Reports indexed songs whose file no longer exists on disk, as virtual
+	// paths. This is the read-only counterpart of `prune_orphans`: it lets an
+	// administrator review what a prune would remove.
+	pub fn list_missing_files(&self) -> Result<Vec<PathBuf>, Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+
+		let all_songs: Vec<String> = {
+			let mut connection = self.db.connect()?;
+			songs::table.select(songs::path).load(&mut connection)?
+		};
+
+		let mut missing = Vec::new();
+		for song_path in all_songs {
+			let path = Path::new(&song_path);
+			if path.exists() {
+				continue;
+			}
+			let virtual_path = vfs
+				.real_to_virtual(path)
+				.map(utils::path_to_forward_slashes)
+				.unwrap_or(song_path);
+			missing.push(PathBuf::from(virtual_path));
+		}
+		missing.sort();
+		Ok(missing)
+	}
+
+	pub fn prune_orphans(&self) -> Result<usize, Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let mut connection = self.db.connect()?;
+
+		let num_removed = connection.transaction(
+			|connection| -> Result<usize, diesel::result::Error> {
+				let all_directories: Vec<String> = directories::table
+					.select(directories::path)
+					.load(connection)?;
+				let all_songs: Vec<String> = songs::table.select(songs::path).load(connection)?;
+
+				let is_orphan = |path: &&String| {
+					let path = Path::new(path.as_str());
+					!path.exists() || vfs.real_to_virtual(path).is_err()
+				};
+				let orphan_directories: Vec<&String> =
+					all_directories.iter().filter(is_orphan).collect();
+				let orphan_songs: Vec<&String> = all_songs.iter().filter(is_orphan).collect();
+
+				let mut num_removed = 0;
+				num_removed += diesel::delete(
+					directories::table.filter(directories::path.eq_any(&orphan_directories)),
+				)
+				.execute(connection)?;
+				num_removed +=
+					diesel::delete(songs::table.filter(songs::path.eq_any(&orphan_songs)))
+						.execute(connection)?;
+				Ok(num_removed)
+			},
+		)?;
+
+		Ok(num_removed)
+	}
+
+	// Re-scans a single file after external edits, which is far cheaper than a
+	// full reindex. New files are inserted and vanished files are removed.
+	pub fn refresh_song(&self, virtual_path: &Path) -> Result<(), Error> {
+		let vfs = self.vfs_manager.get_vfs()?;
+		let real_path = vfs.virtual_to_real(virtual_path)?;
+		let real_path_string = real_path.as_path().to_string_lossy().into_owned();
+
+		let mut connection = self.db.connect()?;
+
+		// Cached chapter markers may no longer match the file; drop them so the
+		// next request re-parses
+		diesel::delete(song_chapters::table.filter(song_chapters::path.eq(&real_path_string)))
+			.execute(&mut connection)?;
+
+		if !real_path.exists() {
+			diesel::delete(songs::table.filter(songs::path.eq(&real_path_string)))
+				.execute(&mut connection)?;
+			return Ok(());
+		}
+
+		let tag_parsing = self.settings_manager.get_tag_parsing().unwrap_or_default();
+		let mut tags = match metadata::read(&real_path, tag_parsing) {
+			Ok(Some(tags)) => tags,
+			Ok(None) => return Ok(()),
+			Err(e) => {
+				error!("Error while reading file metadata for '{:?}': {}", real_path, e);
+				return Ok(());
+			}
+		};
+
+		let infer_tags = self
+			.settings_manager
+			.read()
+			.map(|s| s.index_infer_tags_from_path)
+			.unwrap_or(false);
+		let tags_inferred = infer_tags && infer_tags_from_path(&real_path, &mut tags);
+
+		let search_normalized = song_search_text(&real_path_string, &tags);
+		let file_size = std::fs::metadata(&real_path)
+			.map(|m| m.len() as i64)
+			.unwrap_or(0);
+
+		// Same definition as the traverser: file creation time, with the
+		// modification time as a fallback
+		let date_added = std::fs::metadata(&real_path)
+			.ok()
+			.and_then(|m| m.created().or_else(|_| m.modified()).ok())
+			.and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+			.map(|d| d.as_secs() as i32)
+			.unwrap_or_default();
+
+		let parent = real_path
+			.parent()
+			.map(|p| p.to_string_lossy().into_owned())
+			.unwrap_or_default();
+
+		// Same artwork rules as the collector: embedded art wins, otherwise the
+		// song shares whatever artwork its directory row settled on
+		let artwork = if tags.has_artwork {
+			Some(real_path_string.clone())
+		} else {
+			directories::table
+				.filter(directories::path.eq(&parent))
+				.select(directories::artwork)
+				.first(&mut connection)
+				.optional()?
+				.flatten()
+		};
+
+		// A refreshed song keeps the id of the row it replaces
+		let existing_id = songs::table
+			.filter(songs::path.eq(&real_path_string))
+			.select(songs::id)
+			.first(&mut connection)
+			.optional()?;
+
+		let song = inserter::Song {
+			id: existing_id,
+			path: real_path_string,
+			parent,
+			disc_number: tags.disc_number.map(|n| n as i32),
+			track_number: tags.track_number.map(|n| n as i32),
+			title: tags.title,
+			duration: tags.duration.map(|n| n as i32),
+			artist: tags.artist,
+			album_artist: tags.album_artist,
+			album: tags.album,
+			year: tags.year,
+			artwork,
+			lyricist: tags.lyricist,
+			composer: tags.composer,
+			genre: tags.genre,
+			label: tags.label,
+			bpm: tags.bpm,
+			initial_key: tags.initial_key,
+			encoder_delay: tags.encoder_delay,
+			encoder_padding: tags.encoder_padding,
+			search_normalized,
+			file_size,
+			tags_inferred,
+			track_total: tags.track_total.map(|n| n as i32),
+			disc_total: tags.disc_total.map(|n| n as i32),
+			date_added,
+		};
+
+		// REPLACE semantics cover both new and existing songs, and the explicit
+		// id keeps the row stable when the song already exists
+		diesel::replace_into(songs::table)
+			.values(&song)
+			.execute(&mut connection)?;
+
+		Ok(())
+	}
 }
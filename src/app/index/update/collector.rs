@@ -1,13 +1,17 @@
 use crossbeam_channel::{Receiver, Sender};
 use log::error;
 use regex::Regex;
+use std::collections::{HashMap, HashSet};
 
 use super::*;
+use crate::app::settings::ArtworkPrecedence;
 
 pub struct Collector {
 	receiver: Receiver<traverser::Directory>,
 	sender: Sender<inserter::Item>,
 	album_art_pattern: Option<Regex>,
+	artwork_precedence: ArtworkPrecedence,
+	infer_tags_from_path: bool,
 }
 
 impl Collector {
@@ -15,11 +19,15 @@ impl Collector {
 		receiver: Receiver<traverser::Directory>,
 		sender: Sender<inserter::Item>,
 		album_art_pattern: Option<Regex>,
+		artwork_precedence: ArtworkPrecedence,
+		infer_tags_from_path: bool,
 	) -> Self {
 		Self {
 			receiver,
 			sender,
 			album_art_pattern,
+			artwork_precedence,
+			infer_tags_from_path,
 		}
 	}
 
@@ -30,41 +38,41 @@ impl Collector {
 	}
 
 	fn collect_directory(&self, directory: traverser::Directory) {
-		let mut directory_album = None;
-		let mut directory_year = None;
-		let mut directory_artist = None;
-		let mut inconsistent_directory_album = false;
-		let mut inconsistent_directory_year = false;
-		let mut inconsistent_directory_artist = false;
+		let mut album_tallies = HashMap::new();
+		let mut year_tallies = HashMap::new();
+		let mut artist_tallies = HashMap::new();
+		let mut directory_is_compilation = false;
+		let mut song_artists = HashSet::new();
+		let track_count = directory.songs.len() as i32;
 
 		let directory_artwork = self.get_artwork(&directory);
 		let directory_path_string = directory.path.to_string_lossy().to_string();
 		let directory_parent_string = directory.parent.map(|p| p.to_string_lossy().to_string());
 
 		for song in directory.songs {
-			let tags = song.metadata;
+			let mut tags = song.metadata;
+			let tags_inferred =
+				self.infer_tags_from_path && infer_tags_from_path(&song.path, &mut tags);
 			let path_string = song.path.to_string_lossy().to_string();
+			let search_normalized = song_search_text(&path_string, &tags);
 
-			if tags.year.is_some() {
-				inconsistent_directory_year |=
-					directory_year.is_some() && directory_year != tags.year;
-				directory_year = tags.year;
+			if let Some(year) = tags.year {
+				*year_tallies.entry(year).or_insert(0) += 1;
 			}
 
-			if tags.album.is_some() {
-				inconsistent_directory_album |=
-					directory_album.is_some() && directory_album != tags.album;
-				directory_album = tags.album.as_ref().cloned();
+			if let Some(album) = &tags.album {
+				*album_tallies.entry(album.clone()).or_insert(0) += 1;
 			}
 
-			if tags.album_artist.is_some() {
-				inconsistent_directory_artist |=
-					directory_artist.is_some() && directory_artist != tags.album_artist;
-				directory_artist = tags.album_artist.as_ref().cloned();
-			} else if tags.artist.is_some() {
-				inconsistent_directory_artist |=
-					directory_artist.is_some() && directory_artist != tags.artist;
-				directory_artist = tags.artist.as_ref().cloned();
+			if let Some(album_artist) = &tags.album_artist {
+				*artist_tallies.entry(album_artist.clone()).or_insert(0) += 1;
+			} else if let Some(artist) = &tags.artist {
+				*artist_tallies.entry(artist.clone()).or_insert(0) += 1;
+			}
+
+			directory_is_compilation |= tags.is_compilation;
+			if let Some(artist) = &tags.artist {
+				song_artists.insert(artist.clone());
 			}
 
 			let artwork_path = if tags.has_artwork {
@@ -73,7 +81,8 @@ impl Collector {
 				directory_artwork.as_ref().cloned()
 			};
 
-			if let Err(e) = self.sender.send(inserter::Item::Song(inserter::Song {
+			if let Err(e) = self.sender.send(inserter::Item::Song(Box::new(inserter::Song {
+				id: None,
 				path: path_string,
 				parent: directory_path_string.clone(),
 				disc_number: tags.disc_number.map(|n| n as i32),
@@ -89,24 +98,36 @@ impl Collector {
 				composer: tags.composer,
 				genre: tags.genre,
 				label: tags.label,
-			})) {
+				bpm: tags.bpm,
+				initial_key: tags.initial_key,
+				encoder_delay: tags.encoder_delay,
+				encoder_padding: tags.encoder_padding,
+				search_normalized,
+				file_size: song.file_size,
+				tags_inferred,
+				track_total: tags.track_total.map(|n| n as i32),
+				disc_total: tags.disc_total.map(|n| n as i32),
+				date_added: song.created,
+			}))) {
 				error!("Error while sending song from collector: {}", e);
 			}
 		}
 
-		if inconsistent_directory_year {
-			directory_year = None;
-		}
-		if inconsistent_directory_album {
-			directory_album = None;
-		}
-		if inconsistent_directory_artist {
-			directory_artist = None;
-		}
+		// Directories with mixed metadata keep the dominant value, so browse
+		// grids stay informative without a follow-up query per entry.
+		let directory_year = dominant_value(year_tallies);
+		let directory_album = dominant_value(album_tallies);
+		let directory_artist = dominant_value(artist_tallies);
+
+		// A single album spread across songs by multiple artists is a compilation,
+		// even when no song carries an explicit compilation tag.
+		directory_is_compilation |= directory_album.is_some() && song_artists.len() > 1;
 
 		if let Err(e) = self
 			.sender
-			.send(inserter::Item::Directory(inserter::Directory {
+			.send(inserter::Item::Directory(Box::new(inserter::Directory {
+				id: None,
+				search_normalized: crate::utils::normalize_unicode(&directory_path_string),
 				path: directory_path_string,
 				parent: directory_parent_string,
 				artwork: directory_artwork,
@@ -114,7 +135,9 @@ impl Collector {
 				artist: directory_artist,
 				year: directory_year,
 				date_added: directory.created,
-			})) {
+				is_compilation: directory_is_compilation,
+				track_count,
+			}))) {
 			error!("Error while sending directory from collector: {}", e);
 		}
 	}
@@ -144,6 +167,20 @@ impl Collector {
 			}
 		});
 
-		regex_artwork.or(embedded_artwork)
+		match self.artwork_precedence {
+			ArtworkPrecedence::EmbeddedFirst => embedded_artwork.or(regex_artwork),
+			ArtworkPrecedence::FolderFirst => regex_artwork.or(embedded_artwork),
+		}
 	}
 }
+
+// Picks the most common value, breaking ties on the smaller value so repeated
+// index updates produce the same result.
+fn dominant_value<T: Ord>(tallies: HashMap<T, usize>) -> Option<T> {
+	tallies
+		.into_iter()
+		.max_by(|(value_a, count_a), (value_b, count_b)| {
+			count_a.cmp(count_b).then_with(|| value_b.cmp(value_a))
+		})
+		.map(|(value, _)| value)
+}
@@ -1,14 +1,18 @@
 use crossbeam_channel::Receiver;
 use diesel::prelude::*;
 use log::error;
+use std::collections::HashMap;
 
-use crate::db::{directories, songs, DB};
+use crate::db::{directories, index_checkpoint, songs, DB};
 
 const INDEX_BUILDING_INSERT_BUFFER_SIZE: usize = 1000; // Insertions in each transaction
 
 #[derive(Debug, Insertable)]
 #[diesel(table_name = songs)]
 pub struct Song {
+	// Backfilled at flush time for rows that already exist, so ids are stable
+	// across reindexing
+	pub id: Option<i32>,
 	pub path: String,
 	pub parent: String,
 	pub track_number: Option<i32>,
@@ -24,11 +28,22 @@ pub struct Song {
 	pub composer: Option<String>,
 	pub genre: Option<String>,
 	pub label: Option<String>,
+	pub bpm: Option<i32>,
+	pub initial_key: Option<String>,
+	pub encoder_delay: Option<i32>,
+	pub encoder_padding: Option<i32>,
+	pub search_normalized: String,
+	pub file_size: i64,
+	pub tags_inferred: bool,
+	pub track_total: Option<i32>,
+	pub disc_total: Option<i32>,
+	pub date_added: i32,
 }
 
 #[derive(Debug, Insertable)]
 #[diesel(table_name = directories)]
 pub struct Directory {
+	pub id: Option<i32>,
 	pub path: String,
 	pub parent: Option<String>,
 	pub artist: Option<String>,
@@ -36,28 +51,41 @@ pub struct Directory {
 	pub album: Option<String>,
 	pub artwork: Option<String>,
 	pub date_added: i32,
+	pub is_compilation: bool,
+	pub track_count: i32,
+	pub search_normalized: String,
 }
 
 pub enum Item {
-	Directory(Directory),
-	Song(Song),
+	Directory(Box<Directory>),
+	Song(Box<Song>),
 }
 
 pub struct Inserter {
 	receiver: Receiver<Item>,
 	new_directories: Vec<Directory>,
 	new_songs: Vec<Song>,
+	new_checkpoints: Vec<String>,
+	buffer_size: usize,
+	statements_executed: usize,
 	db: DB,
 }
 
 impl Inserter {
 	pub fn new(db: DB, receiver: Receiver<Item>) -> Self {
-		let new_directories = Vec::with_capacity(INDEX_BUILDING_INSERT_BUFFER_SIZE);
-		let new_songs = Vec::with_capacity(INDEX_BUILDING_INSERT_BUFFER_SIZE);
+		Self::with_buffer_size(db, receiver, INDEX_BUILDING_INSERT_BUFFER_SIZE)
+	}
+
+	pub fn with_buffer_size(db: DB, receiver: Receiver<Item>, buffer_size: usize) -> Self {
+		let new_directories = Vec::with_capacity(buffer_size);
+		let new_songs = Vec::with_capacity(buffer_size);
 		Self {
 			receiver,
 			new_directories,
 			new_songs,
+			new_checkpoints: Vec::with_capacity(buffer_size),
+			buffer_size,
+			statements_executed: 0,
 			db,
 		}
 	}
@@ -68,17 +96,34 @@ impl Inserter {
 		}
 	}
 
+	// Number of insert statements issued so far. One statement covers an
+	// entire batch, so this should stay far below the number of rows.
+	#[cfg(test)]
+	pub fn statements_executed(&self) -> usize {
+		self.statements_executed
+	}
+
+	pub fn flush(&mut self) {
+		if !self.new_directories.is_empty() {
+			self.flush_directories();
+		}
+		if !self.new_songs.is_empty() {
+			self.flush_songs();
+		}
+	}
+
 	fn insert_item(&mut self, insert: Item) {
 		match insert {
 			Item::Directory(d) => {
-				self.new_directories.push(d);
-				if self.new_directories.len() >= INDEX_BUILDING_INSERT_BUFFER_SIZE {
+				self.new_checkpoints.push(d.path.clone());
+				self.new_directories.push(*d);
+				if self.new_directories.len() >= self.buffer_size {
 					self.flush_directories();
 				}
 			}
 			Item::Song(s) => {
-				self.new_songs.push(s);
-				if self.new_songs.len() >= INDEX_BUILDING_INSERT_BUFFER_SIZE {
+				self.new_songs.push(*s);
+				if self.new_songs.len() >= self.buffer_size {
 					self.flush_songs();
 				}
 			}
@@ -86,23 +131,68 @@ impl Inserter {
 	}
 
 	fn flush_directories(&mut self) {
+		// The collector sends every song of a directory before the directory
+		// itself, so flushing songs first makes a checkpointed directory imply
+		// that its songs are durable too
+		if !self.new_songs.is_empty() {
+			self.flush_songs();
+		}
+		self.statements_executed += 1;
 		let res = self.db.connect().ok().and_then(|mut connection| {
-			diesel::insert_into(directories::table)
-				.values(&self.new_directories)
-				.execute(&mut *connection) // TODO https://github.com/diesel-rs/diesel/issues/1822
+			connection
+				.transaction(|connection| {
+					// Directories that are merely being rescanned keep their id
+					let paths: Vec<&String> = self.new_directories.iter().map(|d| &d.path).collect();
+					let existing_ids: HashMap<String, i32> = directories::table
+						.filter(directories::path.eq_any(&paths))
+						.select((directories::path, directories::id))
+						.load::<(String, i32)>(connection)?
+						.into_iter()
+						.collect();
+					for directory in &mut self.new_directories {
+						directory.id = existing_ids.get(&directory.path).copied();
+					}
+					diesel::replace_into(directories::table)
+						.values(&self.new_directories)
+						.execute(connection)?; // TODO https://github.com/diesel-rs/diesel/issues/1822
+					let checkpoints: Vec<_> = self
+						.new_checkpoints
+						.iter()
+						.map(|path| index_checkpoint::path.eq(path))
+						.collect();
+					diesel::replace_into(index_checkpoint::table)
+						.values(&checkpoints)
+						.execute(connection)
+				})
 				.ok()
 		});
 		if res.is_none() {
 			error!("Could not insert new directories in database");
 		}
 		self.new_directories.clear();
+		self.new_checkpoints.clear();
 	}
 
 	fn flush_songs(&mut self) {
+		self.statements_executed += 1;
 		let res = self.db.connect().ok().and_then(|mut connection| {
-			diesel::insert_into(songs::table)
-				.values(&self.new_songs)
-				.execute(&mut *connection) // TODO https://github.com/diesel-rs/diesel/issues/1822
+			connection
+				.transaction(|connection| {
+					// Songs that are merely being rescanned keep their id
+					let paths: Vec<&String> = self.new_songs.iter().map(|s| &s.path).collect();
+					let existing_ids: HashMap<String, i32> = songs::table
+						.filter(songs::path.eq_any(&paths))
+						.select((songs::path, songs::id))
+						.load::<(String, i32)>(connection)?
+						.into_iter()
+						.collect();
+					for song in &mut self.new_songs {
+						song.id = existing_ids.get(&song.path).copied();
+					}
+					diesel::replace_into(songs::table)
+						.values(&self.new_songs)
+						.execute(connection) // TODO https://github.com/diesel-rs/diesel/issues/1822
+				})
 				.ok()
 		});
 		if res.is_none() {
@@ -114,11 +204,67 @@ impl Inserter {
 
 impl Drop for Inserter {
 	fn drop(&mut self) {
-		if !self.new_directories.is_empty() {
-			self.flush_directories();
+		self.flush();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::app::test;
+	use crate::test_name;
+
+	fn song(index: usize) -> Song {
+		Song {
+			id: None,
+			path: format!("root/song {}.mp3", index),
+			parent: "root".to_owned(),
+			track_number: None,
+			disc_number: None,
+			title: Some(format!("Song {}", index)),
+			artist: None,
+			album_artist: None,
+			year: None,
+			album: None,
+			artwork: None,
+			duration: None,
+			lyricist: None,
+			composer: None,
+			genre: None,
+			label: None,
+			bpm: None,
+			initial_key: None,
+			encoder_delay: None,
+			encoder_padding: None,
+			search_normalized: format!("root/song {}.mp3", index),
+			file_size: 0,
+			tags_inferred: false,
+			track_total: None,
+			disc_total: None,
+			date_added: 0,
 		}
-		if !self.new_songs.is_empty() {
-			self.flush_songs();
+	}
+
+	#[test]
+	fn insertions_are_batched() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let (sender, receiver) = crossbeam_channel::unbounded();
+		let mut inserter = Inserter::with_buffer_size(ctx.db.clone(), receiver, 10);
+
+		for i in 0..25 {
+			sender.send(Item::Song(Box::new(song(i)))).unwrap();
 		}
+		drop(sender);
+		inserter.insert();
+
+		// Two full batches of ten, five rows still buffered
+		assert_eq!(inserter.statements_executed(), 2);
+
+		inserter.flush();
+		assert_eq!(inserter.statements_executed(), 3);
+
+		let mut connection = ctx.db.connect().unwrap();
+		let song_count: i64 = songs::table.count().get_result(&mut connection).unwrap();
+		assert_eq!(song_count, 25);
 	}
 }
@@ -3,18 +3,26 @@ use log::{error, info};
 use std::cmp::min;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
 use crate::app::index::metadata::{self, SongTags};
+use crate::app::settings::{PartialFilePolicy, TagParsing};
+use crate::utils;
+
+// No valid audio file fits in fewer bytes than this. Anything smaller with an
+// audio extension is assumed to be a partially synced copy still being written.
+pub const MIN_AUDIO_FILE_SIZE_BYTES: u64 = 128;
 
 #[derive(Debug)]
 pub struct Song {
 	pub path: PathBuf,
+	pub file_size: i64,
 	pub metadata: SongTags,
+	pub created: i32,
 }
 
 #[derive(Debug)]
@@ -28,28 +36,75 @@ pub struct Directory {
 
 pub struct Traverser {
 	directory_sender: Sender<Directory>,
+	follow_symlinks: bool,
+	tag_parsing: TagParsing,
+	partial_file_policy: PartialFilePolicy,
+	num_threads: usize,
+	completed_directories: Arc<HashSet<PathBuf>>,
+	parse_errors: Arc<AtomicUsize>,
+}
+
+// Indexing root with a recursion limit relative to that root. A limit of zero
+// means unlimited.
+#[derive(Debug)]
+pub struct Root {
+	pub path: PathBuf,
+	pub max_depth: u32,
 }
 
 #[derive(Debug)]
 struct WorkItem {
 	parent: Option<PathBuf>,
 	path: PathBuf,
+	depth: u32,
+	max_depth: u32,
 }
 
 impl Traverser {
-	pub fn new(directory_sender: Sender<Directory>) -> Self {
-		Self { directory_sender }
+	pub fn new(
+		directory_sender: Sender<Directory>,
+		follow_symlinks: bool,
+		tag_parsing: TagParsing,
+		partial_file_policy: PartialFilePolicy,
+		num_threads: usize,
+		completed_directories: HashSet<PathBuf>,
+	) -> Self {
+		Self {
+			directory_sender,
+			follow_symlinks,
+			tag_parsing,
+			partial_file_policy,
+			num_threads,
+			completed_directories: Arc::new(completed_directories),
+			parse_errors: Arc::new(AtomicUsize::new(0)),
+		}
 	}
 
-	pub fn traverse(&self, roots: Vec<PathBuf>) {
+	// Number of files whose tags could not be parsed under the active policy.
+	// Only strict parsing reports errors; lenient parsing swallows them.
+	pub fn parse_error_count(&self) -> usize {
+		self.parse_errors.load(Ordering::Relaxed)
+	}
+
+	pub fn traverse(&self, roots: Vec<Root>) {
 		let num_pending_work_items = Arc::new(AtomicUsize::new(roots.len()));
 		let (work_item_sender, work_item_receiver) = crossbeam_channel::unbounded();
 
-		let key = "POLARIS_NUM_TRAVERSER_THREADS";
-		let num_threads = std::env::var_os(key)
-			.map(|v| v.to_string_lossy().to_string())
-			.and_then(|v| usize::from_str(&v).ok())
-			.unwrap_or_else(|| min(num_cpus::get(), 4));
+		let visited_directories = Arc::new(Mutex::new(HashSet::new()));
+		if self.follow_symlinks {
+			let mut visited = visited_directories.lock().unwrap();
+			for root in &roots {
+				let canonical = fs::canonicalize(&root.path).unwrap_or_else(|_| root.path.clone());
+				visited.insert(canonical);
+			}
+		}
+
+		// Zero means automatic: one thread per CPU, capped to keep slow disks
+		// from thrashing
+		let num_threads = match self.num_threads {
+			0 => min(num_cpus::get(), 4),
+			n => n,
+		};
 		info!("Browsing collection using {} threads", num_threads);
 
 		let mut threads = Vec::new();
@@ -58,12 +113,24 @@ impl Traverser {
 			let work_item_receiver = work_item_receiver.clone();
 			let directory_sender = self.directory_sender.clone();
 			let num_pending_work_items = num_pending_work_items.clone();
+			let visited_directories = visited_directories.clone();
+			let follow_symlinks = self.follow_symlinks;
+			let tag_parsing = self.tag_parsing;
+			let partial_file_policy = self.partial_file_policy;
+			let completed_directories = self.completed_directories.clone();
+			let parse_errors = self.parse_errors.clone();
 			threads.push(thread::spawn(move || {
 				let worker = Worker {
 					work_item_sender,
 					work_item_receiver,
 					directory_sender,
 					num_pending_work_items,
+					visited_directories,
+					follow_symlinks,
+					tag_parsing,
+					partial_file_policy,
+					completed_directories,
+					parse_errors,
 				};
 				worker.run();
 			}));
@@ -72,7 +139,9 @@ impl Traverser {
 		for root in roots {
 			let work_item = WorkItem {
 				parent: None,
-				path: root,
+				path: root.path,
+				depth: 0,
+				max_depth: root.max_depth,
 			};
 			if let Err(e) = work_item_sender.send(work_item) {
 				error!("Error initializing traverser: {:#?}", e);
@@ -92,6 +161,12 @@ struct Worker {
 	work_item_receiver: Receiver<WorkItem>,
 	directory_sender: Sender<Directory>,
 	num_pending_work_items: Arc<AtomicUsize>,
+	visited_directories: Arc<Mutex<HashSet<PathBuf>>>,
+	follow_symlinks: bool,
+	tag_parsing: TagParsing,
+	partial_file_policy: PartialFilePolicy,
+	completed_directories: Arc<HashSet<PathBuf>>,
+	parse_errors: Arc<AtomicUsize>,
 }
 
 impl Worker {
@@ -134,6 +209,11 @@ impl Worker {
 	}
 
 	pub fn process_work_item(&self, work_item: WorkItem) {
+		// Directories checkpointed by an interrupted scan are already durably
+		// indexed. They are only walked to reach unvisited children, without
+		// re-parsing their files.
+		let already_indexed = self.completed_directories.contains(&work_item.path);
+
 		let read_dir = match fs::read_dir(&work_item.path) {
 			Ok(read_dir) => read_dir,
 			Err(e) => {
@@ -163,33 +243,96 @@ impl Worker {
 				}
 			};
 
+			let is_symlink = fs::symlink_metadata(&path)
+				.map(|m| m.file_type().is_symlink())
+				.unwrap_or(false);
+			if is_symlink && !self.follow_symlinks {
+				continue;
+			}
+
 			if path.is_dir() {
+				if self.follow_symlinks && !self.mark_directory_visited(&path) {
+					continue;
+				}
 				sub_directories.push(path);
-			} else if let Some(metadata) = metadata::read(&path) {
-				songs.push(Song { path, metadata });
+			} else if already_indexed {
+				continue;
 			} else {
-				other_files.push(path);
+				let file_size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+				if file_size < MIN_AUDIO_FILE_SIZE_BYTES && utils::get_audio_format(&path).is_some()
+				{
+					match self.partial_file_policy {
+						PartialFilePolicy::Skip => {
+							info!(
+								"Skipping `{}`: file is too small to be valid audio, sync may be in progress",
+								path.display()
+							);
+						}
+						PartialFilePolicy::IndexAsPending => {
+							let created = Self::get_date_created(&path).unwrap_or_default();
+							songs.push(Song {
+								path,
+								file_size: file_size as i64,
+								metadata: SongTags::default(),
+								created,
+							});
+						}
+					}
+					continue;
+				}
+				match metadata::read(&path, self.tag_parsing) {
+					Ok(Some(metadata)) => {
+						let created = Self::get_date_created(&path).unwrap_or_default();
+						songs.push(Song {
+							path,
+							file_size: file_size as i64,
+							metadata,
+							created,
+						});
+					}
+					Ok(None) => other_files.push(path),
+					Err(e) => {
+						error!("Error while reading file metadata for '{:?}': {}", path, e);
+						self.parse_errors.fetch_add(1, Ordering::Relaxed);
+						other_files.push(path);
+					}
+				}
 			}
 		}
 
-		let created = Self::get_date_created(&work_item.path).unwrap_or_default();
+		if !already_indexed {
+			let created = Self::get_date_created(&work_item.path).unwrap_or_default();
 
-		self.emit_directory(Directory {
-			path: work_item.path.to_owned(),
-			parent: work_item.parent,
-			songs,
-			other_files,
-			created,
-		});
+			self.emit_directory(Directory {
+				path: work_item.path.to_owned(),
+				parent: work_item.parent,
+				songs,
+				other_files,
+				created,
+			});
+		}
+
+		// Directories beyond the per-mount depth limit are skipped entirely
+		let sub_directory_depth = work_item.depth + 1;
+		if work_item.max_depth != 0 && sub_directory_depth >= work_item.max_depth {
+			return;
+		}
 
 		for sub_directory in sub_directories.into_iter() {
 			self.queue_work(WorkItem {
 				parent: Some(work_item.path.clone()),
 				path: sub_directory,
+				depth: sub_directory_depth,
+				max_depth: work_item.max_depth,
 			});
 		}
 	}
 
+	fn mark_directory_visited(&self, path: &Path) -> bool {
+		let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+		self.visited_directories.lock().unwrap().insert(canonical)
+	}
+
 	fn get_date_created(path: &Path) -> Option<i32> {
 		if let Ok(t) = fs::metadata(path).and_then(|m| m.created().or_else(|_| m.modified())) {
 			t.duration_since(std::time::UNIX_EPOCH)
@@ -1,15 +1,24 @@
+use diesel::prelude::*;
+use log::error;
 use rustfm_scrobble::{Scrobble, Scrobbler};
 use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use user::AuthToken;
 
 use crate::app::{
 	index::{Index, QueryError},
 	user,
 };
+use crate::db::{self, lastfm_scrobbles, DB};
 
 const LASTFM_API_KEY: &str = "02b96c939a2b451c31dfd67add1f696e";
 const LASTFM_API_SECRET: &str = "0f25a80ceef4b470b5cb97d99d4b3420";
 
+// How often the background worker re-attempts queued scrobbles
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(30);
+// Scrobbles that keep failing are dropped after this many submission attempts
+const MAX_SCROBBLE_ATTEMPTS: i32 = 48;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
 	#[error("Failed to authenticate with last.fm")]
@@ -22,17 +31,39 @@ pub enum Error {
 	Query(#[from] QueryError),
 	#[error(transparent)]
 	User(#[from] user::Error),
+	#[error(transparent)]
+	DatabaseConnection(#[from] db::Error),
+	#[error(transparent)]
+	Database(#[from] diesel::result::Error),
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = lastfm_scrobbles)]
+struct NewQueuedScrobble<'a> {
+	username: &'a str,
+	path: &'a str,
+	queued_at: i32,
+}
+
+#[derive(Debug, Queryable)]
+struct QueuedScrobble {
+	id: i32,
+	username: String,
+	path: String,
+	attempts: i32,
 }
 
 #[derive(Clone)]
 pub struct Manager {
+	db: DB,
 	index: Index,
 	user_manager: user::Manager,
 }
 
 impl Manager {
-	pub fn new(index: Index, user_manager: user::Manager) -> Self {
+	pub fn new(db: DB, index: Index, user_manager: user::Manager) -> Self {
 		Self {
+			db,
 			index,
 			user_manager,
 		}
@@ -61,6 +92,83 @@ impl Manager {
 			.map_err(|e| e.into())
 	}
 
+	// Scrobbles are submitted from a background worker so that playback
+	// reporting stays fast even when last.fm is slow or unreachable
+	pub fn enqueue_scrobble(&self, username: &str, track: &Path) -> Result<(), Error> {
+		let queued_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i32)
+			.unwrap_or_default();
+		let mut connection = self.db.connect()?;
+		diesel::insert_into(lastfm_scrobbles::table)
+			.values(NewQueuedScrobble {
+				username,
+				path: &track.to_string_lossy(),
+				queued_at,
+			})
+			.execute(&mut connection)?;
+		Ok(())
+	}
+
+	pub fn begin_queued_submissions(&self) {
+		let manager = self.clone();
+		std::thread::spawn(move || loop {
+			if let Err(e) = manager.submit_queued_scrobbles() {
+				error!("Error while submitting queued scrobbles: {}", e);
+			}
+			std::thread::sleep(QUEUE_POLL_INTERVAL);
+		});
+	}
+
+	// Failed submissions stay in the queue with an incremented attempt count,
+	// so they survive restarts and get retried on the next pass
+	pub fn submit_queued_scrobbles(&self) -> Result<(), Error> {
+		let queued: Vec<QueuedScrobble> = {
+			let mut connection = self.db.connect()?;
+			lastfm_scrobbles::table
+				.select((
+					lastfm_scrobbles::id,
+					lastfm_scrobbles::username,
+					lastfm_scrobbles::path,
+					lastfm_scrobbles::attempts,
+				))
+				.order(lastfm_scrobbles::id.asc())
+				.load(&mut connection)?
+		};
+
+		for entry in queued {
+			let result = self.scrobble(&entry.username, Path::new(&entry.path));
+			let mut connection = self.db.connect()?;
+			match result {
+				Ok(()) => {
+					diesel::delete(
+						lastfm_scrobbles::table.filter(lastfm_scrobbles::id.eq(entry.id)),
+					)
+					.execute(&mut connection)?;
+				}
+				Err(e) if entry.attempts + 1 >= MAX_SCROBBLE_ATTEMPTS => {
+					error!(
+						"Giving up on scrobble of `{}` for user {}: {}",
+						entry.path, entry.username, e
+					);
+					diesel::delete(
+						lastfm_scrobbles::table.filter(lastfm_scrobbles::id.eq(entry.id)),
+					)
+					.execute(&mut connection)?;
+				}
+				Err(_) => {
+					diesel::update(
+						lastfm_scrobbles::table.filter(lastfm_scrobbles::id.eq(entry.id)),
+					)
+					.set(lastfm_scrobbles::attempts.eq(entry.attempts + 1))
+					.execute(&mut connection)?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
 	pub fn scrobble(&self, username: &str, track: &Path) -> Result<(), Error> {
 		let mut scrobbler = Scrobbler::new(LASTFM_API_KEY, LASTFM_API_SECRET);
 		let scrobble = self.scrobble_from_path(track)?;
@@ -90,3 +198,62 @@ impl Manager {
 		))
 	}
 }
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use crate::app::test;
+	use crate::test_name;
+
+	const TEST_USERNAME: &str = "test_user";
+	const TEST_PASSWORD: &str = "password";
+
+	fn list_queue(ctx: &test::Context) -> Vec<(String, String, i32)> {
+		let mut connection = ctx.db.connect().unwrap();
+		lastfm_scrobbles::table
+			.select((
+				lastfm_scrobbles::username,
+				lastfm_scrobbles::path,
+				lastfm_scrobbles::attempts,
+			))
+			.order(lastfm_scrobbles::id.asc())
+			.load(&mut connection)
+			.unwrap()
+	}
+
+	#[test]
+	fn enqueue_scrobble_adds_to_queue() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USERNAME, TEST_PASSWORD, false)
+			.build();
+
+		ctx.lastfm_manager
+			.enqueue_scrobble(TEST_USERNAME, Path::new("root/song.mp3"))
+			.unwrap();
+
+		assert_eq!(
+			list_queue(&ctx),
+			vec![(TEST_USERNAME.to_owned(), "root/song.mp3".to_owned(), 0)]
+		);
+	}
+
+	#[test]
+	fn failed_submissions_are_kept_for_retry() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USERNAME, TEST_PASSWORD, false)
+			.build();
+
+		// The user has no last.fm session key, so the submission fails without
+		// reaching out to last.fm
+		ctx.lastfm_manager
+			.enqueue_scrobble(TEST_USERNAME, Path::new("root/song.mp3"))
+			.unwrap();
+		ctx.lastfm_manager.submit_queued_scrobbles().unwrap();
+
+		assert_eq!(
+			list_queue(&ctx),
+			vec![(TEST_USERNAME.to_owned(), "root/song.mp3".to_owned(), 1)]
+		);
+	}
+}
@@ -0,0 +1,142 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use regex::Regex;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+const BROADCAST_CAPACITY: usize = 64;
+
+// Number of recent lines replayed to a subscriber when it connects
+const BACKLOG_CAPACITY: usize = 100;
+
+// Values following these field names are stripped from log lines before they
+// are buffered, so remote diagnosis cannot leak credentials
+const REDACTED_FIELDS: &str = "password|passwd|secret|token|auth_token|authorization|api_key";
+
+#[derive(Clone)]
+pub struct Manager {
+	backlog: Arc<Mutex<VecDeque<String>>>,
+	broadcast: broadcast::Sender<String>,
+	redactor: Regex,
+}
+
+impl Default for Manager {
+	fn default() -> Self {
+		let (broadcast, _) = broadcast::channel(BROADCAST_CAPACITY);
+		let redactor = Regex::new(&format!(r#"(?i)\b({})\b["'\s]*[=:]["'\s]*[^\s"'&,;]+"#, REDACTED_FIELDS))
+			.expect("Invalid log redaction regex");
+		Self {
+			backlog: Arc::new(Mutex::new(VecDeque::with_capacity(BACKLOG_CAPACITY))),
+			broadcast,
+			redactor,
+		}
+	}
+}
+
+impl Manager {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn record(&self, line: &str) {
+		let line = self.redact(line);
+		{
+			let mut backlog = self.backlog.lock().unwrap();
+			if backlog.len() == BACKLOG_CAPACITY {
+				backlog.pop_front();
+			}
+			backlog.push_back(line.clone());
+		}
+		// Errors only indicate that there are no subscribers
+		self.broadcast.send(line).ok();
+	}
+
+	pub fn backlog(&self) -> Vec<String> {
+		self.backlog.lock().unwrap().iter().cloned().collect()
+	}
+
+	pub fn subscribe(&self) -> broadcast::Receiver<String> {
+		self.broadcast.subscribe()
+	}
+
+	fn redact(&self, line: &str) -> String {
+		self.redactor
+			.replace_all(line, "$1=[REDACTED]")
+			.into_owned()
+	}
+}
+
+impl Log for Manager {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= Level::Info
+	}
+
+	fn log(&self, record: &Record) {
+		if self.enabled(record.metadata()) {
+			self.record(&format!("[{}] {}", record.level(), record.args()));
+		}
+	}
+
+	fn flush(&self) {}
+}
+
+impl simplelog::SharedLogger for Manager {
+	fn level(&self) -> LevelFilter {
+		LevelFilter::Info
+	}
+
+	fn config(&self) -> Option<&simplelog::Config> {
+		None
+	}
+
+	fn as_log(self: Box<Self>) -> Box<dyn Log> {
+		self
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	#[test]
+	fn messages_reach_subscribers() {
+		let manager = Manager::new();
+		let mut receiver = manager.subscribe();
+
+		manager.record("Starting up server");
+
+		assert_eq!(receiver.try_recv(), Ok("Starting up server".to_owned()));
+		assert_eq!(manager.backlog(), vec!["Starting up server".to_owned()]);
+	}
+
+	#[test]
+	fn sensitive_values_are_redacted() {
+		let manager = Manager::new();
+		let mut receiver = manager.subscribe();
+
+		manager.record("Rejected request with auth_token=abcdef123456 from 10.0.0.1");
+		manager.record("Config contains password: hunter2");
+
+		let line = receiver.try_recv().unwrap();
+		assert!(!line.contains("abcdef123456"));
+		assert_eq!(
+			line,
+			"Rejected request with auth_token=[REDACTED] from 10.0.0.1"
+		);
+
+		let line = receiver.try_recv().unwrap();
+		assert!(!line.contains("hunter2"));
+	}
+
+	#[test]
+	fn backlog_is_bounded() {
+		let manager = Manager::new();
+		for n in 0..(BACKLOG_CAPACITY + 10) {
+			manager.record(&format!("line {}", n));
+		}
+		let backlog = manager.backlog();
+		assert_eq!(backlog.len(), BACKLOG_CAPACITY);
+		assert_eq!(backlog[0], "line 10");
+	}
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+const BROADCAST_CAPACITY: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Update {
+	pub username: String,
+	pub path: String,
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	latest: Arc<Mutex<HashMap<String, String>>>,
+	broadcast: broadcast::Sender<Update>,
+}
+
+impl Default for Manager {
+	fn default() -> Self {
+		let (broadcast, _) = broadcast::channel(BROADCAST_CAPACITY);
+		Self {
+			latest: Arc::new(Mutex::new(HashMap::new())),
+			broadcast,
+		}
+	}
+}
+
+impl Manager {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn set(&self, username: &str, path: &str) {
+		self.latest
+			.lock()
+			.unwrap()
+			.insert(username.to_owned(), path.to_owned());
+		// Errors only indicate that there are no subscribers
+		self.broadcast
+			.send(Update {
+				username: username.to_owned(),
+				path: path.to_owned(),
+			})
+			.ok();
+	}
+
+	pub fn get(&self, username: &str) -> Option<String> {
+		self.latest.lock().unwrap().get(username).cloned()
+	}
+
+	pub fn subscribe(&self) -> broadcast::Receiver<Update> {
+		self.broadcast.subscribe()
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	#[test]
+	fn updates_reach_subscribers() {
+		let manager = Manager::new();
+		let mut receiver = manager.subscribe();
+
+		manager.set("alice", "root/Khemmis/Hunted/01 - Above The Water.mp3");
+
+		assert_eq!(
+			manager.get("alice"),
+			Some("root/Khemmis/Hunted/01 - Above The Water.mp3".to_owned())
+		);
+		assert_eq!(manager.get("bob"), None);
+		assert_eq!(
+			receiver.try_recv(),
+			Ok(Update {
+				username: "alice".to_owned(),
+				path: "root/Khemmis/Hunted/01 - Above The Water.mp3".to_owned(),
+			})
+		);
+		assert!(receiver.try_recv().is_err());
+	}
+}
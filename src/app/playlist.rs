@@ -5,6 +5,7 @@ use diesel::BelongingToDsl;
 use std::path::Path;
 
 use crate::app::index::Song;
+use crate::app::settings;
 use crate::app::vfs;
 use crate::db::{self, playlist_songs, playlists, users, DB};
 
@@ -18,6 +19,10 @@ pub enum Error {
 	UserNotFound,
 	#[error("Playlist not found")]
 	PlaylistNotFound,
+	#[error("Playlist quota exceeded")]
+	QuotaExceeded,
+	#[error(transparent)]
+	Settings(#[from] settings::Error),
 	#[error(transparent)]
 	Vfs(#[from] vfs::Error),
 }
@@ -26,11 +31,16 @@ pub enum Error {
 pub struct Manager {
 	db: DB,
 	vfs_manager: vfs::Manager,
+	settings_manager: settings::Manager,
 }
 
 impl Manager {
-	pub fn new(db: DB, vfs_manager: vfs::Manager) -> Self {
-		Self { db, vfs_manager }
+	pub fn new(db: DB, vfs_manager: vfs::Manager, settings_manager: settings::Manager) -> Self {
+		Self {
+			db,
+			vfs_manager,
+			settings_manager,
+		}
 	}
 
 	pub fn list_playlists(&self, owner: &str) -> Result<Vec<String>, Error> {
@@ -64,6 +74,11 @@ impl Manager {
 		let new_playlist: NewPlaylist;
 		let playlist: Playlist;
 		let vfs = self.vfs_manager.get_vfs()?;
+		let quotas = self.settings_manager.read()?;
+
+		if content.len() > quotas.max_songs_per_playlist as usize {
+			return Err(Error::QuotaExceeded);
+		}
 
 		{
 			let mut connection = self.db.connect()?;
@@ -79,6 +94,24 @@ impl Manager {
 					.ok_or(Error::UserNotFound)?
 			};
 
+			// Enforce the playlist quota, except when overwriting an existing playlist
+			{
+				use self::playlists::dsl::*;
+				let existing: Option<i32> = Playlist::belonging_to(&user)
+					.filter(name.eq(playlist_name))
+					.select(id)
+					.first(&mut connection)
+					.optional()?;
+				if existing.is_none() {
+					let playlist_count: i64 = Playlist::belonging_to(&user)
+						.count()
+						.get_result(&mut connection)?;
+					if playlist_count >= quotas.max_playlists_per_user as i64 {
+						return Err(Error::QuotaExceeded);
+					}
+				}
+			}
+
 			// Create playlist
 			new_playlist = NewPlaylist {
 				name: playlist_name.into(),
@@ -166,7 +199,7 @@ impl Manager {
 			// Select songs. Not using Diesel because we need to LEFT JOIN using a custom column
 			let query = diesel::sql_query(
 				r#"
-			SELECT s.id, s.path, s.parent, s.track_number, s.disc_number, s.title, s.artist, s.album_artist, s.year, s.album, s.artwork, s.duration, s.lyricist, s.composer, s.genre, s.label
+			SELECT s.id, s.path, s.parent, s.track_number, s.disc_number, s.title, s.artist, s.album_artist, s.year, s.album, s.artwork, s.duration, s.lyricist, s.composer, s.genre, s.label, s.bpm, s.initial_key, s.encoder_delay, s.encoder_padding, s.search_normalized, s.file_size, s.tags_inferred, s.track_total, s.disc_total, s.date_added
 			FROM playlist_songs ps
 			LEFT JOIN songs s ON ps.path = s.path
 			WHERE ps.playlist = ?
@@ -282,8 +315,9 @@ mod test {
 
 		let playlist_content: Vec<String> = ctx
 			.index
-			.flatten(Path::new(TEST_MOUNT_NAME))
+			.flatten(Path::new(TEST_MOUNT_NAME), None, None, None)
 			.unwrap()
+			.items
 			.into_iter()
 			.map(|s| s.path)
 			.collect();
@@ -304,6 +338,56 @@ mod test {
 		assert_eq!(songs.len(), 13);
 	}
 
+	#[test]
+	fn save_playlist_rejects_too_many_playlists() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.build();
+
+		ctx.settings_manager
+			.amend(&crate::app::settings::NewSettings {
+				max_playlists_per_user: Some(1),
+				..Default::default()
+			})
+			.unwrap();
+
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &Vec::new())
+			.unwrap();
+
+		// Overwriting an existing playlist stays within quota
+		ctx.playlist_manager
+			.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &Vec::new())
+			.unwrap();
+
+		assert!(matches!(
+			ctx.playlist_manager
+				.save_playlist("One Too Many", TEST_USER, &Vec::new()),
+			Err(crate::app::playlist::Error::QuotaExceeded)
+		));
+	}
+
+	#[test]
+	fn save_playlist_rejects_too_many_songs() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user(TEST_USER, TEST_PASSWORD, false)
+			.build();
+
+		ctx.settings_manager
+			.amend(&crate::app::settings::NewSettings {
+				max_songs_per_playlist: Some(2),
+				..Default::default()
+			})
+			.unwrap();
+
+		let content = vec!["a.mp3".to_owned(), "b.mp3".to_owned(), "c.mp3".to_owned()];
+		assert!(matches!(
+			ctx.playlist_manager
+				.save_playlist(TEST_PLAYLIST_NAME, TEST_USER, &content),
+			Err(crate::app::playlist::Error::QuotaExceeded)
+		));
+	}
+
 	#[test]
 	fn delete_playlist_golden_path() {
 		let ctx = test::ContextBuilder::new(test_name!())
@@ -335,8 +419,9 @@ mod test {
 
 		let playlist_content: Vec<String> = ctx
 			.index
-			.flatten(Path::new(TEST_MOUNT_NAME))
+			.flatten(Path::new(TEST_MOUNT_NAME), None, None, None)
 			.unwrap()
+			.items
 			.into_iter()
 			.map(|s| s.path)
 			.collect();
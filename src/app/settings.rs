@@ -1,10 +1,13 @@
 use diesel::prelude::*;
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use crate::db::{self, misc_settings, DB};
+use crate::db::{self, mime_overrides, misc_settings, DB};
+use crate::utils;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -18,6 +21,8 @@ pub enum Error {
 	MiscSettingsNotFound,
 	#[error("Index album art pattern is not a valid regex")]
 	IndexAlbumArtPatternInvalid,
+	#[error("Filesystem error for `{0}`: `{1}`")]
+	Io(PathBuf, std::io::Error),
 	#[error(transparent)]
 	Database(#[from] diesel::result::Error),
 }
@@ -27,26 +32,231 @@ pub struct AuthSecret {
 	pub key: [u8; 32],
 }
 
-#[derive(Debug, Queryable)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtworkPrecedence {
+	EmbeddedFirst,
+	#[default]
+	FolderFirst,
+}
+
+impl ArtworkPrecedence {
+	pub fn from_setting_string(value: &str) -> Self {
+		match value {
+			"embedded_first" => Self::EmbeddedFirst,
+			_ => Self::FolderFirst,
+		}
+	}
+
+	pub fn as_setting_string(self) -> &'static str {
+		match self {
+			Self::EmbeddedFirst => "embedded_first",
+			Self::FolderFirst => "folder_first",
+		}
+	}
+}
+
+// What the indexer does when the same recording is found under several mounts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+	#[default]
+	KeepAll,
+	PreferFirstMount,
+	Deduplicate,
+}
+
+impl DuplicatePolicy {
+	pub fn from_setting_string(value: &str) -> Self {
+		match value {
+			"prefer_first_mount" => Self::PreferFirstMount,
+			"deduplicate" => Self::Deduplicate,
+			_ => Self::KeepAll,
+		}
+	}
+
+	pub fn as_setting_string(self) -> &'static str {
+		match self {
+			Self::KeepAll => "keep_all",
+			Self::PreferFirstMount => "prefer_first_mount",
+			Self::Deduplicate => "deduplicate",
+		}
+	}
+}
+
+// How the indexer reacts to malformed tags: salvage what it can, or fail the
+// file and record a parse error
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagParsing {
+	Strict,
+	#[default]
+	Lenient,
+}
+
+impl TagParsing {
+	pub fn from_setting_string(value: &str) -> Self {
+		match value {
+			"strict" => Self::Strict,
+			_ => Self::Lenient,
+		}
+	}
+
+	pub fn as_setting_string(self) -> &'static str {
+		match self {
+			Self::Strict => "strict",
+			Self::Lenient => "lenient",
+		}
+	}
+}
+
+// What the indexer does with audio files too small to be valid, which are
+// usually partially synced copies still being written by another program
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialFilePolicy {
+	#[default]
+	Skip,
+	IndexAsPending,
+}
+
+impl PartialFilePolicy {
+	pub fn from_setting_string(value: &str) -> Self {
+		match value {
+			"index_as_pending" => Self::IndexAsPending,
+			_ => Self::Skip,
+		}
+	}
+
+	pub fn as_setting_string(self) -> &'static str {
+		match self {
+			Self::Skip => "skip",
+			Self::IndexAsPending => "index_as_pending",
+		}
+	}
+}
+
+#[derive(Clone, Debug, Queryable)]
 pub struct Settings {
 	pub index_sleep_duration_seconds: i32,
 	pub index_album_art_pattern: String,
+	pub max_playlists_per_user: i32,
+	pub max_songs_per_playlist: i32,
+	pub index_follow_symlinks: bool,
+	pub artwork_precedence: String,
+	pub minimum_client_version: String,
+	pub reject_unversioned_clients: bool,
+	pub max_concurrent_streams_per_user: i32,
+	pub index_infer_tags_from_path: bool,
+	pub reindex_on_startup: bool,
+	pub duplicate_policy: String,
+	pub tag_parsing: String,
+	pub send_security_headers: bool,
+	pub assume_https_behind_proxy: bool,
+	pub default_page_size: i32,
+	pub max_page_size: i32,
+	pub partial_file_policy: String,
+	pub trusted_proxies: String,
+	pub forwarded_ip_header: String,
+	pub auth_log_format: String,
+	pub old_auth_secrets: String,
+	pub num_traverser_threads: i32,
+	pub max_results_per_query: i32,
+	pub thumbnail_read_timeout_seconds: i32,
+	pub thumbnail_max_dimension: i32,
+	pub thumbnail_stored_max_dimension: i32,
+}
+
+// Maps a file extension to the Content-Type served for it, for clients that
+// expect something other than the canonical MIME of a format
+#[derive(Clone, Debug, PartialEq, Eq, Insertable, Queryable, Serialize, Deserialize)]
+#[diesel(table_name = mime_overrides)]
+pub struct MimeOverride {
+	pub extension: String,
+	pub mime: String,
 }
 
 #[derive(Debug, Default, Deserialize)]
 pub struct NewSettings {
 	pub reindex_every_n_seconds: Option<i32>,
 	pub album_art_pattern: Option<String>,
+	pub max_playlists_per_user: Option<i32>,
+	pub max_songs_per_playlist: Option<i32>,
+	pub follow_symlinks: Option<bool>,
+	pub artwork_precedence: Option<ArtworkPrecedence>,
+	pub minimum_client_version: Option<String>,
+	pub reject_unversioned_clients: Option<bool>,
+	pub max_concurrent_streams_per_user: Option<i32>,
+	pub infer_tags_from_path: Option<bool>,
+	pub reindex_on_startup: Option<bool>,
+	pub duplicate_policy: Option<DuplicatePolicy>,
+	pub tag_parsing: Option<TagParsing>,
+	pub send_security_headers: Option<bool>,
+	pub assume_https_behind_proxy: Option<bool>,
+	pub default_page_size: Option<i32>,
+	pub max_page_size: Option<i32>,
+	pub partial_file_policy: Option<PartialFilePolicy>,
+	pub mime_overrides: Option<Vec<MimeOverride>>,
+	pub trusted_proxies: Option<String>,
+	pub forwarded_ip_header: Option<String>,
+	pub auth_log_format: Option<String>,
+	pub old_auth_secrets: Option<String>,
+	pub num_traverser_threads: Option<i32>,
+	pub max_results_per_query: Option<i32>,
+	pub thumbnail_read_timeout_seconds: Option<i32>,
+	pub thumbnail_max_dimension: Option<i32>,
+	pub thumbnail_stored_max_dimension: Option<i32>,
 }
 
 #[derive(Clone)]
 pub struct Manager {
 	pub db: DB,
+	setup_marker_path: Option<PathBuf>,
+	cached_settings: Arc<Mutex<Option<Arc<Settings>>>>,
 }
 
 impl Manager {
 	pub fn new(db: DB) -> Self {
-		Self { db }
+		Self {
+			db,
+			setup_marker_path: None,
+			cached_settings: Arc::new(Mutex::new(None)),
+		}
+	}
+
+	pub fn with_setup_marker_path(mut self, setup_marker_path: PathBuf) -> Self {
+		self.setup_marker_path = Some(setup_marker_path);
+		self
+	}
+
+	// Initial setup must stay closed even if the user table is emptied later, so
+	// completion is recorded both in the database and as a marker file
+	pub fn is_setup_complete(&self) -> Result<bool, Error> {
+		if let Some(ref marker_path) = self.setup_marker_path {
+			if marker_path.exists() {
+				return Ok(true);
+			}
+		}
+		use self::misc_settings::dsl::*;
+		let mut connection = self.db.connect()?;
+		misc_settings
+			.select(setup_complete)
+			.get_result(&mut connection)
+			.map_err(|e| match e {
+				diesel::result::Error::NotFound => Error::MiscSettingsNotFound,
+				e => e.into(),
+			})
+	}
+
+	pub fn mark_setup_complete(&self) -> Result<(), Error> {
+		let mut connection = self.db.connect()?;
+		diesel::update(misc_settings::table)
+			.set(misc_settings::setup_complete.eq(true))
+			.execute(&mut connection)?;
+		if let Some(ref marker_path) = self.setup_marker_path {
+			std::fs::write(marker_path, []).map_err(|e| Error::Io(marker_path.clone(), e))?;
+		}
+		Ok(())
 	}
 
 	pub fn get_auth_secret(&self) -> Result<AuthSecret, Error> {
@@ -72,6 +282,38 @@ impl Manager {
 		))
 	}
 
+	pub fn get_artwork_precedence(&self) -> Result<ArtworkPrecedence, Error> {
+		let settings = self.read()?;
+		Ok(ArtworkPrecedence::from_setting_string(
+			&settings.artwork_precedence,
+		))
+	}
+
+	pub fn get_duplicate_policy(&self) -> Result<DuplicatePolicy, Error> {
+		let settings = self.read()?;
+		Ok(DuplicatePolicy::from_setting_string(
+			&settings.duplicate_policy,
+		))
+	}
+
+	pub fn get_tag_parsing(&self) -> Result<TagParsing, Error> {
+		let settings = self.read()?;
+		Ok(TagParsing::from_setting_string(&settings.tag_parsing))
+	}
+
+	pub fn get_partial_file_policy(&self) -> Result<PartialFilePolicy, Error> {
+		let settings = self.read()?;
+		Ok(PartialFilePolicy::from_setting_string(
+			&settings.partial_file_policy,
+		))
+	}
+
+	// Zero means pick a thread count automatically
+	pub fn get_num_traverser_threads(&self) -> Result<usize, Error> {
+		let settings = self.read()?;
+		Ok(settings.num_traverser_threads.max(0) as usize)
+	}
+
 	pub fn get_index_album_art_pattern(&self) -> Result<Regex, Error> {
 		let settings = self.read()?;
 		let regex = Regex::new(&format!("(?i){}", &settings.index_album_art_pattern))
@@ -84,7 +326,35 @@ impl Manager {
 		let mut connection = self.db.connect()?;
 
 		let settings: Settings = misc_settings
-			.select((index_sleep_duration_seconds, index_album_art_pattern))
+			.select((
+				index_sleep_duration_seconds,
+				index_album_art_pattern,
+				max_playlists_per_user,
+				max_songs_per_playlist,
+				index_follow_symlinks,
+				artwork_precedence,
+				minimum_client_version,
+				reject_unversioned_clients,
+				max_concurrent_streams_per_user,
+				index_infer_tags_from_path,
+				reindex_on_startup,
+				duplicate_policy,
+				tag_parsing,
+				send_security_headers,
+				assume_https_behind_proxy,
+				default_page_size,
+				max_page_size,
+				partial_file_policy,
+				trusted_proxies,
+				forwarded_ip_header,
+				auth_log_format,
+				old_auth_secrets,
+				num_traverser_threads,
+				max_results_per_query,
+				thumbnail_read_timeout_seconds,
+				thumbnail_max_dimension,
+				thumbnail_stored_max_dimension,
+			))
 			.get_result(&mut connection)
 			.map_err(|e| match e {
 				diesel::result::Error::NotFound => Error::MiscSettingsNotFound,
@@ -94,6 +364,18 @@ impl Manager {
 		Ok(settings)
 	}
 
+	// Settings are consulted by middleware on every request; this serves a
+	// cached snapshot so request handling does not query the database each
+	// time. The cache is invalidated whenever settings are amended.
+	pub fn read_cached(&self) -> Result<Arc<Settings>, Error> {
+		if let Some(ref settings) = *self.cached_settings.lock().unwrap() {
+			return Ok(settings.clone());
+		}
+		let settings = Arc::new(self.read()?);
+		*self.cached_settings.lock().unwrap() = Some(settings.clone());
+		Ok(settings)
+	}
+
 	pub fn amend(&self, new_settings: &NewSettings) -> Result<(), Error> {
 		let mut connection = self.db.connect()?;
 
@@ -109,6 +391,291 @@ impl Manager {
 				.execute(&mut connection)?;
 		}
 
+		if let Some(max_playlists) = new_settings.max_playlists_per_user {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::max_playlists_per_user.eq(max_playlists))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(max_songs) = new_settings.max_songs_per_playlist {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::max_songs_per_playlist.eq(max_songs))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(follow_symlinks) = new_settings.follow_symlinks {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::index_follow_symlinks.eq(follow_symlinks))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(precedence) = new_settings.artwork_precedence {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::artwork_precedence.eq(precedence.as_setting_string()))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(ref minimum_version) = new_settings.minimum_client_version {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::minimum_client_version.eq(minimum_version))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(reject_unversioned) = new_settings.reject_unversioned_clients {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::reject_unversioned_clients.eq(reject_unversioned))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(max_streams) = new_settings.max_concurrent_streams_per_user {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::max_concurrent_streams_per_user.eq(max_streams))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(infer_tags) = new_settings.infer_tags_from_path {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::index_infer_tags_from_path.eq(infer_tags))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(reindex_on_startup) = new_settings.reindex_on_startup {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::reindex_on_startup.eq(reindex_on_startup))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(policy) = new_settings.duplicate_policy {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::duplicate_policy.eq(policy.as_setting_string()))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(tag_parsing) = new_settings.tag_parsing {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::tag_parsing.eq(tag_parsing.as_setting_string()))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(send_headers) = new_settings.send_security_headers {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::send_security_headers.eq(send_headers))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(assume_https) = new_settings.assume_https_behind_proxy {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::assume_https_behind_proxy.eq(assume_https))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(page_size) = new_settings.default_page_size {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::default_page_size.eq(page_size))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(page_size) = new_settings.max_page_size {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::max_page_size.eq(page_size))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(policy) = new_settings.partial_file_policy {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::partial_file_policy.eq(policy.as_setting_string()))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(ref overrides) = new_settings.mime_overrides {
+			self.set_mime_overrides(overrides)?;
+		}
+
+		if let Some(ref proxies) = new_settings.trusted_proxies {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::trusted_proxies.eq(proxies))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(ref header) = new_settings.forwarded_ip_header {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::forwarded_ip_header.eq(header))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(ref format) = new_settings.auth_log_format {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::auth_log_format.eq(format))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(ref secrets) = new_settings.old_auth_secrets {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::old_auth_secrets.eq(secrets))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(num_threads) = new_settings.num_traverser_threads {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::num_traverser_threads.eq(num_threads))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(max_results) = new_settings.max_results_per_query {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::max_results_per_query.eq(max_results))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(seconds) = new_settings.thumbnail_read_timeout_seconds {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::thumbnail_read_timeout_seconds.eq(seconds))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(dimension) = new_settings.thumbnail_max_dimension {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::thumbnail_max_dimension.eq(dimension))
+				.execute(&mut connection)?;
+		}
+
+		if let Some(dimension) = new_settings.thumbnail_stored_max_dimension {
+			diesel::update(misc_settings::table)
+				.set(misc_settings::thumbnail_stored_max_dimension.eq(dimension))
+				.execute(&mut connection)?;
+		}
+
+		*self.cached_settings.lock().unwrap() = None;
+
 		Ok(())
 	}
+
+	pub fn get_mime_overrides(&self) -> Result<Vec<MimeOverride>, Error> {
+		use self::mime_overrides::dsl::*;
+		let mut connection = self.db.connect()?;
+		let overrides = mime_overrides
+			.select((extension, mime))
+			.load(&mut connection)?;
+		Ok(overrides)
+	}
+
+	pub fn set_mime_overrides(&self, overrides: &[MimeOverride]) -> Result<(), Error> {
+		let mut connection = self.db.connect()?;
+		connection.transaction::<_, diesel::result::Error, _>(|connection| {
+			use self::mime_overrides::dsl::*;
+			diesel::delete(mime_overrides).execute(&mut *connection)?;
+			diesel::insert_into(mime_overrides)
+				.values(overrides)
+				.execute(&mut *connection)?;
+			Ok(())
+		})?;
+		Ok(())
+	}
+
+	// Content-Type to serve an audio file with: a configured override wins,
+	// then the canonical MIME of the format, then a generic fallback
+	pub fn get_audio_mime_type(&self, path: &Path) -> String {
+		if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+			let extension = extension.to_lowercase();
+			if let Ok(mut connection) = self.db.connect() {
+				if let Ok(Some(override_mime)) = mime_overrides::table
+					.filter(mime_overrides::extension.eq(&extension))
+					.select(mime_overrides::mime)
+					.first::<String>(&mut connection)
+					.optional()
+				{
+					return override_mime;
+				}
+			}
+		}
+		match utils::get_audio_format(path) {
+			Some(format) => utils::get_audio_mime(format).to_owned(),
+			None => "application/octet-stream".to_owned(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use crate::app::test;
+	use crate::test_name;
+
+	#[test]
+	fn setup_completion_survives_user_deletion() {
+		let ctx = test::ContextBuilder::new(test_name!())
+			.user("admin", "password", true)
+			.build();
+
+		assert!(!ctx.settings_manager.is_setup_complete().unwrap());
+		ctx.settings_manager.mark_setup_complete().unwrap();
+
+		ctx.user_manager.delete("admin").unwrap();
+		assert_eq!(ctx.user_manager.count().unwrap(), 0);
+		assert!(ctx.settings_manager.is_setup_complete().unwrap());
+	}
+
+	#[test]
+	fn mime_overrides_take_precedence_over_canonical_types() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let manager = &ctx.settings_manager;
+
+		assert_eq!(
+			manager.get_audio_mime_type(Path::new("music/song.flac")),
+			"audio/flac"
+		);
+		assert_eq!(
+			manager.get_audio_mime_type(Path::new("music/song.xyz")),
+			"application/octet-stream"
+		);
+
+		manager
+			.set_mime_overrides(&[MimeOverride {
+				extension: "flac".to_owned(),
+				mime: "audio/x-flac".to_owned(),
+			}])
+			.unwrap();
+
+		assert_eq!(
+			manager.get_audio_mime_type(Path::new("music/song.flac")),
+			"audio/x-flac"
+		);
+		assert_eq!(
+			manager.get_audio_mime_type(Path::new("music/song.mp3")),
+			"audio/mpeg"
+		);
+	}
+
+	#[test]
+	fn amend_invalidates_cached_settings() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let manager = &ctx.settings_manager;
+
+		let initial = manager.read_cached().unwrap();
+		assert_ne!(initial.minimum_client_version, "5.0.0");
+
+		manager
+			.amend(&NewSettings {
+				minimum_client_version: Some("5.0.0".to_owned()),
+				..Default::default()
+			})
+			.unwrap();
+
+		let amended = manager.read_cached().unwrap();
+		assert_eq!(amended.minimum_client_version, "5.0.0");
+	}
+
+	#[test]
+	fn setup_marker_file_marks_setup_complete() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let marker_path = ctx.test_directory.join("setup_complete");
+
+		let manager = Manager::new(ctx.db.clone()).with_setup_marker_path(marker_path.clone());
+		assert!(!manager.is_setup_complete().unwrap());
+
+		// A marker left behind by a previous installation wins over a fresh database
+		std::fs::write(&marker_path, []).unwrap();
+		assert!(manager.is_setup_complete().unwrap());
+	}
 }
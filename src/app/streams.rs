@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::app::settings;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error(transparent)]
+	Settings(#[from] settings::Error),
+	#[error("Too many concurrent streams for this user")]
+	TooManyStreams,
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	settings_manager: settings::Manager,
+	active_streams: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Manager {
+	pub fn new(settings_manager: settings::Manager) -> Self {
+		Self {
+			settings_manager,
+			active_streams: Arc::new(Mutex::new(HashMap::new())),
+		}
+	}
+
+	pub fn acquire(&self, username: &str) -> Result<Lease, Error> {
+		let limit = self
+			.settings_manager
+			.read()?
+			.max_concurrent_streams_per_user;
+		let mut active_streams = self.active_streams.lock().unwrap();
+		let count = active_streams.get(username).copied().unwrap_or(0);
+		if limit > 0 && count >= limit as usize {
+			return Err(Error::TooManyStreams);
+		}
+		active_streams.insert(username.to_owned(), count + 1);
+		Ok(Lease {
+			username: username.to_owned(),
+			active_streams: self.active_streams.clone(),
+		})
+	}
+}
+
+// Occupies one stream slot for a user until dropped, which happens when the
+// response has been sent in full or the client disconnected.
+pub struct Lease {
+	username: String,
+	active_streams: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Drop for Lease {
+	fn drop(&mut self) {
+		let mut active_streams = self.active_streams.lock().unwrap();
+		if let Some(count) = active_streams.get_mut(&self.username) {
+			*count -= 1;
+			if *count == 0 {
+				active_streams.remove(&self.username);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use crate::app::test;
+	use crate::test_name;
+
+	#[test]
+	fn enforces_concurrent_stream_limit() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		ctx.settings_manager
+			.amend(&settings::NewSettings {
+				max_concurrent_streams_per_user: Some(2),
+				..Default::default()
+			})
+			.unwrap();
+		let manager = Manager::new(ctx.settings_manager.clone());
+
+		let _first = manager.acquire("alice").unwrap();
+		let second = manager.acquire("alice").unwrap();
+		assert!(matches!(
+			manager.acquire("alice"),
+			Err(Error::TooManyStreams)
+		));
+
+		// Other users have their own allowance
+		let _other = manager.acquire("bob").unwrap();
+
+		// Finishing a stream frees up a slot
+		drop(second);
+		assert!(manager.acquire("alice").is_ok());
+	}
+
+	#[test]
+	fn zero_limit_means_unlimited_streams() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let manager = Manager::new(ctx.settings_manager.clone());
+		let leases = (0..10)
+			.map(|_| manager.acquire("alice"))
+			.collect::<Result<Vec<_>, _>>();
+		assert!(leases.is_ok());
+	}
+}
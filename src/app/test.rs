@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
-use crate::app::{config, ddns, index::Index, lastfm, playlist, settings, thumbnail, user, vfs};
+use crate::app::{
+	config, ddns, favorite, index::Index, lastfm, playlist, settings, thumbnail, user, vfs,
+};
 use crate::db::DB;
 use crate::test::*;
 
@@ -9,6 +11,7 @@ pub struct Context {
 	pub index: Index,
 	pub config_manager: config::Manager,
 	pub ddns_manager: ddns::Manager,
+	pub favorite_manager: favorite::Manager,
 	pub lastfm_manager: lastfm::Manager,
 	pub playlist_manager: playlist::Manager,
 	pub settings_manager: settings::Manager,
@@ -20,6 +23,7 @@ pub struct Context {
 
 pub struct ContextBuilder {
 	config: config::Config,
+	artwork_precache: bool,
 	pub test_directory: PathBuf,
 }
 
@@ -28,9 +32,15 @@ impl ContextBuilder {
 		Self {
 			test_directory: prepare_test_directory(test_name),
 			config: config::Config::default(),
+			artwork_precache: false,
 		}
 	}
 
+	pub fn artwork_precache(mut self) -> Self {
+		self.artwork_precache = true;
+		self
+	}
+
 	pub fn user(mut self, name: &str, password: &str, is_admin: bool) -> Self {
 		self.config
 			.users
@@ -43,13 +53,20 @@ impl ContextBuilder {
 		self
 	}
 
-	pub fn mount(mut self, name: &str, source: &str) -> Self {
+	pub fn mount(self, name: &str, source: &str) -> Self {
+		self.mount_with_max_depth(name, source, 0)
+	}
+
+	pub fn mount_with_max_depth(mut self, name: &str, source: &str, max_depth: i32) -> Self {
 		self.config
 			.mount_dirs
 			.get_or_insert(Vec::new())
 			.push(vfs::MountDir {
 				name: name.to_owned(),
 				source: source.to_owned(),
+				include_in_discovery: true,
+				max_depth,
+				display_name: String::new(),
 			});
 		self
 	}
@@ -70,10 +87,18 @@ impl ContextBuilder {
 			vfs_manager.clone(),
 			ddns_manager.clone(),
 		);
-		let index = Index::new(db.clone(), vfs_manager.clone(), settings_manager.clone());
-		let playlist_manager = playlist::Manager::new(db.clone(), vfs_manager.clone());
 		let thumbnail_manager = thumbnail::Manager::new(cache_output_dir);
-		let lastfm_manager = lastfm::Manager::new(index.clone(), user_manager.clone());
+		let index = Index::new(
+			db.clone(),
+			vfs_manager.clone(),
+			settings_manager.clone(),
+			thumbnail_manager.clone(),
+			self.artwork_precache,
+		);
+		let favorite_manager = favorite::Manager::new(db.clone(), vfs_manager.clone());
+		let playlist_manager =
+			playlist::Manager::new(db.clone(), vfs_manager.clone(), settings_manager.clone());
+		let lastfm_manager = lastfm::Manager::new(db.clone(), index.clone(), user_manager.clone());
 
 		config_manager.apply(&self.config).unwrap();
 
@@ -82,6 +107,7 @@ impl ContextBuilder {
 			index,
 			config_manager,
 			ddns_manager,
+			favorite_manager,
 			lastfm_manager,
 			playlist_manager,
 			settings_manager,
@@ -1,11 +1,19 @@
+use id3::TagLike;
 use image::{DynamicImage, GenericImage, GenericImageView, ImageBuffer, ImageOutputFormat};
 use std::cmp;
-use std::collections::hash_map::DefaultHasher;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::hash::{Hash, Hasher};
+use std::io::Cursor;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
 
-use crate::utils::{get_audio_format, AudioFormat};
+use crate::utils::{get_audio_format, read_dsf_id3_tag, AudioFormat};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -21,15 +29,41 @@ pub enum Error {
 	Metaflac(PathBuf, metaflac::Error),
 	#[error("Could not read thumbnail from mp4 file in `{0}`:\n\n{1}")]
 	Mp4aMeta(PathBuf, mp4ameta::Error),
+	#[error("Timed out while generating a thumbnail for `{0}`")]
+	Timeout(PathBuf),
 	#[error("This file format is not supported: {0}")]
 	UnsupportedFormat(&'static str),
 }
 
-#[derive(Debug, Hash)]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Format {
+	// Keeps PNG output when the source image has an alpha channel, JPEG otherwise
+	#[default]
+	Auto,
+	Jpeg,
+	Png,
+}
+
+impl Format {
+	// Cache file extensions a thumbnail of this format may be stored under
+	fn extensions(self) -> &'static [&'static str] {
+		match self {
+			Self::Auto => &["jpg", "png"],
+			Self::Jpeg => &["jpg"],
+			Self::Png => &["png"],
+		}
+	}
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Options {
 	pub max_dimension: Option<u32>,
 	pub resize_if_almost_square: bool,
 	pub pad_to_square: bool,
+	pub format: Format,
+	// Encode JPEG output as progressive scans, so large covers render a
+	// low-fidelity version before the transfer completes
+	pub progressive: bool,
 }
 
 impl Default for Options {
@@ -38,47 +72,247 @@ impl Default for Options {
 			max_dimension: Some(400),
 			resize_if_almost_square: true,
 			pad_to_square: true,
+			format: Format::Auto,
+			progressive: false,
 		}
 	}
 }
 
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheVerificationReport {
+	pub removed: usize,
+	pub kept: usize,
+}
+
+pub struct Atlas {
+	pub image_bytes: Vec<u8>,
+	pub tiles: Vec<AtlasTile>,
+}
+
+// Position of one source image within an atlas, identified by its index in the
+// requested batch
+pub struct AtlasTile {
+	pub index: usize,
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32,
+}
+
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_MAX_OUTPUT_DIMENSION: u32 = 1600;
+const DEFAULT_STORED_MAX_DIMENSION: u32 = 1280;
+
+// Generations in progress, keyed by thumbnail hash. Waiters block on the
+// condvar until the generating request flips the flag and notifies them.
+type InFlightMap = HashMap<u64, Arc<(Mutex<bool>, Condvar)>>;
+
 #[derive(Clone)]
 pub struct Manager {
 	thumbnails_dir_path: PathBuf,
+	read_timeout: Duration,
+	max_output_dimension: u32,
+	stored_max_dimension: u32,
+	in_flight: Arc<Mutex<InFlightMap>>,
+	generation_count: Arc<AtomicUsize>,
 }
 
 impl Manager {
 	pub fn new(thumbnails_dir_path: PathBuf) -> Self {
 		Self {
 			thumbnails_dir_path,
+			read_timeout: DEFAULT_READ_TIMEOUT,
+			max_output_dimension: DEFAULT_MAX_OUTPUT_DIMENSION,
+			stored_max_dimension: DEFAULT_STORED_MAX_DIMENSION,
+			in_flight: Arc::default(),
+			generation_count: Arc::default(),
 		}
 	}
 
+	pub fn with_read_timeout(mut self, read_timeout: Duration) -> Self {
+		self.read_timeout = read_timeout;
+		self
+	}
+
+	pub fn with_max_output_dimension(mut self, max_output_dimension: u32) -> Self {
+		self.max_output_dimension = max_output_dimension;
+		self
+	}
+
+	pub fn with_stored_max_dimension(mut self, stored_max_dimension: u32) -> Self {
+		self.stored_max_dimension = stored_max_dimension;
+		self
+	}
+
+	// Options used for pre-downscaled covers cached at index time
+	fn stored_cover_options(&self) -> Options {
+		Options {
+			max_dimension: Some(self.stored_max_dimension),
+			resize_if_almost_square: false,
+			pad_to_square: false,
+			format: Format::Auto,
+			progressive: false,
+		}
+	}
+
+	pub fn precache_cover(&self, image_path: &Path) -> Result<PathBuf, Error> {
+		let options = self.stored_cover_options();
+		match self.retrieve_thumbnail(image_path, &options) {
+			Some(path) => Ok(path),
+			None => self.create_thumbnail(image_path, &options),
+		}
+	}
+
+	pub fn clamp_options(&self, thumbnailoptions: &Options) -> Options {
+		let mut clamped = thumbnailoptions.clone();
+		clamped.max_dimension = Some(
+			thumbnailoptions
+				.max_dimension
+				.map_or(self.max_output_dimension, |d| {
+					cmp::min(d, self.max_output_dimension)
+				}),
+		);
+		clamped
+	}
+
 	pub fn get_thumbnail(
 		&self,
 		image_path: &Path,
 		thumbnailoptions: &Options,
 	) -> Result<PathBuf, Error> {
-		match self.retrieve_thumbnail(image_path, thumbnailoptions) {
+		let thumbnailoptions = self.clamp_options(thumbnailoptions);
+		match self.retrieve_thumbnail(image_path, &thumbnailoptions) {
 			Some(path) => Ok(path),
-			None => self.create_thumbnail(image_path, thumbnailoptions),
+			None => self.create_thumbnail_coalesced(image_path, &thumbnailoptions),
+		}
+	}
+
+	// Under load, many requests can ask for the same uncached thumbnail at
+	// once. Only one of them runs the decode and resize; the others block
+	// until it finishes and then read the freshly written cache entry.
+	fn create_thumbnail_coalesced(
+		&self,
+		image_path: &Path,
+		thumbnailoptions: &Options,
+	) -> Result<PathBuf, Error> {
+		let key = Manager::hash(image_path, thumbnailoptions);
+		loop {
+			let existing = {
+				let mut in_flight = self.in_flight.lock().unwrap();
+				match in_flight.entry(key) {
+					Entry::Occupied(entry) => Some(entry.get().clone()),
+					Entry::Vacant(entry) => {
+						entry.insert(Arc::default());
+						None
+					}
+				}
+			};
+
+			let Some(state) = existing else {
+				// This request owns the generation. The cache may have been
+				// populated while we were acquiring the slot, so check again
+				// before doing the expensive work.
+				let result = match self.retrieve_thumbnail(image_path, thumbnailoptions) {
+					Some(path) => Ok(path),
+					None => self.create_thumbnail(image_path, thumbnailoptions),
+				};
+				if let Some(state) = self.in_flight.lock().unwrap().remove(&key) {
+					let (lock, condvar) = &*state;
+					*lock.lock().unwrap() = true;
+					condvar.notify_all();
+				}
+				return result;
+			};
+
+			let (lock, condvar) = &*state;
+			let mut done = lock.lock().unwrap();
+			while !*done {
+				done = condvar.wait(done).unwrap();
+			}
+			drop(done);
+
+			if let Some(path) = self.retrieve_thumbnail(image_path, thumbnailoptions) {
+				return Ok(path);
+			}
+			// The generating request failed and wrote nothing; start over and
+			// try generating from this request instead
+		}
+	}
+
+	// Renders the requested covers as JPEG tiles of a single atlas image, so a
+	// grid view can be populated with one request. Sources that cannot be
+	// thumbnailed are left out of the returned coordinates rather than failing
+	// the whole batch.
+	pub fn get_atlas(&self, image_paths: &[PathBuf], tile_dimension: u32) -> Result<Atlas, Error> {
+		let options = self.clamp_options(&Options {
+			max_dimension: Some(tile_dimension),
+			resize_if_almost_square: true,
+			pad_to_square: true,
+			format: Format::Jpeg,
+			progressive: false,
+		});
+		let tile_dimension = options.max_dimension.unwrap_or(tile_dimension);
+
+		let mut sources = Vec::new();
+		for (index, image_path) in image_paths.iter().enumerate() {
+			let Ok(thumbnail_path) = self.get_thumbnail(image_path, &options) else {
+				continue;
+			};
+			let Ok(image) = image::open(&thumbnail_path) else {
+				continue;
+			};
+			sources.push((index, image));
 		}
+
+		let columns = cmp::max(1, (sources.len() as f64).sqrt().ceil() as u32);
+		let rows = cmp::max(1, sources.len() as u32).div_ceil(columns);
+		let mut atlas_image = image::RgbImage::new(columns * tile_dimension, rows * tile_dimension);
+
+		let mut tiles = Vec::with_capacity(sources.len());
+		for (tile_number, (index, image)) in sources.into_iter().enumerate() {
+			let x = (tile_number as u32 % columns) * tile_dimension;
+			let y = (tile_number as u32 / columns) * tile_dimension;
+			image::imageops::replace(&mut atlas_image, &image.into_rgb8(), x as i64, y as i64);
+			tiles.push(AtlasTile {
+				index,
+				x,
+				y,
+				width: tile_dimension,
+				height: tile_dimension,
+			});
+		}
+
+		let mut image_bytes = Vec::new();
+		DynamicImage::ImageRgb8(atlas_image)
+			.write_to(&mut Cursor::new(&mut image_bytes), ImageOutputFormat::Jpeg(80))
+			.map_err(|e| Error::Image(self.thumbnails_dir_path.clone(), e))?;
+
+		Ok(Atlas { image_bytes, tiles })
 	}
 
-	fn get_thumbnail_path(&self, image_path: &Path, thumbnailoptions: &Options) -> PathBuf {
-		let hash = Manager::hash(image_path, thumbnailoptions);
+	fn get_thumbnail_path(
+		&self,
+		image_path: &Path,
+		thumbnailoptions: &Options,
+		extension: &str,
+	) -> PathBuf {
+		let path_hash = Manager::hash_path(image_path);
+		let options_hash = Manager::hash(image_path, thumbnailoptions);
 		let mut thumbnail_path = self.thumbnails_dir_path.clone();
-		thumbnail_path.push(format!("{}.jpg", hash));
+		thumbnail_path.push(format!("{}-{}.{}", path_hash, options_hash, extension));
 		thumbnail_path
 	}
 
 	fn retrieve_thumbnail(&self, image_path: &Path, thumbnailoptions: &Options) -> Option<PathBuf> {
-		let path = self.get_thumbnail_path(image_path, thumbnailoptions);
-		if path.exists() {
-			Some(path)
-		} else {
-			None
+		// Auto format thumbnails may be cached as either JPEG or PNG depending
+		// on whether the source image had an alpha channel
+		for extension in thumbnailoptions.format.extensions() {
+			let path = self.get_thumbnail_path(image_path, thumbnailoptions, extension);
+			if path.exists() {
+				return Some(path);
+			}
 		}
+		None
 	}
 
 	fn create_thumbnail(
@@ -86,30 +320,176 @@ impl Manager {
 		image_path: &Path,
 		thumbnailoptions: &Options,
 	) -> Result<PathBuf, Error> {
-		let thumbnail = generate_thumbnail(image_path, thumbnailoptions)?;
+		self.generation_count.fetch_add(1, Ordering::SeqCst);
+		let thumbnail = {
+			let source_path = self.get_source_image_path(image_path, thumbnailoptions);
+			let options = thumbnailoptions.clone();
+			run_with_timeout(
+				move || generate_thumbnail(&source_path, &options),
+				self.read_timeout,
+			)
+			.ok_or_else(|| Error::Timeout(image_path.to_owned()))??
+		};
 		let quality = 80;
 
+		let format = match thumbnailoptions.format {
+			Format::Auto if thumbnail.color().has_alpha() => Format::Png,
+			Format::Auto => Format::Jpeg,
+			format => format,
+		};
+		let (extension, output_format, thumbnail) = match format {
+			Format::Png => ("png", ImageOutputFormat::Png, thumbnail),
+			// JPEG has no alpha channel, flatten the image before encoding
+			_ => (
+				"jpg",
+				ImageOutputFormat::Jpeg(quality),
+				DynamicImage::ImageRgb8(thumbnail.into_rgb8()),
+			),
+		};
+
 		fs::create_dir_all(&self.thumbnails_dir_path)
 			.map_err(|e| Error::Io(self.thumbnails_dir_path.clone(), e))?;
-		let path = self.get_thumbnail_path(image_path, thumbnailoptions);
+		let path = self.get_thumbnail_path(image_path, thumbnailoptions, extension);
 		let mut out_file =
 			File::create(&path).map_err(|e| Error::Io(self.thumbnails_dir_path.clone(), e))?;
-		thumbnail
-			.write_to(&mut out_file, ImageOutputFormat::Jpeg(quality))
-			.map_err(|e| Error::Image(image_path.to_owned(), e))?;
+		if thumbnailoptions.progressive && format != Format::Png {
+			write_progressive_jpeg(&mut out_file, &thumbnail, quality)
+				.map_err(|e| Error::Io(path.clone(), e))?;
+		} else {
+			thumbnail
+				.write_to(&mut out_file, output_format)
+				.map_err(|e| Error::Image(image_path.to_owned(), e))?;
+		}
 		Ok(path)
 	}
 
+	// Small enough requests can be served from the pre-downscaled cover cached
+	// at index time, which avoids decoding the original image again
+	fn get_source_image_path(&self, image_path: &Path, thumbnailoptions: &Options) -> PathBuf {
+		let stored_options = self.stored_cover_options();
+		let can_use_stored_cover = *thumbnailoptions != stored_options
+			&& thumbnailoptions
+				.max_dimension
+				.is_some_and(|d| d <= self.stored_max_dimension);
+		if can_use_stored_cover {
+			if let Some(stored_path) = self.retrieve_thumbnail(image_path, &stored_options) {
+				return stored_path;
+			}
+		}
+		image_path.to_owned()
+	}
+
+	pub fn invalidate(&self, image_path: &Path) -> Result<(), Error> {
+		let prefix = format!("{}-", Manager::hash_path(image_path));
+		let entries = match fs::read_dir(&self.thumbnails_dir_path) {
+			Ok(entries) => entries,
+			Err(_) => return Ok(()),
+		};
+		for entry in entries.flatten() {
+			if entry.file_name().to_string_lossy().starts_with(&prefix) {
+				fs::remove_file(entry.path()).map_err(|e| Error::Io(entry.path(), e))?;
+			}
+		}
+		Ok(())
+	}
+
+	// Removes cache entries whose source image is gone, and entries left
+	// unreadable by an interrupted write. `valid_sources` is the set of images
+	// the collection can still produce thumbnails for.
+	pub fn verify_cache(
+		&self,
+		valid_sources: &[PathBuf],
+	) -> Result<CacheVerificationReport, Error> {
+		let valid_hashes: HashSet<String> = valid_sources
+			.iter()
+			.filter(|source| source.exists())
+			.map(|source| Manager::hash_path(source).to_string())
+			.collect();
+
+		let mut report = CacheVerificationReport::default();
+		let entries = match fs::read_dir(&self.thumbnails_dir_path) {
+			Ok(entries) => entries,
+			Err(_) => return Ok(report),
+		};
+		for entry in entries.flatten() {
+			let file_name = entry.file_name().to_string_lossy().into_owned();
+			let is_orphaned = file_name
+				.split_once('-')
+				.is_none_or(|(hash, _)| !valid_hashes.contains(hash));
+			let is_corrupt = !is_orphaned && image::open(entry.path()).is_err();
+			if is_orphaned || is_corrupt {
+				fs::remove_file(entry.path()).map_err(|e| Error::Io(entry.path(), e))?;
+				report.removed += 1;
+			} else {
+				report.kept += 1;
+			}
+		}
+		Ok(report)
+	}
+
+	// Number of thumbnails generated from scratch, letting tests verify that
+	// concurrent requests for the same thumbnail decode the source only once
+	#[cfg(test)]
+	pub fn generation_count(&self) -> usize {
+		self.generation_count.load(Ordering::SeqCst)
+	}
+
 	fn hash(path: &Path, thumbnailoptions: &Options) -> u64 {
 		let mut hasher = DefaultHasher::new();
-		path.hash(&mut hasher);
+		Manager::hash_path(path).hash(&mut hasher);
 		thumbnailoptions.hash(&mut hasher);
 		hasher.finish()
 	}
+
+	fn hash_path(path: &Path) -> u64 {
+		let mut hasher = DefaultHasher::new();
+		path.hash(&mut hasher);
+		hasher.finish()
+	}
+}
+
+fn run_with_timeout<T, F>(f: F, timeout: Duration) -> Option<T>
+where
+	T: Send + 'static,
+	F: FnOnce() -> T + Send + 'static,
+{
+	let (sender, receiver) = mpsc::channel();
+	thread::spawn(move || {
+		// The receiver may be gone if we timed out, in which case the result is discarded
+		sender.send(f()).ok();
+	});
+	receiver.recv_timeout(timeout).ok()
+}
+
+// The `image` crate only emits baseline JPEG, so progressive output goes
+// through a dedicated encoder
+fn write_progressive_jpeg(
+	out: &mut File,
+	image: &DynamicImage,
+	quality: u8,
+) -> Result<(), std::io::Error> {
+	let rgb = image.to_rgb8();
+	let (width, height) = rgb.dimensions();
+	let mut encoder = jpeg_encoder::Encoder::new(out, quality);
+	encoder.set_progressive(true);
+	encoder
+		.encode(
+			rgb.as_raw(),
+			width as u16,
+			height as u16,
+			jpeg_encoder::ColorType::Rgb,
+		)
+		.map_err(std::io::Error::other)
 }
 
 fn generate_thumbnail(image_path: &Path, options: &Options) -> Result<DynamicImage, Error> {
-	let source_image = DynamicImage::ImageRgb8(read(image_path)?.into_rgb8());
+	// Preserve the alpha channel when the source has one so auto format
+	// selection can emit PNG output with intact transparency
+	let source_image = read(image_path)?;
+	let source_image = match source_image.color().has_alpha() {
+		true => DynamicImage::ImageRgba8(source_image.into_rgba8()),
+		false => DynamicImage::ImageRgb8(source_image.into_rgb8()),
+	};
 	let (source_width, source_height) = source_image.dimensions();
 	let largest_dimension = cmp::max(source_width, source_height);
 	let out_dimension = cmp::min(
@@ -124,14 +504,25 @@ fn generate_thumbnail(image_path: &Path, options: &Options) -> Result<DynamicIma
 	if is_almost_square && options.resize_if_almost_square {
 		final_image = source_image.thumbnail_exact(out_dimension, out_dimension);
 	} else if options.pad_to_square {
+		let has_alpha = source_image.color().has_alpha();
 		let scaled_image = source_image.thumbnail(out_dimension, out_dimension);
 		let (scaled_width, scaled_height) = scaled_image.dimensions();
-		let background = image::Rgb([255, 255_u8, 255_u8]);
-		final_image = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(
-			out_dimension,
-			out_dimension,
-			background,
-		));
+		final_image = if has_alpha {
+			// Pad with transparency rather than stamping a white border
+			let background = image::Rgba([255, 255, 255, 0_u8]);
+			DynamicImage::ImageRgba8(ImageBuffer::from_pixel(
+				out_dimension,
+				out_dimension,
+				background,
+			))
+		} else {
+			let background = image::Rgb([255, 255_u8, 255_u8]);
+			DynamicImage::ImageRgb8(ImageBuffer::from_pixel(
+				out_dimension,
+				out_dimension,
+				background,
+			))
+		};
 		final_image
 			.copy_from(
 				&scaled_image,
@@ -146,10 +537,88 @@ fn generate_thumbnail(image_path: &Path, options: &Options) -> Result<DynamicIma
 	Ok(final_image)
 }
 
+pub fn write(image_path: &Path, image_bytes: &[u8]) -> Result<(), Error> {
+	match get_audio_format(image_path) {
+		Some(AudioFormat::FLAC) => write_flac(image_path, image_bytes),
+		Some(AudioFormat::MP3) => write_mp3(image_path, image_bytes),
+		Some(AudioFormat::MP4) => write_mp4(image_path, image_bytes),
+		Some(AudioFormat::AIFF) => Err(Error::UnsupportedFormat("aiff")),
+		Some(AudioFormat::APE) => Err(Error::UnsupportedFormat("ape")),
+		Some(AudioFormat::DSF) => Err(Error::UnsupportedFormat("dsf")),
+		Some(AudioFormat::MPC) => Err(Error::UnsupportedFormat("mpc")),
+		Some(AudioFormat::OGG) => Err(Error::UnsupportedFormat("ogg")),
+		Some(AudioFormat::OPUS) => Err(Error::UnsupportedFormat("opus")),
+		Some(AudioFormat::WAVE) => Err(Error::UnsupportedFormat("wave")),
+		None => Err(Error::UnsupportedFormat("unknown")),
+	}
+}
+
+fn guess_image_mime(image_path: &Path, image_bytes: &[u8]) -> Result<&'static str, Error> {
+	let format = image::guess_format(image_bytes)
+		.map_err(|e| Error::Image(image_path.to_owned(), e))?;
+	match format {
+		image::ImageFormat::Bmp => Ok("image/bmp"),
+		image::ImageFormat::Gif => Ok("image/gif"),
+		image::ImageFormat::Jpeg => Ok("image/jpeg"),
+		image::ImageFormat::Png => Ok("image/png"),
+		_ => Err(Error::UnsupportedFormat("image")),
+	}
+}
+
+fn write_flac(path: &Path, image_bytes: &[u8]) -> Result<(), Error> {
+	let mime = guess_image_mime(path, image_bytes)?;
+	let mut tag =
+		metaflac::Tag::read_from_path(path).map_err(|e| Error::Metaflac(path.to_owned(), e))?;
+	tag.remove_picture_type(metaflac::block::PictureType::CoverFront);
+	tag.add_picture(
+		mime,
+		metaflac::block::PictureType::CoverFront,
+		image_bytes.to_owned(),
+	);
+	tag.save().map_err(|e| Error::Metaflac(path.to_owned(), e))
+}
+
+fn write_mp3(path: &Path, image_bytes: &[u8]) -> Result<(), Error> {
+	let mime = guess_image_mime(path, image_bytes)?;
+	let mut tag = id3::Tag::read_from_path(path).or_else(|error| {
+		if let Some(tag) = error.partial_tag {
+			Ok(tag)
+		} else if matches!(error.kind, id3::ErrorKind::NoTag) {
+			Ok(id3::Tag::new())
+		} else {
+			Err(Error::Id3(path.to_owned(), error))
+		}
+	})?;
+	tag.remove_picture_by_type(id3::frame::PictureType::CoverFront);
+	tag.add_frame(id3::frame::Picture {
+		mime_type: mime.to_owned(),
+		picture_type: id3::frame::PictureType::CoverFront,
+		description: "".to_owned(),
+		data: image_bytes.to_owned(),
+	});
+	tag.write_to_path(path, id3::Version::Id3v24)
+		.map_err(|e| Error::Id3(path.to_owned(), e))
+}
+
+fn write_mp4(path: &Path, image_bytes: &[u8]) -> Result<(), Error> {
+	let format = match guess_image_mime(path, image_bytes)? {
+		"image/bmp" => mp4ameta::ImgFmt::Bmp,
+		"image/jpeg" => mp4ameta::ImgFmt::Jpeg,
+		"image/png" => mp4ameta::ImgFmt::Png,
+		_ => return Err(Error::UnsupportedFormat("image")),
+	};
+	let mut tag =
+		mp4ameta::Tag::read_from_path(path).map_err(|e| Error::Mp4aMeta(path.to_owned(), e))?;
+	tag.set_artwork(mp4ameta::Img::new(format, image_bytes.to_owned()));
+	tag.write_to_path(path)
+		.map_err(|e| Error::Mp4aMeta(path.to_owned(), e))
+}
+
 fn read(image_path: &Path) -> Result<DynamicImage, Error> {
 	match get_audio_format(image_path) {
 		Some(AudioFormat::AIFF) => read_aiff(image_path),
 		Some(AudioFormat::APE) => read_ape(image_path),
+		Some(AudioFormat::DSF) => read_dsf(image_path),
 		Some(AudioFormat::FLAC) => read_flac(image_path),
 		Some(AudioFormat::MP3) => read_mp3(image_path),
 		Some(AudioFormat::MP4) => read_mp4(image_path),
@@ -161,6 +630,117 @@ fn read(image_path: &Path) -> Result<DynamicImage, Error> {
 	}
 }
 
+// Original artwork bytes as they appear in the file, without any re-encoding
+pub struct RawImage {
+	pub bytes: Vec<u8>,
+	pub mime: String,
+}
+
+pub fn read_raw(path: &Path) -> Result<RawImage, Error> {
+	let embedded = match get_audio_format(path) {
+		Some(AudioFormat::AIFF) => id3::Tag::read_from_aiff_path(path)
+			.map_err(|e| Error::Id3(path.to_owned(), e))
+			.and_then(|t| read_raw_id3(path, &t)),
+		Some(AudioFormat::APE) => Err(Error::UnsupportedFormat("ape")),
+		Some(AudioFormat::DSF) => read_dsf_id3_tag(path)
+			.map_err(|e| Error::Id3(path.to_owned(), e))
+			.and_then(|t| read_raw_id3(path, &t)),
+		Some(AudioFormat::FLAC | AudioFormat::OGG | AudioFormat::OPUS) => read_raw_flac(path),
+		Some(AudioFormat::MP3) => id3::Tag::read_from_path(path)
+			.map_err(|e| Error::Id3(path.to_owned(), e))
+			.and_then(|t| read_raw_id3(path, &t)),
+		Some(AudioFormat::MP4) => read_raw_mp4(path),
+		Some(AudioFormat::MPC) => Err(Error::UnsupportedFormat("mpc")),
+		Some(AudioFormat::WAVE) => id3::Tag::read_from_wav_path(path)
+			.map_err(|e| Error::Id3(path.to_owned(), e))
+			.and_then(|t| read_raw_id3(path, &t)),
+		None => return read_raw_file(path),
+	};
+	match embedded {
+		// Albums without embedded art usually ship a cover image next to the songs
+		Err(Error::EmbeddedArtworkNotFound(_)) => read_raw_sibling(path),
+		other => other,
+	}
+}
+
+fn read_raw_file(path: &Path) -> Result<RawImage, Error> {
+	let bytes = fs::read(path).map_err(|e| Error::Io(path.to_owned(), e))?;
+	let mime = guess_image_mime(path, &bytes)?.to_owned();
+	Ok(RawImage { bytes, mime })
+}
+
+fn read_raw_sibling(path: &Path) -> Result<RawImage, Error> {
+	let parent = path
+		.parent()
+		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))?;
+	let entries = fs::read_dir(parent).map_err(|e| Error::Io(parent.to_owned(), e))?;
+	for entry in entries.flatten() {
+		if image::ImageFormat::from_path(entry.path()).is_err() {
+			continue;
+		}
+		if let Ok(image) = read_raw_file(&entry.path()) {
+			return Ok(image);
+		}
+	}
+	Err(Error::EmbeddedArtworkNotFound(path.to_owned()))
+}
+
+fn read_raw_flac(path: &Path) -> Result<RawImage, Error> {
+	let tag =
+		metaflac::Tag::read_from_path(path).map_err(|e| Error::Metaflac(path.to_owned(), e))?;
+	let mut candidates: Vec<&metaflac::block::Picture> = tag
+		.pictures()
+		.filter(|p| p.picture_type == metaflac::block::PictureType::CoverFront)
+		.collect();
+	if candidates.is_empty() {
+		candidates = tag.pictures().collect();
+	}
+	// Declared dimensions break ties without decoding any candidate
+	candidates
+		.into_iter()
+		.max_by_key(|p| (p.width as u64 * p.height as u64, p.data.len()))
+		.map(|p| RawImage {
+			bytes: p.data.clone(),
+			mime: p.mime_type.clone(),
+		})
+		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))
+}
+
+fn read_raw_id3(path: &Path, tag: &id3::Tag) -> Result<RawImage, Error> {
+	let mut candidates: Vec<&id3::frame::Picture> = tag
+		.pictures()
+		.filter(|p| p.picture_type == id3::frame::PictureType::CoverFront)
+		.collect();
+	if candidates.is_empty() {
+		candidates = tag.pictures().collect();
+	}
+	candidates
+		.into_iter()
+		.max_by_key(|p| p.data.len())
+		.map(|p| RawImage {
+			bytes: p.data.clone(),
+			mime: p.mime_type.clone(),
+		})
+		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))
+}
+
+fn read_raw_mp4(path: &Path) -> Result<RawImage, Error> {
+	let tag =
+		mp4ameta::Tag::read_from_path(path).map_err(|e| Error::Mp4aMeta(path.to_owned(), e))?;
+	tag.artworks()
+		.max_by_key(|a| a.data.len())
+		.map(|a| RawImage {
+			bytes: a.data.to_vec(),
+			mime: match a.fmt {
+				mp4ameta::ImgFmt::Bmp => "image/bmp",
+				mp4ameta::ImgFmt::Jpeg => "image/jpeg",
+				mp4ameta::ImgFmt::Png => "image/png",
+			}
+			.to_owned(),
+		})
+		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))
+}
+
 fn read_ape(_: &Path) -> Result<DynamicImage, Error> {
 	Err(Error::UnsupportedFormat("ape"))
 }
@@ -168,10 +748,29 @@ fn read_ape(_: &Path) -> Result<DynamicImage, Error> {
 fn read_flac(path: &Path) -> Result<DynamicImage, Error> {
 	let tag =
 		metaflac::Tag::read_from_path(path).map_err(|e| Error::Metaflac(path.to_owned(), e))?;
-	if let Some(p) = tag.pictures().next() {
-		return image::load_from_memory(&p.data).map_err(|e| Error::Image(path.to_owned(), e));
+	let mut candidates: Vec<&metaflac::block::Picture> = tag
+		.pictures()
+		.filter(|p| p.picture_type == metaflac::block::PictureType::CoverFront)
+		.collect();
+	if candidates.is_empty() {
+		candidates = tag.pictures().collect();
 	}
-	Err(Error::EmbeddedArtworkNotFound(path.to_owned()))
+	if candidates.is_empty() {
+		return Err(Error::EmbeddedArtworkNotFound(path.to_owned()));
+	}
+
+	// Picture headers declare their dimensions, which lets us pick the largest
+	// cover without decoding every candidate
+	if candidates.iter().all(|p| p.width > 0 && p.height > 0) {
+		let largest = candidates
+			.iter()
+			.max_by_key(|p| p.width as u64 * p.height as u64)
+			.unwrap();
+		return image::load_from_memory(&largest.data)
+			.map_err(|e| Error::Image(path.to_owned(), e));
+	}
+
+	largest_image(path, candidates.into_iter().map(|p| p.data.as_slice()))
 }
 
 fn read_mp3(path: &Path) -> Result<DynamicImage, Error> {
@@ -179,6 +778,11 @@ fn read_mp3(path: &Path) -> Result<DynamicImage, Error> {
 	read_id3(path, &tag)
 }
 
+fn read_dsf(path: &Path) -> Result<DynamicImage, Error> {
+	let tag = read_dsf_id3_tag(path).map_err(|e| Error::Id3(path.to_owned(), e))?;
+	read_id3(path, &tag)
+}
+
 fn read_aiff(path: &Path) -> Result<DynamicImage, Error> {
 	let tag = id3::Tag::read_from_aiff_path(path).map_err(|e| Error::Id3(path.to_owned(), e))?;
 	read_id3(path, &tag)
@@ -190,20 +794,43 @@ fn read_wave(path: &Path) -> Result<DynamicImage, Error> {
 }
 
 fn read_id3(path: &Path, tag: &id3::Tag) -> Result<DynamicImage, Error> {
-	tag.pictures()
-		.next()
-		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))
-		.and_then(|d| {
-			image::load_from_memory(&d.data).map_err(|e| Error::Image(path.to_owned(), e))
-		})
+	let mut candidates: Vec<&id3::frame::Picture> = tag
+		.pictures()
+		.filter(|p| p.picture_type == id3::frame::PictureType::CoverFront)
+		.collect();
+	if candidates.is_empty() {
+		candidates = tag.pictures().collect();
+	}
+	if candidates.is_empty() {
+		return Err(Error::EmbeddedArtworkNotFound(path.to_owned()));
+	}
+	largest_image(path, candidates.into_iter().map(|p| p.data.as_slice()))
 }
 
 fn read_mp4(path: &Path) -> Result<DynamicImage, Error> {
 	let tag =
 		mp4ameta::Tag::read_from_path(path).map_err(|e| Error::Mp4aMeta(path.to_owned(), e))?;
-	tag.artwork()
-		.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))
-		.and_then(|d| image::load_from_memory(d.data).map_err(|e| Error::Image(path.to_owned(), e)))
+	let candidates: Vec<&[u8]> = tag.artworks().map(|a| a.data).collect();
+	if candidates.is_empty() {
+		return Err(Error::EmbeddedArtworkNotFound(path.to_owned()));
+	}
+	largest_image(path, candidates.into_iter())
+}
+
+// Decodes every candidate cover and returns the one with the largest surface
+fn largest_image<'a>(
+	path: &Path,
+	candidates: impl Iterator<Item = &'a [u8]>,
+) -> Result<DynamicImage, Error> {
+	let area = |image: &DynamicImage| image.width() as u64 * image.height() as u64;
+	let mut largest: Option<DynamicImage> = None;
+	for data in candidates {
+		let image = image::load_from_memory(data).map_err(|e| Error::Image(path.to_owned(), e))?;
+		if largest.as_ref().map(area) < Some(area(&image)) {
+			largest = Some(image);
+		}
+	}
+	largest.ok_or_else(|| Error::EmbeddedArtworkNotFound(path.to_owned()))
 }
 
 fn read_vorbis(path: &Path) -> Result<DynamicImage, Error> {
@@ -218,6 +845,275 @@ fn read_opus(path: &Path) -> Result<DynamicImage, Error> {
 mod test {
 
 	use super::*;
+	use crate::test::prepare_test_directory;
+	use crate::test_name;
+
+	#[test]
+	fn can_write_artwork_data() {
+		let output_dir = prepare_test_directory(test_name!());
+		let image_bytes = fs::read("test-data/artwork/Folder.png").unwrap();
+		let expected_img = image::open("test-data/artwork/Folder.png")
+			.unwrap()
+			.to_rgb8();
+
+		for extension in ["flac", "mp3", "m4a"] {
+			let path = output_dir.join(format!("sample.{}", extension));
+			fs::copy(format!("test-data/formats/sample.{}", extension), &path).unwrap();
+			write(&path, &image_bytes).unwrap();
+			let read_img = read(&path).unwrap().to_rgb8();
+			assert_eq!(read_img, expected_img);
+		}
+
+		let ape_path = output_dir.join("sample.ape");
+		fs::copy("test-data/formats/sample.ape", &ape_path).unwrap();
+		assert!(matches!(
+			write(&ape_path, &image_bytes),
+			Err(Error::UnsupportedFormat("ape"))
+		));
+	}
+
+	#[test]
+	fn picks_largest_embedded_cover() {
+		let output_dir = prepare_test_directory(test_name!());
+
+		let encode = |dimension: u32| {
+			let mut bytes: Vec<u8> = Vec::new();
+			let image = DynamicImage::ImageRgb8(ImageBuffer::new(dimension, dimension));
+			image
+				.write_to(&mut std::io::Cursor::new(&mut bytes), ImageOutputFormat::Png)
+				.unwrap();
+			bytes
+		};
+
+		let mp3_path = output_dir.join("sample.mp3");
+		fs::copy("test-data/formats/sample.mp3", &mp3_path).unwrap();
+		let mut tag = id3::Tag::read_from_path(&mp3_path).unwrap();
+		for (description, dimension) in [("small", 16), ("large", 64)] {
+			tag.add_frame(id3::frame::Picture {
+				mime_type: "image/png".to_owned(),
+				picture_type: id3::frame::PictureType::CoverFront,
+				description: description.to_owned(),
+				data: encode(dimension),
+			});
+		}
+		tag.write_to_path(&mp3_path, id3::Version::Id3v24).unwrap();
+		assert_eq!(read(&mp3_path).unwrap().dimensions(), (64, 64));
+
+		let flac_path = output_dir.join("sample.flac");
+		fs::copy("test-data/formats/sample.flac", &flac_path).unwrap();
+		let mut tag = metaflac::Tag::read_from_path(&flac_path).unwrap();
+		for dimension in [64, 16] {
+			// `add_picture` replaces same-type pictures, so push blocks directly
+			let mut picture = metaflac::block::Picture::new();
+			picture.picture_type = metaflac::block::PictureType::CoverFront;
+			picture.mime_type = "image/png".to_owned();
+			picture.width = dimension;
+			picture.height = dimension;
+			picture.data = encode(dimension);
+			tag.push_block(metaflac::Block::Picture(picture));
+		}
+		tag.save().unwrap();
+		assert_eq!(read(&flac_path).unwrap().dimensions(), (64, 64));
+	}
+
+	#[test]
+	fn read_raw_returns_exact_embedded_bytes() {
+		let output_dir = prepare_test_directory(test_name!());
+		let image_bytes = fs::read("test-data/artwork/Folder.png").unwrap();
+
+		let flac_path = output_dir.join("sample.flac");
+		fs::copy("test-data/formats/sample.flac", &flac_path).unwrap();
+		write(&flac_path, &image_bytes).unwrap();
+
+		let raw = read_raw(&flac_path).unwrap();
+		assert_eq!(raw.bytes, image_bytes);
+		assert_eq!(raw.mime, "image/png");
+	}
+
+	#[test]
+	fn read_raw_falls_back_to_folder_image() {
+		let output_dir = prepare_test_directory(test_name!());
+
+		let song_path = output_dir.join("sample.mp3");
+		fs::copy("test-data/formats/sample.mp3", &song_path).unwrap();
+		let folder_bytes = fs::read("test-data/artwork/Folder.png").unwrap();
+		fs::write(output_dir.join("Folder.png"), &folder_bytes).unwrap();
+
+		let raw = read_raw(&song_path).unwrap();
+		assert_eq!(raw.bytes, folder_bytes);
+		assert_eq!(raw.mime, "image/png");
+	}
+
+	#[test]
+	fn precached_covers_respect_stored_max_dimension() {
+		let output_dir = prepare_test_directory(test_name!());
+		let manager = Manager::new(output_dir.join("thumbnails")).with_stored_max_dimension(48);
+
+		let cached_path = manager
+			.precache_cover(Path::new("test-data/artwork/Folder.png"))
+			.unwrap();
+		let cached_image = image::open(cached_path).unwrap();
+		assert!(cached_image.width() <= 48);
+		assert!(cached_image.height() <= 48);
+	}
+
+	#[test]
+	fn auto_format_follows_source_transparency() {
+		let output_dir = prepare_test_directory(test_name!());
+		let manager = Manager::new(output_dir.join("thumbnails"));
+
+		let transparent_path = output_dir.join("transparent.png");
+		DynamicImage::ImageRgba8(ImageBuffer::from_pixel(32, 32, image::Rgba([255, 0, 0, 128])))
+			.save(&transparent_path)
+			.unwrap();
+
+		let opaque_path = output_dir.join("opaque.jpg");
+		DynamicImage::ImageRgb8(ImageBuffer::from_pixel(32, 32, image::Rgb([10, 20, 30])))
+			.save(&opaque_path)
+			.unwrap();
+
+		// Transparent sources keep their alpha channel in a PNG thumbnail
+		let thumbnail_path = manager
+			.get_thumbnail(&transparent_path, &Options::default())
+			.unwrap();
+		assert_eq!(thumbnail_path.extension().unwrap(), "png");
+		let thumbnail = image::open(&thumbnail_path).unwrap();
+		assert!(thumbnail.color().has_alpha());
+
+		// Opaque sources compress better as JPEG
+		let thumbnail_path = manager
+			.get_thumbnail(&opaque_path, &Options::default())
+			.unwrap();
+		assert_eq!(thumbnail_path.extension().unwrap(), "jpg");
+
+		// An explicit format request overrides the automatic pick
+		let jpeg_options = Options {
+			format: Format::Jpeg,
+			..Options::default()
+		};
+		let thumbnail_path = manager
+			.get_thumbnail(&transparent_path, &jpeg_options)
+			.unwrap();
+		assert_eq!(thumbnail_path.extension().unwrap(), "jpg");
+	}
+
+	#[test]
+	fn concurrent_requests_generate_thumbnail_once() {
+		let output_dir = prepare_test_directory(test_name!());
+		let manager = Manager::new(output_dir.join("thumbnails"));
+
+		let source_path = output_dir.join("cover.png");
+		DynamicImage::ImageRgb8(ImageBuffer::from_fn(64, 64, |x, y| {
+			image::Rgb([(x * 4) as u8, (y * 4) as u8, 128])
+		}))
+		.save(&source_path)
+		.unwrap();
+
+		let num_requests = 8;
+		let barrier = Arc::new(std::sync::Barrier::new(num_requests));
+		let handles: Vec<_> = (0..num_requests)
+			.map(|_| {
+				let manager = manager.clone();
+				let source_path = source_path.clone();
+				let barrier = barrier.clone();
+				thread::spawn(move || {
+					barrier.wait();
+					manager.get_thumbnail(&source_path, &Options::default())
+				})
+			})
+			.collect();
+
+		let thumbnail_paths: Vec<_> = handles
+			.into_iter()
+			.map(|handle| handle.join().unwrap().unwrap())
+			.collect();
+		assert!(thumbnail_paths.iter().all(|p| *p == thumbnail_paths[0]));
+		assert_eq!(manager.generation_count(), 1);
+	}
+
+	#[test]
+	fn progressive_jpeg_differs_from_baseline() {
+		let output_dir = prepare_test_directory(test_name!());
+		let manager = Manager::new(output_dir.join("thumbnails"));
+
+		let source_path = output_dir.join("cover.png");
+		DynamicImage::ImageRgb8(ImageBuffer::from_fn(64, 64, |x, y| {
+			image::Rgb([(x * 4) as u8, (y * 4) as u8, 128])
+		}))
+		.save(&source_path)
+		.unwrap();
+
+		let baseline_options = Options {
+			format: Format::Jpeg,
+			..Options::default()
+		};
+		let progressive_options = Options {
+			format: Format::Jpeg,
+			progressive: true,
+			..Options::default()
+		};
+
+		let baseline_path = manager.get_thumbnail(&source_path, &baseline_options).unwrap();
+		let progressive_path = manager
+			.get_thumbnail(&source_path, &progressive_options)
+			.unwrap();
+		assert_ne!(baseline_path, progressive_path);
+
+		let baseline = std::fs::read(&baseline_path).unwrap();
+		let progressive = std::fs::read(&progressive_path).unwrap();
+		assert_ne!(baseline, progressive);
+
+		// Both decode to valid images of the same dimensions
+		let baseline_image = image::load_from_memory(&baseline).unwrap();
+		let progressive_image = image::load_from_memory(&progressive).unwrap();
+		assert_eq!(baseline_image.dimensions(), progressive_image.dimensions());
+
+		// Progressive streams start a scan with SOF2, baseline with SOF0
+		let has_marker = |bytes: &[u8], marker: u8| {
+			bytes.windows(2).any(|w| w == [0xFF, marker])
+		};
+		assert!(has_marker(&baseline, 0xC0));
+		assert!(!has_marker(&baseline, 0xC2));
+		assert!(has_marker(&progressive, 0xC2));
+	}
+
+	#[test]
+	fn oversized_requests_are_clamped() {
+		let manager = Manager::new(PathBuf::new()).with_max_output_dimension(600);
+
+		let oversized = Options {
+			max_dimension: Some(10_000),
+			..Options::default()
+		};
+		assert_eq!(manager.clamp_options(&oversized).max_dimension, Some(600));
+
+		let native = Options {
+			max_dimension: None,
+			..Options::default()
+		};
+		assert_eq!(manager.clamp_options(&native).max_dimension, Some(600));
+
+		let in_range = Options {
+			max_dimension: Some(400),
+			..Options::default()
+		};
+		assert_eq!(manager.clamp_options(&in_range).max_dimension, Some(400));
+	}
+
+	#[test]
+	fn slow_reads_time_out() {
+		let slow = run_with_timeout(
+			|| {
+				thread::sleep(Duration::from_millis(200));
+				42
+			},
+			Duration::from_millis(50),
+		);
+		assert_eq!(slow, None);
+
+		let fast = run_with_timeout(|| 42, Duration::from_secs(5));
+		assert_eq!(fast, Some(42));
+	}
 
 	#[test]
 	fn can_read_artwork_data() {
@@ -243,6 +1139,11 @@ mod test {
 			.ok();
 		assert_eq!(ape_img, None);
 
+		let dsf_img = read(Path::new("test-data/artwork/sample.dsf"))
+			.unwrap()
+			.to_rgb8();
+		assert_eq!(dsf_img, embedded_img);
+
 		let flac_img = read(Path::new("test-data/artwork/sample.flac"))
 			.unwrap()
 			.to_rgb8();
@@ -258,6 +1159,12 @@ mod test {
 			.to_rgb8();
 		assert_eq!(m4a_img, embedded_img);
 
+		// Audiobooks share the m4a cover extraction path
+		let m4b_img = read(Path::new("test-data/formats/sample.m4b"))
+			.unwrap()
+			.to_rgb8();
+		assert_eq!(m4b_img, embedded_img);
+
 		let ogg_img = read(Path::new("test-data/artwork/sample.ogg"))
 			.map(|d| d.to_rgb8())
 			.ok();
@@ -1,12 +1,15 @@
 use diesel::prelude::*;
 use pbkdf2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use pbkdf2::Pbkdf2;
+use rand::distributions::Alphanumeric;
 use rand::rngs::OsRng;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::app::settings::AuthSecret;
-use crate::db::{self, users, DB};
+use crate::db::{self, admin_changes, password_history, users, DB};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -20,6 +23,8 @@ pub enum Error {
 	EmptyPassword,
 	#[error("Username does not exist")]
 	IncorrectUsername,
+	#[error("Cannot demote the last administrator")]
+	LastAdminDemotion,
 	#[error("Password does not match username")]
 	IncorrectPassword,
 	#[error("Invalid auth token")]
@@ -30,6 +35,8 @@ pub enum Error {
 	MissingLastFMSessionKey,
 	#[error("Failed to hash password")]
 	PasswordHashing,
+	#[error("Password was used too recently")]
+	PasswordRecentlyUsed,
 	#[error("Failed to encode authorization token")]
 	AuthorizationTokenEncoding,
 	#[error("Failed to encode Branca token")]
@@ -50,6 +57,24 @@ impl User {
 	}
 }
 
+// One grant or revocation of admin rights, kept for compliance audits. The
+// actor is absent for changes made from the command line or a config file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AdminChange {
+	pub actor: Option<String>,
+	pub old_is_admin: bool,
+	pub new_is_admin: bool,
+	pub changed_at: i32,
+}
+
+// What happened to a single user within a batch of admin changes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdminUpdateOutcome {
+	Updated,
+	Unchanged,
+	NotFound,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct NewUser {
 	pub name: String,
@@ -60,10 +85,25 @@ pub struct NewUser {
 #[derive(Debug)]
 pub struct AuthToken(pub String);
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum AuthorizationScope {
 	PolarisAuth,
 	LastFMLink,
+	ReadOnly,
+	StreamOnly,
+}
+
+impl AuthorizationScope {
+	// Maximum age of a token carrying this scope, in seconds. Zero means the
+	// token never expires.
+	fn ttl_seconds(&self) -> u32 {
+		match self {
+			AuthorizationScope::PolarisAuth => 0,
+			AuthorizationScope::LastFMLink => 10 * 60,
+			AuthorizationScope::ReadOnly => 30 * 24 * 60 * 60,
+			AuthorizationScope::StreamOnly => 30 * 24 * 60 * 60,
+		}
+	}
 }
 
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -79,15 +119,48 @@ pub struct Preferences {
 	pub web_theme_accent: Option<String>,
 }
 
+const DEFAULT_TOKEN_SKEW_TOLERANCE_SECONDS: u32 = 5;
+
 #[derive(Clone)]
 pub struct Manager {
 	db: DB,
 	auth_secret: AuthSecret,
+	old_auth_secrets: Vec<AuthSecret>,
+	password_history_depth: usize,
+	token_skew_tolerance_seconds: u32,
 }
 
 impl Manager {
 	pub fn new(db: DB, auth_secret: AuthSecret) -> Self {
-		Self { db, auth_secret }
+		Self {
+			db,
+			auth_secret,
+			old_auth_secrets: Vec::new(),
+			password_history_depth: 0,
+			token_skew_tolerance_seconds: DEFAULT_TOKEN_SKEW_TOLERANCE_SECONDS,
+		}
+	}
+
+	// Retired keys that remain valid for token verification during a rotation
+	// window. New tokens are always signed with the primary secret.
+	pub fn with_old_auth_secrets(mut self, old_auth_secrets: Vec<AuthSecret>) -> Self {
+		self.old_auth_secrets = old_auth_secrets;
+		self
+	}
+
+	// Rejects password changes that reuse any of the user's last N passwords.
+	// A depth of zero disables enforcement.
+	pub fn with_password_history_depth(mut self, depth: usize) -> Self {
+		self.password_history_depth = depth;
+		self
+	}
+
+	// Grace period added to token expiration checks, so that clients whose
+	// clocks run slightly ahead of the server do not see freshly issued
+	// short-lived tokens rejected.
+	pub fn with_token_skew_tolerance(mut self, seconds: u32) -> Self {
+		self.token_skew_tolerance_seconds = seconds;
+		self
 	}
 
 	pub fn create(&self, new_user: &NewUser) -> Result<(), Error> {
@@ -106,6 +179,7 @@ impl Manager {
 		diesel::insert_into(users::table)
 			.values(&new_user)
 			.execute(&mut connection)?;
+		self.record_password_history(&new_user.name, &new_user.password_hash)?;
 		Ok(())
 	}
 
@@ -117,24 +191,190 @@ impl Manager {
 	}
 
 	pub fn set_password(&self, username: &str, password: &str) -> Result<(), Error> {
+		self.check_password_history(username, password)?;
 		let hash = hash_password(password)?;
+		{
+			let mut connection = self.db.connect()?;
+			use crate::db::users::dsl::*;
+			diesel::update(users.filter(name.eq(username)))
+				.set(password_hash.eq(&hash))
+				.execute(&mut connection)?;
+		}
+		self.record_password_history(username, &hash)?;
+		Ok(())
+	}
+
+	fn check_password_history(&self, username: &str, password: &str) -> Result<(), Error> {
+		if self.password_history_depth == 0 {
+			return Ok(());
+		}
 		let mut connection = self.db.connect()?;
-		use crate::db::users::dsl::*;
-		diesel::update(users.filter(name.eq(username)))
-			.set(password_hash.eq(hash))
-			.execute(&mut connection)?;
+		let recent_hashes: Vec<String> = password_history::table
+			.filter(password_history::username.eq(username))
+			.order(password_history::id.desc())
+			.limit(self.password_history_depth as i64)
+			.select(password_history::password_hash)
+			.load(&mut connection)?;
+		if recent_hashes.iter().any(|h| verify_password(h, password)) {
+			return Err(Error::PasswordRecentlyUsed);
+		}
 		Ok(())
 	}
 
-	pub fn set_is_admin(&self, username: &str, is_admin: bool) -> Result<(), Error> {
-		use crate::db::users::dsl::*;
+	fn record_password_history(&self, username: &str, hash: &str) -> Result<(), Error> {
+		if self.password_history_depth == 0 {
+			return Ok(());
+		}
+		let changed_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i32)
+			.unwrap_or_default();
 		let mut connection = self.db.connect()?;
-		diesel::update(users.filter(name.eq(username)))
-			.set(admin.eq(is_admin as i32))
+		diesel::insert_into(password_history::table)
+			.values((
+				password_history::username.eq(username),
+				password_history::password_hash.eq(hash),
+				password_history::changed_at.eq(changed_at),
+			))
 			.execute(&mut connection)?;
+		// Entries beyond the enforced depth no longer block reuse, so drop them
+		let stale_ids: Vec<i32> = password_history::table
+			.filter(password_history::username.eq(username))
+			.order(password_history::id.desc())
+			.offset(self.password_history_depth as i64)
+			.select(password_history::id)
+			.load(&mut connection)?;
+		if !stale_ids.is_empty() {
+			diesel::delete(password_history::table.filter(password_history::id.eq_any(stale_ids)))
+				.execute(&mut connection)?;
+		}
 		Ok(())
 	}
 
+	pub fn set_is_admin(
+		&self,
+		username: &str,
+		is_admin: bool,
+		actor: Option<&str>,
+	) -> Result<(), Error> {
+		let mut connection = self.db.connect()?;
+		Self::apply_admin_change(&mut connection, username, is_admin, actor)?;
+		Ok(())
+	}
+
+	// Applies a batch of admin changes in a single transaction. A batch that
+	// would leave the instance without any administrator is rejected as a
+	// whole, so partial application can never lock every admin out.
+	pub fn set_admin_many(
+		&self,
+		updates: &[(String, bool)],
+		actor: Option<&str>,
+	) -> Result<Vec<(String, AdminUpdateOutcome)>, Error> {
+		let mut connection = self.db.connect()?;
+		connection.transaction(|connection| {
+			let mut results = Vec::with_capacity(updates.len());
+			for (username, is_admin) in updates {
+				let outcome = Self::apply_admin_change(connection, username, *is_admin, actor)?;
+				results.push((username.clone(), outcome));
+			}
+
+			let demotes = updates.iter().any(|(_, is_admin)| !is_admin);
+			let admin_count: i64 = users::table
+				.filter(users::admin.eq(1))
+				.count()
+				.get_result(connection)?;
+			if demotes && admin_count == 0 {
+				return Err(Error::LastAdminDemotion);
+			}
+
+			Ok(results)
+		})
+	}
+
+	fn apply_admin_change(
+		connection: &mut SqliteConnection,
+		username: &str,
+		is_admin: bool,
+		actor: Option<&str>,
+	) -> Result<AdminUpdateOutcome, diesel::result::Error> {
+		let old_is_admin: i32 = match users::table
+			.filter(users::name.eq(username))
+			.select(users::admin)
+			.get_result(connection)
+		{
+			// Preserved behavior: changing admin rights of an unknown user is
+			// a no-op rather than an error
+			Err(diesel::result::Error::NotFound) => return Ok(AdminUpdateOutcome::NotFound),
+			result => result?,
+		};
+
+		diesel::update(users::table.filter(users::name.eq(username)))
+			.set(users::admin.eq(is_admin as i32))
+			.execute(connection)?;
+
+		// Only actual grants and revocations are worth auditing; re-asserting
+		// the current value (e.g. when applying a config file) is not
+		if old_is_admin == is_admin as i32 {
+			return Ok(AdminUpdateOutcome::Unchanged);
+		}
+
+		let changed_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i32)
+			.unwrap_or_default();
+		diesel::insert_into(admin_changes::table)
+			.values((
+				admin_changes::username.eq(username),
+				admin_changes::actor.eq(actor),
+				admin_changes::old_is_admin.eq(old_is_admin),
+				admin_changes::new_is_admin.eq(is_admin as i32),
+				admin_changes::changed_at.eq(changed_at),
+			))
+			.execute(connection)?;
+		Ok(AdminUpdateOutcome::Updated)
+	}
+
+	pub fn get_admin_history(&self, username: &str) -> Result<Vec<AdminChange>, Error> {
+		let mut connection = self.db.connect()?;
+		let changes: Vec<(Option<String>, i32, i32, i32)> = admin_changes::table
+			.filter(admin_changes::username.eq(username))
+			.order(admin_changes::id.asc())
+			.select((
+				admin_changes::actor,
+				admin_changes::old_is_admin,
+				admin_changes::new_is_admin,
+				admin_changes::changed_at,
+			))
+			.load(&mut connection)?;
+		Ok(changes
+			.into_iter()
+			.map(
+				|(actor, old_is_admin, new_is_admin, changed_at)| AdminChange {
+					actor,
+					old_is_admin: old_is_admin != 0,
+					new_is_admin: new_is_admin != 0,
+					changed_at,
+				},
+			)
+			.collect())
+	}
+
+	// Recovery path for a locked-out administrator. Only reachable from the
+	// command line, never exposed as a remote route.
+	pub fn reset_admin(&self, username: &str) -> Result<String, Error> {
+		if !self.exists(username)? {
+			return Err(Error::IncorrectUsername);
+		}
+		let password: String = OsRng
+			.sample_iter(&Alphanumeric)
+			.take(16)
+			.map(char::from)
+			.collect();
+		self.set_password(username, &password)?;
+		self.set_is_admin(username, true, None)?;
+		Ok(password)
+	}
+
 	pub fn login(&self, username: &str, password: &str) -> Result<AuthToken, Error> {
 		use crate::db::users::dsl::*;
 		let mut connection = self.db.connect()?;
@@ -143,7 +383,12 @@ impl Manager {
 			.filter(name.eq(username))
 			.get_result(&mut connection)
 		{
-			Err(diesel::result::Error::NotFound) => Err(Error::IncorrectUsername),
+			Err(diesel::result::Error::NotFound) => {
+				// Spend the same PBKDF2 time as a wrong-password attempt, so
+				// response timing does not reveal whether a username exists
+				verify_password(dummy_password_hash(), password);
+				Err(Error::IncorrectUsername)
+			}
 			Ok(hash) => {
 				let hash: String = hash;
 				if verify_password(&hash, password) {
@@ -160,6 +405,20 @@ impl Manager {
 		}
 	}
 
+	pub fn authenticate_any(
+		&self,
+		auth_token: &AuthToken,
+		scopes: &[AuthorizationScope],
+	) -> Result<Authorization, Error> {
+		for scope in scopes {
+			match self.authenticate(auth_token, *scope) {
+				Err(Error::IncorrectAuthorizationScope) | Err(Error::InvalidAuthToken) => continue,
+				result => return result,
+			}
+		}
+		Err(Error::InvalidAuthToken)
+	}
+
 	pub fn authenticate(
 		&self,
 		auth_token: &AuthToken,
@@ -179,12 +438,14 @@ impl Manager {
 		scope: AuthorizationScope,
 	) -> Result<Authorization, Error> {
 		let AuthToken(data) = auth_token;
-		let ttl = match scope {
-			AuthorizationScope::PolarisAuth => 0,      // permanent
-			AuthorizationScope::LastFMLink => 10 * 60, // 10 minutes
+		let ttl = match scope.ttl_seconds() {
+			0 => 0,
+			ttl => ttl + self.token_skew_tolerance_seconds,
 		};
-		let authorization = branca::decode(data, &self.auth_secret.key, ttl)
-			.map_err(|_| Error::InvalidAuthToken)?;
+		let authorization = std::iter::once(&self.auth_secret)
+			.chain(self.old_auth_secrets.iter())
+			.find_map(|secret| branca::decode(data, &secret.key, ttl).ok())
+			.ok_or(Error::InvalidAuthToken)?;
 		let authorization: Authorization =
 			serde_json::from_slice(&authorization[..]).map_err(|_| Error::InvalidAuthToken)?;
 		if authorization.scope != scope {
@@ -291,6 +552,17 @@ impl Manager {
 		Ok(())
 	}
 
+	pub fn generate_scoped_auth_token(
+		&self,
+		username: &str,
+		scope: AuthorizationScope,
+	) -> Result<AuthToken, Error> {
+		self.generate_auth_token(&Authorization {
+			username: username.to_owned(),
+			scope,
+		})
+	}
+
 	pub fn generate_lastfm_link_token(&self, username: &str) -> Result<AuthToken, Error> {
 		self.generate_auth_token(&Authorization {
 			username: username.to_owned(),
@@ -334,6 +606,13 @@ fn hash_password(password: &str) -> Result<String, Error> {
 	}
 }
 
+// Hash verified against when a login names an unknown user, so that both
+// failure paths cost one PBKDF2 run
+fn dummy_password_hash() -> &'static str {
+	static HASH: OnceLock<String> = OnceLock::new();
+	HASH.get_or_init(|| hash_password("polaris-timing-equalization").unwrap())
+}
+
 fn verify_password(password_hash: &str, attempted_password: &str) -> bool {
 	match PasswordHash::new(password_hash) {
 		Ok(h) => Pbkdf2
@@ -453,6 +732,34 @@ mod test {
 		));
 	}
 
+	#[test]
+	fn unknown_user_logins_verify_a_dummy_hash() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+
+		let new_user = NewUser {
+			name: TEST_USERNAME.to_owned(),
+			password: TEST_PASSWORD.to_owned(),
+			admin: false,
+		};
+		ctx.user_manager.create(&new_user).unwrap();
+
+		// The dummy hash must be a well-formed PBKDF2 hash that genuinely
+		// verifies, so the unknown-user path costs the same password
+		// verification as a wrong-password attempt
+		assert!(PasswordHash::new(dummy_password_hash()).is_ok());
+		assert!(verify_password(
+			dummy_password_hash(),
+			"polaris-timing-equalization"
+		));
+		assert!(!verify_password(dummy_password_hash(), "not the password"));
+
+		let known_user = ctx.user_manager.login(TEST_USERNAME, "not the password");
+		assert!(matches!(known_user.unwrap_err(), Error::IncorrectPassword));
+
+		let unknown_user = ctx.user_manager.login("nobody", "not the password");
+		assert!(matches!(unknown_user.unwrap_err(), Error::IncorrectUsername));
+	}
+
 	#[test]
 	fn login_golden_path() {
 		let ctx = test::ContextBuilder::new(test_name!()).build();
@@ -465,6 +772,160 @@ mod test {
 		assert!(ctx.user_manager.login(TEST_USERNAME, TEST_PASSWORD).is_ok())
 	}
 
+	#[test]
+	fn reset_admin_generates_working_credentials() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let new_user = NewUser {
+			name: TEST_USERNAME.to_owned(),
+			password: TEST_PASSWORD.to_owned(),
+			admin: false,
+		};
+		ctx.user_manager.create(&new_user).unwrap();
+
+		let password = ctx.user_manager.reset_admin(TEST_USERNAME).unwrap();
+		assert_ne!(password, TEST_PASSWORD);
+		assert!(ctx.user_manager.login(TEST_USERNAME, TEST_PASSWORD).is_err());
+		assert!(ctx.user_manager.login(TEST_USERNAME, &password).is_ok());
+		assert!(ctx.user_manager.is_admin(TEST_USERNAME).unwrap());
+	}
+
+	#[test]
+	fn reset_admin_rejects_unknown_user() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		assert!(matches!(
+			ctx.user_manager.reset_admin("nobody"),
+			Err(Error::IncorrectUsername)
+		));
+	}
+
+	#[test]
+	fn admin_changes_are_recorded_in_order() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		ctx.user_manager
+			.create(&NewUser {
+				name: TEST_USERNAME.to_owned(),
+				password: TEST_PASSWORD.to_owned(),
+				admin: false,
+			})
+			.unwrap();
+
+		ctx.user_manager
+			.set_is_admin(TEST_USERNAME, true, Some("Hank"))
+			.unwrap();
+		ctx.user_manager
+			.set_is_admin(TEST_USERNAME, false, Some("Skyler"))
+			.unwrap();
+
+		let history = ctx.user_manager.get_admin_history(TEST_USERNAME).unwrap();
+		assert_eq!(history.len(), 2);
+		assert_eq!(history[0].actor.as_deref(), Some("Hank"));
+		assert!(!history[0].old_is_admin);
+		assert!(history[0].new_is_admin);
+		assert_eq!(history[1].actor.as_deref(), Some("Skyler"));
+		assert!(history[1].old_is_admin);
+		assert!(!history[1].new_is_admin);
+	}
+
+	#[test]
+	fn admin_changes_skip_unknown_users() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		ctx.user_manager
+			.set_is_admin("nobody", true, None)
+			.unwrap();
+		assert_eq!(ctx.user_manager.get_admin_history("nobody").unwrap(), vec![]);
+	}
+
+	#[test]
+	fn set_admin_many_applies_a_safe_batch() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		for (name, admin) in [("Walter", true), ("Jesse", false), ("Mike", false)] {
+			ctx.user_manager
+				.create(&NewUser {
+					name: name.to_owned(),
+					password: TEST_PASSWORD.to_owned(),
+					admin,
+				})
+				.unwrap();
+		}
+
+		let updates = vec![
+			("Walter".to_owned(), false),
+			("Jesse".to_owned(), true),
+			("Mike".to_owned(), false),
+			("nobody".to_owned(), true),
+		];
+		let results = ctx.user_manager.set_admin_many(&updates, None).unwrap();
+		assert_eq!(
+			results,
+			vec![
+				("Walter".to_owned(), AdminUpdateOutcome::Updated),
+				("Jesse".to_owned(), AdminUpdateOutcome::Updated),
+				("Mike".to_owned(), AdminUpdateOutcome::Unchanged),
+				("nobody".to_owned(), AdminUpdateOutcome::NotFound),
+			]
+		);
+		assert!(!ctx.user_manager.is_admin("Walter").unwrap());
+		assert!(ctx.user_manager.is_admin("Jesse").unwrap());
+	}
+
+	#[test]
+	fn set_admin_many_rejects_demoting_the_last_admin() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		for (name, admin) in [("Walter", true), ("Jesse", true)] {
+			ctx.user_manager
+				.create(&NewUser {
+					name: name.to_owned(),
+					password: TEST_PASSWORD.to_owned(),
+					admin,
+				})
+				.unwrap();
+		}
+
+		let updates = vec![("Walter".to_owned(), false), ("Jesse".to_owned(), false)];
+		let error = ctx.user_manager.set_admin_many(&updates, None).unwrap_err();
+		assert!(matches!(error, Error::LastAdminDemotion));
+
+		// The rejection is atomic: neither demotion went through
+		assert!(ctx.user_manager.is_admin("Walter").unwrap());
+		assert!(ctx.user_manager.is_admin("Jesse").unwrap());
+		assert_eq!(ctx.user_manager.get_admin_history("Walter").unwrap(), vec![]);
+	}
+
+	#[test]
+	fn old_auth_secrets_verify_existing_tokens() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let new_user = NewUser {
+			name: TEST_USERNAME.to_owned(),
+			password: TEST_PASSWORD.to_owned(),
+			admin: false,
+		};
+		ctx.user_manager.create(&new_user).unwrap();
+
+		let old_secret = AuthSecret { key: [27; 32] };
+		let new_secret = AuthSecret { key: [8; 32] };
+
+		let old_manager = Manager::new(ctx.db.clone(), old_secret.clone());
+		let old_token = old_manager.login(TEST_USERNAME, TEST_PASSWORD).unwrap();
+
+		let rotated_manager = Manager::new(ctx.db.clone(), new_secret.clone())
+			.with_old_auth_secrets(vec![old_secret]);
+		assert!(rotated_manager
+			.authenticate(&old_token, AuthorizationScope::PolarisAuth)
+			.is_ok());
+
+		// Tokens issued after the rotation must verify with the primary key alone
+		let new_token = rotated_manager
+			.login(TEST_USERNAME, TEST_PASSWORD)
+			.unwrap();
+		let new_only_manager = Manager::new(ctx.db.clone(), new_secret);
+		assert!(new_only_manager
+			.authenticate(&new_token, AuthorizationScope::PolarisAuth)
+			.is_ok());
+		assert!(new_only_manager
+			.authenticate(&old_token, AuthorizationScope::PolarisAuth)
+			.is_err());
+	}
+
 	#[test]
 	fn authenticate_rejects_bad_token() {
 		let ctx = test::ContextBuilder::new(test_name!()).build();
@@ -534,4 +995,112 @@ mod test {
 			Error::IncorrectAuthorizationScope
 		));
 	}
+
+	#[test]
+	fn authenticate_tolerates_small_clock_skew() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+
+		let new_user = NewUser {
+			name: TEST_USERNAME.to_owned(),
+			password: TEST_PASSWORD.to_owned(),
+			admin: false,
+		};
+		ctx.user_manager.create(&new_user).unwrap();
+
+		let make_token = |timestamp: u32| {
+			let authorization = Authorization {
+				username: TEST_USERNAME.to_owned(),
+				scope: AuthorizationScope::LastFMLink,
+			};
+			let serialized = serde_json::to_string(&authorization).unwrap();
+			AuthToken(
+				branca::encode(
+					serialized.as_bytes(),
+					&ctx.user_manager.auth_secret.key,
+					timestamp,
+				)
+				.unwrap(),
+			)
+		};
+
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_secs() as u32;
+
+		// Issued by a clock running slightly ahead of ours
+		let skewed_token = make_token(now + 3);
+		assert!(ctx
+			.user_manager
+			.authenticate(&skewed_token, AuthorizationScope::LastFMLink)
+			.is_ok());
+
+		// Expired well beyond the tolerance window
+		let stale_token = make_token(now - 10 * 60 - 2 * DEFAULT_TOKEN_SKEW_TOLERANCE_SECONDS);
+		assert!(matches!(
+			ctx.user_manager
+				.authenticate(&stale_token, AuthorizationScope::LastFMLink)
+				.unwrap_err(),
+			Error::InvalidAuthToken
+		));
+	}
+
+	#[test]
+	fn password_history_rejects_recent_reuse() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let manager = ctx.user_manager.clone().with_password_history_depth(2);
+
+		let new_user = NewUser {
+			name: TEST_USERNAME.to_owned(),
+			password: TEST_PASSWORD.to_owned(),
+			admin: false,
+		};
+		manager.create(&new_user).unwrap();
+
+		assert!(matches!(
+			manager.set_password(TEST_USERNAME, TEST_PASSWORD).unwrap_err(),
+			Error::PasswordRecentlyUsed
+		));
+
+		manager.set_password(TEST_USERNAME, "second_password").unwrap();
+		assert!(matches!(
+			manager.set_password(TEST_USERNAME, TEST_PASSWORD).unwrap_err(),
+			Error::PasswordRecentlyUsed
+		));
+	}
+
+	#[test]
+	fn password_history_allows_reuse_beyond_depth() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+		let manager = ctx.user_manager.clone().with_password_history_depth(2);
+
+		let new_user = NewUser {
+			name: TEST_USERNAME.to_owned(),
+			password: TEST_PASSWORD.to_owned(),
+			admin: false,
+		};
+		manager.create(&new_user).unwrap();
+
+		manager.set_password(TEST_USERNAME, "second_password").unwrap();
+		manager.set_password(TEST_USERNAME, "third_password").unwrap();
+
+		// The original password has aged out of the enforced history
+		manager.set_password(TEST_USERNAME, TEST_PASSWORD).unwrap();
+		manager.login(TEST_USERNAME, TEST_PASSWORD).unwrap();
+	}
+
+	#[test]
+	fn password_history_disabled_by_default() {
+		let ctx = test::ContextBuilder::new(test_name!()).build();
+
+		let new_user = NewUser {
+			name: TEST_USERNAME.to_owned(),
+			password: TEST_PASSWORD.to_owned(),
+			admin: false,
+		};
+		ctx.user_manager.create(&new_user).unwrap();
+		ctx.user_manager
+			.set_password(TEST_USERNAME, TEST_PASSWORD)
+			.unwrap();
+	}
 }
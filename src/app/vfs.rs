@@ -18,17 +18,44 @@ pub enum Error {
 	Database(#[from] diesel::result::Error),
 }
 
+fn default_include_in_discovery() -> bool {
+	true
+}
+
 #[derive(Clone, Debug, Deserialize, Insertable, PartialEq, Eq, Queryable, Serialize)]
 #[diesel(table_name = mount_points)]
 pub struct MountDir {
 	pub source: String,
 	pub name: String,
+	#[serde(default = "default_include_in_discovery")]
+	pub include_in_discovery: bool,
+	// How many directory levels below the mount root the indexer may recurse
+	// into. Zero means unlimited.
+	#[serde(default)]
+	pub max_depth: i32,
+	// Human-facing label. The `name` stays stable so virtual paths and client
+	// bookmarks survive a relabel; empty means the name is displayed as-is.
+	#[serde(default)]
+	pub display_name: String,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub struct Mount {
 	pub source: PathBuf,
 	pub name: String,
+	pub include_in_discovery: bool,
+	pub max_depth: i32,
+	pub display_name: String,
+}
+
+impl Mount {
+	pub fn display_name(&self) -> &str {
+		if self.display_name.is_empty() {
+			&self.name
+		} else {
+			&self.display_name
+		}
+	}
 }
 
 impl From<MountDir> for Mount {
@@ -41,10 +68,17 @@ impl From<MountDir> for Mount {
 		Self {
 			name: m.name,
 			source,
+			include_in_discovery: m.include_in_discovery,
+			max_depth: m.max_depth,
+			display_name: m.display_name,
 		}
 	}
 }
 
+/// Maps between real filesystem paths and virtual paths, where the first
+/// component of a virtual path is the name of a mount (eg. `MyMusic/Artist/Album`).
+/// Mount names keep the underlying directory layout private and let multiple
+/// mounts share identical internal structure without colliding.
 #[allow(clippy::upper_case_acronyms)]
 pub struct VFS {
 	mounts: Vec<Mount>,
@@ -70,9 +104,16 @@ impl VFS {
 	}
 
 	pub fn virtual_to_real<P: AsRef<Path>>(&self, virtual_path: P) -> Result<PathBuf, Error> {
+		// Clients may send either separator, regardless of the host OS
+		let components: PathBuf = virtual_path
+			.as_ref()
+			.to_string_lossy()
+			.split(['/', '\\'])
+			.filter(|c| !c.is_empty())
+			.collect();
 		for mount in &self.mounts {
 			let mount_path = Path::new(&mount.name);
-			if let Ok(p) = virtual_path.as_ref().strip_prefix(mount_path) {
+			if let Ok(p) = components.strip_prefix(mount_path) {
 				return if p.components().count() == 0 {
 					Ok(mount.source.clone())
 				} else {
@@ -108,7 +149,7 @@ impl Manager {
 		use self::mount_points::dsl::*;
 		let mut connection = self.db.connect()?;
 		let mount_dirs: Vec<MountDir> = mount_points
-			.select((source, name))
+			.select((source, name, include_in_discovery, max_depth, display_name))
 			.get_results(&mut connection)?;
 		Ok(mount_dirs)
 	}
@@ -137,6 +178,9 @@ mod test {
 		let vfs = VFS::new(vec![Mount {
 			name: "root".to_owned(),
 			source: Path::new("test_dir").to_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
 		}]);
 		let real_path: PathBuf = ["test_dir", "somewhere", "something.png"].iter().collect();
 		let virtual_path: PathBuf = ["root", "somewhere", "something.png"].iter().collect();
@@ -149,17 +193,102 @@ mod test {
 		let vfs = VFS::new(vec![Mount {
 			name: "root".to_owned(),
 			source: Path::new("test_dir").to_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
 		}]);
 		let real_path = Path::new("test_dir");
 		let converted_path = vfs.virtual_to_real(Path::new("root")).unwrap();
 		assert_eq!(converted_path, real_path);
 	}
 
+	#[test]
+	fn converts_windows_style_virtual_to_real() {
+		let vfs = VFS::new(vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
+		}]);
+		let real_path: PathBuf = ["test_dir", "somewhere", "something.png"].iter().collect();
+		let converted_path = vfs
+			.virtual_to_real(Path::new(r"root\somewhere\something.png"))
+			.unwrap();
+		assert_eq!(converted_path, real_path);
+	}
+
+	#[test]
+	fn display_name_falls_back_to_mount_name() {
+		let labeled = Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: "My Music".to_owned(),
+		};
+		assert_eq!(labeled.display_name(), "My Music");
+
+		let unlabeled = Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
+		};
+		assert_eq!(unlabeled.display_name(), "root");
+	}
+
+	#[test]
+	fn mount_name_selects_the_matching_source() {
+		let vfs = VFS::new(vec![
+			Mount {
+				name: "MyMusic".to_owned(),
+				source: Path::new("first_dir").to_owned(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
+			},
+			Mount {
+				name: "MoreMusic".to_owned(),
+				source: Path::new("second_dir").to_owned(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
+			},
+		]);
+
+		// Both mounts contain the same internal structure
+		let virtual_path: PathBuf = ["MoreMusic", "Artist", "Album"].iter().collect();
+		let real_path: PathBuf = ["second_dir", "Artist", "Album"].iter().collect();
+		assert_eq!(vfs.virtual_to_real(virtual_path).unwrap(), real_path);
+
+		let virtual_path: PathBuf = ["MyMusic", "Artist", "Album"].iter().collect();
+		let real_path: PathBuf = ["first_dir", "Artist", "Album"].iter().collect();
+		assert_eq!(vfs.virtual_to_real(virtual_path).unwrap(), real_path);
+	}
+
+	#[test]
+	fn rejects_unknown_mount_name() {
+		let vfs = VFS::new(vec![Mount {
+			name: "root".to_owned(),
+			source: Path::new("test_dir").to_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
+		}]);
+		let virtual_path: PathBuf = ["unknown_mount", "somewhere"].iter().collect();
+		assert!(vfs.virtual_to_real(virtual_path).is_err());
+	}
+
 	#[test]
 	fn converts_real_to_virtual() {
 		let vfs = VFS::new(vec![Mount {
 			name: "root".to_owned(),
 			source: Path::new("test_dir").to_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
 		}]);
 		let virtual_path: PathBuf = ["root", "somewhere", "something.png"].iter().collect();
 		let real_path: PathBuf = ["test_dir", "somewhere", "something.png"].iter().collect();
@@ -200,6 +329,9 @@ mod test {
 			let mount_dir = MountDir {
 				source: test.to_owned(),
 				name: "name".to_owned(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
 			};
 			let mount: Mount = mount_dir.into();
 			assert_eq!(mount.source, correct_path);
@@ -0,0 +1,167 @@
+use lewton::inside_ogg::OggStreamReader;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::utils::{get_audio_format, AudioFormat};
+
+pub const DEFAULT_BUCKET_COUNT: usize = 512;
+
+// Ceiling on the number of peaks a single request may ask for, so clients
+// cannot turn a waveform query into a full-resolution sample dump
+pub const MAX_BUCKET_COUNT: usize = 4096;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("Filesystem error for `{0}`: `{1}`")]
+	Io(PathBuf, std::io::Error),
+	#[error("Could not serialize waveform data:\n\n{0}")]
+	Serialization(#[from] serde_json::Error),
+	#[error("Waveforms are not supported for this file format: {0}")]
+	UnsupportedFormat(&'static str),
+	#[error("Could not decode audio samples in `{0}`:\n\n{1}")]
+	Vorbis(PathBuf, lewton::VorbisError),
+}
+
+// Lowest and highest sample amplitude within one bucket, normalized to [-1, 1]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Peak {
+	pub min: f32,
+	pub max: f32,
+}
+
+#[derive(Clone)]
+pub struct Manager {
+	waveforms_dir_path: PathBuf,
+}
+
+impl Manager {
+	pub fn new(waveforms_dir_path: PathBuf) -> Self {
+		Self { waveforms_dir_path }
+	}
+
+	pub fn get_waveform(&self, audio_path: &Path, num_buckets: usize) -> Result<Vec<Peak>, Error> {
+		let num_buckets = num_buckets.clamp(1, MAX_BUCKET_COUNT);
+		let cache_path = self.get_waveform_path(audio_path, num_buckets)?;
+		if let Ok(cached) = File::open(&cache_path) {
+			if let Ok(peaks) = serde_json::from_reader(cached) {
+				return Ok(peaks);
+			}
+		}
+
+		let peaks = compute_waveform(audio_path, num_buckets)?;
+
+		fs::create_dir_all(&self.waveforms_dir_path)
+			.map_err(|e| Error::Io(self.waveforms_dir_path.clone(), e))?;
+		let serialized = serde_json::to_vec(&peaks)?;
+		fs::write(&cache_path, serialized).map_err(|e| Error::Io(cache_path.clone(), e))?;
+
+		Ok(peaks)
+	}
+
+	// Cache entries are keyed on source path, modification time and bucket
+	// count, so an edited file is re-analyzed instead of served stale
+	fn get_waveform_path(&self, audio_path: &Path, num_buckets: usize) -> Result<PathBuf, Error> {
+		let metadata =
+			fs::metadata(audio_path).map_err(|e| Error::Io(audio_path.to_owned(), e))?;
+
+		let mut path_hasher = DefaultHasher::new();
+		audio_path.hash(&mut path_hasher);
+
+		let mut params_hasher = DefaultHasher::new();
+		if let Ok(modified) = metadata.modified() {
+			modified.hash(&mut params_hasher);
+		}
+		num_buckets.hash(&mut params_hasher);
+
+		let mut waveform_path = self.waveforms_dir_path.clone();
+		waveform_path.push(format!(
+			"{}-{}.json",
+			path_hasher.finish(),
+			params_hasher.finish()
+		));
+		Ok(waveform_path)
+	}
+}
+
+fn compute_waveform(audio_path: &Path, num_buckets: usize) -> Result<Vec<Peak>, Error> {
+	let samples = decode_samples(audio_path)?;
+	let mut peaks = vec![Peak::default(); num_buckets];
+	for (index, sample) in samples.iter().enumerate() {
+		let amplitude = *sample as f32 / -(i16::MIN as f32);
+		let peak = &mut peaks[index * num_buckets / samples.len()];
+		peak.min = peak.min.min(amplitude);
+		peak.max = peak.max.max(amplitude);
+	}
+	Ok(peaks)
+}
+
+fn decode_samples(audio_path: &Path) -> Result<Vec<i16>, Error> {
+	match get_audio_format(audio_path) {
+		Some(AudioFormat::OGG) => decode_vorbis_samples(audio_path),
+		Some(AudioFormat::AIFF) => Err(Error::UnsupportedFormat("aiff")),
+		Some(AudioFormat::APE) => Err(Error::UnsupportedFormat("ape")),
+		Some(AudioFormat::DSF) => Err(Error::UnsupportedFormat("dsf")),
+		Some(AudioFormat::FLAC) => Err(Error::UnsupportedFormat("flac")),
+		Some(AudioFormat::MP3) => Err(Error::UnsupportedFormat("mp3")),
+		Some(AudioFormat::MP4) => Err(Error::UnsupportedFormat("mp4")),
+		Some(AudioFormat::MPC) => Err(Error::UnsupportedFormat("mpc")),
+		Some(AudioFormat::OPUS) => Err(Error::UnsupportedFormat("opus")),
+		Some(AudioFormat::WAVE) => Err(Error::UnsupportedFormat("wave")),
+		None => Err(Error::UnsupportedFormat("unknown")),
+	}
+}
+
+// Channels are left interleaved: a bucket then holds the extrema across all
+// channels, which is what a seek-bar rendering wants anyway
+fn decode_vorbis_samples(audio_path: &Path) -> Result<Vec<i16>, Error> {
+	let file = File::open(audio_path).map_err(|e| Error::Io(audio_path.to_owned(), e))?;
+	let mut reader =
+		OggStreamReader::new(file).map_err(|e| Error::Vorbis(audio_path.to_owned(), e))?;
+	let mut samples = Vec::new();
+	while let Some(packet) = reader
+		.read_dec_packet_itl()
+		.map_err(|e| Error::Vorbis(audio_path.to_owned(), e))?
+	{
+		samples.extend(packet);
+	}
+	Ok(samples)
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use crate::test::prepare_test_directory;
+	use crate::test_name;
+
+	#[test]
+	fn waveform_has_requested_resolution() {
+		let test_directory = prepare_test_directory(test_name!());
+		let manager = Manager::new(test_directory.join("waveforms"));
+		let audio_path = Path::new("test-data/formats/sample.ogg");
+
+		let peaks = manager.get_waveform(audio_path, 64).unwrap();
+		assert_eq!(peaks.len(), 64);
+		assert!(peaks.iter().all(|p| (-1.0..=1.0).contains(&p.min)));
+		assert!(peaks.iter().all(|p| (-1.0..=1.0).contains(&p.max)));
+		assert!(peaks.iter().all(|p| p.min <= p.max));
+		assert!(peaks.iter().any(|p| p.max > 0.0));
+
+		// A second request is served from the cache and matches
+		let cached = manager.get_waveform(audio_path, 64).unwrap();
+		assert_eq!(cached, peaks);
+	}
+
+	#[test]
+	fn waveform_rejects_undecodable_formats() {
+		let test_directory = prepare_test_directory(test_name!());
+		let manager = Manager::new(test_directory.join("waveforms"));
+		let audio_path = Path::new("test-data/formats/sample.mp3");
+
+		let result = manager.get_waveform(audio_path, 64);
+		assert!(matches!(result, Err(Error::UnsupportedFormat("mp3"))));
+	}
+}
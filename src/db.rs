@@ -4,6 +4,7 @@ use diesel::RunQueryDsl;
 use diesel_migrations::EmbeddedMigrations;
 use diesel_migrations::MigrationHarness;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 mod schema;
 
@@ -15,14 +16,29 @@ const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 pub enum Error {
 	#[error("Could not initialize database connection pool")]
 	ConnectionPoolBuild,
-	#[error("Could not acquire database connection from pool")]
-	ConnectionPool,
+	#[error("Timed out while waiting for a database connection")]
+	PoolTimeout,
 	#[error("Filesystem error for `{0}`: `{1}`")]
 	Io(PathBuf, std::io::Error),
 	#[error("Could not apply database migrations")]
 	Migration,
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct PoolSettings {
+	pub max_size: u32,
+	pub acquire_timeout: Duration,
+}
+
+impl Default for PoolSettings {
+	fn default() -> Self {
+		Self {
+			max_size: 10,
+			acquire_timeout: Duration::from_secs(30),
+		}
+	}
+}
+
 #[derive(Clone)]
 pub struct DB {
 	pool: r2d2::Pool<ConnectionManager<SqliteConnection>>,
@@ -51,11 +67,17 @@ impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error>
 
 impl DB {
 	pub fn new(path: &Path) -> Result<DB, Error> {
+		DB::new_with_pool_settings(path, &PoolSettings::default())
+	}
+
+	pub fn new_with_pool_settings(path: &Path, pool_settings: &PoolSettings) -> Result<DB, Error> {
 		let directory = path.parent().unwrap();
 		std::fs::create_dir_all(directory).map_err(|e| Error::Io(directory.to_owned(), e))?;
 		let manager = ConnectionManager::<SqliteConnection>::new(path.to_string_lossy());
 		let pool = diesel::r2d2::Pool::builder()
 			.connection_customizer(Box::new(ConnectionCustomizer {}))
+			.max_size(pool_settings.max_size)
+			.connection_timeout(pool_settings.acquire_timeout)
 			.build(manager)
 			.or(Err(Error::ConnectionPoolBuild))?;
 		let db = DB { pool };
@@ -64,7 +86,7 @@ impl DB {
 	}
 
 	pub fn connect(&self) -> Result<PooledConnection<ConnectionManager<SqliteConnection>>, Error> {
-		self.pool.get().or(Err(Error::ConnectionPool))
+		self.pool.get().or(Err(Error::PoolTimeout))
 	}
 
 	#[cfg(test)]
@@ -85,6 +107,22 @@ impl DB {
 	}
 }
 
+#[test]
+fn pool_timeout_fires_under_contention() {
+	use crate::test::*;
+	use crate::test_name;
+	let output_dir = prepare_test_directory(test_name!());
+	let db_path = output_dir.join("db.sqlite");
+	let pool_settings = PoolSettings {
+		max_size: 1,
+		acquire_timeout: Duration::from_millis(100),
+	};
+	let db = DB::new_with_pool_settings(&db_path, &pool_settings).unwrap();
+
+	let _held_connection = db.connect().unwrap();
+	assert!(matches!(db.connect(), Err(Error::PoolTimeout)));
+}
+
 #[test]
 fn run_migrations() {
 	use crate::test::*;
@@ -1,3 +1,24 @@
+table! {
+	admin_changes (id) {
+		id -> Integer,
+		username -> Text,
+		actor -> Nullable<Text>,
+		old_is_admin -> Integer,
+		new_is_admin -> Integer,
+		changed_at -> Integer,
+	}
+}
+
+table! {
+	audit_log (id) {
+		id -> Integer,
+		timestamp -> Integer,
+		event -> Text,
+		actor -> Nullable<Text>,
+		details -> Nullable<Text>,
+	}
+}
+
 table! {
 	ddns_config (id) {
 		id -> Integer,
@@ -17,6 +38,51 @@ table! {
 		album -> Nullable<Text>,
 		artwork -> Nullable<Text>,
 		date_added -> Integer,
+		is_compilation -> Bool,
+		track_count -> Integer,
+		search_normalized -> Text,
+	}
+}
+
+table! {
+	favorites (id) {
+		id -> Integer,
+		owner -> Integer,
+		path -> Text,
+	}
+}
+
+table! {
+	index_checkpoint (path) {
+		path -> Text,
+	}
+}
+
+table! {
+	index_metadata (id) {
+		id -> Integer,
+		last_index_start -> Integer,
+		last_index_end -> Integer,
+		file_count -> Integer,
+		error_count -> Integer,
+	}
+}
+
+table! {
+	lastfm_scrobbles (id) {
+		id -> Integer,
+		username -> Text,
+		path -> Text,
+		queued_at -> Integer,
+		attempts -> Integer,
+	}
+}
+
+table! {
+	mime_overrides (id) {
+		id -> Integer,
+		extension -> Text,
+		mime -> Text,
 	}
 }
 
@@ -26,6 +92,32 @@ table! {
 		auth_secret -> Binary,
 		index_sleep_duration_seconds -> Integer,
 		index_album_art_pattern -> Text,
+		max_playlists_per_user -> Integer,
+		max_songs_per_playlist -> Integer,
+		index_follow_symlinks -> Bool,
+		artwork_precedence -> Text,
+		minimum_client_version -> Text,
+		reject_unversioned_clients -> Bool,
+		max_concurrent_streams_per_user -> Integer,
+		index_infer_tags_from_path -> Bool,
+		setup_complete -> Bool,
+		reindex_on_startup -> Bool,
+		duplicate_policy -> Text,
+		tag_parsing -> Text,
+		send_security_headers -> Bool,
+		assume_https_behind_proxy -> Bool,
+		default_page_size -> Integer,
+		max_page_size -> Integer,
+		partial_file_policy -> Text,
+		trusted_proxies -> Text,
+		forwarded_ip_header -> Text,
+		auth_log_format -> Text,
+		old_auth_secrets -> Text,
+		num_traverser_threads -> Integer,
+		max_results_per_query -> Integer,
+		thumbnail_read_timeout_seconds -> Integer,
+		thumbnail_max_dimension -> Integer,
+		thumbnail_stored_max_dimension -> Integer,
 	}
 }
 
@@ -34,6 +126,27 @@ table! {
 		id -> Integer,
 		source -> Text,
 		name -> Text,
+		include_in_discovery -> Bool,
+		max_depth -> Integer,
+		display_name -> Text,
+	}
+}
+
+table! {
+	password_history (id) {
+		id -> Integer,
+		username -> Text,
+		password_hash -> Text,
+		changed_at -> Integer,
+	}
+}
+
+table! {
+	play_history (id) {
+		id -> Integer,
+		username -> Text,
+		path -> Text,
+		played_at -> Integer,
 	}
 }
 
@@ -54,6 +167,25 @@ table! {
 	}
 }
 
+table! {
+	song_chapters (id) {
+		id -> Integer,
+		path -> Text,
+		ordering -> Integer,
+		title -> Text,
+		start_ms -> Integer,
+	}
+}
+
+table! {
+	song_tags (id) {
+		id -> Integer,
+		path -> Text,
+		key -> Text,
+		value -> Text,
+	}
+}
+
 table! {
 	songs (id) {
 		id -> Integer,
@@ -72,6 +204,16 @@ table! {
 		composer -> Nullable<Text>,
 		genre -> Nullable<Text>,
 		label -> Nullable<Text>,
+		bpm -> Nullable<Integer>,
+		initial_key -> Nullable<Text>,
+		encoder_delay -> Nullable<Integer>,
+		encoder_padding -> Nullable<Integer>,
+		search_normalized -> Text,
+		file_size -> BigInt,
+		tags_inferred -> Bool,
+		track_total -> Nullable<Integer>,
+		disc_total -> Nullable<Integer>,
+		date_added -> Integer,
 	}
 }
 
@@ -88,16 +230,28 @@ table! {
 	}
 }
 
+joinable!(favorites -> users (owner));
 joinable!(playlist_songs -> playlists (playlist));
 joinable!(playlists -> users (owner));
 
 allow_tables_to_appear_in_same_query!(
+	admin_changes,
+	audit_log,
 	ddns_config,
 	directories,
+	favorites,
+	index_checkpoint,
+	index_metadata,
+	lastfm_scrobbles,
+	mime_overrides,
 	misc_settings,
 	mount_points,
+	password_history,
+	play_history,
 	playlist_songs,
 	playlists,
+	song_chapters,
+	song_tags,
 	songs,
 	users,
 );
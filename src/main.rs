@@ -44,6 +44,8 @@ pub enum Error {
 	#[cfg(unix)]
 	#[error("Could not notify systemd of initialization success:\n\n{0}")]
 	SystemDNotify(std::io::Error),
+	#[error(transparent)]
+	User(#[from] app::user::Error),
 }
 
 #[cfg(unix)]
@@ -73,6 +75,7 @@ fn notify_ready() -> Result<(), Error> {
 fn init_logging<T: AsRef<Path>>(
 	log_level: LevelFilter,
 	log_file_path: &Option<T>,
+	logs_manager: &app::logs::Manager,
 ) -> Result<(), Error> {
 	let log_config = simplelog::ConfigBuilder::new()
 		.set_location_level(LevelFilter::Error)
@@ -98,6 +101,8 @@ fn init_logging<T: AsRef<Path>>(
 		));
 	}
 
+	loggers.push(Box::new(logs_manager.clone()));
+
 	CombinedLogger::init(loggers).map_err(Error::LogInitialization)?;
 
 	Ok(())
@@ -122,7 +127,8 @@ fn main() -> Result<(), Error> {
 
 	// Logging
 	let log_level = cli_options.log_level.unwrap_or(LevelFilter::Info);
-	init_logging(log_level, &paths.log_file_path)?;
+	let logs_manager = app::logs::Manager::new();
+	init_logging(log_level, &paths.log_file_path, &logs_manager)?;
 
 	// Fork
 	#[cfg(unix)]
@@ -140,9 +146,20 @@ fn main() -> Result<(), Error> {
 	info!("Web client files location is {:#?}", paths.web_dir_path);
 
 	// Create and run app
-	let app = app::App::new(cli_options.port.unwrap_or(5050), paths)?;
+	let app = app::App::new(cli_options.port.unwrap_or(5050), paths)?.with_logs_manager(logs_manager);
+
+	// Admin password reset is a local maintenance action; do it and exit
+	// without starting the server
+	if let Some(username) = &cli_options.reset_admin_username {
+		let password = app.user_manager.reset_admin(username)?;
+		println!("New password for {}: {}", username, password);
+		return Ok(());
+	}
+
+	app.index.begin_startup_reindex();
 	app.index.begin_periodic_updates();
 	app.ddns_manager.begin_periodic_updates();
+	app.lastfm_manager.begin_queued_submissions();
 
 	// Start server
 	info!("Starting up server");
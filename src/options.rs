@@ -14,6 +14,7 @@ pub struct CLIOptions {
 	pub swagger_dir_path: Option<PathBuf>,
 	pub port: Option<u16>,
 	pub log_level: Option<LevelFilter>,
+	pub reset_admin_username: Option<String>,
 }
 
 pub struct Manager {
@@ -46,6 +47,7 @@ impl Manager {
 			swagger_dir_path: matches.opt_str("s").map(PathBuf::from),
 			port: matches.opt_str("p").and_then(|p| p.parse().ok()),
 			log_level: matches.opt_str("log-level").and_then(|l| l.parse().ok()),
+			reset_admin_username: matches.opt_str("reset-admin"),
 		})
 	}
 
@@ -83,6 +85,13 @@ fn get_options() -> getopts::Options {
 		"run polaris in the foreground instead of daemonizing",
 	);
 
+	options.optopt(
+		"",
+		"reset-admin",
+		"set a new random password for USERNAME, grant them admin rights and exit",
+		"USERNAME",
+	);
+
 	options.optflag("h", "help", "print this help menu");
 	options
 }
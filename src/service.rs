@@ -1,5 +1,11 @@
+mod auth_log;
+pub mod client_ip;
+pub mod client_version;
 mod dto;
 mod error;
+mod openapi;
+mod pretty_json;
+mod security_headers;
 
 #[cfg(test)]
 mod test;
@@ -1,13 +1,16 @@
 use actix_web::{
-	dev::Service,
+	dev::{Service, ServiceResponse},
+	http::StatusCode,
 	middleware::{Compress, Logger, NormalizePath},
 	rt::System,
 	web::{self, ServiceConfig},
-	App as ActixApp, HttpServer,
+	App as ActixApp, HttpResponse, HttpServer,
 };
+use futures_util::future::{ready, Either, FutureExt};
 use log::error;
 
-use crate::app::App;
+use crate::app::{settings, App};
+use crate::service::{auth_log, client_ip, client_version, pretty_json, security_headers};
 
 mod api;
 
@@ -17,18 +20,65 @@ pub mod test;
 pub fn make_config(app: App) -> impl FnOnce(&mut ServiceConfig) + Clone {
 	move |cfg: &mut ServiceConfig| {
 		cfg.app_data(web::Data::new(app.index))
+			.app_data(web::Data::new(app.audit_manager))
 			.app_data(web::Data::new(app.config_manager))
 			.app_data(web::Data::new(app.ddns_manager))
+			.app_data(web::Data::new(app.favorite_manager))
+			.app_data(web::Data::new(app.files_manager))
+			.app_data(web::Data::new(app.history_manager))
 			.app_data(web::Data::new(app.lastfm_manager))
+			.app_data(web::Data::new(app.logs_manager))
+			.app_data(web::Data::new(app.now_playing_manager))
 			.app_data(web::Data::new(app.playlist_manager))
 			.app_data(web::Data::new(app.settings_manager))
+			.app_data(web::Data::new(app.streams_manager))
 			.app_data(web::Data::new(app.thumbnail_manager))
 			.app_data(web::Data::new(app.user_manager))
 			.app_data(web::Data::new(app.vfs_manager))
+			.app_data(web::Data::new(app.waveform_manager))
 			.service(
 				web::scope("/api")
 					.configure(api::make_config())
-					.wrap(NormalizePath::trim()),
+					.wrap(NormalizePath::trim())
+					.wrap_fn(|req, srv| {
+						// Reject clients older than the configured minimum version. The
+						// cached read keeps this hot path off the database
+						let rejection = req
+							.app_data::<web::Data<settings::Manager>>()
+							.and_then(|manager| manager.read_cached().ok())
+							.and_then(|settings| {
+								let declared_version = req
+									.headers()
+									.get(client_version::VERSION_HEADER)
+									.and_then(|value| value.to_str().ok());
+								client_version::check(declared_version, &settings).err()
+							});
+						match rejection {
+							None => Either::Left(
+								srv.call(req)
+									.map(|response| response.map(ServiceResponse::map_into_boxed_body)),
+							),
+							Some(message) => {
+								let response =
+									HttpResponse::build(StatusCode::UPGRADE_REQUIRED).body(message);
+								Either::Right(ready(Ok(req
+									.into_response(response)
+									.map_into_boxed_body())))
+							}
+						}
+					})
+					.wrap_fn(|req, srv| {
+						// Opt-in pretty-printing of JSON responses for development
+						let pretty = pretty_json::requested(req.query_string());
+						let response_future = srv.call(req);
+						async move {
+							let response = response_future.await?;
+							if !pretty {
+								return Ok(response);
+							}
+							pretty_json::reformat(response).await
+						}
+					}),
 			)
 			.service(
 				actix_files::Files::new("/swagger", app.swagger_dir_path)
@@ -45,19 +95,49 @@ pub fn make_config(app: App) -> impl FnOnce(&mut ServiceConfig) + Clone {
 
 pub fn run(app: App) -> Result<(), std::io::Error> {
 	let address = ("0.0.0.0", app.port);
+	// Proxy trust and the auth log format are read once here; changing these
+	// settings requires a restart
+	let (proxy_settings, auth_log_format) = match app.settings_manager.read() {
+		Ok(settings) => (
+			client_ip::ProxySettings::from_settings(&settings),
+			auth_log::Format::from_setting_string(&settings.auth_log_format),
+		),
+		Err(_) => Default::default(),
+	};
 	System::new().block_on(
 		HttpServer::new(move || {
+			let error_log_proxy_settings = proxy_settings.clone();
+			let headers_proxy_settings = proxy_settings.clone();
 			ActixApp::new()
+				.app_data(web::Data::new(proxy_settings.clone()))
+				.app_data(web::Data::new(auth_log_format))
 				.wrap(Logger::default())
-				.wrap_fn(|req, srv| {
+				.wrap_fn(move |req, srv| {
 					// For some reason, actix logs error as DEBUG level.
 					// This logs them as ERROR level
 					// See https://github.com/actix/actix-web/issues/2637
+					let client_ip = client_ip::resolve(req.request(), &error_log_proxy_settings);
 					let response_future = srv.call(req);
-					async {
+					async move {
 						let response = response_future.await?;
 						if let Some(error) = response.response().error() {
-							error!("{}", error);
+							match client_ip {
+								Some(ip) => error!("[{}] {}", ip, error),
+								None => error!("{}", error),
+							}
+						}
+						Ok(response)
+					}
+				})
+				.wrap_fn(move |req, srv| {
+					// Attach security headers when enabled in settings
+					let headers =
+						security_headers::for_request(req.request(), &headers_proxy_settings);
+					let response_future = srv.call(req);
+					async move {
+						let mut response = response_future.await?;
+						for (name, value) in headers {
+							response.headers_mut().insert(name, value);
 						}
 						Ok(response)
 					}
@@ -1,42 +1,56 @@
 use actix_files::NamedFile;
-use actix_web::body::BoxBody;
-use actix_web::http::header::ContentEncoding;
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::http::header::{ContentEncoding, HeaderValue, CONTENT_TYPE};
 use actix_web::{
 	delete,
 	dev::Payload,
 	error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
 	get,
 	http::StatusCode,
-	post, put,
+	patch, post, put,
 	web::{self, Data, Json, JsonConfig, ServiceConfig},
-	FromRequest, HttpRequest, HttpResponse, Responder, ResponseError,
+	CustomizeResponder, FromRequest, HttpRequest, HttpResponse, Responder, ResponseError,
 };
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use base64::prelude::*;
 use futures_util::future::err;
-use percent_encoding::percent_decode_str;
+use futures_util::StreamExt;
+use percent_encoding::{percent_decode_str, percent_encode, NON_ALPHANUMERIC};
+use std::collections::HashMap;
 use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::str;
+use std::task::{Context, Poll};
 
 use crate::app::{
-	config, ddns,
+	audit, config, ddns, favorite, files, history,
 	index::{self, Index},
-	lastfm, playlist, settings, thumbnail, user,
+	lastfm, logs, now_playing, playlist, settings, streams, thumbnail, user,
 	vfs::{self, MountDir},
+	waveform,
 };
-use crate::service::{dto, error::*};
+use crate::service::{auth_log, client_ip, dto, error::*, openapi};
+use crate::utils;
 
 pub fn make_config() -> impl FnOnce(&mut ServiceConfig) + Clone {
 	move |cfg: &mut ServiceConfig| {
 		let megabyte = 1024 * 1024;
 		cfg.app_data(JsonConfig::default().limit(4 * megabyte)) // 4MB
 			.service(version)
+			.service(capabilities)
+			.service(get_openapi)
 			.service(initial_setup)
+			.service(initial_setup_admin)
 			.service(apply_config)
-			.service(get_settings)
-			.service(put_settings)
+			.service(
+				// Settings bodies are small, so reject anything oversized with a 413
+				web::resource("/settings")
+					.app_data(JsonConfig::default().limit(megabyte)) // 1MB
+					.route(web::get().to(get_settings))
+					.route(web::put().to(put_settings)),
+			)
+			.service(test_mount)
 			.service(list_mount_dirs)
 			.service(put_mount_dirs)
 			.service(get_ddns_config)
@@ -44,25 +58,74 @@ pub fn make_config() -> impl FnOnce(&mut ServiceConfig) + Clone {
 			.service(list_users)
 			.service(create_user)
 			.service(update_user)
+			.service(update_users_admin)
+			.service(get_admin_history)
 			.service(delete_user)
 			.service(get_preferences)
 			.service(put_preferences)
 			.service(trigger_index)
+			.service(prune_index)
+			.service(index_status)
+			.service(index_dry_run)
+			.service(get_audit_log)
+			.service(missing_artwork)
+			.service(missing_files)
+			.service(list_duplicates)
+			.service(move_file)
 			.service(login)
+			.service(create_scoped_token)
 			.service(browse_root)
+			.service(get_directory_summary)
+			.service(browse_m3u)
 			.service(browse)
+			.service(tree_root)
+			.service(tree)
 			.service(flatten_root)
 			.service(flatten)
+			.service(resolve_songs)
 			.service(random)
+			.service(adjacent_albums)
 			.service(recent)
+			.service(recent_songs)
+			.service(compilations)
+			.service(artist_detail)
+			.service(album_artists)
 			.service(search_root)
 			.service(search)
 			.service(get_audio)
+			.service(stream_album)
 			.service(get_thumbnail)
+			.service(get_thumbnails_batch)
+			.service(verify_thumbnail_cache)
+			.service(get_artwork_original)
+			.service(
+				// Cover art files routinely exceed the default payload cap
+				web::resource("/artwork/{path:.*}")
+					.app_data(web::PayloadConfig::new(8 * megabyte)) // 8MB
+					.route(web::put().to(put_artwork)),
+			)
+			.service(reindex_song)
+			.service(update_song_tags)
+			.service(get_waveform)
+			.service(list_favorites)
+			.service(get_favorite_status)
+			.service(add_favorite)
+			.service(remove_favorite)
+			.service(get_custom_tags)
+			.service(put_custom_tag)
+			.service(delete_custom_tag)
+			.service(get_song_chapters)
+			.service(get_song_by_id)
+			.service(get_directory_by_id)
 			.service(list_playlists)
 			.service(save_playlist)
 			.service(read_playlist)
 			.service(delete_playlist)
+			.service(post_now_playing)
+			.service(events_now_playing)
+			.service(stream_logs)
+			.service(post_played)
+			.service(get_recently_played)
 			.service(lastfm_now_playing)
 			.service(lastfm_scrobble)
 			.service(lastfm_link_token)
@@ -77,19 +140,29 @@ impl ResponseError for APIError {
 			APIError::AuthorizationTokenEncoding => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::AdminPermissionRequired => StatusCode::UNAUTHORIZED,
 			APIError::AudioFileIOError => StatusCode::NOT_FOUND,
+			APIError::ArtistNotFound => StatusCode::NOT_FOUND,
 			APIError::AuthenticationRequired => StatusCode::UNAUTHORIZED,
+			APIError::AuthorizationScopeTooNarrow => StatusCode::FORBIDDEN,
+			APIError::BatchSizeExceeded(_) => StatusCode::BAD_REQUEST,
 			APIError::BrancaTokenEncoding => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::DdnsUpdateQueryFailed(s) => {
 				StatusCode::from_u16(*s).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
 			}
 			APIError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::DeletingOwnAccount => StatusCode::CONFLICT,
+			APIError::DirectoryNotFound => StatusCode::NOT_FOUND,
 			APIError::EmbeddedArtworkNotFound => StatusCode::NOT_FOUND,
 			APIError::EmptyPassword => StatusCode::BAD_REQUEST,
 			APIError::EmptyUsername => StatusCode::BAD_REQUEST,
+			APIError::FileMoveCrossMount => StatusCode::BAD_REQUEST,
+			APIError::FileMoveDestinationExists(_) => StatusCode::CONFLICT,
+			APIError::FileMoveInvalidPath(_) => StatusCode::BAD_REQUEST,
+			APIError::FileMoveSourceNotFound(_) => StatusCode::NOT_FOUND,
 			APIError::IncorrectCredentials => StatusCode::UNAUTHORIZED,
+			APIError::InitialSetupAlreadyCompleted => StatusCode::CONFLICT,
 			APIError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::Io(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
+			APIError::LastAdminDemotion => StatusCode::CONFLICT,
 			APIError::LastFMAccountNotLinked => StatusCode::NO_CONTENT,
 			APIError::LastFMLinkContentBase64DecodeError => StatusCode::BAD_REQUEST,
 			APIError::LastFMLinkContentEncodingError => StatusCode::BAD_REQUEST,
@@ -98,18 +171,29 @@ impl ResponseError for APIError {
 			APIError::LastFMScrobblerAuthentication(_) => StatusCode::FAILED_DEPENDENCY,
 			APIError::OwnAdminPrivilegeRemoval => StatusCode::CONFLICT,
 			APIError::PasswordHashing => StatusCode::INTERNAL_SERVER_ERROR,
+			APIError::PasswordRecentlyUsed => StatusCode::CONFLICT,
+			APIError::PlaintextCredentialsRejected => StatusCode::FORBIDDEN,
 			APIError::PlaylistNotFound => StatusCode::NOT_FOUND,
+			APIError::PlaylistQuotaExceeded => StatusCode::FORBIDDEN,
+			APIError::PoolTimeout => StatusCode::SERVICE_UNAVAILABLE,
 			APIError::Settings(_) => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::SongMetadataNotFound => StatusCode::NOT_FOUND,
 			APIError::ThumbnailFlacDecoding(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::ThumbnailFileIOError => StatusCode::NOT_FOUND,
 			APIError::ThumbnailId3Decoding(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
 			APIError::ThumbnailImageDecoding(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
+			APIError::TagUpdate(_) => StatusCode::INTERNAL_SERVER_ERROR,
+			APIError::TooManyConcurrentStreams => StatusCode::TOO_MANY_REQUESTS,
 			APIError::ThumbnailMp4Decoding(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
+			APIError::ThumbnailTimeout(_) => StatusCode::SERVICE_UNAVAILABLE,
 			APIError::TomlDeserialization(_) => StatusCode::INTERNAL_SERVER_ERROR,
+			APIError::UnsupportedTagFormat(_) => StatusCode::BAD_REQUEST,
 			APIError::UnsupportedThumbnailFormat(_) => StatusCode::INTERNAL_SERVER_ERROR,
+			APIError::UnsupportedWaveformFormat(_) => StatusCode::BAD_REQUEST,
 			APIError::UserNotFound => StatusCode::NOT_FOUND,
 			APIError::VFSPathNotFound => StatusCode::NOT_FOUND,
+			APIError::WaveformDecoding(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
+			APIError::WaveformSerialization(_) => StatusCode::INTERNAL_SERVER_ERROR,
 		}
 	}
 
@@ -123,6 +207,27 @@ struct Auth {
 	username: String,
 }
 
+// Scopes accepted as general API authentication. `LastFMLink` tokens are
+// single-purpose and deliberately absent.
+const API_AUTH_SCOPES: &[user::AuthorizationScope] = &[
+	user::AuthorizationScope::PolarisAuth,
+	user::AuthorizationScope::ReadOnly,
+	user::AuthorizationScope::StreamOnly,
+];
+
+// Which routes a token with a restricted scope may call. `PolarisAuth` tokens
+// are not restricted.
+fn scope_permits(scope: user::AuthorizationScope, method: &actix_web::http::Method, path: &str) -> bool {
+	match scope {
+		user::AuthorizationScope::PolarisAuth => true,
+		user::AuthorizationScope::LastFMLink => false,
+		user::AuthorizationScope::ReadOnly => method == actix_web::http::Method::GET,
+		user::AuthorizationScope::StreamOnly => {
+			method == actix_web::http::Method::GET && path.starts_with("/api/audio/")
+		}
+	}
+}
+
 impl FromRequest for Auth {
 	type Error = actix_web::Error;
 	type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
@@ -137,39 +242,63 @@ impl FromRequest for Auth {
 		let query_params_future =
 			web::Query::<dto::AuthQueryParameters>::from_request(request, payload);
 
+		let method = request.method().clone();
+		let path = request.path().to_owned();
+		let proxy_settings = request
+			.app_data::<Data<client_ip::ProxySettings>>()
+			.map(|settings| settings.get_ref().clone())
+			.unwrap_or_default();
+		let client_ip = client_ip::resolve(request, &proxy_settings);
+		let auth_log_format = request
+			.app_data::<Data<auth_log::Format>>()
+			.map(|format| *format.get_ref())
+			.unwrap_or_default();
+
 		Box::pin(async move {
-			// Auth via bearer token in query parameter
-			if let Ok(query) = query_params_future.await {
-				let auth_token = user::AuthToken(query.auth_token.clone());
-				let authorization = block(move || {
-					user_manager.authenticate(&auth_token, user::AuthorizationScope::PolarisAuth)
-				})
-				.await?;
-				return Ok(Auth {
-					username: authorization.username,
-				});
-			}
+			let auth_token = {
+				// Auth via bearer token in query parameter
+				if let Ok(query) = query_params_future.await {
+					user::AuthToken(query.auth_token.clone())
+				// Auth via bearer token in authorization header
+				} else if let Ok(bearer_auth) = bearer_auth_future.await {
+					user::AuthToken(bearer_auth.token().to_owned())
+				} else {
+					return Err(ErrorUnauthorized(APIError::AuthenticationRequired));
+				}
+			};
 
-			// Auth via bearer token in authorization header
-			if let Ok(bearer_auth) = bearer_auth_future.await {
-				let auth_token = user::AuthToken(bearer_auth.token().to_owned());
-				let authorization = block(move || {
-					user_manager.authenticate(&auth_token, user::AuthorizationScope::PolarisAuth)
-				})
-				.await?;
-				return Ok(Auth {
-					username: authorization.username,
-				});
+			let authorization =
+				match block(move || user_manager.authenticate_any(&auth_token, API_AUTH_SCOPES))
+					.await
+				{
+					Ok(authorization) => authorization,
+					Err(e) => {
+						auth_log::record(
+							&auth_log::Event {
+								event: "token_rejected",
+								outcome: auth_log::Outcome::Failure,
+								username: None,
+								scope: None,
+								client_ip,
+							},
+							auth_log_format,
+						);
+						return Err(e.into());
+					}
+				};
+			if !scope_permits(authorization.scope, &method, &path) {
+				return Err(ErrorForbidden(APIError::AuthorizationScopeTooNarrow));
 			}
-
-			Err(ErrorUnauthorized(APIError::AuthenticationRequired))
+			Ok(Auth {
+				username: authorization.username,
+			})
 		})
 	}
 }
 
 #[derive(Debug)]
 struct AdminRights {
-	auth: Option<Auth>,
+	auth: Auth,
 }
 
 impl FromRequest for AdminRights {
@@ -183,22 +312,33 @@ impl FromRequest for AdminRights {
 		};
 
 		let auth_future = Auth::from_request(request, payload);
+		let proxy_settings = request
+			.app_data::<Data<client_ip::ProxySettings>>()
+			.map(|settings| settings.get_ref().clone())
+			.unwrap_or_default();
+		let client_ip = client_ip::resolve(request, &proxy_settings);
+		let auth_log_format = request
+			.app_data::<Data<auth_log::Format>>()
+			.map(|format| *format.get_ref())
+			.unwrap_or_default();
 
 		Box::pin(async move {
-			let user_manager_count = user_manager.clone();
-			let user_count = block(move || user_manager_count.count()).await;
-			match user_count {
-				Err(e) => return Err(e.into()),
-				Ok(0) => return Ok(AdminRights { auth: None }),
-				_ => (),
-			};
-
 			let auth = auth_future.await?;
 			let username = auth.username.clone();
 			let is_admin = block(move || user_manager.is_admin(&username)).await?;
 			if is_admin {
-				Ok(AdminRights { auth: Some(auth) })
+				Ok(AdminRights { auth })
 			} else {
+				auth_log::record(
+					&auth_log::Event {
+						event: "admin_denied",
+						outcome: auth_log::Outcome::Denied,
+						username: Some(&auth.username),
+						scope: None,
+						client_ip,
+					},
+					auth_log_format,
+				);
 				Err(ErrorForbidden(APIError::AdminPermissionRequired))
 			}
 		})
@@ -228,6 +368,28 @@ impl Responder for MediaFile {
 	}
 }
 
+// Response body that occupies a stream slot until it has been sent in full or
+// the client disconnected
+struct LeasedBody {
+	body: BoxBody,
+	_lease: streams::Lease,
+}
+
+impl MessageBody for LeasedBody {
+	type Error = Box<dyn std::error::Error>;
+
+	fn size(&self) -> BodySize {
+		self.body.size()
+	}
+
+	fn poll_next(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<web::Bytes, Self::Error>>> {
+		Pin::new(&mut self.get_mut().body).poll_next(cx)
+	}
+}
+
 async fn block<F, I, E>(f: F) -> Result<I, APIError>
 where
 	F: FnOnce() -> Result<I, E> + Send + 'static,
@@ -240,6 +402,74 @@ where
 		.and_then(|r| r.map_err(|e| e.into()))
 }
 
+// Responses capped by the global rows-per-response limit keep their plain array
+// body; truncation is advertised in a header instead
+fn truncatable_response<T: serde::Serialize>(
+	results: index::Truncated<T>,
+	mode: dto::EmptyFieldMode,
+) -> HttpResponse {
+	let mut response = HttpResponse::Ok();
+	if results.truncated {
+		response.insert_header(("Polaris-Results-Truncated", "true"));
+	}
+	match serialize_with_empty_field_mode(&results.items, mode) {
+		Some(value) => response.json(value),
+		None => response.json(results.items),
+	}
+}
+
+fn empty_field_mode(request: &HttpRequest) -> dto::EmptyFieldMode {
+	request
+		.headers()
+		.get("Accept-Profile")
+		.and_then(|v| v.to_str().ok())
+		.map(dto::EmptyFieldMode::from_accept_profile)
+		.unwrap_or_default()
+}
+
+// Plain JSON body honoring the requested empty-field handling
+fn collection_response<T: serde::Serialize>(items: T, mode: dto::EmptyFieldMode) -> HttpResponse {
+	match serialize_with_empty_field_mode(&items, mode) {
+		Some(value) => HttpResponse::Ok().json(value),
+		None => HttpResponse::Ok().json(items),
+	}
+}
+
+// Returns None when the default serialization can be used as-is
+fn serialize_with_empty_field_mode<T: serde::Serialize>(
+	items: &T,
+	mode: dto::EmptyFieldMode,
+) -> Option<serde_json::Value> {
+	if mode == dto::EmptyFieldMode::Null {
+		return None;
+	}
+	let mut value = serde_json::to_value(items).ok()?;
+	adapt_empty_fields(&mut value, mode);
+	Some(value)
+}
+
+fn adapt_empty_fields(value: &mut serde_json::Value, mode: dto::EmptyFieldMode) {
+	match value {
+		serde_json::Value::Array(values) => {
+			for value in values {
+				adapt_empty_fields(value, mode);
+			}
+		}
+		serde_json::Value::Object(map) => {
+			if mode == dto::EmptyFieldMode::Omit {
+				map.retain(|_, v| !v.is_null());
+			}
+			for value in map.values_mut() {
+				if mode == dto::EmptyFieldMode::EmptyString && value.is_null() {
+					*value = serde_json::Value::String(String::new());
+				}
+				adapt_empty_fields(value, mode);
+			}
+		}
+		_ => (),
+	}
+}
+
 #[get("/version")]
 async fn version() -> Json<dto::Version> {
 	let current_version = dto::Version {
@@ -249,6 +479,27 @@ async fn version() -> Json<dto::Version> {
 	Json(current_version)
 }
 
+#[get("/capabilities")]
+async fn capabilities(
+	settings_manager: Data<settings::Manager>,
+) -> Result<Json<dto::Capabilities>, APIError> {
+	let settings = block(move || settings_manager.read()).await?;
+	Ok(Json(dto::Capabilities {
+		// This server streams files as-is and has no transcoding pipeline
+		transcoding: false,
+		hls: false,
+		search: true,
+		playlists: settings.max_playlists_per_user > 0,
+		lastfm: true,
+		thumbnails: true,
+	}))
+}
+
+#[get("/openapi.json")]
+async fn get_openapi() -> Json<serde_json::Value> {
+	Json(openapi::specification())
+}
+
 #[get("/initial_setup")]
 async fn initial_setup(
 	user_manager: Data<user::Manager>,
@@ -264,6 +515,34 @@ async fn initial_setup(
 	Ok(Json(initial_setup))
 }
 
+#[post("/initial_setup/admin")]
+async fn initial_setup_admin(
+	user_manager: Data<user::Manager>,
+	settings_manager: Data<settings::Manager>,
+	new_user: Json<dto::NewUser>,
+) -> Result<HttpResponse, APIError> {
+	block(move || -> Result<(), APIError> {
+		// Once setup has completed, deleting every user must not re-open this
+		// bootstrap path; recovery goes through the CLI instead
+		if settings_manager.is_setup_complete().map_err(APIError::from)? {
+			return Err(APIError::InitialSetupAlreadyCompleted);
+		}
+		if user_manager.count().map_err(APIError::from)? > 0 {
+			return Err(APIError::InitialSetupAlreadyCompleted);
+		}
+		let new_user = user::NewUser {
+			admin: true,
+			..new_user.to_owned().into()
+		};
+		user_manager.create(&new_user).map_err(APIError::from)?;
+		settings_manager
+			.mark_setup_complete()
+			.map_err(APIError::from)
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
 #[put("/config")]
 async fn apply_config(
 	_admin_rights: AdminRights,
@@ -274,25 +553,76 @@ async fn apply_config(
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
-#[get("/settings")]
 async fn get_settings(
 	settings_manager: Data<settings::Manager>,
 	_admin_rights: AdminRights,
 ) -> Result<Json<dto::Settings>, APIError> {
-	let settings = block(move || settings_manager.read()).await?;
-	Ok(Json(settings.into()))
+	let settings = block(move || -> Result<_, APIError> {
+		let mut settings: dto::Settings = settings_manager.read()?.into();
+		settings.mime_overrides = settings_manager
+			.get_mime_overrides()?
+			.into_iter()
+			.map(|m| m.into())
+			.collect();
+		Ok(settings)
+	})
+	.await?;
+	Ok(Json(settings))
 }
 
-#[put("/settings")]
 async fn put_settings(
-	_admin_rights: AdminRights,
+	admin_rights: AdminRights,
 	settings_manager: Data<settings::Manager>,
+	audit_manager: Data<audit::Manager>,
 	new_settings: Json<dto::NewSettings>,
 ) -> Result<HttpResponse, APIError> {
-	block(move || settings_manager.amend(&new_settings.to_owned().into())).await?;
+	block(move || -> Result<(), APIError> {
+		settings_manager.amend(&new_settings.to_owned().into())?;
+		audit_manager
+			.record(
+				audit::Event::SettingsChanged,
+				Some(&admin_rights.auth.username),
+				None,
+			)
+			.ok();
+		Ok(())
+	})
+	.await?;
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
+#[post("/settings/test_mount")]
+async fn test_mount(
+	_admin_rights: AdminRights,
+	input: Json<dto::TestMountInput>,
+) -> Result<Json<dto::TestMountOutput>, APIError> {
+	const SCAN_CAP: usize = 100; // Entries to look at during the shallow scan
+
+	let result = block(move || -> Result<dto::TestMountOutput, APIError> {
+		let path = PathBuf::from(&input.path);
+		let mut output = dto::TestMountOutput {
+			exists: path.exists(),
+			is_directory: path.is_dir(),
+			..Default::default()
+		};
+		if output.is_directory {
+			if let Ok(entries) = std::fs::read_dir(&path) {
+				output.is_readable = true;
+				output.audio_files_found = entries
+					.flatten()
+					.take(SCAN_CAP)
+					.filter(|entry| utils::get_audio_format(&entry.path()).is_some())
+					.count();
+			}
+		} else if output.exists {
+			output.is_readable = std::fs::File::open(&path).is_ok();
+		}
+		Ok(output)
+	})
+	.await?;
+	Ok(Json(result))
+}
+
 #[get("/mount_dirs")]
 async fn list_mount_dirs(
 	vfs_manager: Data<vfs::Manager>,
@@ -346,11 +676,23 @@ async fn list_users(
 #[post("/user")]
 async fn create_user(
 	user_manager: Data<user::Manager>,
-	_admin_rights: AdminRights,
+	audit_manager: Data<audit::Manager>,
+	admin_rights: AdminRights,
 	new_user: Json<dto::NewUser>,
 ) -> Result<HttpResponse, APIError> {
-	let new_user = new_user.to_owned().into();
-	block(move || user_manager.create(&new_user)).await?;
+	let new_user: user::NewUser = new_user.to_owned().into();
+	block(move || -> Result<(), APIError> {
+		user_manager.create(&new_user)?;
+		audit_manager
+			.record(
+				audit::Event::UserCreated,
+				Some(&admin_rights.auth.username),
+				Some(&new_user.name),
+			)
+			.ok();
+		Ok(())
+	})
+	.await?;
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
@@ -361,10 +703,8 @@ async fn update_user(
 	name: web::Path<String>,
 	user_update: Json<dto::UserUpdate>,
 ) -> Result<HttpResponse, APIError> {
-	if let Some(auth) = &admin_rights.auth {
-		if auth.username == name.as_str() && user_update.new_is_admin == Some(false) {
-			return Err(APIError::OwnAdminPrivilegeRemoval);
-		}
+	if admin_rights.auth.username == name.as_str() && user_update.new_is_admin == Some(false) {
+		return Err(APIError::OwnAdminPrivilegeRemoval);
 	}
 
 	block(move || -> Result<(), APIError> {
@@ -372,7 +712,7 @@ async fn update_user(
 			user_manager.set_password(&name, password)?;
 		}
 		if let Some(is_admin) = &user_update.new_is_admin {
-			user_manager.set_is_admin(&name, *is_admin)?;
+			user_manager.set_is_admin(&name, *is_admin, Some(&admin_rights.auth.username))?;
 		}
 		Ok(())
 	})
@@ -380,18 +720,71 @@ async fn update_user(
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
+#[put("/users/admin")]
+async fn update_users_admin(
+	user_manager: Data<user::Manager>,
+	admin_rights: AdminRights,
+	input: Json<dto::AdminUpdatesInput>,
+) -> Result<Json<Vec<dto::AdminUpdateResult>>, APIError> {
+	let demotes_self = input
+		.updates
+		.iter()
+		.any(|u| u.username == admin_rights.auth.username && !u.is_admin);
+	if demotes_self {
+		return Err(APIError::OwnAdminPrivilegeRemoval);
+	}
+
+	let updates: Vec<(String, bool)> = input
+		.updates
+		.iter()
+		.map(|u| (u.username.clone(), u.is_admin))
+		.collect();
+	let results = block(move || {
+		user_manager.set_admin_many(&updates, Some(&admin_rights.auth.username))
+	})
+	.await?;
+	let results = results
+		.into_iter()
+		.map(|(username, outcome)| dto::AdminUpdateResult {
+			username,
+			outcome: outcome.into(),
+		})
+		.collect();
+	Ok(Json(results))
+}
+
+#[get("/user/{name}/admin_history")]
+async fn get_admin_history(
+	user_manager: Data<user::Manager>,
+	_admin_rights: AdminRights,
+	name: web::Path<String>,
+) -> Result<Json<Vec<dto::AdminChange>>, APIError> {
+	let changes = block(move || user_manager.get_admin_history(&name)).await?;
+	Ok(Json(changes.into_iter().map(|c| c.into()).collect()))
+}
+
 #[delete("/user/{name}")]
 async fn delete_user(
 	user_manager: Data<user::Manager>,
+	audit_manager: Data<audit::Manager>,
 	admin_rights: AdminRights,
 	name: web::Path<String>,
 ) -> Result<HttpResponse, APIError> {
-	if let Some(auth) = &admin_rights.auth {
-		if auth.username == name.as_str() {
-			return Err(APIError::DeletingOwnAccount);
-		}
+	if admin_rights.auth.username == name.as_str() {
+		return Err(APIError::DeletingOwnAccount);
 	}
-	block(move || user_manager.delete(&name)).await?;
+	block(move || -> Result<(), APIError> {
+		user_manager.delete(&name)?;
+		audit_manager
+			.record(
+				audit::Event::UserDeleted,
+				Some(&admin_rights.auth.username),
+				Some(&name),
+			)
+			.ok();
+		Ok(())
+	})
+	.await?;
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
@@ -418,20 +811,106 @@ async fn put_preferences(
 async fn trigger_index(
 	index: Data<Index>,
 	_admin_rights: AdminRights,
-) -> Result<HttpResponse, APIError> {
-	index.trigger_reindex();
-	Ok(HttpResponse::new(StatusCode::OK))
+	options: web::Query<dto::TriggerIndexOptions>,
+) -> Result<Json<dto::TriggerIndexOutput>, APIError> {
+	let status = match options.full {
+		true => index.trigger_full_reindex(options.force),
+		false => index.trigger_reindex(options.force),
+	};
+	Ok(Json(dto::TriggerIndexOutput {
+		status: status.into(),
+	}))
+}
+
+#[post("/index/prune")]
+async fn prune_index(
+	index: Data<Index>,
+	_admin_rights: AdminRights,
+) -> Result<Json<dto::PruneResult>, APIError> {
+	let removed = block(move || index.prune_orphans()).await?;
+	Ok(Json(dto::PruneResult { removed }))
+}
+
+#[get("/index/status")]
+async fn index_status(
+	index: Data<Index>,
+	_admin_rights: AdminRights,
+) -> Result<Json<Option<index::IndexStatus>>, APIError> {
+	let status = block(move || index.get_index_status()).await?;
+	Ok(Json(status))
+}
+
+#[post("/index/dry_run")]
+async fn index_dry_run(
+	index: Data<Index>,
+	_admin_rights: AdminRights,
+	input: Json<dto::DryRunInput>,
+) -> Result<Json<Vec<index::DryRunEntry>>, APIError> {
+	let entries = block(move || index.dry_run(Path::new(&input.path))).await?;
+	Ok(Json(entries))
 }
 
 #[post("/auth")]
 async fn login(
+	request: HttpRequest,
 	user_manager: Data<user::Manager>,
+	audit_manager: Data<audit::Manager>,
+	settings_manager: Data<settings::Manager>,
+	proxy_settings: Data<client_ip::ProxySettings>,
+	auth_log_format: Data<auth_log::Format>,
 	credentials: Json<dto::Credentials>,
 ) -> Result<HttpResponse, APIError> {
 	let username = credentials.username.clone();
+	let client_ip = client_ip::resolve(&request, &proxy_settings);
+	let https_forwarded = client_ip::https_forwarded(&request, &proxy_settings);
+	let auth_log_format = *auth_log_format.get_ref();
 	let (user::AuthToken(token), is_admin) =
 		block(move || -> Result<(user::AuthToken, bool), APIError> {
-			let auth_token = user_manager.login(&credentials.username, &credentials.password)?;
+			// When TLS termination is assumed, credentials that detectably did
+			// not travel over HTTPS are refused before being checked
+			let assume_https = settings_manager
+				.read()
+				.map(|settings| settings.assume_https_behind_proxy)
+				.unwrap_or(false);
+			if assume_https && !https_forwarded {
+				auth_log::record(
+					&auth_log::Event {
+						event: "plaintext_login_rejected",
+						outcome: auth_log::Outcome::Denied,
+						username: Some(&credentials.username),
+						scope: Some("polaris_auth"),
+						client_ip,
+					},
+					auth_log_format,
+				);
+				return Err(APIError::PlaintextCredentialsRejected);
+			}
+
+			let login_result = user_manager.login(&credentials.username, &credentials.password);
+			let event = match login_result {
+				Ok(_) => audit::Event::Login,
+				Err(_) => audit::Event::LoginFailed,
+			};
+			audit_manager
+				.record(event, Some(&credentials.username), None)
+				.ok();
+			auth_log::record(
+				&auth_log::Event {
+					event: match login_result {
+						Ok(_) => "login",
+						Err(_) => "login_failed",
+					},
+					outcome: match login_result {
+						Ok(_) => auth_log::Outcome::Success,
+						Err(_) => auth_log::Outcome::Failure,
+					},
+					username: Some(&credentials.username),
+					scope: Some("polaris_auth"),
+					client_ip,
+				},
+				auth_log_format,
+			);
+			let auth_token = login_result?;
 			let is_admin = user_manager.is_admin(&credentials.username)?;
 			Ok((auth_token, is_admin))
 		})
@@ -447,93 +926,540 @@ async fn login(
 
 #[get("/browse")]
 async fn browse_root(
+	request: HttpRequest,
 	index: Data<Index>,
+	vfs_manager: Data<vfs::Manager>,
 	_auth: Auth,
-) -> Result<Json<Vec<index::CollectionFile>>, APIError> {
-	let result = block(move || index.browse(Path::new(""))).await?;
-	Ok(Json(result))
+	options: web::Query<dto::BrowseOptions>,
+) -> Result<HttpResponse, APIError> {
+	let grouping = options.grouping.map(Into::into).unwrap_or_default();
+	let (result, mounts) = block(move || -> Result<_, APIError> {
+		let files = index.browse(Path::new(""), grouping)?;
+		let mounts = vfs_manager.get_vfs()?.mounts().clone();
+		Ok((files, mounts))
+	})
+	.await?;
+
+	// Top-level entries are mount roots; annotate them with their human-facing
+	// label. Virtual paths keep using the stable mount name.
+	let mut value = serde_json::to_value(&result).map_err(|_| APIError::Internal)?;
+	if let serde_json::Value::Array(entries) = &mut value {
+		for entry in entries {
+			let Some(directory) = entry.get_mut("Directory") else {
+				continue;
+			};
+			let mount = directory
+				.get("path")
+				.and_then(|p| p.as_str())
+				.and_then(|path| mounts.iter().find(|m| m.name == path));
+			if let Some(mount) = mount {
+				directory["display_name"] =
+					serde_json::Value::String(mount.display_name().to_owned());
+			}
+		}
+	}
+	Ok(collection_response(value, empty_field_mode(&request)))
+}
+
+// Registered before `browse` so that the trailing segment is not mistaken for
+// part of the directory path.
+#[get("/browse/{path:.*}/summary")]
+async fn get_directory_summary(
+	index: Data<Index>,
+	_auth: Auth,
+	path: web::Path<String>,
+) -> Result<Json<index::DirectorySummary>, APIError> {
+	let summary = block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		index.get_directory_summary(Path::new(path.as_ref()))
+	})
+	.await?;
+	Ok(Json(summary))
+}
+
+// Minimal extended-M3U rendering of a song list. Durations are omitted from
+// #EXTINF when unknown rather than guessed.
+fn format_m3u(songs: &[index::Song]) -> String {
+	let mut playlist = String::from("#EXTM3U\n");
+	for song in songs {
+		let display = match (&song.artist, &song.title) {
+			(Some(artist), Some(title)) => format!("{} - {}", artist, title),
+			(None, Some(title)) => title.clone(),
+			_ => Path::new(&song.path)
+				.file_stem()
+				.map(|stem| stem.to_string_lossy().into_owned())
+				.unwrap_or_else(|| song.path.clone()),
+		};
+		let duration = song.duration.map(|d| d.to_string()).unwrap_or_default();
+		playlist.push_str(&format!("#EXTINF:{},{}\n", duration, display));
+		playlist.push_str(&format!(
+			"/api/audio/{}\n",
+			percent_encode(song.path.as_bytes(), NON_ALPHANUMERIC)
+		));
+	}
+	playlist
+}
+
+#[get("/browse/{path:.*}/m3u")]
+async fn browse_m3u(
+	index: Data<Index>,
+	_auth: Auth,
+	path: web::Path<String>,
+) -> Result<HttpResponse, APIError> {
+	let mut songs = block(move || -> Result<Vec<index::Song>, APIError> {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		let songs = index.flatten(Path::new(path.as_ref()), None, None, None)?;
+		Ok(songs.items)
+	})
+	.await?;
+	songs.sort_by(|a, b| {
+		(a.disc_number, a.track_number, &a.path).cmp(&(b.disc_number, b.track_number, &b.path))
+	});
+
+	Ok(HttpResponse::Ok()
+		.content_type("audio/x-mpegurl")
+		.body(format_m3u(&songs)))
 }
 
 #[get("/browse/{path:.*}")]
 async fn browse(
+	request: HttpRequest,
 	index: Data<Index>,
 	_auth: Auth,
 	path: web::Path<String>,
-) -> Result<Json<Vec<index::CollectionFile>>, APIError> {
+	options: web::Query<dto::BrowseOptions>,
+) -> Result<HttpResponse, APIError> {
+	let grouping = options.grouping.map(Into::into).unwrap_or_default();
 	let result = block(move || {
 		let path = percent_decode_str(&path).decode_utf8_lossy();
-		index.browse(Path::new(path.as_ref()))
+		index.browse(Path::new(path.as_ref()), grouping)
 	})
 	.await?;
-	Ok(Json(result))
+	Ok(collection_response(result, empty_field_mode(&request)))
+}
+
+#[get("/tree")]
+async fn tree_root(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+	options: web::Query<dto::TreeOptions>,
+) -> Result<HttpResponse, APIError> {
+	let depth = options.depth.unwrap_or(index::TREE_MAX_DEPTH);
+	let entries = block(move || index.browse_recursive(Path::new(""), depth)).await?;
+	Ok(truncatable_response(entries, empty_field_mode(&request)))
+}
+
+#[get("/tree/{path:.*}")]
+async fn tree(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+	path: web::Path<String>,
+	options: web::Query<dto::TreeOptions>,
+) -> Result<HttpResponse, APIError> {
+	let depth = options.depth.unwrap_or(index::TREE_MAX_DEPTH);
+	let entries = block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		index.browse_recursive(Path::new(path.as_ref()), depth)
+	})
+	.await?;
+	Ok(truncatable_response(entries, empty_field_mode(&request)))
 }
 
 #[get("/flatten")]
-async fn flatten_root(index: Data<Index>, _auth: Auth) -> Result<Json<Vec<index::Song>>, APIError> {
-	let songs = block(move || index.flatten(Path::new(""))).await?;
-	Ok(Json(songs))
+async fn flatten_root(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+	options: web::Query<dto::FlattenOptions>,
+) -> Result<HttpResponse, APIError> {
+	let songs =
+		block(move || {
+			let custom_tag = options.0.custom_tag_filter();
+			index.flatten(Path::new(""), options.bpm_min, options.bpm_max, custom_tag)
+		})
+			.await?;
+	Ok(truncatable_response(songs, empty_field_mode(&request)))
 }
 
 #[get("/flatten/{path:.*}")]
 async fn flatten(
+	request: HttpRequest,
 	index: Data<Index>,
 	_auth: Auth,
 	path: web::Path<String>,
-) -> Result<Json<Vec<index::Song>>, APIError> {
+	options: web::Query<dto::FlattenOptions>,
+) -> Result<HttpResponse, APIError> {
 	let songs = block(move || {
 		let path = percent_decode_str(&path).decode_utf8_lossy();
-		index.flatten(Path::new(path.as_ref()))
+		let custom_tag = options.0.custom_tag_filter();
+		index.flatten(
+			Path::new(path.as_ref()),
+			options.bpm_min,
+			options.bpm_max,
+			custom_tag,
+		)
+	})
+	.await?;
+	Ok(truncatable_response(songs, empty_field_mode(&request)))
+}
+
+const RESOLVE_SONGS_MAX_BATCH_SIZE: usize = 1000;
+
+#[post("/songs/resolve")]
+async fn resolve_songs(
+	index: Data<Index>,
+	_auth: Auth,
+	input: Json<dto::ResolveSongsInput>,
+) -> Result<Json<Vec<Option<index::Song>>>, APIError> {
+	if input.paths.len() > RESOLVE_SONGS_MAX_BATCH_SIZE {
+		return Err(APIError::BatchSizeExceeded(RESOLVE_SONGS_MAX_BATCH_SIZE));
+	}
+	let songs = block(move || {
+		let paths: Vec<PathBuf> = input.paths.iter().map(PathBuf::from).collect();
+		index.get_songs(&paths)
 	})
 	.await?;
 	Ok(Json(songs))
 }
 
+#[get("/audit")]
+async fn get_audit_log(
+	audit_manager: Data<audit::Manager>,
+	settings_manager: Data<settings::Manager>,
+	_admin_rights: AdminRights,
+	options: web::Query<dto::AuditListOptions>,
+) -> Result<Json<Vec<dto::AuditEntry>>, APIError> {
+	let options = options.into_inner();
+	let entries = block(move || -> Result<_, APIError> {
+		let settings = settings_manager.read()?;
+		Ok(audit_manager.list(&options.to_list_options(&settings))?)
+	})
+	.await?;
+	Ok(Json(entries.into_iter().map(|e| e.into()).collect()))
+}
+
+#[get("/maintenance/missing_artwork")]
+async fn missing_artwork(
+	index: Data<Index>,
+	settings_manager: Data<settings::Manager>,
+	_admin_rights: AdminRights,
+	pagination: web::Query<dto::Pagination>,
+) -> Result<Json<Vec<index::Directory>>, APIError> {
+	let result = block(move || -> Result<_, APIError> {
+		let settings = settings_manager.read()?;
+		let (offset, limit) = pagination.resolve(&settings);
+		Ok(index.list_directories_without_artwork(offset, limit)?)
+	})
+	.await?;
+	Ok(Json(result))
+}
+
+#[get("/maintenance/missing_files")]
+async fn missing_files(
+	index: Data<Index>,
+	_admin_rights: AdminRights,
+) -> Result<Json<Vec<PathBuf>>, APIError> {
+	let result = block(move || index.list_missing_files().map_err(APIError::from)).await?;
+	Ok(Json(result))
+}
+
+#[get("/maintenance/duplicates")]
+async fn list_duplicates(
+	index: Data<Index>,
+	_admin_rights: AdminRights,
+) -> Result<Json<Vec<Vec<index::Song>>>, APIError> {
+	let result = block(move || index.list_duplicates().map_err(APIError::from)).await?;
+	Ok(Json(result))
+}
+
+#[post("/files/move")]
+async fn move_file(
+	files_manager: Data<files::Manager>,
+	_admin_rights: AdminRights,
+	input: Json<dto::FileMoveInput>,
+) -> Result<HttpResponse, APIError> {
+	block(move || {
+		files_manager.move_file(Path::new(&input.source), Path::new(&input.destination))
+	})
+	.await?;
+	Ok(HttpResponse::Ok().finish())
+}
+
 #[get("/random")]
-async fn random(index: Data<Index>, _auth: Auth) -> Result<Json<Vec<index::Directory>>, APIError> {
-	let result = block(move || index.get_random_albums(20)).await?;
+async fn random(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+	options: web::Query<dto::AlbumListOptions>,
+) -> Result<HttpResponse, APIError> {
+	let result =
+		block(move || index.get_random_albums(20, options.exclude_compilations)).await?;
+	Ok(collection_response(result, empty_field_mode(&request)))
+}
+
+#[get("/album/{path:.*}/adjacent")]
+async fn adjacent_albums(
+	index: Data<Index>,
+	_auth: Auth,
+	path: web::Path<String>,
+	options: web::Query<dto::AdjacentAlbumsOptions>,
+) -> Result<Json<index::AdjacentAlbums>, APIError> {
+	let result = block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		index.get_adjacent_albums(Path::new(path.as_ref()), options.sort.into())
+	})
+	.await?;
 	Ok(Json(result))
 }
 
 #[get("/recent")]
-async fn recent(index: Data<Index>, _auth: Auth) -> Result<Json<Vec<index::Directory>>, APIError> {
-	let result = block(move || index.get_recent_albums(20)).await?;
+async fn recent(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+	options: web::Query<dto::RecentAlbumsOptions>,
+) -> Result<HttpResponse, APIError> {
+	let result = block(move || {
+		index.get_recent_albums(20, options.exclude_compilations, options.sort.into())
+	})
+	.await?;
+	Ok(collection_response(result, empty_field_mode(&request)))
+}
+
+#[get("/recent_songs")]
+async fn recent_songs(
+	request: HttpRequest,
+	index: Data<Index>,
+	settings_manager: Data<settings::Manager>,
+	_auth: Auth,
+	pagination: web::Query<dto::Pagination>,
+) -> Result<HttpResponse, APIError> {
+	let result = block(move || -> Result<_, APIError> {
+		let settings = settings_manager.read()?;
+		let (offset, limit) = pagination.resolve(&settings);
+		Ok(index.get_recent_songs(limit, offset)?)
+	})
+	.await?;
+	Ok(collection_response(result, empty_field_mode(&request)))
+}
+
+#[get("/compilations")]
+async fn compilations(
+	request: HttpRequest,
+	index: Data<Index>,
+	_auth: Auth,
+) -> Result<HttpResponse, APIError> {
+	let result = block(move || index.get_compilations()).await?;
+	Ok(collection_response(result, empty_field_mode(&request)))
+}
+
+#[get("/artist/{name}")]
+async fn artist_detail(
+	index: Data<Index>,
+	_auth: Auth,
+	name: web::Path<String>,
+) -> Result<Json<index::ArtistDetail>, APIError> {
+	let result = block(move || {
+		let name = percent_decode_str(&name).decode_utf8_lossy();
+		index.get_artist_detail(name.as_ref())
+	})
+	.await?;
+	Ok(Json(result))
+}
+
+#[get("/album_artists")]
+async fn album_artists(
+	index: Data<Index>,
+	_auth: Auth,
+) -> Result<Json<Vec<index::AlbumArtist>>, APIError> {
+	let result = block(move || index.list_album_artists()).await?;
 	Ok(Json(result))
 }
 
 #[get("/search")]
 async fn search_root(
+	request: HttpRequest,
 	index: Data<Index>,
 	_auth: Auth,
-) -> Result<Json<Vec<index::CollectionFile>>, APIError> {
-	let result = block(move || index.search("")).await?;
-	Ok(Json(result))
+	options: web::Query<dto::SearchOptions>,
+) -> Result<HttpResponse, APIError> {
+	let result = block(move || index.search("", options.fuzzy)).await?;
+	Ok(truncatable_response(result, empty_field_mode(&request)))
 }
 
 #[get("/search/{query:.*}")]
 async fn search(
+	request: HttpRequest,
 	index: Data<Index>,
 	_auth: Auth,
 	query: web::Path<String>,
-) -> Result<Json<Vec<index::CollectionFile>>, APIError> {
-	let result = block(move || index.search(&query)).await?;
-	Ok(Json(result))
+	options: web::Query<dto::SearchOptions>,
+) -> Result<HttpResponse, APIError> {
+	let result = block(move || index.search(&query, options.fuzzy)).await?;
+	Ok(truncatable_response(result, empty_field_mode(&request)))
 }
 
 #[get("/audio/{path:.*}")]
 async fn get_audio(
 	vfs_manager: Data<vfs::Manager>,
-	_auth: Auth,
+	settings_manager: Data<settings::Manager>,
+	streams_manager: Data<streams::Manager>,
+	auth: Auth,
 	path: web::Path<String>,
-) -> Result<MediaFile, APIError> {
-	let audio_path = block(move || {
+	request: HttpRequest,
+) -> Result<HttpResponse, APIError> {
+	let lease = streams_manager.acquire(&auth.username)?;
+
+	let (audio_path, content_type) = block(move || -> Result<_, APIError> {
 		let vfs = vfs_manager.get_vfs()?;
 		let path = percent_decode_str(&path).decode_utf8_lossy();
-		vfs.virtual_to_real(Path::new(path.as_ref()))
+		let audio_path = vfs.virtual_to_real(Path::new(path.as_ref()))?;
+		let content_type = settings_manager.get_audio_mime_type(&audio_path);
+		Ok((audio_path, content_type))
 	})
 	.await?;
 
 	let named_file = NamedFile::open(audio_path).map_err(|_| APIError::AudioFileIOError)?;
-	Ok(MediaFile::new(named_file))
+	let mut response = MediaFile::new(named_file).respond_to(&request);
+	if let Ok(value) = HeaderValue::from_str(&content_type) {
+		response.headers_mut().insert(CONTENT_TYPE, value);
+	}
+	Ok(response.map_body(|_, body| BoxBody::new(LeasedBody { body, _lease: lease })))
+}
+
+// One track scheduled for concatenated streaming, resolved to a real file
+struct AlbumTrack {
+	real_path: PathBuf,
+	file_size: u64,
+}
+
+// Tracks are streamed one chunk at a time so that memory usage stays bounded
+// regardless of album size
+const ALBUM_STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+struct AlbumStreamState {
+	remaining_paths: std::vec::IntoIter<PathBuf>,
+	current_file: Option<std::fs::File>,
+}
+
+// Streams every track of a directory back-to-back as a single response when
+// they all share a container that tolerates concatenated frames (MP3, FLAC).
+// The `Polaris-Track-Offsets` header lists the byte offset at which each track
+// begins. Directories with mixed formats get a JSON track listing instead.
+#[get("/stream_album/{path:.*}")]
+async fn stream_album(
+	index: Data<Index>,
+	vfs_manager: Data<vfs::Manager>,
+	streams_manager: Data<streams::Manager>,
+	auth: Auth,
+	path: web::Path<String>,
+) -> Result<HttpResponse, APIError> {
+	let (songs, tracks) = block(
+		move || -> Result<(Vec<index::Song>, Vec<AlbumTrack>), APIError> {
+			let path = percent_decode_str(&path).decode_utf8_lossy();
+			let files = index.browse(Path::new(path.as_ref()), index::BrowseGrouping::default())?;
+			let songs: Vec<index::Song> = files
+				.into_iter()
+				.filter_map(|f| match f {
+					index::CollectionFile::Song(s) => Some(*s),
+					_ => None,
+				})
+				.collect();
+			let vfs = vfs_manager.get_vfs()?;
+			let mut tracks = Vec::with_capacity(songs.len());
+			for song in &songs {
+				let real_path = vfs.virtual_to_real(Path::new(&song.path))?;
+				let file_size = std::fs::metadata(&real_path)
+					.map_err(|_| APIError::AudioFileIOError)?
+					.len();
+				tracks.push(AlbumTrack {
+					real_path,
+					file_size,
+				});
+			}
+			Ok((songs, tracks))
+		},
+	)
+	.await?;
+
+	let formats: Vec<Option<utils::AudioFormat>> = tracks
+		.iter()
+		.map(|track| utils::get_audio_format(&track.real_path))
+		.collect();
+	let concat_format = match formats.split_first() {
+		Some((first @ Some(utils::AudioFormat::MP3 | utils::AudioFormat::FLAC), rest))
+			if rest.iter().all(|f| f == first) =>
+		{
+			*first
+		}
+		_ => None,
+	};
+
+	let format = match concat_format {
+		Some(format) => format,
+		None => return Ok(HttpResponse::Ok().json(songs)),
+	};
+
+	// The JSON track listing above is not a stream; only the concatenated
+	// audio response occupies a stream slot
+	let lease = streams_manager.acquire(&auth.username)?;
+
+	let mut offsets = Vec::with_capacity(tracks.len());
+	let mut total_size = 0u64;
+	for track in &tracks {
+		offsets.push(total_size.to_string());
+		total_size += track.file_size;
+	}
+
+	let content_type = match format {
+		utils::AudioFormat::FLAC => "audio/flac",
+		_ => "audio/mpeg",
+	};
+
+	let paths: Vec<PathBuf> = tracks.into_iter().map(|track| track.real_path).collect();
+	let state = AlbumStreamState {
+		remaining_paths: paths.into_iter(),
+		current_file: None,
+	};
+	let body = futures_util::stream::unfold(state, |mut state| async {
+		loop {
+			let mut file = match state.current_file.take() {
+				Some(file) => file,
+				None => {
+					let path = state.remaining_paths.next()?;
+					match web::block(move || std::fs::File::open(path)).await {
+						Ok(Ok(file)) => file,
+						_ => return Some((Err(APIError::AudioFileIOError), state)),
+					}
+				}
+			};
+			let read_result = web::block(move || -> std::io::Result<_> {
+				use std::io::Read;
+				let mut buffer = vec![0u8; ALBUM_STREAM_CHUNK_SIZE];
+				let bytes_read = file.read(&mut buffer)?;
+				buffer.truncate(bytes_read);
+				Ok((file, buffer))
+			})
+			.await;
+			match read_result {
+				// End of the current track; move on to the next one
+				Ok(Ok((_, buffer))) if buffer.is_empty() => continue,
+				Ok(Ok((file, buffer))) => {
+					state.current_file = Some(file);
+					return Some((Ok(web::Bytes::from(buffer)), state));
+				}
+				_ => return Some((Err(APIError::AudioFileIOError), state)),
+			}
+		}
+	});
+
+	let response = HttpResponse::Ok()
+		.content_type(content_type)
+		.insert_header(("Polaris-Track-Offsets", offsets.join(",")))
+		.streaming(body);
+	Ok(response.map_body(|_, body| BoxBody::new(LeasedBody { body, _lease: lease })))
 }
 
 #[get("/thumbnail/{path:.*}")]
@@ -543,8 +1469,9 @@ async fn get_thumbnail(
 	_auth: Auth,
 	path: web::Path<String>,
 	options_input: web::Query<dto::ThumbnailOptions>,
-) -> Result<MediaFile, APIError> {
+) -> Result<CustomizeResponder<MediaFile>, APIError> {
 	let options = thumbnail::Options::from(options_input.0);
+	let effective_options = thumbnails_manager.clamp_options(&options);
 
 	let thumbnail_path = block(move || -> Result<PathBuf, APIError> {
 		let vfs = vfs_manager.get_vfs()?;
@@ -558,7 +1485,265 @@ async fn get_thumbnail(
 
 	let named_file = NamedFile::open(thumbnail_path).map_err(|_| APIError::ThumbnailFileIOError)?;
 
-	Ok(MediaFile::new(named_file))
+	let max_dimension = effective_options.max_dimension.unwrap_or_default();
+	// Advertise range support so interrupted fetches of large covers can resume
+	Ok(MediaFile::new(named_file)
+		.customize()
+		.insert_header(("accept-ranges", "bytes"))
+		.insert_header(("x-polaris-thumbnail-max-dimension", max_dimension)))
+}
+
+#[post("/cache/thumbnails/verify")]
+async fn verify_thumbnail_cache(
+	index: Data<Index>,
+	_admin_rights: AdminRights,
+) -> Result<Json<thumbnail::CacheVerificationReport>, APIError> {
+	let report = block(move || index.verify_thumbnail_cache().map_err(APIError::from)).await?;
+	Ok(Json(report))
+}
+
+const THUMBNAIL_ATLAS_MAX_BATCH_SIZE: usize = 64;
+
+#[post("/thumbnails/batch")]
+async fn get_thumbnails_batch(
+	vfs_manager: Data<vfs::Manager>,
+	thumbnails_manager: Data<thumbnail::Manager>,
+	_auth: Auth,
+	input: Json<dto::ThumbnailBatchInput>,
+) -> Result<HttpResponse, APIError> {
+	if input.paths.len() > THUMBNAIL_ATLAS_MAX_BATCH_SIZE {
+		return Err(APIError::BatchSizeExceeded(THUMBNAIL_ATLAS_MAX_BATCH_SIZE));
+	}
+	let tile_dimension = input
+		.size
+		.and_then(Into::into)
+		.unwrap_or(thumbnail::Options::default().max_dimension.unwrap_or(400));
+
+	let (atlas, tiles) = block(move || -> Result<_, APIError> {
+		let vfs = vfs_manager.get_vfs()?;
+		let image_paths: Vec<PathBuf> = input
+			.paths
+			.iter()
+			.map(|path| {
+				vfs.virtual_to_real(Path::new(path))
+					.unwrap_or_else(|_| PathBuf::new())
+			})
+			.collect();
+		let atlas = thumbnails_manager.get_atlas(&image_paths, tile_dimension)?;
+		let tiles: Vec<dto::AtlasTile> = atlas
+			.tiles
+			.iter()
+			.map(|tile| dto::AtlasTile {
+				path: input.paths[tile.index].clone(),
+				x: tile.x,
+				y: tile.y,
+				width: tile.width,
+				height: tile.height,
+			})
+			.collect();
+		Ok((atlas, tiles))
+	})
+	.await?;
+
+	let coordinate_map = serde_json::to_string(&tiles).map_err(|_| APIError::Internal)?;
+	Ok(HttpResponse::Ok()
+		.content_type("image/jpeg")
+		.insert_header(("Polaris-Atlas-Map", coordinate_map))
+		.body(atlas.image_bytes))
+}
+
+#[post("/song/{path:.*}/reindex")]
+async fn reindex_song(
+	index: Data<Index>,
+	_admin_rights: AdminRights,
+	path: web::Path<String>,
+) -> Result<HttpResponse, APIError> {
+	block(move || -> Result<(), APIError> {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		index
+			.refresh_song(Path::new(path.as_ref()))
+			.map_err(|e| e.into())
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[patch("/song/{path:.*}/tags")]
+async fn update_song_tags(
+	index: Data<Index>,
+	vfs_manager: Data<vfs::Manager>,
+	_admin_rights: AdminRights,
+	path: web::Path<String>,
+	changes: Json<index::metadata::TagChanges>,
+) -> Result<HttpResponse, APIError> {
+	block(move || -> Result<(), APIError> {
+		let vfs = vfs_manager.get_vfs()?;
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		let real_path = vfs.virtual_to_real(Path::new(path.as_ref()))?;
+		index::metadata::update_tags(&real_path, &changes)?;
+		index.refresh_song(Path::new(path.as_ref()))?;
+		Ok(())
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/song/{path:.*}/waveform")]
+async fn get_waveform(
+	vfs_manager: Data<vfs::Manager>,
+	waveform_manager: Data<waveform::Manager>,
+	_auth: Auth,
+	path: web::Path<String>,
+	options: web::Query<dto::WaveformOptions>,
+) -> Result<Json<Vec<waveform::Peak>>, APIError> {
+	let num_buckets = options.buckets.unwrap_or(waveform::DEFAULT_BUCKET_COUNT);
+	let peaks = block(move || -> Result<Vec<waveform::Peak>, APIError> {
+		let vfs = vfs_manager.get_vfs()?;
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		let audio_path = vfs.virtual_to_real(Path::new(path.as_ref()))?;
+		waveform_manager
+			.get_waveform(&audio_path, num_buckets)
+			.map_err(|e| e.into())
+	})
+	.await?;
+	Ok(Json(peaks))
+}
+
+#[get("/artwork/{path:.*}/original")]
+async fn get_artwork_original(
+	vfs_manager: Data<vfs::Manager>,
+	_auth: Auth,
+	path: web::Path<String>,
+) -> Result<HttpResponse, APIError> {
+	let image = block(move || -> Result<thumbnail::RawImage, APIError> {
+		let vfs = vfs_manager.get_vfs()?;
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		let image_path = vfs.virtual_to_real(Path::new(path.as_ref()))?;
+		thumbnail::read_raw(&image_path).map_err(|e| e.into())
+	})
+	.await?;
+	Ok(HttpResponse::Ok()
+		.content_type(image.mime)
+		.body(image.bytes))
+}
+
+async fn put_artwork(
+	vfs_manager: Data<vfs::Manager>,
+	thumbnails_manager: Data<thumbnail::Manager>,
+	_admin_rights: AdminRights,
+	path: web::Path<String>,
+	image_bytes: web::Bytes,
+) -> Result<HttpResponse, APIError> {
+	block(move || -> Result<(), APIError> {
+		let vfs = vfs_manager.get_vfs()?;
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		let image_path = vfs.virtual_to_real(Path::new(path.as_ref()))?;
+		thumbnail::write(&image_path, &image_bytes)?;
+		thumbnails_manager.invalidate(&image_path)?;
+		Ok(())
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[post("/now_playing")]
+async fn post_now_playing(
+	now_playing_manager: Data<now_playing::Manager>,
+	auth: Auth,
+	input: Json<dto::NowPlayingInput>,
+) -> Result<HttpResponse, APIError> {
+	now_playing_manager.set(&auth.username, &input.path);
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+fn now_playing_event(path: &str) -> web::Bytes {
+	web::Bytes::from(format!("data: {}\n\n", path))
+}
+
+#[get("/events/now_playing")]
+async fn events_now_playing(
+	now_playing_manager: Data<now_playing::Manager>,
+	auth: Auth,
+) -> HttpResponse {
+	let receiver = now_playing_manager.subscribe();
+	let initial = now_playing_manager
+		.get(&auth.username)
+		.map(|path| Ok::<_, std::convert::Infallible>(now_playing_event(&path)));
+
+	let updates = futures_util::stream::unfold(
+		(receiver, auth.username),
+		|(mut receiver, username)| async move {
+			loop {
+				use tokio::sync::broadcast::error::RecvError;
+				match receiver.recv().await {
+					Ok(update) if update.username == username => {
+						let event = now_playing_event(&update.path);
+						return Some((Ok(event), (receiver, username)));
+					}
+					Ok(_) | Err(RecvError::Lagged(_)) => continue,
+					Err(RecvError::Closed) => return None,
+				}
+			}
+		},
+	);
+
+	HttpResponse::Ok()
+		.content_type("text/event-stream")
+		.insert_header((actix_web::http::header::CACHE_CONTROL, "no-cache"))
+		.streaming(futures_util::stream::iter(initial).chain(updates))
+}
+
+fn log_event(line: &str) -> web::Bytes {
+	web::Bytes::from(format!("data: {}\n\n", line))
+}
+
+#[get("/logs/stream")]
+async fn stream_logs(logs_manager: Data<logs::Manager>, _admin_rights: AdminRights) -> HttpResponse {
+	let receiver = logs_manager.subscribe();
+	let backlog: Vec<_> = logs_manager
+		.backlog()
+		.iter()
+		.map(|line| Ok::<_, std::convert::Infallible>(log_event(line)))
+		.collect();
+
+	let updates = futures_util::stream::unfold(receiver, |mut receiver| async move {
+		loop {
+			use tokio::sync::broadcast::error::RecvError;
+			match receiver.recv().await {
+				Ok(line) => return Some((Ok(log_event(&line)), receiver)),
+				Err(RecvError::Lagged(_)) => continue,
+				Err(RecvError::Closed) => return None,
+			}
+		}
+	});
+
+	HttpResponse::Ok()
+		.content_type("text/event-stream")
+		.insert_header((actix_web::http::header::CACHE_CONTROL, "no-cache"))
+		.streaming(futures_util::stream::iter(backlog).chain(updates))
+}
+
+#[post("/song/{path:.*}/played")]
+async fn post_played(
+	history_manager: Data<history::Manager>,
+	auth: Auth,
+	path: web::Path<String>,
+) -> Result<HttpResponse, APIError> {
+	block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		history_manager.record(&auth.username, path.as_ref())
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/recently_played")]
+async fn get_recently_played(
+	history_manager: Data<history::Manager>,
+	auth: Auth,
+) -> Result<Json<Vec<history::Entry>>, APIError> {
+	let entries = block(move || history_manager.list(&auth.username)).await?;
+	Ok(Json(entries))
 }
 
 #[get("/playlists")]
@@ -606,6 +1791,137 @@ async fn delete_playlist(
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
+#[get("/favorites")]
+async fn list_favorites(
+	favorite_manager: Data<favorite::Manager>,
+	auth: Auth,
+) -> Result<Json<Vec<index::Song>>, APIError> {
+	let songs = block(move || favorite_manager.list_favorites(&auth.username)).await?;
+	Ok(Json(songs))
+}
+
+#[get("/song/{path:.*}/favorite")]
+async fn get_favorite_status(
+	favorite_manager: Data<favorite::Manager>,
+	auth: Auth,
+	path: web::Path<String>,
+) -> Result<Json<bool>, APIError> {
+	let is_favorite = block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		favorite_manager.is_favorite(&auth.username, Path::new(path.as_ref()))
+	})
+	.await?;
+	Ok(Json(is_favorite))
+}
+
+#[put("/song/{path:.*}/favorite")]
+async fn add_favorite(
+	favorite_manager: Data<favorite::Manager>,
+	auth: Auth,
+	path: web::Path<String>,
+) -> Result<HttpResponse, APIError> {
+	block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		favorite_manager.add_favorite(&auth.username, Path::new(path.as_ref()))
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[delete("/song/{path:.*}/favorite")]
+async fn remove_favorite(
+	favorite_manager: Data<favorite::Manager>,
+	auth: Auth,
+	path: web::Path<String>,
+) -> Result<HttpResponse, APIError> {
+	block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		favorite_manager.remove_favorite(&auth.username, Path::new(path.as_ref()))
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/song/{path:.*}/tags/custom")]
+async fn get_custom_tags(
+	index: Data<Index>,
+	_auth: Auth,
+	path: web::Path<String>,
+) -> Result<Json<HashMap<String, String>>, APIError> {
+	let tags = block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		index.get_custom_tags(Path::new(path.as_ref()))
+	})
+	.await?;
+	Ok(Json(tags))
+}
+
+#[put("/song/{path:.*}/tags/custom")]
+async fn put_custom_tag(
+	index: Data<Index>,
+	_auth: Auth,
+	path: web::Path<String>,
+	tag: Json<dto::CustomTag>,
+) -> Result<HttpResponse, APIError> {
+	block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		index.set_custom_tag(Path::new(path.as_ref()), &tag.key, &tag.value)
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[delete("/song/{path:.*}/tags/custom")]
+async fn delete_custom_tag(
+	index: Data<Index>,
+	_auth: Auth,
+	path: web::Path<String>,
+	options: web::Query<dto::DeleteCustomTagOptions>,
+) -> Result<HttpResponse, APIError> {
+	block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		index.remove_custom_tag(Path::new(path.as_ref()), &options.key)
+	})
+	.await?;
+	Ok(HttpResponse::new(StatusCode::OK))
+}
+
+#[get("/song/{path:.*}/chapters")]
+async fn get_song_chapters(
+	index: Data<Index>,
+	_auth: Auth,
+	path: web::Path<String>,
+) -> Result<Json<Vec<index::metadata::ChapterMarker>>, APIError> {
+	let chapters = block(move || {
+		let path = percent_decode_str(&path).decode_utf8_lossy();
+		index.get_chapters(Path::new(path.as_ref()))
+	})
+	.await?;
+	Ok(Json(chapters))
+}
+
+// Id-based lookups, for clients that keep references to songs across file
+// moves and renames
+#[get("/song/id/{id}")]
+async fn get_song_by_id(
+	index: Data<Index>,
+	_auth: Auth,
+	id: web::Path<i32>,
+) -> Result<Json<index::Song>, APIError> {
+	let song = block(move || index.get_song_by_id(id.into_inner())).await?;
+	Ok(Json(song))
+}
+
+#[get("/directory/id/{id}")]
+async fn get_directory_by_id(
+	index: Data<Index>,
+	_auth: Auth,
+	id: web::Path<i32>,
+) -> Result<Json<index::Directory>, APIError> {
+	let directory = block(move || index.get_directory_by_id(id.into_inner())).await?;
+	Ok(Json(directory))
+}
+
 #[put("/lastfm/now_playing/{path:.*}")]
 async fn lastfm_now_playing(
 	lastfm_manager: Data<lastfm::Manager>,
@@ -637,13 +1953,27 @@ async fn lastfm_scrobble(
 			return Err(APIError::LastFMAccountNotLinked);
 		}
 		let path = percent_decode_str(&path).decode_utf8_lossy();
-		lastfm_manager.scrobble(&auth.username, Path::new(path.as_ref()))?;
+		lastfm_manager.enqueue_scrobble(&auth.username, Path::new(path.as_ref()))?;
 		Ok(())
 	})
 	.await?;
 	Ok(HttpResponse::new(StatusCode::OK))
 }
 
+#[post("/auth/scoped")]
+async fn create_scoped_token(
+	user_manager: Data<user::Manager>,
+	auth: Auth,
+	scope_request: Json<dto::ScopedTokenRequest>,
+) -> Result<Json<dto::ScopedToken>, APIError> {
+	let scope = scope_request.scope;
+	let user::AuthToken(token) = block(move || {
+		user_manager.generate_scoped_auth_token(&auth.username, scope.into())
+	})
+	.await?;
+	Ok(Json(dto::ScopedToken { token, scope }))
+}
+
 #[get("/lastfm/link_token")]
 async fn lastfm_link_token(
 	lastfm_manager: Data<lastfm::Manager>,
@@ -1,8 +1,9 @@
 use actix_test::TestServer;
 use actix_web::{
+	dev::Service,
 	middleware::{Compress, Logger},
 	rt::{System, SystemRunner},
-	web::Bytes,
+	web::{self, Bytes},
 	App as ActixApp,
 };
 use http::{response::Builder, Method, Request, Response};
@@ -14,6 +15,7 @@ use crate::app::App;
 use crate::paths::Paths;
 use crate::service::actix::*;
 use crate::service::dto;
+use crate::service::{client_ip, security_headers};
 use crate::service::test::TestService;
 use crate::test::*;
 
@@ -72,6 +74,52 @@ impl ActixTestService {
 
 		(response_builder, body)
 	}
+
+	// Sends the request body verbatim, for endpoints that expect raw bytes
+	// rather than JSON
+	fn process_internal_raw(&mut self, request: &Request<Vec<u8>>) -> (Builder, Option<Bytes>) {
+		let url = request.uri().to_string();
+		let body = request.body().clone();
+
+		let mut actix_request = match *request.method() {
+			Method::GET => self.server.get(url),
+			Method::POST => self.server.post(url),
+			Method::PUT => self.server.put(url),
+			Method::DELETE => self.server.delete(url),
+			_ => unimplemented!(),
+		}
+		.timeout(std::time::Duration::from_secs(30));
+
+		for (name, value) in request.headers() {
+			actix_request = actix_request.insert_header((name, value.clone()));
+		}
+
+		if let Some(ref authorization) = self.authorization {
+			actix_request = actix_request.bearer_auth(&authorization.token);
+		}
+
+		let mut actix_response = self
+			.system_runner
+			.block_on(async move { actix_request.send_body(body).await.unwrap() });
+
+		let mut response_builder = Response::builder().status(actix_response.status());
+		let headers = response_builder.headers_mut().unwrap();
+		for (name, value) in actix_response.headers().iter() {
+			headers.append(name, value.clone());
+		}
+
+		let is_success = actix_response.status().is_success();
+		let body = if is_success {
+			Some(
+				self.system_runner
+					.block_on(async move { actix_response.body().await.unwrap() }),
+			)
+		} else {
+			None
+		};
+
+		(response_builder, body)
+	}
 }
 
 impl TestService for ActixTestService {
@@ -94,8 +142,27 @@ impl TestService for ActixTestService {
 		let system_runner = System::new();
 		let server = actix_test::start(move || {
 			let config = make_config(app.clone());
+			// The test server is reached over loopback; trusting it lets tests
+			// exercise forwarded-header handling
+			let proxy_settings = client_ip::ProxySettings {
+				trusted_proxies: vec![[127, 0, 0, 1].into()],
+				..Default::default()
+			};
 			ActixApp::new()
+				.app_data(web::Data::new(proxy_settings.clone()))
+				.app_data(web::Data::new(auth_log::Format::default()))
 				.wrap(Logger::default())
+				.wrap_fn(move |req, srv| {
+					let headers = security_headers::for_request(req.request(), &proxy_settings);
+					let response_future = srv.call(req);
+					async move {
+						let mut response = response_future.await?;
+						for (name, value) in headers {
+							response.headers_mut().insert(name, value);
+						}
+						Ok(response)
+					}
+				})
 				.wrap(Compress::default())
 				.configure(config)
 		});
@@ -112,6 +179,11 @@ impl TestService for ActixTestService {
 		response_builder.body(()).unwrap()
 	}
 
+	fn fetch_raw(&mut self, request: &Request<Vec<u8>>) -> Response<()> {
+		let (response_builder, _body) = self.process_internal_raw(request);
+		response_builder.body(()).unwrap()
+	}
+
 	fn fetch_bytes<T: Serialize + Clone + 'static>(
 		&mut self,
 		request: &Request<T>,
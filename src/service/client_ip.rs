@@ -0,0 +1,180 @@
+use actix_web::HttpRequest;
+use std::net::IpAddr;
+
+use crate::app::settings;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ForwardedHeader {
+	XForwardedFor,
+	XRealIP,
+}
+
+impl ForwardedHeader {
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::XForwardedFor => "x-forwarded-for",
+			Self::XRealIP => "x-real-ip",
+		}
+	}
+
+	pub fn from_setting_string(value: &str) -> Self {
+		match value {
+			v if v.eq_ignore_ascii_case("x-real-ip") => Self::XRealIP,
+			_ => Self::XForwardedFor,
+		}
+	}
+
+	pub fn as_setting_string(self) -> &'static str {
+		self.name()
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct ProxySettings {
+	pub trusted_proxies: Vec<IpAddr>,
+	pub forwarded_header: ForwardedHeader,
+}
+
+impl Default for ProxySettings {
+	fn default() -> Self {
+		Self {
+			trusted_proxies: Vec::new(),
+			forwarded_header: ForwardedHeader::XForwardedFor,
+		}
+	}
+}
+
+impl ProxySettings {
+	pub fn from_settings(settings: &settings::Settings) -> Self {
+		let trusted_proxies = settings
+			.trusted_proxies
+			.split(',')
+			.filter_map(|s| s.trim().parse().ok())
+			.collect();
+		Self {
+			trusted_proxies,
+			forwarded_header: ForwardedHeader::from_setting_string(&settings.forwarded_ip_header),
+		}
+	}
+}
+
+// Returns the address the peer claims to be forwarding for, but only when the
+// direct peer is a trusted proxy. Anyone can send a forwarded header, so its
+// content is meaningless coming from an untrusted address.
+pub fn client_ip(peer: IpAddr, forwarded: Option<&str>, settings: &ProxySettings) -> IpAddr {
+	if !settings.trusted_proxies.contains(&peer) {
+		return peer;
+	}
+	let forwarded_ip = forwarded.and_then(|value| match settings.forwarded_header {
+		ForwardedHeader::XForwardedFor => value.split(',').next().and_then(|s| s.trim().parse().ok()),
+		ForwardedHeader::XRealIP => value.trim().parse().ok(),
+	});
+	forwarded_ip.unwrap_or(peer)
+}
+
+// True when the direct peer is a trusted proxy declaring that the original
+// request arrived over HTTPS. Like forwarded addresses, the header means
+// nothing coming from an untrusted peer.
+pub fn is_https_forwarded(
+	peer: IpAddr,
+	forwarded_proto: Option<&str>,
+	settings: &ProxySettings,
+) -> bool {
+	if !settings.trusted_proxies.contains(&peer) {
+		return false;
+	}
+	match forwarded_proto {
+		Some(value) => value.trim().eq_ignore_ascii_case("https"),
+		None => false,
+	}
+}
+
+pub fn https_forwarded(request: &HttpRequest, settings: &ProxySettings) -> bool {
+	let peer = match request.peer_addr() {
+		Some(address) => address.ip(),
+		None => return false,
+	};
+	let forwarded_proto = request
+		.headers()
+		.get("x-forwarded-proto")
+		.and_then(|value| value.to_str().ok());
+	is_https_forwarded(peer, forwarded_proto, settings)
+}
+
+pub fn resolve(request: &HttpRequest, settings: &ProxySettings) -> Option<IpAddr> {
+	let peer = request.peer_addr()?.ip();
+	let forwarded = request
+		.headers()
+		.get(settings.forwarded_header.name())
+		.and_then(|value| value.to_str().ok());
+	Some(client_ip(peer, forwarded, settings))
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	fn ip(s: &str) -> IpAddr {
+		s.parse().unwrap()
+	}
+
+	#[test]
+	fn honors_forwarded_header_from_trusted_proxy() {
+		let settings = ProxySettings {
+			trusted_proxies: vec![ip("10.0.0.1")],
+			forwarded_header: ForwardedHeader::XForwardedFor,
+		};
+		assert_eq!(
+			client_ip(ip("10.0.0.1"), Some("203.0.113.7, 10.0.0.1"), &settings),
+			ip("203.0.113.7")
+		);
+
+		let settings = ProxySettings {
+			trusted_proxies: vec![ip("10.0.0.1")],
+			forwarded_header: ForwardedHeader::XRealIP,
+		};
+		assert_eq!(
+			client_ip(ip("10.0.0.1"), Some("203.0.113.7"), &settings),
+			ip("203.0.113.7")
+		);
+	}
+
+	#[test]
+	fn ignores_forwarded_header_from_untrusted_peer() {
+		let settings = ProxySettings {
+			trusted_proxies: vec![ip("10.0.0.1")],
+			forwarded_header: ForwardedHeader::XForwardedFor,
+		};
+		assert_eq!(
+			client_ip(ip("192.0.2.50"), Some("203.0.113.7"), &settings),
+			ip("192.0.2.50")
+		);
+	}
+
+	#[test]
+	fn https_forwarding_needs_a_trusted_proxy() {
+		let settings = ProxySettings {
+			trusted_proxies: vec![ip("10.0.0.1")],
+			forwarded_header: ForwardedHeader::XForwardedFor,
+		};
+		assert!(is_https_forwarded(ip("10.0.0.1"), Some("https"), &settings));
+		assert!(is_https_forwarded(ip("10.0.0.1"), Some(" HTTPS "), &settings));
+		assert!(!is_https_forwarded(ip("10.0.0.1"), Some("http"), &settings));
+		assert!(!is_https_forwarded(ip("10.0.0.1"), None, &settings));
+		assert!(!is_https_forwarded(ip("192.0.2.50"), Some("https"), &settings));
+	}
+
+	#[test]
+	fn falls_back_to_peer_on_missing_or_garbage_header() {
+		let settings = ProxySettings {
+			trusted_proxies: vec![ip("10.0.0.1")],
+			forwarded_header: ForwardedHeader::XForwardedFor,
+		};
+		assert_eq!(client_ip(ip("10.0.0.1"), None, &settings), ip("10.0.0.1"));
+		assert_eq!(
+			client_ip(ip("10.0.0.1"), Some("not-an-ip"), &settings),
+			ip("10.0.0.1")
+		);
+	}
+}
@@ -0,0 +1,109 @@
+use crate::app::settings::Settings;
+
+pub const VERSION_HEADER: &str = "x-polaris-client-version";
+
+fn parse_version(value: &str) -> Option<Vec<u32>> {
+	let value = value.trim();
+	if value.is_empty() {
+		return None;
+	}
+	value.split('.').map(|c| c.trim().parse().ok()).collect()
+}
+
+// Compares dotted version numbers component by component, treating missing
+// trailing components as zero so "1.2" and "1.2.0" are equivalent.
+fn meets_minimum(version: &[u32], minimum: &[u32]) -> bool {
+	for i in 0..version.len().max(minimum.len()) {
+		let v = version.get(i).copied().unwrap_or(0);
+		let m = minimum.get(i).copied().unwrap_or(0);
+		if v != m {
+			return v > m;
+		}
+	}
+	true
+}
+
+// Validates the version a client declared about itself against the minimum
+// version configured by the server admin. Returns a message suitable for a
+// 426 Upgrade Required response when the client is too old.
+pub fn check(client_version: Option<&str>, settings: &Settings) -> Result<(), String> {
+	let minimum = match parse_version(&settings.minimum_client_version) {
+		Some(minimum) => minimum,
+		None => return Ok(()), // No minimum version configured
+	};
+	match client_version.and_then(parse_version) {
+		Some(ref version) if meets_minimum(version, &minimum) => Ok(()),
+		Some(_) => Err(format!(
+			"This server requires client version {} or newer",
+			settings.minimum_client_version
+		)),
+		None if settings.reject_unversioned_clients => Err(format!(
+			"This server requires clients to declare a version of {} or newer via the {} header",
+			settings.minimum_client_version, VERSION_HEADER
+		)),
+		None => Ok(()),
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	fn settings(minimum_client_version: &str, reject_unversioned_clients: bool) -> Settings {
+		Settings {
+			index_sleep_duration_seconds: 0,
+			index_album_art_pattern: "".to_owned(),
+			max_playlists_per_user: 0,
+			max_songs_per_playlist: 0,
+			index_follow_symlinks: false,
+			artwork_precedence: "".to_owned(),
+			minimum_client_version: minimum_client_version.to_owned(),
+			reject_unversioned_clients,
+			max_concurrent_streams_per_user: 0,
+			index_infer_tags_from_path: false,
+			reindex_on_startup: false,
+			duplicate_policy: "".to_owned(),
+			tag_parsing: "".to_owned(),
+			send_security_headers: false,
+			assume_https_behind_proxy: false,
+			default_page_size: 100,
+			max_page_size: 1000,
+			partial_file_policy: "".to_owned(),
+			trusted_proxies: "".to_owned(),
+			forwarded_ip_header: "".to_owned(),
+			auth_log_format: "".to_owned(),
+			old_auth_secrets: "".to_owned(),
+			num_traverser_threads: 0,
+			max_results_per_query: 0,
+			thumbnail_read_timeout_seconds: 0,
+			thumbnail_max_dimension: 0,
+			thumbnail_stored_max_dimension: 0,
+		}
+	}
+
+	#[test]
+	fn enforces_minimum_version() {
+		let settings = settings("5.2.0", false);
+		assert!(check(Some("5.1.9"), &settings).is_err());
+		assert!(check(Some("5.2.0"), &settings).is_ok());
+		assert!(check(Some("5.2"), &settings).is_ok());
+		assert!(check(Some("5.10.0"), &settings).is_ok());
+		assert!(check(Some("6.0.0"), &settings).is_ok());
+	}
+
+	#[test]
+	fn allows_everything_when_no_minimum_is_configured() {
+		let settings = settings("", true);
+		assert!(check(Some("0.0.1"), &settings).is_ok());
+		assert!(check(None, &settings).is_ok());
+	}
+
+	#[test]
+	fn handles_unversioned_clients_per_setting() {
+		assert!(check(None, &settings("5.2.0", false)).is_ok());
+		assert!(check(None, &settings("5.2.0", true)).is_err());
+		// A garbage version is no better than no version at all
+		assert!(check(Some("latest"), &settings("5.2.0", true)).is_err());
+	}
+}
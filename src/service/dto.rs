@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-use crate::app::{config, ddns, settings, thumbnail, user, vfs};
+use crate::app::{audit, config, ddns, index, settings, thumbnail, user, vfs};
+use crate::service::{auth_log, client_ip};
 use std::convert::From;
 
 pub const API_MAJOR_VERSION: i32 = 7;
@@ -17,6 +18,18 @@ pub struct InitialSetup {
 	pub has_any_users: bool,
 }
 
+// Feature matrix advertised to clients so they can adapt their UI without
+// probing individual endpoints
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Capabilities {
+	pub transcoding: bool,
+	pub hls: bool,
+	pub search: bool,
+	pub playlists: bool,
+	pub lastfm: bool,
+	pub thumbnails: bool,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Credentials {
 	pub username: String,
@@ -30,6 +43,33 @@ pub struct Authorization {
 	pub is_admin: bool,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+	ReadOnly,
+	StreamOnly,
+}
+
+impl From<TokenScope> for user::AuthorizationScope {
+	fn from(scope: TokenScope) -> Self {
+		match scope {
+			TokenScope::ReadOnly => user::AuthorizationScope::ReadOnly,
+			TokenScope::StreamOnly => user::AuthorizationScope::StreamOnly,
+		}
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScopedTokenRequest {
+	pub scope: TokenScope,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScopedToken {
+	pub token: String,
+	pub scope: TokenScope,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct AuthQueryParameters {
 	pub auth_token: String,
@@ -39,6 +79,8 @@ pub struct AuthQueryParameters {
 pub struct ThumbnailOptions {
 	pub size: Option<ThumbnailSize>,
 	pub pad: Option<bool>,
+	pub format: Option<ThumbnailFormat>,
+	pub progressive: Option<bool>,
 }
 
 impl From<ThumbnailOptions> for thumbnail::Options {
@@ -46,10 +88,35 @@ impl From<ThumbnailOptions> for thumbnail::Options {
 		let mut options = thumbnail::Options::default();
 		options.max_dimension = dto.size.map_or(options.max_dimension, Into::into);
 		options.pad_to_square = dto.pad.unwrap_or(options.pad_to_square);
+		options.format = dto.format.map_or(options.format, Into::into);
+		options.progressive = dto.progressive.unwrap_or(options.progressive);
 		options
 	}
 }
 
+
+#[derive(Serialize, Deserialize)]
+pub struct WaveformOptions {
+	pub buckets: Option<usize>,
+}
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThumbnailFormat {
+	Auto,
+	Jpeg,
+	Png,
+}
+
+impl From<ThumbnailFormat> for thumbnail::Format {
+	fn from(format: ThumbnailFormat) -> Self {
+		match format {
+			ThumbnailFormat::Auto => Self::Auto,
+			ThumbnailFormat::Jpeg => Self::Jpeg,
+			ThumbnailFormat::Png => Self::Png,
+		}
+	}
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ThumbnailSize {
@@ -69,6 +136,296 @@ impl Into<Option<u32>> for ThumbnailSize {
 	}
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ThumbnailBatchInput {
+	pub paths: Vec<String>,
+	pub size: Option<ThumbnailSize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasTile {
+	pub path: String,
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32,
+}
+
+// How absent optional fields are serialized in collection responses, selected
+// per-request with the `Accept-Profile` header. The default keeps the
+// historical behavior of emitting explicit nulls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmptyFieldMode {
+	#[default]
+	Null,
+	Omit,
+	EmptyString,
+}
+
+impl EmptyFieldMode {
+	pub fn from_accept_profile(value: &str) -> Self {
+		match value {
+			"omit-empty" => Self::Omit,
+			"empty-strings" => Self::EmptyString,
+			_ => Self::Null,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BrowseOptions {
+	pub grouping: Option<BrowseGrouping>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrowseGrouping {
+	FoldersFirst,
+	FilesFirst,
+	Mixed,
+}
+
+impl From<BrowseGrouping> for index::BrowseGrouping {
+	fn from(grouping: BrowseGrouping) -> Self {
+		match grouping {
+			BrowseGrouping::FoldersFirst => Self::FoldersFirst,
+			BrowseGrouping::FilesFirst => Self::FilesFirst,
+			BrowseGrouping::Mixed => Self::Mixed,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchOptions {
+	#[serde(default)]
+	pub fuzzy: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeOptions {
+	pub depth: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlattenOptions {
+	pub bpm_min: Option<i32>,
+	pub bpm_max: Option<i32>,
+	pub custom_tag: Option<String>,
+	pub custom_tag_value: Option<String>,
+}
+
+impl FlattenOptions {
+	pub fn custom_tag_filter(&self) -> Option<index::CustomTagFilter> {
+		self.custom_tag.clone().map(|key| index::CustomTagFilter {
+			key,
+			value: self.custom_tag_value.clone(),
+		})
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomTag {
+	pub key: String,
+	pub value: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeleteCustomTagOptions {
+	pub key: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pagination {
+	pub offset: Option<i64>,
+	pub limit: Option<i64>,
+}
+
+impl Pagination {
+	// Effective (offset, limit) under the server's pagination settings: an
+	// omitted limit falls back to the default page size and an oversized one
+	// is capped at the maximum
+	pub fn resolve(&self, settings: &settings::Settings) -> (i64, i64) {
+		let offset = self.offset.unwrap_or(0).max(0);
+		let limit = self
+			.limit
+			.unwrap_or(settings.default_page_size as i64)
+			.clamp(0, settings.max_page_size as i64);
+		(offset, limit)
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlbumListOptions {
+	#[serde(default)]
+	pub exclude_compilations: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecentSort {
+	#[default]
+	Added,
+	Released,
+}
+
+impl From<RecentSort> for index::RecentSort {
+	fn from(s: RecentSort) -> Self {
+		match s {
+			RecentSort::Added => Self::Added,
+			RecentSort::Released => Self::Released,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlbumSort {
+	#[default]
+	Name,
+	Year,
+}
+
+impl From<AlbumSort> for index::AlbumSort {
+	fn from(s: AlbumSort) -> Self {
+		match s {
+			AlbumSort::Name => Self::Name,
+			AlbumSort::Year => Self::Year,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdjacentAlbumsOptions {
+	#[serde(default)]
+	pub sort: AlbumSort,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentAlbumsOptions {
+	#[serde(default)]
+	pub exclude_compilations: bool,
+	#[serde(default)]
+	pub sort: RecentSort,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolveSongsInput {
+	pub paths: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NowPlayingInput {
+	pub path: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditListOptions {
+	pub offset: Option<i64>,
+	pub limit: Option<i64>,
+	pub event: Option<audit::Event>,
+	pub actor: Option<String>,
+}
+
+impl AuditListOptions {
+	pub fn to_list_options(&self, settings: &settings::Settings) -> audit::ListOptions {
+		let pagination = Pagination {
+			offset: self.offset,
+			limit: self.limit,
+		};
+		let (offset, limit) = pagination.resolve(settings);
+		audit::ListOptions {
+			offset,
+			limit,
+			event: self.event,
+			actor: self.actor.clone(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+	pub timestamp: i32,
+	pub event: String,
+	pub actor: Option<String>,
+	pub details: Option<String>,
+}
+
+impl From<audit::Entry> for AuditEntry {
+	fn from(e: audit::Entry) -> Self {
+		Self {
+			timestamp: e.timestamp,
+			event: e.event,
+			actor: e.actor,
+			details: e.details,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminChange {
+	pub actor: Option<String>,
+	pub old_is_admin: bool,
+	pub new_is_admin: bool,
+	pub changed_at: i32,
+}
+
+impl From<user::AdminChange> for AdminChange {
+	fn from(c: user::AdminChange) -> Self {
+		Self {
+			actor: c.actor,
+			old_is_admin: c.old_is_admin,
+			new_is_admin: c.new_is_admin,
+			changed_at: c.changed_at,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriggerIndexOptions {
+	#[serde(default)]
+	pub force: bool,
+	// Discard checkpoints from any interrupted scan and re-parse everything
+	#[serde(default)]
+	pub full: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReindexStatus {
+	Scheduled,
+	AlreadyScheduled,
+}
+
+impl From<index::ReindexTrigger> for ReindexStatus {
+	fn from(t: index::ReindexTrigger) -> Self {
+		match t {
+			index::ReindexTrigger::Scheduled => Self::Scheduled,
+			index::ReindexTrigger::AlreadyScheduled => Self::AlreadyScheduled,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriggerIndexOutput {
+	pub status: ReindexStatus,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMoveInput {
+	pub source: String,
+	pub destination: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PruneResult {
+	pub removed: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DryRunInput {
+	pub path: String,
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ListPlaylistsEntry {
 	pub name: String,
@@ -129,6 +486,41 @@ pub struct UserUpdate {
 	pub new_is_admin: Option<bool>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminUpdate {
+	pub username: String,
+	pub is_admin: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminUpdatesInput {
+	pub updates: Vec<AdminUpdate>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminUpdateOutcome {
+	Updated,
+	Unchanged,
+	NotFound,
+}
+
+impl From<user::AdminUpdateOutcome> for AdminUpdateOutcome {
+	fn from(outcome: user::AdminUpdateOutcome) -> Self {
+		match outcome {
+			user::AdminUpdateOutcome::Updated => Self::Updated,
+			user::AdminUpdateOutcome::Unchanged => Self::Unchanged,
+			user::AdminUpdateOutcome::NotFound => Self::NotFound,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminUpdateResult {
+	pub username: String,
+	pub outcome: AdminUpdateOutcome,
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub struct DDNSConfig {
 	pub host: String,
@@ -151,15 +543,30 @@ impl From<ddns::Config> for DDNSConfig {
 		Self {
 			host: c.host,
 			username: c.username,
-			password: c.password,
+			// The real password never leaves the server
+			password: if c.password.is_empty() {
+				String::new()
+			} else {
+				ddns::PASSWORD_REDACTED.to_owned()
+			},
 		}
 	}
 }
 
+fn default_include_in_discovery() -> bool {
+	true
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub struct MountDir {
 	pub source: String,
 	pub name: String,
+	#[serde(default = "default_include_in_discovery")]
+	pub include_in_discovery: bool,
+	#[serde(default)]
+	pub max_depth: i32,
+	#[serde(default)]
+	pub display_name: String,
 }
 
 impl From<MountDir> for vfs::MountDir {
@@ -167,6 +574,9 @@ impl From<MountDir> for vfs::MountDir {
 		Self {
 			name: m.name,
 			source: m.source,
+			include_in_discovery: m.include_in_discovery,
+			max_depth: m.max_depth,
+			display_name: m.display_name,
 		}
 	}
 }
@@ -176,6 +586,9 @@ impl From<vfs::MountDir> for MountDir {
 		Self {
 			name: m.name,
 			source: m.source,
+			include_in_discovery: m.include_in_discovery,
+			max_depth: m.max_depth,
+			display_name: m.display_name,
 		}
 	}
 }
@@ -205,6 +618,32 @@ impl From<Config> for config::Config {
 pub struct NewSettings {
 	pub album_art_pattern: Option<String>,
 	pub reindex_every_n_seconds: Option<i32>,
+	pub max_playlists_per_user: Option<i32>,
+	pub max_songs_per_playlist: Option<i32>,
+	pub follow_symlinks: Option<bool>,
+	pub artwork_precedence: Option<ArtworkPrecedence>,
+	pub minimum_client_version: Option<String>,
+	pub reject_unversioned_clients: Option<bool>,
+	pub max_concurrent_streams_per_user: Option<i32>,
+	pub infer_tags_from_path: Option<bool>,
+	pub reindex_on_startup: Option<bool>,
+	pub duplicate_policy: Option<DuplicatePolicy>,
+	pub tag_parsing: Option<TagParsing>,
+	pub send_security_headers: Option<bool>,
+	pub assume_https_behind_proxy: Option<bool>,
+	pub default_page_size: Option<i32>,
+	pub max_page_size: Option<i32>,
+	pub partial_file_policy: Option<PartialFilePolicy>,
+	pub mime_overrides: Option<Vec<MimeOverride>>,
+	pub trusted_proxies: Option<Vec<String>>,
+	pub forwarded_ip_header: Option<ForwardedIpHeader>,
+	pub auth_log_format: Option<AuthLogFormat>,
+	pub old_auth_secrets: Option<Vec<String>>,
+	pub num_traverser_threads: Option<i32>,
+	pub max_results_per_query: Option<i32>,
+	pub thumbnail_read_timeout_seconds: Option<i32>,
+	pub thumbnail_max_dimension: Option<i32>,
+	pub thumbnail_stored_max_dimension: Option<i32>,
 }
 
 impl From<NewSettings> for settings::NewSettings {
@@ -212,14 +651,271 @@ impl From<NewSettings> for settings::NewSettings {
 		Self {
 			album_art_pattern: s.album_art_pattern,
 			reindex_every_n_seconds: s.reindex_every_n_seconds,
+			max_playlists_per_user: s.max_playlists_per_user,
+			max_songs_per_playlist: s.max_songs_per_playlist,
+			follow_symlinks: s.follow_symlinks,
+			artwork_precedence: s.artwork_precedence.map(|p| p.into()),
+			minimum_client_version: s.minimum_client_version,
+			reject_unversioned_clients: s.reject_unversioned_clients,
+			max_concurrent_streams_per_user: s.max_concurrent_streams_per_user,
+			infer_tags_from_path: s.infer_tags_from_path,
+			reindex_on_startup: s.reindex_on_startup,
+			duplicate_policy: s.duplicate_policy.map(|p| p.into()),
+			tag_parsing: s.tag_parsing.map(|p| p.into()),
+			send_security_headers: s.send_security_headers,
+			assume_https_behind_proxy: s.assume_https_behind_proxy,
+			default_page_size: s.default_page_size,
+			max_page_size: s.max_page_size,
+			partial_file_policy: s.partial_file_policy.map(|p| p.into()),
+			mime_overrides: s
+				.mime_overrides
+				.map(|v| v.into_iter().map(|m| m.into()).collect()),
+			trusted_proxies: s.trusted_proxies.map(|v| v.join(",")),
+			forwarded_ip_header: s.forwarded_ip_header.map(|h| {
+				client_ip::ForwardedHeader::from(h)
+					.as_setting_string()
+					.to_owned()
+			}),
+			auth_log_format: s
+				.auth_log_format
+				.map(|f| auth_log::Format::from(f).as_setting_string().to_owned()),
+			old_auth_secrets: s.old_auth_secrets.map(|v| v.join(",")),
+			num_traverser_threads: s.num_traverser_threads,
+			max_results_per_query: s.max_results_per_query,
+			thumbnail_read_timeout_seconds: s.thumbnail_read_timeout_seconds,
+			thumbnail_max_dimension: s.thumbnail_max_dimension,
+			thumbnail_stored_max_dimension: s.thumbnail_stored_max_dimension,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MimeOverride {
+	pub extension: String,
+	pub mime: String,
+}
+
+impl From<MimeOverride> for settings::MimeOverride {
+	fn from(m: MimeOverride) -> Self {
+		Self {
+			extension: m.extension,
+			mime: m.mime,
+		}
+	}
+}
+
+impl From<settings::MimeOverride> for MimeOverride {
+	fn from(m: settings::MimeOverride) -> Self {
+		Self {
+			extension: m.extension,
+			mime: m.mime,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtworkPrecedence {
+	EmbeddedFirst,
+	#[default]
+	FolderFirst,
+}
+
+impl From<ArtworkPrecedence> for settings::ArtworkPrecedence {
+	fn from(p: ArtworkPrecedence) -> Self {
+		match p {
+			ArtworkPrecedence::EmbeddedFirst => Self::EmbeddedFirst,
+			ArtworkPrecedence::FolderFirst => Self::FolderFirst,
 		}
 	}
 }
 
+impl From<settings::ArtworkPrecedence> for ArtworkPrecedence {
+	fn from(p: settings::ArtworkPrecedence) -> Self {
+		match p {
+			settings::ArtworkPrecedence::EmbeddedFirst => Self::EmbeddedFirst,
+			settings::ArtworkPrecedence::FolderFirst => Self::FolderFirst,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+	#[default]
+	KeepAll,
+	PreferFirstMount,
+	Deduplicate,
+}
+
+impl From<DuplicatePolicy> for settings::DuplicatePolicy {
+	fn from(p: DuplicatePolicy) -> Self {
+		match p {
+			DuplicatePolicy::KeepAll => Self::KeepAll,
+			DuplicatePolicy::PreferFirstMount => Self::PreferFirstMount,
+			DuplicatePolicy::Deduplicate => Self::Deduplicate,
+		}
+	}
+}
+
+impl From<settings::DuplicatePolicy> for DuplicatePolicy {
+	fn from(p: settings::DuplicatePolicy) -> Self {
+		match p {
+			settings::DuplicatePolicy::KeepAll => Self::KeepAll,
+			settings::DuplicatePolicy::PreferFirstMount => Self::PreferFirstMount,
+			settings::DuplicatePolicy::Deduplicate => Self::Deduplicate,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagParsing {
+	Strict,
+	#[default]
+	Lenient,
+}
+
+impl From<TagParsing> for settings::TagParsing {
+	fn from(p: TagParsing) -> Self {
+		match p {
+			TagParsing::Strict => Self::Strict,
+			TagParsing::Lenient => Self::Lenient,
+		}
+	}
+}
+
+impl From<settings::TagParsing> for TagParsing {
+	fn from(p: settings::TagParsing) -> Self {
+		match p {
+			settings::TagParsing::Strict => Self::Strict,
+			settings::TagParsing::Lenient => Self::Lenient,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialFilePolicy {
+	#[default]
+	Skip,
+	IndexAsPending,
+}
+
+impl From<PartialFilePolicy> for settings::PartialFilePolicy {
+	fn from(p: PartialFilePolicy) -> Self {
+		match p {
+			PartialFilePolicy::Skip => Self::Skip,
+			PartialFilePolicy::IndexAsPending => Self::IndexAsPending,
+		}
+	}
+}
+
+impl From<settings::PartialFilePolicy> for PartialFilePolicy {
+	fn from(p: settings::PartialFilePolicy) -> Self {
+		match p {
+			settings::PartialFilePolicy::Skip => Self::Skip,
+			settings::PartialFilePolicy::IndexAsPending => Self::IndexAsPending,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ForwardedIpHeader {
+	#[default]
+	#[serde(rename = "x-forwarded-for")]
+	XForwardedFor,
+	#[serde(rename = "x-real-ip")]
+	XRealIp,
+}
+
+impl From<ForwardedIpHeader> for client_ip::ForwardedHeader {
+	fn from(h: ForwardedIpHeader) -> Self {
+		match h {
+			ForwardedIpHeader::XForwardedFor => Self::XForwardedFor,
+			ForwardedIpHeader::XRealIp => Self::XRealIP,
+		}
+	}
+}
+
+impl From<client_ip::ForwardedHeader> for ForwardedIpHeader {
+	fn from(h: client_ip::ForwardedHeader) -> Self {
+		match h {
+			client_ip::ForwardedHeader::XForwardedFor => Self::XForwardedFor,
+			client_ip::ForwardedHeader::XRealIP => Self::XRealIp,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthLogFormat {
+	#[default]
+	Text,
+	Json,
+}
+
+impl From<AuthLogFormat> for auth_log::Format {
+	fn from(f: AuthLogFormat) -> Self {
+		match f {
+			AuthLogFormat::Text => Self::Text,
+			AuthLogFormat::Json => Self::Json,
+		}
+	}
+}
+
+impl From<auth_log::Format> for AuthLogFormat {
+	fn from(f: auth_log::Format) -> Self {
+		match f {
+			auth_log::Format::Text => Self::Text,
+			auth_log::Format::Json => Self::Json,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestMountInput {
+	pub path: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestMountOutput {
+	pub exists: bool,
+	pub is_directory: bool,
+	pub is_readable: bool,
+	pub audio_files_found: usize,
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Settings {
 	pub album_art_pattern: String,
 	pub reindex_every_n_seconds: i32,
+	pub max_playlists_per_user: i32,
+	pub max_songs_per_playlist: i32,
+	pub follow_symlinks: bool,
+	pub artwork_precedence: ArtworkPrecedence,
+	pub minimum_client_version: Option<String>,
+	pub reject_unversioned_clients: bool,
+	pub max_concurrent_streams_per_user: i32,
+	pub infer_tags_from_path: bool,
+	pub reindex_on_startup: bool,
+	pub duplicate_policy: DuplicatePolicy,
+	pub tag_parsing: TagParsing,
+	pub send_security_headers: bool,
+	pub assume_https_behind_proxy: bool,
+	pub default_page_size: i32,
+	pub max_page_size: i32,
+	pub partial_file_policy: PartialFilePolicy,
+	pub mime_overrides: Vec<MimeOverride>,
+	// Old auth secrets are write-only and deliberately absent here
+	pub trusted_proxies: Vec<String>,
+	pub forwarded_ip_header: ForwardedIpHeader,
+	pub auth_log_format: AuthLogFormat,
+	pub num_traverser_threads: i32,
+	pub max_results_per_query: i32,
+	pub thumbnail_read_timeout_seconds: i32,
+	pub thumbnail_max_dimension: i32,
+	pub thumbnail_stored_max_dimension: i32,
 }
 
 impl From<settings::Settings> for Settings {
@@ -227,6 +923,52 @@ impl From<settings::Settings> for Settings {
 		Self {
 			album_art_pattern: s.index_album_art_pattern,
 			reindex_every_n_seconds: s.index_sleep_duration_seconds,
+			max_playlists_per_user: s.max_playlists_per_user,
+			max_songs_per_playlist: s.max_songs_per_playlist,
+			follow_symlinks: s.index_follow_symlinks,
+			artwork_precedence: settings::ArtworkPrecedence::from_setting_string(
+				&s.artwork_precedence,
+			)
+			.into(),
+			minimum_client_version: match s.minimum_client_version.is_empty() {
+				true => None,
+				false => Some(s.minimum_client_version),
+			},
+			reject_unversioned_clients: s.reject_unversioned_clients,
+			max_concurrent_streams_per_user: s.max_concurrent_streams_per_user,
+			infer_tags_from_path: s.index_infer_tags_from_path,
+			reindex_on_startup: s.reindex_on_startup,
+			duplicate_policy: settings::DuplicatePolicy::from_setting_string(&s.duplicate_policy)
+				.into(),
+			tag_parsing: settings::TagParsing::from_setting_string(&s.tag_parsing).into(),
+			send_security_headers: s.send_security_headers,
+			assume_https_behind_proxy: s.assume_https_behind_proxy,
+			default_page_size: s.default_page_size,
+			max_page_size: s.max_page_size,
+			partial_file_policy: settings::PartialFilePolicy::from_setting_string(
+				&s.partial_file_policy,
+			)
+			.into(),
+			// Stored apart from the misc settings row; the settings handler
+			// fills these in
+			mime_overrides: Vec::new(),
+			trusted_proxies: s
+				.trusted_proxies
+				.split(',')
+				.map(str::trim)
+				.filter(|p| !p.is_empty())
+				.map(str::to_owned)
+				.collect(),
+			forwarded_ip_header: client_ip::ForwardedHeader::from_setting_string(
+				&s.forwarded_ip_header,
+			)
+			.into(),
+			auth_log_format: auth_log::Format::from_setting_string(&s.auth_log_format).into(),
+			num_traverser_threads: s.num_traverser_threads,
+			max_results_per_query: s.max_results_per_query,
+			thumbnail_read_timeout_seconds: s.thumbnail_read_timeout_seconds,
+			thumbnail_max_dimension: s.thumbnail_max_dimension,
+			thumbnail_stored_max_dimension: s.thumbnail_stored_max_dimension,
 		}
 	}
 }
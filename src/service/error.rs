@@ -1,8 +1,12 @@
 use std::path::PathBuf;
 use thiserror::Error;
 
-use crate::app::index::QueryError;
-use crate::app::{config, ddns, lastfm, playlist, settings, thumbnail, user, vfs};
+use crate::app::index::{self, metadata, QueryError};
+use crate::app::{
+	audit, config, ddns, favorite, files, history, lastfm, playlist, settings, streams, thumbnail,
+	user, vfs,
+	waveform,
+};
 use crate::db;
 
 #[derive(Error, Debug)]
@@ -13,8 +17,14 @@ pub enum APIError {
 	AdminPermissionRequired,
 	#[error("Audio file could not be opened")]
 	AudioFileIOError,
+	#[error("Artist not found")]
+	ArtistNotFound,
 	#[error("Authentication is required")]
 	AuthenticationRequired,
+	#[error("Provided token does not grant access to this endpoint")]
+	AuthorizationScopeTooNarrow,
+	#[error("Requested batch size exceeds the maximum of {0}")]
+	BatchSizeExceeded(usize),
 	#[error("Could not encode Branca token")]
 	BrancaTokenEncoding,
 	#[error("Database error:\n\n{0}")]
@@ -23,14 +33,28 @@ pub enum APIError {
 	DdnsUpdateQueryFailed(u16),
 	#[error("Cannot delete your own account")]
 	DeletingOwnAccount,
+	#[error("Directory not found")]
+	DirectoryNotFound,
 	#[error("EmbeddedArtworkNotFound")]
 	EmbeddedArtworkNotFound,
 	#[error("EmptyUsername")]
 	EmptyUsername,
+	#[error("Moving files between mounts is not supported")]
+	FileMoveCrossMount,
+	#[error("File move destination `{0}` already exists")]
+	FileMoveDestinationExists(PathBuf),
+	#[error("File move path `{0}` is invalid")]
+	FileMoveInvalidPath(PathBuf),
+	#[error("File move source `{0}` does not exist")]
+	FileMoveSourceNotFound(PathBuf),
 	#[error("EmptyPassword")]
 	EmptyPassword,
 	#[error("Incorrect Credentials")]
 	IncorrectCredentials,
+	#[error("Initial setup has already been completed")]
+	InitialSetupAlreadyCompleted,
+	#[error("Cannot demote the last administrator")]
+	LastAdminDemotion,
 	#[error("No last.fm account has been linked")]
 	LastFMAccountNotLinked,
 	#[error("Could not decode content as base64 after linking last.fm account")]
@@ -51,8 +75,16 @@ pub enum APIError {
 	OwnAdminPrivilegeRemoval,
 	#[error("Could not hash password")]
 	PasswordHashing,
+	#[error("Password was used too recently")]
+	PasswordRecentlyUsed,
+	#[error("Refusing credentials submitted over plaintext")]
+	PlaintextCredentialsRejected,
 	#[error("Playlist not found")]
 	PlaylistNotFound,
+	#[error("Playlist quota exceeded")]
+	PlaylistQuotaExceeded,
+	#[error("Timed out while waiting for a database connection")]
+	PoolTimeout,
 	#[error("Settings error:\n\n{0}")]
 	Settings(settings::Error),
 	#[error("Song not found")]
@@ -67,14 +99,41 @@ pub enum APIError {
 	ThumbnailImageDecoding(PathBuf, image::error::ImageError),
 	#[error("Could not decode thumbnail from mp4 file `{0}`:\n\n{1}")]
 	ThumbnailMp4Decoding(PathBuf, mp4ameta::Error),
+	#[error("Timed out while generating a thumbnail for `{0}`")]
+	ThumbnailTimeout(PathBuf),
+	#[error("Could not update song tags:\n\n{0}")]
+	TagUpdate(metadata::Error),
+	#[error("Too many concurrent streams for this user")]
+	TooManyConcurrentStreams,
 	#[error("Toml deserialization error:\n\n{0}")]
 	TomlDeserialization(toml::de::Error),
+	#[error("This file format does not support tag editing: `{0}`")]
+	UnsupportedTagFormat(&'static str),
 	#[error("Unsupported thumbnail format: `{0}`")]
 	UnsupportedThumbnailFormat(&'static str),
+	#[error("Waveforms are not supported for this file format: `{0}`")]
+	UnsupportedWaveformFormat(&'static str),
 	#[error("User not found")]
 	UserNotFound,
 	#[error("Path not found in virtual filesystem")]
 	VFSPathNotFound,
+	#[error("Could not decode audio samples in `{0}`:
+
+{1}")]
+	WaveformDecoding(PathBuf, lewton::VorbisError),
+	#[error("Could not serialize waveform data:
+
+{0}")]
+	WaveformSerialization(serde_json::Error),
+}
+
+impl From<audit::Error> for APIError {
+	fn from(error: audit::Error) -> APIError {
+		match error {
+			audit::Error::Database(e) => APIError::Database(e),
+			audit::Error::DatabaseConnection(e) => e.into(),
+		}
+	}
 }
 
 impl From<config::Error> for APIError {
@@ -90,24 +149,88 @@ impl From<config::Error> for APIError {
 	}
 }
 
+impl From<favorite::Error> for APIError {
+	fn from(error: favorite::Error) -> APIError {
+		match error {
+			favorite::Error::Database(e) => APIError::Database(e),
+			favorite::Error::DatabaseConnection(e) => e.into(),
+			favorite::Error::UserNotFound => APIError::UserNotFound,
+			favorite::Error::Vfs(e) => e.into(),
+		}
+	}
+}
+
 impl From<playlist::Error> for APIError {
 	fn from(error: playlist::Error) -> APIError {
 		match error {
 			playlist::Error::Database(e) => APIError::Database(e),
 			playlist::Error::DatabaseConnection(e) => e.into(),
 			playlist::Error::PlaylistNotFound => APIError::PlaylistNotFound,
+			playlist::Error::QuotaExceeded => APIError::PlaylistQuotaExceeded,
+			playlist::Error::Settings(e) => e.into(),
 			playlist::Error::UserNotFound => APIError::UserNotFound,
 			playlist::Error::Vfs(e) => e.into(),
 		}
 	}
 }
 
+impl From<files::Error> for APIError {
+	fn from(error: files::Error) -> APIError {
+		match error {
+			files::Error::CrossMountMove => APIError::FileMoveCrossMount,
+			files::Error::Database(e) => APIError::Database(e),
+			files::Error::DatabaseConnection(e) => e.into(),
+			files::Error::DestinationExists(p) => APIError::FileMoveDestinationExists(p),
+			files::Error::InvalidPath(p) => APIError::FileMoveInvalidPath(p),
+			files::Error::Io(p, e) => APIError::Io(p, e),
+			files::Error::SourceNotFound(p) => APIError::FileMoveSourceNotFound(p),
+			files::Error::Vfs(e) => e.into(),
+		}
+	}
+}
+
+impl From<history::Error> for APIError {
+	fn from(error: history::Error) -> APIError {
+		match error {
+			history::Error::Database(e) => APIError::Database(e),
+			history::Error::DatabaseConnection(e) => e.into(),
+		}
+	}
+}
+
+impl From<metadata::Error> for APIError {
+	fn from(error: metadata::Error) -> APIError {
+		match error {
+			metadata::Error::Io(p, e) => APIError::Io(p, e),
+			metadata::Error::UnsupportedFormat(f) => APIError::UnsupportedTagFormat(f),
+			e => APIError::TagUpdate(e),
+		}
+	}
+}
+
+impl From<index::Error> for APIError {
+	fn from(error: index::Error) -> APIError {
+		match error {
+			index::Error::IndexClean(_) => APIError::Internal,
+			index::Error::Database(e) => APIError::Database(e),
+			index::Error::DatabaseConnection(e) => e.into(),
+			index::Error::Thumbnail(e) => e.into(),
+			index::Error::Vfs(e) => e.into(),
+		}
+	}
+}
+
 impl From<QueryError> for APIError {
 	fn from(error: QueryError) -> APIError {
 		match error {
 			QueryError::Database(e) => APIError::Database(e),
 			QueryError::DatabaseConnection(e) => e.into(),
+			QueryError::ArtistNotFound(_) => APIError::ArtistNotFound,
+			QueryError::DirectoryNotFound(_) => APIError::DirectoryNotFound,
+			QueryError::DirectoryIdNotFound(_) => APIError::DirectoryNotFound,
 			QueryError::SongNotFound(_) => APIError::SongMetadataNotFound,
+			QueryError::SongIdNotFound(_) => APIError::SongMetadataNotFound,
+			QueryError::Metadata(e) => e.into(),
 			QueryError::Vfs(e) => e.into(),
 		}
 	}
@@ -121,11 +244,21 @@ impl From<settings::Error> for APIError {
 			settings::Error::AuthenticationSecretInvalid => APIError::Settings(error),
 			settings::Error::MiscSettingsNotFound => APIError::Settings(error),
 			settings::Error::IndexAlbumArtPatternInvalid => APIError::Settings(error),
+			settings::Error::Io(p, e) => APIError::Io(p, e),
 			settings::Error::Database(e) => APIError::Database(e),
 		}
 	}
 }
 
+impl From<streams::Error> for APIError {
+	fn from(error: streams::Error) -> APIError {
+		match error {
+			streams::Error::Settings(e) => e.into(),
+			streams::Error::TooManyStreams => APIError::TooManyConcurrentStreams,
+		}
+	}
+}
+
 impl From<user::Error> for APIError {
 	fn from(error: user::Error) -> APIError {
 		match error {
@@ -139,8 +272,10 @@ impl From<user::Error> for APIError {
 			user::Error::IncorrectPassword => APIError::IncorrectCredentials,
 			user::Error::IncorrectUsername => APIError::IncorrectCredentials,
 			user::Error::InvalidAuthToken => APIError::IncorrectCredentials,
+			user::Error::LastAdminDemotion => APIError::LastAdminDemotion,
 			user::Error::MissingLastFMSessionKey => APIError::IncorrectCredentials,
 			user::Error::PasswordHashing => APIError::PasswordHashing,
+			user::Error::PasswordRecentlyUsed => APIError::PasswordRecentlyUsed,
 		}
 	}
 }
@@ -171,7 +306,7 @@ impl From<db::Error> for APIError {
 	fn from(error: db::Error) -> APIError {
 		match error {
 			db::Error::ConnectionPoolBuild => APIError::Internal,
-			db::Error::ConnectionPool => APIError::Internal,
+			db::Error::PoolTimeout => APIError::PoolTimeout,
 			db::Error::Io(p, e) => APIError::Io(p, e),
 			db::Error::Migration => APIError::Internal,
 		}
@@ -186,6 +321,8 @@ impl From<lastfm::Error> for APIError {
 			lastfm::Error::NowPlaying(e) => APIError::LastFMNowPlaying(e),
 			lastfm::Error::Query(e) => e.into(),
 			lastfm::Error::User(e) => e.into(),
+			lastfm::Error::Database(e) => APIError::Database(e),
+			lastfm::Error::DatabaseConnection(e) => e.into(),
 		}
 	}
 }
@@ -199,7 +336,19 @@ impl From<thumbnail::Error> for APIError {
 			thumbnail::Error::Io(p, e) => APIError::Io(p, e),
 			thumbnail::Error::Metaflac(p, e) => APIError::ThumbnailFlacDecoding(p, e),
 			thumbnail::Error::Mp4aMeta(p, e) => APIError::ThumbnailMp4Decoding(p, e),
+			thumbnail::Error::Timeout(p) => APIError::ThumbnailTimeout(p),
 			thumbnail::Error::UnsupportedFormat(f) => APIError::UnsupportedThumbnailFormat(f),
 		}
 	}
 }
+
+impl From<waveform::Error> for APIError {
+	fn from(error: waveform::Error) -> APIError {
+		match error {
+			waveform::Error::Io(p, e) => APIError::Io(p, e),
+			waveform::Error::Serialization(e) => APIError::WaveformSerialization(e),
+			waveform::Error::UnsupportedFormat(f) => APIError::UnsupportedWaveformFormat(f),
+			waveform::Error::Vorbis(p, e) => APIError::WaveformDecoding(p, e),
+		}
+	}
+}
@@ -0,0 +1,871 @@
+use serde_json::{json, Value};
+
+use crate::service::dto;
+
+/// Hand-maintained OpenAPI description of the HTTP API.
+///
+/// Every route registered in `actix::api::make_config` must appear in this
+/// document. The `version`, `auth`, `browse`, `flatten`, `random`, `recent`
+/// and `settings` endpoints are documented in full; other endpoints have
+/// summary entries. A test below validates that the document stays in sync
+/// with the registered routes.
+pub fn specification() -> Value {
+	json!({
+		"openapi": "3.0.3",
+		"info": {
+			"title": "Polaris",
+			"description": "Music streaming server API. All paths are mounted under `/api`.",
+			"version": format!("{}.{}", dto::API_MAJOR_VERSION, dto::API_MINOR_VERSION),
+		},
+		"components": {
+			"securitySchemes": {
+				"auth_token": {
+					"type": "http",
+					"scheme": "bearer",
+					"description": "Token issued by `POST /auth`. Also accepted as an `auth_token` query parameter.",
+				}
+			},
+			"schemas": {
+				"Version": {
+					"type": "object",
+					"required": ["major", "minor"],
+					"properties": {
+						"major": { "type": "integer" },
+						"minor": { "type": "integer" },
+					}
+				},
+				"Credentials": {
+					"type": "object",
+					"required": ["username", "password"],
+					"properties": {
+						"username": { "type": "string" },
+						"password": { "type": "string" },
+					}
+				},
+				"Authorization": {
+					"type": "object",
+					"required": ["username", "token", "is_admin"],
+					"properties": {
+						"username": { "type": "string" },
+						"token": { "type": "string" },
+						"is_admin": { "type": "boolean" },
+					}
+				},
+				"CollectionFile": {
+					"type": "object",
+					"description": "Either a directory or a song, tagged with a `Directory` or `Song` key.",
+				},
+				"Song": {
+					"type": "object",
+					"required": ["id", "path"],
+					"properties": {
+						"id": { "type": "integer" },
+						"path": { "type": "string" },
+						"track_number": { "type": "integer", "nullable": true },
+						"disc_number": { "type": "integer", "nullable": true },
+						"title": { "type": "string", "nullable": true },
+						"artist": { "type": "string", "nullable": true },
+						"album_artist": { "type": "string", "nullable": true },
+						"year": { "type": "integer", "nullable": true },
+						"album": { "type": "string", "nullable": true },
+						"artwork": { "type": "string", "nullable": true },
+						"duration": { "type": "integer", "nullable": true },
+						"bpm": { "type": "integer", "nullable": true },
+						"initial_key": { "type": "string", "nullable": true },
+						"encoder_delay": { "type": "integer", "nullable": true },
+						"encoder_padding": { "type": "integer", "nullable": true },
+						"file_size": { "type": "integer" },
+						"tags_inferred": { "type": "boolean" },
+						"track_total": { "type": "integer", "nullable": true },
+						"disc_total": { "type": "integer", "nullable": true },
+						"date_added": { "type": "integer" },
+					}
+				},
+				"Directory": {
+					"type": "object",
+					"required": ["id", "path"],
+					"properties": {
+						"id": { "type": "integer" },
+						"path": { "type": "string" },
+						"artist": { "type": "string", "nullable": true },
+						"year": { "type": "integer", "nullable": true },
+						"album": { "type": "string", "nullable": true },
+						"artwork": { "type": "string", "nullable": true },
+						"date_added": { "type": "integer" },
+						"is_compilation": { "type": "boolean" },
+						"track_count": { "type": "integer" },
+					}
+				},
+				"DirectorySummary": {
+					"type": "object",
+					"required": ["song_count", "total_size", "total_duration"],
+					"properties": {
+						"song_count": { "type": "integer" },
+						"total_size": { "type": "integer" },
+						"total_duration": { "type": "integer" },
+					}
+				},
+				"Settings": {
+					"type": "object",
+					"required": [
+						"album_art_pattern",
+						"reindex_every_n_seconds",
+						"max_playlists_per_user",
+						"max_songs_per_playlist",
+						"follow_symlinks",
+						"artwork_precedence",
+						"reject_unversioned_clients",
+						"max_concurrent_streams_per_user",
+						"infer_tags_from_path",
+						"reindex_on_startup",
+						"duplicate_policy",
+						"tag_parsing",
+						"send_security_headers",
+						"assume_https_behind_proxy",
+						"default_page_size",
+						"max_page_size",
+						"partial_file_policy",
+						"mime_overrides",
+						"trusted_proxies",
+						"forwarded_ip_header",
+						"auth_log_format",
+						"num_traverser_threads",
+						"max_results_per_query",
+						"thumbnail_read_timeout_seconds",
+						"thumbnail_max_dimension",
+						"thumbnail_stored_max_dimension"
+					],
+					"properties": {
+						"album_art_pattern": { "type": "string" },
+						"reindex_every_n_seconds": { "type": "integer" },
+						"max_playlists_per_user": { "type": "integer" },
+						"max_songs_per_playlist": { "type": "integer" },
+						"follow_symlinks": { "type": "boolean" },
+						"artwork_precedence": {
+							"type": "string",
+							"enum": ["embedded_first", "folder_first"]
+						},
+						"minimum_client_version": { "type": "string", "nullable": true },
+						"reject_unversioned_clients": { "type": "boolean" },
+						"max_concurrent_streams_per_user": { "type": "integer" },
+						"infer_tags_from_path": { "type": "boolean" },
+						"reindex_on_startup": { "type": "boolean" },
+						"duplicate_policy": {
+							"type": "string",
+							"enum": ["keep_all", "prefer_first_mount", "deduplicate"]
+						},
+						"tag_parsing": {
+							"type": "string",
+							"enum": ["strict", "lenient"]
+						},
+						"send_security_headers": { "type": "boolean" },
+						"assume_https_behind_proxy": { "type": "boolean" },
+						"default_page_size": { "type": "integer" },
+						"max_page_size": { "type": "integer" },
+						"partial_file_policy": {
+							"type": "string",
+							"enum": ["skip", "index_as_pending"]
+						},
+						"mime_overrides": {
+							"type": "array",
+							"items": {
+								"type": "object",
+								"required": ["extension", "mime"],
+								"properties": {
+									"extension": { "type": "string" },
+									"mime": { "type": "string" }
+								}
+							}
+						},
+						"trusted_proxies": {
+							"type": "array",
+							"items": { "type": "string" }
+						},
+						"forwarded_ip_header": {
+							"type": "string",
+							"enum": ["x-forwarded-for", "x-real-ip"]
+						},
+						"auth_log_format": {
+							"type": "string",
+							"enum": ["text", "json"]
+						},
+						"num_traverser_threads": { "type": "integer" },
+						"max_results_per_query": { "type": "integer" },
+						"thumbnail_read_timeout_seconds": { "type": "integer" },
+						"thumbnail_max_dimension": { "type": "integer" },
+						"thumbnail_stored_max_dimension": { "type": "integer" },
+					}
+				},
+				"NewSettings": {
+					"type": "object",
+					"properties": {
+						"album_art_pattern": { "type": "string", "nullable": true },
+						"reindex_every_n_seconds": { "type": "integer", "nullable": true },
+						"max_playlists_per_user": { "type": "integer", "nullable": true },
+						"max_songs_per_playlist": { "type": "integer", "nullable": true },
+						"follow_symlinks": { "type": "boolean", "nullable": true },
+						"artwork_precedence": {
+							"type": "string",
+							"enum": ["embedded_first", "folder_first"],
+							"nullable": true
+						},
+						"minimum_client_version": { "type": "string", "nullable": true },
+						"reject_unversioned_clients": { "type": "boolean", "nullable": true },
+						"max_concurrent_streams_per_user": { "type": "integer", "nullable": true },
+						"infer_tags_from_path": { "type": "boolean", "nullable": true },
+						"reindex_on_startup": { "type": "boolean", "nullable": true },
+						"duplicate_policy": {
+							"type": "string",
+							"enum": ["keep_all", "prefer_first_mount", "deduplicate"],
+							"nullable": true
+						},
+						"tag_parsing": {
+							"type": "string",
+							"enum": ["strict", "lenient"],
+							"nullable": true
+						},
+						"send_security_headers": { "type": "boolean", "nullable": true },
+						"assume_https_behind_proxy": { "type": "boolean", "nullable": true },
+						"default_page_size": { "type": "integer", "nullable": true },
+						"max_page_size": { "type": "integer", "nullable": true },
+						"partial_file_policy": {
+							"type": "string",
+							"enum": ["skip", "index_as_pending"],
+							"nullable": true
+						},
+						"mime_overrides": {
+							"type": "array",
+							"nullable": true,
+							"items": {
+								"type": "object",
+								"required": ["extension", "mime"],
+								"properties": {
+									"extension": { "type": "string" },
+									"mime": { "type": "string" }
+								}
+							}
+						},
+						"trusted_proxies": {
+							"type": "array",
+							"nullable": true,
+							"items": { "type": "string" }
+						},
+						"forwarded_ip_header": {
+							"type": "string",
+							"enum": ["x-forwarded-for", "x-real-ip"],
+							"nullable": true
+						},
+						"auth_log_format": {
+							"type": "string",
+							"enum": ["text", "json"],
+							"nullable": true
+						},
+						"old_auth_secrets": {
+							"type": "array",
+							"nullable": true,
+							"items": { "type": "string" }
+						},
+						"num_traverser_threads": { "type": "integer", "nullable": true },
+						"max_results_per_query": { "type": "integer", "nullable": true },
+						"thumbnail_read_timeout_seconds": { "type": "integer", "nullable": true },
+						"thumbnail_max_dimension": { "type": "integer", "nullable": true },
+						"thumbnail_stored_max_dimension": { "type": "integer", "nullable": true },
+					}
+				},
+			}
+		},
+		"paths": {
+			"/version": {
+				"get": {
+					"summary": "Read the API version",
+					"responses": {
+						"200": {
+							"description": "The API version implemented by this server",
+							"content": {
+								"application/json": {
+									"schema": { "$ref": "#/components/schemas/Version" }
+								}
+							}
+						}
+					}
+				}
+			},
+			"/capabilities": {
+				"get": { "summary": "List the features supported by this server", "responses": { "200": { "description": "OK" } } }
+			},
+			"/auth": {
+				"post": {
+					"summary": "Sign in and obtain an authentication token",
+					"requestBody": {
+						"required": true,
+						"content": {
+							"application/json": {
+								"schema": { "$ref": "#/components/schemas/Credentials" }
+							}
+						}
+					},
+					"responses": {
+						"200": {
+							"description": "Successful authentication",
+							"content": {
+								"application/json": {
+									"schema": { "$ref": "#/components/schemas/Authorization" }
+								}
+							}
+						},
+						"401": { "description": "Incorrect credentials" }
+					}
+				}
+			},
+			"/artist/{name}": {
+				"get": { "summary": "Read aggregated details about one artist", "security": [{ "auth_token": [] }], "responses": { "200": { "description": "Albums, track count and metadata for the artist" }, "404": { "description": "No songs are credited to this artist" } } }
+			},
+			"/auth/scoped": {
+				"post": { "summary": "Issue a limited-access token for the signed-in user", "security": [{ "auth_token": [] }], "responses": { "200": { "description": "A token restricted to the requested scope" } } }
+			},
+			"/browse": {
+				"get": {
+					"summary": "List the virtual filesystem root",
+					"security": [{ "auth_token": [] }],
+					"parameters": [{
+						"name": "grouping",
+						"in": "query",
+						"schema": { "type": "string", "enum": ["folders_first", "files_first", "mixed"] }
+					}],
+					"responses": {
+						"200": {
+							"description": "Files and directories at the collection root",
+							"content": {
+								"application/json": {
+									"schema": {
+										"type": "array",
+										"items": { "$ref": "#/components/schemas/CollectionFile" }
+									}
+								}
+							}
+						}
+					}
+				}
+			},
+			"/browse/{path}/m3u": {
+				"get": { "summary": "Export a directory as an M3U8 playlist", "responses": { "200": { "description": "An extended M3U playlist" } } }
+			},
+			"/browse/{path}": {
+				"get": {
+					"summary": "List a directory of the virtual filesystem",
+					"security": [{ "auth_token": [] }],
+					"parameters": [
+						{
+							"name": "path",
+							"in": "path",
+							"required": true,
+							"schema": { "type": "string" }
+						},
+						{
+							"name": "grouping",
+							"in": "query",
+							"schema": { "type": "string", "enum": ["folders_first", "files_first", "mixed"] }
+						}
+					],
+					"responses": {
+						"200": {
+							"description": "Files and directories within the requested directory",
+							"content": {
+								"application/json": {
+									"schema": {
+										"type": "array",
+										"items": { "$ref": "#/components/schemas/CollectionFile" }
+									}
+								}
+							}
+						},
+						"404": { "description": "No such directory" }
+					}
+				}
+			},
+			"/tree": {
+				"get": {
+					"summary": "List the virtual filesystem root as a nested tree",
+					"security": [{ "auth_token": [] }],
+					"parameters": [{
+						"name": "depth",
+						"in": "query",
+						"schema": { "type": "integer" }
+					}],
+					"responses": { "200": { "description": "OK" } }
+				}
+			},
+			"/tree/{path}": {
+				"get": {
+					"summary": "List a directory's recursive content as a nested tree",
+					"security": [{ "auth_token": [] }],
+					"parameters": [
+						{
+							"name": "path",
+							"in": "path",
+							"required": true,
+							"schema": { "type": "string" }
+						},
+						{
+							"name": "depth",
+							"in": "query",
+							"schema": { "type": "integer" }
+						}
+					],
+					"responses": {
+						"200": { "description": "OK" },
+						"404": { "description": "No such directory" }
+					}
+				}
+			},
+			"/stream_album/{path}": {
+				"get": { "summary": "Stream a directory's same-format tracks as one concatenated response", "responses": { "200": { "description": "OK" } } }
+			},
+			"/browse/{path}/summary": {
+				"get": {
+					"summary": "Summarize the recursive content of a directory",
+					"security": [{ "auth_token": [] }],
+					"parameters": [{
+						"name": "path",
+						"in": "path",
+						"required": true,
+						"schema": { "type": "string" }
+					}],
+					"responses": {
+						"200": {
+							"description": "Recursive song count, total file size and total duration",
+							"content": {
+								"application/json": {
+									"schema": { "$ref": "#/components/schemas/DirectorySummary" }
+								}
+							}
+						},
+						"404": { "description": "No such directory" }
+					}
+				}
+			},
+			"/flatten": {
+				"get": {
+					"summary": "List every song in the collection",
+					"security": [{ "auth_token": [] }],
+					"parameters": [
+						{
+							"name": "bpm_min",
+							"in": "query",
+							"schema": { "type": "integer" }
+						},
+						{
+							"name": "bpm_max",
+							"in": "query",
+							"schema": { "type": "integer" }
+						}
+					],
+					"responses": {
+						"200": {
+							"description": "All songs, in path order",
+							"content": {
+								"application/json": {
+									"schema": {
+										"type": "array",
+										"items": { "$ref": "#/components/schemas/Song" }
+									}
+								}
+							}
+						}
+					}
+				}
+			},
+			"/flatten/{path}": {
+				"get": {
+					"summary": "List every song within a directory",
+					"security": [{ "auth_token": [] }],
+					"parameters": [
+						{
+							"name": "path",
+							"in": "path",
+							"required": true,
+							"schema": { "type": "string" }
+						},
+						{
+							"name": "bpm_min",
+							"in": "query",
+							"schema": { "type": "integer" }
+						},
+						{
+							"name": "bpm_max",
+							"in": "query",
+							"schema": { "type": "integer" }
+						}
+					],
+					"responses": {
+						"200": {
+							"description": "All songs under the requested directory, in path order",
+							"content": {
+								"application/json": {
+									"schema": {
+										"type": "array",
+										"items": { "$ref": "#/components/schemas/Song" }
+									}
+								}
+							}
+						},
+						"404": { "description": "No such directory" }
+					}
+				}
+			},
+			"/random": {
+				"get": {
+					"summary": "List random albums",
+					"security": [{ "auth_token": [] }],
+					"parameters": [{
+						"name": "exclude_compilations",
+						"in": "query",
+						"schema": { "type": "boolean", "default": false }
+					}],
+					"responses": {
+						"200": {
+							"description": "Random albums from the collection",
+							"content": {
+								"application/json": {
+									"schema": {
+										"type": "array",
+										"items": { "$ref": "#/components/schemas/Directory" }
+									}
+								}
+							}
+						}
+					}
+				}
+			},
+			"/album/{path}/adjacent": {
+				"get": { "summary": "Fetch the previous and next albums under the same parent directory", "responses": { "200": { "description": "OK" } } }
+			},
+			"/recent": {
+				"get": {
+					"summary": "List recently added albums",
+					"security": [{ "auth_token": [] }],
+					"parameters": [{
+						"name": "exclude_compilations",
+						"in": "query",
+						"schema": { "type": "boolean", "default": false }
+					},
+					{
+						"name": "sort",
+						"in": "query",
+						"schema": { "type": "string", "enum": ["added", "released"], "default": "added" }
+					}],
+					"responses": {
+						"200": {
+							"description": "Albums sorted by most recently added",
+							"content": {
+								"application/json": {
+									"schema": {
+										"type": "array",
+										"items": { "$ref": "#/components/schemas/Directory" }
+									}
+								}
+							}
+						}
+					}
+				}
+			},
+			"/recent_songs": {
+				"get": {
+					"summary": "List recently added songs",
+					"security": [{ "auth_token": [] }],
+					"parameters": [{
+						"name": "offset",
+						"in": "query",
+						"schema": { "type": "integer", "default": 0 }
+					},
+					{
+						"name": "limit",
+						"in": "query",
+						"schema": { "type": "integer" }
+					}],
+					"responses": {
+						"200": {
+							"description": "Songs sorted by most recently added",
+							"content": {
+								"application/json": {
+									"schema": {
+										"type": "array",
+										"items": { "$ref": "#/components/schemas/Song" }
+									}
+								}
+							}
+						}
+					}
+				}
+			},
+			"/settings": {
+				"get": {
+					"summary": "Read server settings",
+					"security": [{ "auth_token": [] }],
+					"description": "Requires admin rights.",
+					"responses": {
+						"200": {
+							"description": "Current server settings",
+							"content": {
+								"application/json": {
+									"schema": { "$ref": "#/components/schemas/Settings" }
+								}
+							}
+						},
+						"401": { "description": "Not an admin" }
+					}
+				},
+				"put": {
+					"summary": "Amend server settings",
+					"security": [{ "auth_token": [] }],
+					"description": "Requires admin rights. Omitted fields are left unchanged.",
+					"requestBody": {
+						"required": true,
+						"content": {
+							"application/json": {
+								"schema": { "$ref": "#/components/schemas/NewSettings" }
+							}
+						}
+					},
+					"responses": {
+						"200": { "description": "Settings saved" },
+						"401": { "description": "Not an admin" }
+					}
+				}
+			},
+			"/openapi.json": {
+				"get": { "summary": "Read this document", "responses": { "200": { "description": "OK" } } }
+			},
+			"/initial_setup": {
+				"get": { "summary": "Check whether an admin account exists", "responses": { "200": { "description": "OK" } } }
+			},
+			"/initial_setup/admin": {
+				"post": { "summary": "Create the first admin account", "responses": { "200": { "description": "OK" } } }
+			},
+			"/config": {
+				"put": { "summary": "Apply a full server configuration (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/settings/test_mount": {
+				"post": { "summary": "Probe a filesystem path before mounting it (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/mount_dirs": {
+				"get": { "summary": "List mount points (admin)", "responses": { "200": { "description": "OK" } } },
+				"put": { "summary": "Replace mount points (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/ddns": {
+				"get": { "summary": "Read dynamic DNS configuration (admin)", "responses": { "200": { "description": "OK" } } },
+				"put": { "summary": "Amend dynamic DNS configuration (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/users": {
+				"get": { "summary": "List user accounts (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/user": {
+				"post": { "summary": "Create a user account (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/user/{name}": {
+				"put": { "summary": "Update a user account", "responses": { "200": { "description": "OK" } } },
+				"delete": { "summary": "Delete a user account (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/user/{name}/admin_history": {
+				"get": { "summary": "List admin grants and revocations for a user (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/users/admin": {
+				"put": { "summary": "Update the admin status of several users at once (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/preferences": {
+				"get": { "summary": "Read the current user's preferences", "responses": { "200": { "description": "OK" } } },
+				"put": { "summary": "Amend the current user's preferences", "responses": { "200": { "description": "OK" } } }
+			},
+			"/trigger_index": {
+				"post": { "summary": "Request a collection re-index (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/index/prune": {
+				"post": { "summary": "Remove index entries whose files are gone (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/index/status": {
+				"get": { "summary": "Read the outcome of the latest index pass (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/index/dry_run": {
+				"post": { "summary": "Preview the metadata a reindex would parse from a directory (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/song/{path}/reindex": {
+				"post": { "summary": "Re-scan a single song file (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/song/{path}/waveform": {
+				"get": {
+					"summary": "Read downsampled waveform peaks for a song",
+					"parameters": [
+						{
+							"name": "buckets",
+							"in": "query",
+							"schema": { "type": "integer" }
+						}
+					],
+					"responses": { "200": { "description": "OK" } }
+				}
+			},
+			"/songs/resolve": {
+				"post": { "summary": "Resolve a list of paths to songs", "responses": { "200": { "description": "OK" } } }
+			},
+			"/audit": {
+				"get": { "summary": "Read the audit log (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/maintenance/missing_artwork": {
+				"get": { "summary": "List albums with no artwork (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/maintenance/missing_files": {
+				"get": { "summary": "List indexed songs whose file is missing on disk", "responses": { "200": { "description": "OK" } } }
+			},
+			"/maintenance/duplicates": {
+				"get": { "summary": "List songs duplicated across the collection (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/files/move": {
+				"post": { "summary": "Move a file within a mount (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/compilations": {
+				"get": { "summary": "List compilation albums", "responses": { "200": { "description": "OK" } } }
+			},
+			"/album_artists": {
+				"get": { "summary": "List album artists with album counts", "responses": { "200": { "description": "OK" } } }
+			},
+			"/search": {
+				"get": { "summary": "Search with an empty query", "responses": { "200": { "description": "OK" } } }
+			},
+			"/search/{query}": {
+				"get": {
+					"summary": "Search the collection",
+					"parameters": [
+						{
+							"name": "query",
+							"in": "path",
+							"required": true,
+							"schema": { "type": "string" }
+						},
+						{
+							"name": "fuzzy",
+							"in": "query",
+							"schema": { "type": "boolean" }
+						}
+					],
+					"responses": { "200": { "description": "OK" } }
+				}
+			},
+			"/audio/{path}": {
+				"get": { "summary": "Stream a song file", "responses": { "200": { "description": "OK" }, "206": { "description": "Partial content" } } }
+			},
+			"/thumbnail/{path}": {
+				"get": { "summary": "Read album art as a thumbnail", "responses": { "200": { "description": "OK" } } }
+			},
+			"/thumbnails/batch": {
+				"post": { "summary": "Read several thumbnails as a single atlas image", "responses": { "200": { "description": "OK" } } }
+			},
+			"/cache/thumbnails/verify": {
+				"post": { "summary": "Remove stale or corrupt thumbnail cache entries (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/artwork/{path}": {
+				"put": { "summary": "Upload album art for a directory (admin)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/artwork/{path}/original": {
+				"get": { "summary": "Read album art without re-encoding", "responses": { "200": { "description": "OK" } } }
+			},
+			"/now_playing": {
+				"post": { "summary": "Report the song the current user is playing", "responses": { "200": { "description": "OK" } } }
+			},
+			"/logs/stream": {
+				"get": { "summary": "Stream recent and live log lines (admin)", "responses": { "200": { "description": "An SSE stream of log lines" } } }
+			},
+			"/events/now_playing": {
+				"get": { "summary": "Subscribe to now-playing events (server-sent events)", "responses": { "200": { "description": "OK" } } }
+			},
+			"/song/{path}/played": {
+				"post": { "summary": "Record a song in the current user's play history", "responses": { "200": { "description": "OK" } } }
+			},
+			"/recently_played": {
+				"get": { "summary": "List the current user's recently played songs", "responses": { "200": { "description": "OK" } } }
+			},
+			"/favorites": {
+				"get": { "summary": "List the current user's favorite songs", "responses": { "200": { "description": "OK" } } }
+			},
+			"/song/{path}/favorite": {
+				"get": { "summary": "Check whether a song is a favorite of the current user", "responses": { "200": { "description": "OK" } } },
+				"put": { "summary": "Mark a song as a favorite of the current user", "responses": { "200": { "description": "OK" } } },
+				"delete": { "summary": "Remove a song from the current user's favorites", "responses": { "200": { "description": "OK" } } }
+			},
+			"/song/{path}/tags/custom": {
+				"get": { "summary": "Read the custom tags attached to a song", "responses": { "200": { "description": "OK" } } },
+				"put": { "summary": "Attach or update a custom tag on a song", "responses": { "200": { "description": "OK" } } },
+				"delete": { "summary": "Remove a custom tag from a song", "responses": { "200": { "description": "OK" } } }
+			},
+			"/song/{path}/chapters": {
+				"get": { "summary": "List the chapter markers of an audiobook file", "responses": { "200": { "description": "OK" } } }
+			},
+			"/song/id/{id}": {
+				"get": { "summary": "Read a song by its stable id", "responses": { "200": { "description": "OK" } } }
+			},
+			"/directory/id/{id}": {
+				"get": { "summary": "Read a directory by its stable id", "responses": { "200": { "description": "OK" } } }
+			},
+			"/playlists": {
+				"get": { "summary": "List the current user's playlists", "responses": { "200": { "description": "OK" } } }
+			},
+			"/playlist/{name}": {
+				"put": { "summary": "Save a playlist", "responses": { "200": { "description": "OK" } } },
+				"get": { "summary": "Read a playlist", "responses": { "200": { "description": "OK" } } },
+				"delete": { "summary": "Delete a playlist", "responses": { "200": { "description": "OK" } } }
+			},
+			"/lastfm/now_playing/{path}": {
+				"put": { "summary": "Report the playing song to LastFM", "responses": { "200": { "description": "OK" } } }
+			},
+			"/lastfm/scrobble/{path}": {
+				"post": { "summary": "Scrobble a song to LastFM", "responses": { "200": { "description": "OK" } } }
+			},
+			"/lastfm/link_token": {
+				"get": { "summary": "Obtain a token to link a LastFM account", "responses": { "200": { "description": "OK" } } }
+			},
+			"/lastfm/link": {
+				"get": { "summary": "Complete a LastFM account link", "responses": { "200": { "description": "OK" } } },
+				"delete": { "summary": "Unlink the current user's LastFM account", "responses": { "200": { "description": "OK" } } }
+			},
+		}
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	// Extracts (method, path) pairs from the route attributes in api.rs, so
+	// that this test fails whenever a route is added without documenting it.
+	fn registered_routes() -> Vec<(String, String)> {
+		let api_source = include_str!("actix/api.rs");
+		let route_regex = regex::Regex::new(r#"#\[(get|post|put|delete)\("([^"]+)"\)\]"#).unwrap();
+		let mut routes: Vec<(String, String)> = route_regex
+			.captures_iter(api_source)
+			.map(|captures| (captures[1].to_owned(), captures[2].to_owned()))
+			.collect();
+		// The /settings resource registers its handlers without route attributes
+		routes.push(("get".to_owned(), "/settings".to_owned()));
+		routes.push(("put".to_owned(), "/settings".to_owned()));
+		routes
+	}
+
+	#[test]
+	fn every_registered_route_is_documented() {
+		let specification = specification();
+		let paths = specification["paths"].as_object().unwrap();
+		let routes = registered_routes();
+		assert!(routes.len() > 40);
+		for (method, route) in routes {
+			let documented_path = route
+				.replace("{path:.*}", "{path}")
+				.replace("{query:.*}", "{query}");
+			let operation = &paths[&documented_path][&method];
+			assert!(
+				operation.is_object(),
+				"`{} {}` is registered but missing from the OpenAPI document",
+				method,
+				route
+			);
+		}
+	}
+}
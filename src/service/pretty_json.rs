@@ -0,0 +1,64 @@
+use actix_web::{
+	body::{self, BoxBody},
+	dev::ServiceResponse,
+	error::ErrorInternalServerError,
+	http::header,
+};
+
+/// Returns true when the query string opts into pretty-printed JSON responses
+/// with `pretty=true`. This is a development convenience; the check is a plain
+/// query string scan so requests that do not opt in pay nothing.
+pub fn requested(query_string: &str) -> bool {
+	query_string.split('&').any(|pair| pair == "pretty=true")
+}
+
+/// Re-serializes a JSON response body with indentation. Responses that are not
+/// JSON, or whose body does not parse as JSON, pass through untouched.
+pub async fn reformat(
+	response: ServiceResponse<BoxBody>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+	let is_json = response
+		.headers()
+		.get(header::CONTENT_TYPE)
+		.and_then(|value| value.to_str().ok())
+		.map(|value| value.starts_with("application/json"))
+		.unwrap_or(false);
+	if !is_json {
+		return Ok(response);
+	}
+
+	let (request, response) = response.into_parts();
+	let (response, body) = response.into_parts();
+	let bytes = body::to_bytes(body)
+		.await
+		.map_err(ErrorInternalServerError)?;
+
+	let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+		Ok(value) => match serde_json::to_string_pretty(&value) {
+			Ok(pretty) => BoxBody::new(pretty),
+			Err(_) => BoxBody::new(bytes),
+		},
+		Err(_) => BoxBody::new(bytes),
+	};
+
+	Ok(ServiceResponse::new(request, response.set_body(body)))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn detects_opt_in() {
+		assert!(requested("pretty=true"));
+		assert!(requested("auth_token=abc&pretty=true"));
+	}
+
+	#[test]
+	fn ignores_other_queries() {
+		assert!(!requested(""));
+		assert!(!requested("pretty=false"));
+		assert!(!requested("pretty"));
+		assert!(!requested("auth_token=abc"));
+	}
+}
@@ -0,0 +1,77 @@
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{web, HttpRequest};
+
+use crate::app::settings;
+use crate::service::client_ip::{self, ProxySettings};
+
+// Hardening headers attached to every response when enabled in settings. The
+// values are deliberately not configurable; they are safe defaults for an
+// internet-facing deployment serving an API and a single-page web client.
+pub fn headers(enabled: bool, https_forwarded: bool) -> Vec<(&'static str, &'static str)> {
+	if !enabled {
+		return Vec::new();
+	}
+	let mut headers = vec![
+		("x-content-type-options", "nosniff"),
+		("x-frame-options", "DENY"),
+		("referrer-policy", "strict-origin-when-cross-origin"),
+	];
+	// HSTS must only be advertised when requests actually arrive over TLS;
+	// sending it from a plaintext deployment would lock browsers out
+	if https_forwarded {
+		headers.push(("strict-transport-security", "max-age=63072000"));
+	}
+	headers
+}
+
+pub fn for_request(
+	request: &HttpRequest,
+	proxy_settings: &ProxySettings,
+) -> Vec<(HeaderName, HeaderValue)> {
+	// This runs for every response, including static assets; the cached read
+	// keeps it off the database
+	let enabled = request
+		.app_data::<web::Data<settings::Manager>>()
+		.and_then(|manager| manager.read_cached().ok())
+		.map(|settings| settings.send_security_headers)
+		.unwrap_or(false);
+	let https_forwarded = client_ip::https_forwarded(request, proxy_settings);
+	headers(enabled, https_forwarded)
+		.into_iter()
+		.map(|(name, value)| {
+			(
+				HeaderName::from_static(name),
+				HeaderValue::from_static(value),
+			)
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn no_headers_when_disabled() {
+		assert_eq!(headers(false, false), Vec::new());
+		assert_eq!(headers(false, true), Vec::new());
+	}
+
+	#[test]
+	fn plaintext_deployments_do_not_advertise_hsts() {
+		let headers = headers(true, false);
+		assert!(headers.contains(&("x-content-type-options", "nosniff")));
+		assert!(headers.contains(&("x-frame-options", "DENY")));
+		assert!(headers
+			.iter()
+			.all(|(name, _)| *name != "strict-transport-security"));
+	}
+
+	#[test]
+	fn hsts_requires_forwarded_https() {
+		let headers = headers(true, true);
+		assert!(headers
+			.iter()
+			.any(|(name, _)| *name == "strict-transport-security"));
+	}
+}
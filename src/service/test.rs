@@ -8,9 +8,13 @@ pub mod constants;
 pub mod protocol;
 
 mod admin;
+mod audit;
 mod auth;
 mod collection;
 mod ddns;
+mod favorite;
+mod files;
+mod history;
 mod lastfm;
 mod media;
 mod playlist;
@@ -28,6 +32,7 @@ pub use crate::service::actix::test::ServiceType;
 pub trait TestService {
 	fn new(test_name: &str) -> Self;
 	fn fetch<T: Serialize + Clone + 'static>(&mut self, request: &Request<T>) -> Response<()>;
+	fn fetch_raw(&mut self, request: &Request<Vec<u8>>) -> Response<()>;
 	fn fetch_bytes<T: Serialize + Clone + 'static>(
 		&mut self,
 		request: &Request<T>,
@@ -38,6 +43,11 @@ pub trait TestService {
 	) -> Response<U>;
 
 	fn complete_initial_setup(&mut self) {
+		let bootstrap_request = protocol::initial_setup_admin(TEST_USERNAME_ADMIN, TEST_PASSWORD_ADMIN);
+		let bootstrap_response = self.fetch(&bootstrap_request);
+		assert_eq!(bootstrap_response.status(), StatusCode::OK);
+		self.login_admin();
+
 		let configuration = dto::Config {
 			users: Some(vec![
 				dto::NewUser {
@@ -54,12 +64,16 @@ pub trait TestService {
 			mount_dirs: Some(vec![dto::MountDir {
 				name: TEST_MOUNT_NAME.into(),
 				source: TEST_MOUNT_SOURCE.into(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
 			}]),
 			..Default::default()
 		};
 		let request = protocol::apply_config(configuration);
 		let response = self.fetch(&request);
 		assert_eq!(response.status(), StatusCode::OK);
+		self.logout();
 	}
 
 	fn login_internal(&mut self, username: &str, password: &str) {
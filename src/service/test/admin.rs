@@ -13,6 +13,60 @@ fn returns_api_version() {
 	assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[test]
+fn responses_are_compact_by_default() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::version();
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let body = std::str::from_utf8(response.body()).unwrap();
+	assert!(!body.contains('\n'));
+}
+
+#[test]
+fn pretty_query_parameter_indents_responses() {
+	let mut service = ServiceType::new(&test_name!());
+	let mut request = protocol::version();
+	*request.uri_mut() = "/api/version?pretty=true".parse().unwrap();
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let body = std::str::from_utf8(response.body()).unwrap();
+	assert!(body.contains('\n'));
+	assert_eq!(
+		serde_json::from_str::<serde_json::Value>(body).unwrap()["major"],
+		serde_json::json!(dto::API_MAJOR_VERSION)
+	);
+}
+
+#[test]
+fn capabilities_reflect_server_features() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::capabilities();
+	let response = service.fetch_json::<_, dto::Capabilities>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		response.body(),
+		&dto::Capabilities {
+			transcoding: false,
+			hls: false,
+			search: true,
+			playlists: true,
+			lastfm: true,
+			thumbnails: true,
+		}
+	);
+}
+
+#[test]
+fn returns_openapi_document() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::openapi();
+	let response = service.fetch_json::<_, serde_json::Value>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let specification = response.body();
+	assert!(specification["paths"]["/version"]["get"].is_object());
+}
+
 #[test]
 fn initial_setup_golden_path() {
 	let mut service = ServiceType::new(&test_name!());
@@ -42,6 +96,24 @@ fn initial_setup_golden_path() {
 	}
 }
 
+#[test]
+fn initial_setup_admin_works_exactly_once() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::initial_setup_admin("first_admin", "first_password");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[test]
+fn admin_rights_are_not_granted_with_zero_users() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::trigger_index();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
 #[test]
 fn trigger_index_golden_path() {
 	let mut service = ServiceType::new(&test_name!());
@@ -79,3 +151,43 @@ fn trigger_index_requires_admin() {
 	let response = service.fetch(&request);
 	assert_eq!(response.status(), StatusCode::FORBIDDEN);
 }
+
+#[test]
+fn missing_artwork_follows_page_size_settings() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+
+	// The test collection has exactly one album without artwork
+	let request = protocol::missing_artwork(None);
+	let response = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 1);
+
+	// With a default page size of zero, omitting the limit returns nothing
+	let request = protocol::put_settings(dto::NewSettings {
+		default_page_size: Some(0),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::missing_artwork(None);
+	let response = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.body().is_empty());
+
+	// An explicit limit cannot exceed the configured maximum
+	let request = protocol::put_settings(dto::NewSettings {
+		max_page_size: Some(0),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::missing_artwork(Some(100));
+	let response = service.fetch_json::<_, Vec<index::Directory>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.body().is_empty());
+}
@@ -0,0 +1,110 @@
+use http::StatusCode;
+
+use crate::service::dto;
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
+use crate::test_name;
+
+#[test]
+fn audit_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::audit(None);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn audit_records_logins_and_user_changes() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let bad_login = protocol::login("intruder", "not_the_password");
+	let response = service.fetch(&bad_login);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	service.login_admin();
+
+	let new_user = dto::NewUser {
+		name: "new_member".to_owned(),
+		password: "shiny_password".to_owned(),
+		admin: false,
+	};
+	let response = service.fetch(&protocol::create_user(new_user));
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::audit(None);
+	let response = service.fetch_json::<_, Vec<dto::AuditEntry>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let entries = response.body();
+
+	assert!(entries
+		.iter()
+		.any(|e| e.event == "login_failed" && e.actor.as_deref() == Some("intruder")));
+	assert!(entries
+		.iter()
+		.any(|e| e.event == "login" && e.actor.as_deref() == Some(TEST_USERNAME_ADMIN)));
+	assert!(entries.iter().any(|e| e.event == "user_created"
+		&& e.actor.as_deref() == Some(TEST_USERNAME_ADMIN)
+		&& e.details.as_deref() == Some("new_member")));
+}
+
+#[test]
+fn audit_filters_by_event_type() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let bad_login = protocol::login(TEST_USERNAME_ADMIN, "not_the_password");
+	let response = service.fetch(&bad_login);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	service.login_admin();
+
+	let request = protocol::audit(Some("login_failed"));
+	let response = service.fetch_json::<_, Vec<dto::AuditEntry>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let entries = response.body();
+	assert_eq!(entries.len(), 1);
+	assert!(entries.iter().all(|e| e.event == "login_failed"));
+}
+
+#[test]
+fn audit_pagination_follows_page_size_settings() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	for _ in 0..3 {
+		let bad_login = protocol::login("intruder", "not_the_password");
+		let response = service.fetch(&bad_login);
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+	}
+
+	service.login_admin();
+
+	// Omitting the limit falls back to the configured default page size
+	let request = protocol::put_settings(dto::NewSettings {
+		default_page_size: Some(2),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::audit(None);
+	let response = service.fetch_json::<_, Vec<dto::AuditEntry>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 2);
+
+	// An explicit limit beyond the configured maximum is capped
+	let request = protocol::put_settings(dto::NewSettings {
+		max_page_size: Some(3),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::audit_limited(100);
+	let response = service.fetch_json::<_, Vec<dto::AuditEntry>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 3);
+}
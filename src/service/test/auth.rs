@@ -1,4 +1,5 @@
 use headers::{self, HeaderMapExt};
+use http::header::{HeaderName, HeaderValue};
 use http::StatusCode;
 
 use crate::service::dto;
@@ -40,6 +41,36 @@ fn login_golden_path() {
 	assert!(!authorization.token.is_empty());
 }
 
+#[test]
+fn plaintext_login_is_rejected_when_https_is_assumed() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_settings(dto::NewSettings {
+		assume_https_behind_proxy: Some(true),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	service.logout();
+
+	// Credentials that detectably travelled over plaintext are refused
+	let request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+	// The same submission forwarded over HTTPS by the trusted proxy passes
+	let mut request = protocol::login(TEST_USERNAME, TEST_PASSWORD);
+	request.headers_mut().insert(
+		HeaderName::from_static("x-forwarded-proto"),
+		HeaderValue::from_static("https"),
+	);
+	let response = service.fetch_json::<_, dto::Authorization>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().username, TEST_USERNAME);
+}
+
 #[test]
 fn authentication_via_bearer_http_header_rejects_bad_token() {
 	let mut service = ServiceType::new(&test_name!());
@@ -110,3 +141,59 @@ fn authentication_via_query_param_golden_path() {
 	let response = service.fetch(&request);
 	assert_eq!(response.status(), StatusCode::OK);
 }
+
+fn acquire_scoped_token(service: &mut ServiceType, scope: dto::TokenScope) -> String {
+	let request = protocol::create_scoped_token(scope);
+	let response = service.fetch_json::<_, dto::ScopedToken>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	response.body().token.clone()
+}
+
+#[test]
+fn read_only_token_cannot_write() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+
+	let token = acquire_scoped_token(&mut service, dto::TokenScope::ReadOnly);
+	service.set_authorization(Some(dto::Authorization {
+		username: TEST_USERNAME_ADMIN.into(),
+		token,
+		is_admin: true,
+	}));
+
+	let request = protocol::browse(&std::path::PathBuf::new());
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::put_settings(dto::NewSettings::default());
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn stream_only_token_is_limited_to_audio() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+
+	let token = acquire_scoped_token(&mut service, dto::TokenScope::StreamOnly);
+	service.set_authorization(Some(dto::Authorization {
+		username: TEST_USERNAME_ADMIN.into(),
+		token,
+		is_admin: true,
+	}));
+
+	let path: std::path::PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let request = protocol::audio(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::browse(&std::path::PathBuf::new());
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -2,6 +2,7 @@ use http::StatusCode;
 use std::path::{Path, PathBuf};
 
 use crate::app::index;
+use crate::service::dto;
 use crate::service::test::{add_trailing_slash, constants::*, protocol, ServiceType, TestService};
 use crate::test_name;
 
@@ -44,6 +45,39 @@ fn browse_directory() {
 	assert_eq!(entries.len(), 5);
 }
 
+#[test]
+fn browse_accept_profile_controls_empty_fields() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	// The `Tobokegao` artist folder has no album tag
+	let path: PathBuf = [TEST_MOUNT_NAME].iter().collect();
+	let fetch_first_directory = |service: &mut ServiceType, profile: Option<&'static str>| {
+		let mut request = protocol::browse(&path);
+		if let Some(profile) = profile {
+			request
+				.headers_mut()
+				.insert("Accept-Profile", http::HeaderValue::from_static(profile));
+		}
+		let response = service.fetch_bytes(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+		let entries: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+		entries[0]["Directory"].clone()
+	};
+
+	let directory = fetch_first_directory(&mut service, None);
+	assert_eq!(directory["album"], serde_json::Value::Null);
+
+	let directory = fetch_first_directory(&mut service, Some("omit-empty"));
+	assert!(directory.get("album").is_none());
+
+	let directory = fetch_first_directory(&mut service, Some("empty-strings"));
+	assert_eq!(directory["album"], serde_json::Value::String(String::new()));
+}
+
 #[test]
 fn browse_bad_directory() {
 	let mut service = ServiceType::new(&test_name!());
@@ -56,6 +90,149 @@ fn browse_bad_directory() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[test]
+fn directory_summary_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let request = protocol::directory_summary(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn directory_summary_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let request = protocol::directory_summary(&path);
+	let response = service.fetch_json::<_, index::DirectorySummary>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let summary = response.body();
+	assert_eq!(summary.song_count, 5);
+	assert!(summary.total_size > 0);
+}
+
+#[test]
+fn mount_display_name_shows_in_browse_but_not_in_paths() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	// Relabel the mount without touching its stable name
+	let configuration = dto::Config {
+		mount_dirs: Some(vec![dto::MountDir {
+			name: TEST_MOUNT_NAME.into(),
+			source: TEST_MOUNT_SOURCE.into(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: "My Music".into(),
+		}]),
+		..Default::default()
+	};
+	let request = protocol::apply_config(configuration);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	service.index();
+	service.login();
+
+	let request = protocol::browse(&PathBuf::new());
+	let response = service.fetch_json::<_, serde_json::Value>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let entries = response.body().as_array().unwrap().clone();
+	assert_eq!(entries.len(), 1);
+	let directory = &entries[0]["Directory"];
+	assert_eq!(directory["path"], TEST_MOUNT_NAME);
+	assert_eq!(directory["display_name"], "My Music");
+
+	// Virtual paths keep resolving through the stable mount name
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::browse(&path);
+	let response = service.fetch_json::<_, Vec<index::CollectionFile>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 5);
+
+	// The display name is a label, not a path component
+	let path: PathBuf = ["My Music", "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::browse(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn adjacent_albums_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic"].iter().collect();
+	let request = protocol::adjacent_albums(&path, None);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn adjacent_albums_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic"].iter().collect();
+	let request = protocol::adjacent_albums(&path, None);
+	let response = service.fetch_json::<_, index::AdjacentAlbums>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let neighbors = response.body();
+	assert_eq!(neighbors.previous, None);
+	assert_eq!(
+		neighbors.next.as_ref().and_then(|d| d.album.as_deref()),
+		Some("Picnic (Remixes)")
+	);
+}
+
+#[test]
+fn browse_m3u_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::browse_m3u(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn browse_m3u_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::browse_m3u(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let playlist = String::from_utf8(response.body().clone()).unwrap();
+	let lines: Vec<&str> = playlist.lines().collect();
+	assert_eq!(lines[0], "#EXTM3U");
+	assert_eq!(lines.len(), 11);
+
+	let titles = [
+		"Above The Water",
+		"Candlelight",
+		"Three Gates",
+		"Beyond The Door",
+		"Hunted",
+	];
+	for (i, title) in titles.iter().enumerate() {
+		let info = lines[1 + 2 * i];
+		assert!(info.starts_with("#EXTINF:"));
+		assert!(info.ends_with(&format!(",Khemmis - {}", title)));
+		assert!(lines[2 + 2 * i].starts_with("/api/audio/"));
+	}
+}
+
 #[test]
 fn flatten_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -106,6 +283,60 @@ fn flatten_bad_directory() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[test]
+fn custom_tags_require_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let request = protocol::custom_tags(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn custom_tags_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::put_custom_tag(&path, "mood", "somber");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::custom_tags(&path);
+	let response = service.fetch_json::<_, std::collections::HashMap<String, String>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().get("mood"), Some(&"somber".to_owned()));
+
+	// Songs carrying the tag can be singled out of a flatten
+	let request = protocol::flatten_with_custom_tag(Path::new(TEST_MOUNT_NAME), "mood", None);
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 1);
+
+	let request =
+		protocol::flatten_with_custom_tag(Path::new(TEST_MOUNT_NAME), "mood", Some("upbeat"));
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 0);
+
+	let request = protocol::delete_custom_tag(&path, "mood");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::custom_tags(&path);
+	let response = service.fetch_json::<_, std::collections::HashMap<String, String>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.body().is_empty());
+}
+
 #[test]
 fn random_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -61,3 +61,56 @@ fn put_ddns_config_golden_path() {
 	let response = service.fetch(&request);
 	assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[test]
+fn get_ddns_config_redacts_password() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_ddns_config(dto::DDNSConfig {
+		host: "test".to_owned(),
+		username: "test".to_owned(),
+		password: "correct horse".to_owned(),
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::get_ddns_config();
+	let response = service.fetch_json::<_, dto::DDNSConfig>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let config = response.body();
+	assert_eq!(config.host, "test");
+	assert_eq!(config.username, "test");
+	assert_eq!(config.password, "***");
+}
+
+#[test]
+fn put_ddns_config_accepts_redacted_placeholder() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_ddns_config(dto::DDNSConfig {
+		host: "test".to_owned(),
+		username: "test".to_owned(),
+		password: "correct horse".to_owned(),
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	// Sending the placeholder back keeps the stored password
+	let request = protocol::put_ddns_config(dto::DDNSConfig {
+		host: "test".to_owned(),
+		username: "updated".to_owned(),
+		password: "***".to_owned(),
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::get_ddns_config();
+	let response = service.fetch_json::<_, dto::DDNSConfig>(&request);
+	let config = response.body();
+	assert_eq!(config.username, "updated");
+	assert_eq!(config.password, "***");
+}
@@ -0,0 +1,88 @@
+use http::StatusCode;
+use std::path::PathBuf;
+
+use crate::app::index;
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
+use crate::test_name;
+
+fn song_path() -> PathBuf {
+	[TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect()
+}
+
+#[test]
+fn list_favorites_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::favorites();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn add_favorite_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::add_favorite(&song_path());
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn favorites_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let request = protocol::favorites();
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 0);
+
+	let request = protocol::favorite_status(&song_path());
+	let response = service.fetch_json::<_, bool>(&request);
+	assert!(!response.body());
+
+	let request = protocol::add_favorite(&song_path());
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::favorites();
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let songs = response.body();
+	assert_eq!(songs.len(), 1);
+	assert_eq!(songs[0].title, Some("Candlelight".to_owned()));
+
+	let request = protocol::favorite_status(&song_path());
+	let response = service.fetch_json::<_, bool>(&request);
+	assert!(*response.body());
+
+	let request = protocol::remove_favorite(&song_path());
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::favorites();
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 0);
+}
+
+#[test]
+fn favorites_do_not_leak_across_users() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+
+	let request = protocol::add_favorite(&song_path());
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	service.login();
+	let request = protocol::favorites();
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().len(), 0);
+}
@@ -0,0 +1,239 @@
+use http::StatusCode;
+use std::path::{Path, PathBuf};
+
+use crate::app::index;
+use crate::service::dto;
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
+use crate::test_name;
+
+#[test]
+fn move_file_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::move_file(
+		"collection/Khemmis/Hunted/02 - Candlelight.mp3",
+		"collection/Khemmis/Hunted/02 - Renamed.mp3",
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn move_file_updates_disk_and_song_path() {
+	let test_name = test_name!();
+	let mut service = ServiceType::new(&test_name);
+	service.complete_initial_setup();
+	service.login_admin();
+
+	// Work on a private copy of the collection since we are moving files around
+	let output_dir: PathBuf = ["test-output", &test_name].iter().collect();
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy("test-data/small-collection", &output_dir, &copy_options).unwrap();
+	let collection_dir = output_dir.join("small-collection");
+
+	let configuration = dto::Config {
+		mount_dirs: Some(vec![dto::MountDir {
+			name: TEST_MOUNT_NAME.into(),
+			source: collection_dir.to_string_lossy().into_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
+		}]),
+		..Default::default()
+	};
+	let response = service.fetch(&protocol::apply_config(configuration));
+	assert_eq!(response.status(), StatusCode::OK);
+	service.index();
+
+	let request = protocol::move_file(
+		"collection/Khemmis/Hunted/02 - Candlelight.mp3",
+		"collection/Khemmis/Hunted/02 - Renamed.mp3",
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let album_dir = collection_dir.join("Khemmis").join("Hunted");
+	assert!(!album_dir.join("02 - Candlelight.mp3").exists());
+	assert!(album_dir.join("02 - Renamed.mp3").exists());
+
+	let request = protocol::flatten(Path::new(TEST_MOUNT_NAME));
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	let songs = response.body();
+	assert!(songs.iter().any(|s| s.path.ends_with("02 - Renamed.mp3")));
+	assert!(
+		!songs
+			.iter()
+			.any(|s| s.path.ends_with("02 - Candlelight.mp3"))
+	);
+}
+
+#[test]
+fn move_file_preserves_favorites() {
+	let test_name = test_name!();
+	let mut service = ServiceType::new(&test_name);
+	service.complete_initial_setup();
+	service.login_admin();
+
+	// Work on a private copy of the collection since we are moving files around
+	let output_dir: PathBuf = ["test-output", &test_name].iter().collect();
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy("test-data/small-collection", &output_dir, &copy_options).unwrap();
+	let collection_dir = output_dir.join("small-collection");
+
+	let configuration = dto::Config {
+		mount_dirs: Some(vec![dto::MountDir {
+			name: TEST_MOUNT_NAME.into(),
+			source: collection_dir.to_string_lossy().into_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
+		}]),
+		..Default::default()
+	};
+	let response = service.fetch(&protocol::apply_config(configuration));
+	assert_eq!(response.status(), StatusCode::OK);
+	service.index();
+
+	let source: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let destination: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Renamed.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::add_favorite(&source);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::move_file(
+		"collection/Khemmis/Hunted/02 - Candlelight.mp3",
+		"collection/Khemmis/Hunted/02 - Renamed.mp3",
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::favorites();
+	let response = service.fetch_json::<_, Vec<index::Song>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let songs = response.body();
+	assert_eq!(songs.len(), 1);
+	assert!(songs[0].path.ends_with("02 - Renamed.mp3"));
+
+	let request = protocol::favorite_status(&destination);
+	let response = service.fetch_json::<_, bool>(&request);
+	assert!(*response.body());
+}
+
+#[test]
+fn move_file_preserves_custom_tags() {
+	let test_name = test_name!();
+	let mut service = ServiceType::new(&test_name);
+	service.complete_initial_setup();
+	service.login_admin();
+
+	// Work on a private copy of the collection since we are moving files around
+	let output_dir: PathBuf = ["test-output", &test_name].iter().collect();
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy("test-data/small-collection", &output_dir, &copy_options).unwrap();
+	let collection_dir = output_dir.join("small-collection");
+
+	let configuration = dto::Config {
+		mount_dirs: Some(vec![dto::MountDir {
+			name: TEST_MOUNT_NAME.into(),
+			source: collection_dir.to_string_lossy().into_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
+		}]),
+		..Default::default()
+	};
+	let response = service.fetch(&protocol::apply_config(configuration));
+	assert_eq!(response.status(), StatusCode::OK);
+	service.index();
+
+	let source: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let destination: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Renamed.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::put_custom_tag(&source, "mood", "somber");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::move_file(
+		"collection/Khemmis/Hunted/02 - Candlelight.mp3",
+		"collection/Khemmis/Hunted/02 - Renamed.mp3",
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::custom_tags(&destination);
+	let response = service.fetch_json::<_, std::collections::HashMap<String, String>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body().get("mood"), Some(&"somber".to_owned()));
+}
+
+#[test]
+fn move_file_rejects_unsafe_requests() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let configuration = dto::Config {
+		mount_dirs: Some(vec![
+			dto::MountDir {
+				name: TEST_MOUNT_NAME.into(),
+				source: TEST_MOUNT_SOURCE.into(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
+			},
+			dto::MountDir {
+				name: "other".into(),
+				source: TEST_MOUNT_SOURCE.into(),
+				include_in_discovery: true,
+				max_depth: 0,
+				display_name: String::new(),
+			},
+		]),
+		..Default::default()
+	};
+	let response = service.fetch(&protocol::apply_config(configuration));
+	assert_eq!(response.status(), StatusCode::OK);
+
+	// Traversal out of the mount
+	let request = protocol::move_file(
+		"collection/../../secrets.mp3",
+		"collection/Khemmis/Hunted/02 - Renamed.mp3",
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+	// Moving across mounts
+	let request = protocol::move_file(
+		"collection/Khemmis/Hunted/02 - Candlelight.mp3",
+		"other/Khemmis/Hunted/02 - Candlelight.mp3",
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+	// Overwriting an existing file
+	let request = protocol::move_file(
+		"collection/Khemmis/Hunted/02 - Candlelight.mp3",
+		"collection/Khemmis/Hunted/01 - Above The Water.mp3",
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::CONFLICT);
+
+	// Missing source file
+	let request = protocol::move_file(
+		"collection/Khemmis/Hunted/99 - Ghost Track.mp3",
+		"collection/Khemmis/Hunted/99 - Renamed.mp3",
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
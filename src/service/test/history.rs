@@ -0,0 +1,50 @@
+use http::StatusCode;
+use std::path::Path;
+
+use crate::app::history;
+use crate::service::test::{constants::*, protocol, ServiceType, TestService};
+use crate::test_name;
+
+#[test]
+fn recently_played_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let request = protocol::recently_played();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn post_played_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let path: std::path::PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let request = protocol::post_played(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn recently_played_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let first: std::path::PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "01 - Above The Water.mp3"]
+		.iter()
+		.collect();
+	let second: std::path::PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	for path in [&first, &second] {
+		let response = service.fetch(&protocol::post_played(path));
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	let request = protocol::recently_played();
+	let response = service.fetch_json::<_, Vec<history::Entry>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let entries = response.body();
+	let paths: Vec<&Path> = entries.iter().map(|e| Path::new(&e.path)).collect();
+	assert_eq!(paths, vec![second.as_path(), first.as_path()]);
+}
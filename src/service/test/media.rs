@@ -1,7 +1,7 @@
 use http::{header, HeaderValue, StatusCode};
 use std::path::PathBuf;
 
-use crate::service::dto::ThumbnailSize;
+use crate::service::dto::{self, ThumbnailSize};
 use crate::service::test::{constants::*, protocol, ServiceType, TestService};
 use crate::test_name;
 
@@ -110,6 +110,78 @@ fn audio_bad_path_returns_not_found() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[test]
+fn stream_album_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::stream_album(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn stream_album_concatenates_tracks_with_manifest() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted"].iter().collect();
+	let request = protocol::stream_album(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let offsets: Vec<usize> = response
+		.headers()
+		.get("Polaris-Track-Offsets")
+		.unwrap()
+		.to_str()
+		.unwrap()
+		.split(',')
+		.map(|o| o.parse().unwrap())
+		.collect();
+	assert_eq!(offsets.len(), 5);
+	assert_eq!(offsets[0], 0);
+	assert!(offsets.windows(2).all(|w| w[0] < w[1]));
+
+	let total_size: usize = std::fs::read_dir("test-data/small-collection/Khemmis/Hunted")
+		.unwrap()
+		.filter_map(|e| e.ok())
+		.filter(|e| e.path().extension().is_some_and(|x| x == "mp3"))
+		.map(|e| e.metadata().unwrap().len() as usize)
+		.sum();
+	assert_eq!(response.body().len(), total_size);
+
+	// The second track starts exactly at its manifest offset
+	let second_track =
+		std::fs::read("test-data/small-collection/Khemmis/Hunted/02 - Candlelight.mp3").unwrap();
+	let start = offsets[1];
+	assert_eq!(
+		&response.body()[start..start + second_track.len()],
+		&second_track[..]
+	);
+}
+
+#[test]
+fn stream_album_falls_back_to_playlist_without_streamable_tracks() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	// This directory only contains a sub-directory, so there is nothing to concatenate
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis"].iter().collect();
+	let request = protocol::stream_album(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		response.headers().get(header::CONTENT_TYPE).unwrap(),
+		"application/json"
+	);
+}
+
 #[test]
 fn thumbnail_requires_auth() {
 	let mut service = ServiceType::new(&test_name!());
@@ -159,6 +231,185 @@ fn thumbnail_bad_path_returns_not_found() {
 	assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[test]
+fn thumbnails_batch_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+
+	let request = protocol::thumbnails_batch(&[&path], None);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn thumbnails_batch_returns_atlas_and_coordinates() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let khemmis_cover: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+	let tobokegao_cover: PathBuf = [TEST_MOUNT_NAME, "Tobokegao", "Picnic", "Folder.png"]
+		.iter()
+		.collect();
+	let bad_path: PathBuf = ["not_my_collection", "Folder.jpg"].iter().collect();
+
+	let request = protocol::thumbnails_batch(
+		&[&khemmis_cover, &tobokegao_cover, &bad_path],
+		Some(ThumbnailSize::Small),
+	);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		response.headers().get(header::CONTENT_TYPE).unwrap(),
+		"image/jpeg"
+	);
+
+	let coordinate_map = response.headers().get("Polaris-Atlas-Map").unwrap();
+	let tiles: Vec<dto::AtlasTile> =
+		serde_json::from_str(coordinate_map.to_str().unwrap()).unwrap();
+	assert_eq!(tiles.len(), 2);
+
+	let atlas = image::load_from_memory(response.body()).unwrap().to_rgb8();
+	for tile in tiles {
+		assert_eq!(tile.width, 400);
+		assert_eq!(tile.height, 400);
+		assert!(tile.x + tile.width <= atlas.width());
+		assert!(tile.y + tile.height <= atlas.height());
+	}
+}
+
+#[test]
+fn artwork_original_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+
+	let request = protocol::artwork_original(&path);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn artwork_original_returns_exact_file_bytes() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+	service.index();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "Folder.jpg"]
+		.iter()
+		.collect();
+
+	let request = protocol::artwork_original(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		response.headers().get(header::CONTENT_TYPE).unwrap(),
+		"image/jpeg"
+	);
+
+	let original_bytes =
+		std::fs::read("test-data/small-collection/Khemmis/Hunted/Folder.jpg").unwrap();
+	assert_eq!(response.body(), &original_bytes);
+}
+
+#[test]
+fn put_artwork_requires_auth() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::put_artwork(&path, Vec::new());
+	let response = service.fetch_raw(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn put_artwork_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+
+	let request = protocol::put_artwork(&path, Vec::new());
+	let response = service.fetch_raw(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn put_artwork_accepts_large_images() {
+	let test_name = test_name!();
+	let mut service = ServiceType::new(&test_name);
+	service.complete_initial_setup();
+	service.login_admin();
+
+	// Work on a private copy of the collection since artwork is written into
+	// the audio file
+	let output_dir: PathBuf = ["test-output", &test_name].iter().collect();
+	let copy_options = fs_extra::dir::CopyOptions::new();
+	fs_extra::dir::copy("test-data/small-collection", &output_dir, &copy_options).unwrap();
+	let collection_dir = output_dir.join("small-collection");
+
+	let configuration = dto::Config {
+		mount_dirs: Some(vec![dto::MountDir {
+			name: TEST_MOUNT_NAME.into(),
+			source: collection_dir.to_string_lossy().into_owned(),
+			include_in_discovery: true,
+			max_depth: 0,
+			display_name: String::new(),
+		}]),
+		..Default::default()
+	};
+	let response = service.fetch(&protocol::apply_config(configuration));
+	assert_eq!(response.status(), StatusCode::OK);
+	service.index();
+
+	// A noisy image compresses poorly, guaranteeing a payload well above the
+	// 256KB default cap
+	let mut noise = image::RgbImage::new(600, 600);
+	let mut seed = 0x12345678u32;
+	for pixel in noise.pixels_mut() {
+		seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+		*pixel = image::Rgb([(seed >> 8) as u8, (seed >> 16) as u8, (seed >> 24) as u8]);
+	}
+	let mut image_bytes = Vec::new();
+	image::DynamicImage::ImageRgb8(noise)
+		.write_to(
+			&mut std::io::Cursor::new(&mut image_bytes),
+			image::ImageOutputFormat::Png,
+		)
+		.unwrap();
+	assert!(image_bytes.len() > 256 * 1024);
+
+	let path: PathBuf = [TEST_MOUNT_NAME, "Khemmis", "Hunted", "02 - Candlelight.mp3"]
+		.iter()
+		.collect();
+	let request = protocol::put_artwork(&path, image_bytes.clone());
+	let response = service.fetch_raw(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::artwork_original(&path);
+	let response = service.fetch_bytes(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body(), &image_bytes);
+}
+
 #[test]
 fn thumbnail_size_default() {
 	thumbnail_size(&test_name!(), None, None, 400);
@@ -21,6 +21,14 @@ pub fn swagger_index() -> Request<()> {
 		.unwrap()
 }
 
+pub fn openapi() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/openapi.json")
+		.body(())
+		.unwrap()
+}
+
 pub fn version() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -29,6 +37,14 @@ pub fn version() -> Request<()> {
 		.unwrap()
 }
 
+pub fn capabilities() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/capabilities")
+		.body(())
+		.unwrap()
+}
+
 pub fn initial_setup() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -37,6 +53,19 @@ pub fn initial_setup() -> Request<()> {
 		.unwrap()
 }
 
+pub fn initial_setup_admin(username: &str, password: &str) -> Request<dto::NewUser> {
+	let new_user = dto::NewUser {
+		name: username.into(),
+		password: password.into(),
+		admin: true,
+	};
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/initial_setup/admin")
+		.body(new_user)
+		.unwrap()
+}
+
 pub fn login(username: &str, password: &str) -> Request<dto::Credentials> {
 	let credentials = dto::Credentials {
 		username: username.into(),
@@ -65,6 +94,14 @@ pub fn get_settings() -> Request<()> {
 		.unwrap()
 }
 
+pub fn create_scoped_token(scope: dto::TokenScope) -> Request<dto::ScopedTokenRequest> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/auth/scoped")
+		.body(dto::ScopedTokenRequest { scope })
+		.unwrap()
+}
+
 pub fn put_settings(settings: dto::NewSettings) -> Request<dto::NewSettings> {
 	Request::builder()
 		.method(Method::PUT)
@@ -73,6 +110,27 @@ pub fn put_settings(settings: dto::NewSettings) -> Request<dto::NewSettings> {
 		.unwrap()
 }
 
+pub fn test_mount(path: &str) -> Request<dto::TestMountInput> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/settings/test_mount")
+		.body(dto::TestMountInput {
+			path: path.to_owned(),
+		})
+		.unwrap()
+}
+
+pub fn move_file(source: &str, destination: &str) -> Request<dto::FileMoveInput> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/files/move")
+		.body(dto::FileMoveInput {
+			source: source.to_owned(),
+			destination: destination.to_owned(),
+		})
+		.unwrap()
+}
+
 pub fn get_ddns_config() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -113,6 +171,21 @@ pub fn update_user(username: &str, user_update: dto::UserUpdate) -> Request<dto:
 		.unwrap()
 }
 
+pub fn update_users_admin(input: dto::AdminUpdatesInput) -> Request<dto::AdminUpdatesInput> {
+	Request::builder()
+		.method(Method::PUT)
+		.uri("/api/users/admin")
+		.body(input)
+		.unwrap()
+}
+
+pub fn admin_history(username: &str) -> Request<()> {
+	Request::builder()
+		.uri(format!("/api/user/{}/admin_history", username))
+		.body(())
+		.unwrap()
+}
+
 pub fn delete_user(username: &str) -> Request<()> {
 	Request::builder()
 		.method(Method::DELETE)
@@ -137,6 +210,38 @@ pub fn put_preferences(preferences: user::Preferences) -> Request<user::Preferen
 		.unwrap()
 }
 
+pub fn audit_limited(limit: i64) -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri(format!("/api/audit?limit={}", limit))
+		.body(())
+		.unwrap()
+}
+
+pub fn missing_artwork(limit: Option<i64>) -> Request<()> {
+	let uri = match limit {
+		Some(limit) => format!("/api/maintenance/missing_artwork?limit={}", limit),
+		None => "/api/maintenance/missing_artwork".to_owned(),
+	};
+	Request::builder()
+		.method(Method::GET)
+		.uri(uri)
+		.body(())
+		.unwrap()
+}
+
+pub fn audit(event: Option<&str>) -> Request<()> {
+	let uri = match event {
+		Some(event) => format!("/api/audit?event={}", event),
+		None => "/api/audit".to_owned(),
+	};
+	Request::builder()
+		.method(Method::GET)
+		.uri(uri)
+		.body(())
+		.unwrap()
+}
+
 pub fn trigger_index() -> Request<()> {
 	Request::builder()
 		.method(Method::POST)
@@ -155,6 +260,36 @@ pub fn browse(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn stream_album(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/stream_album/{}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn directory_summary(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/browse/{}/summary", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn browse_m3u(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/browse/{}/m3u", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn flatten(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/flatten/{}", url_encode(path.as_ref()));
@@ -165,6 +300,56 @@ pub fn flatten(path: &Path) -> Request<()> {
 		.unwrap()
 }
 
+pub fn flatten_with_custom_tag(path: &Path, key: &str, value: Option<&str>) -> Request<()> {
+	let path = path.to_string_lossy();
+	let mut endpoint = format!(
+		"/api/flatten/{}?custom_tag={}",
+		url_encode(path.as_ref()),
+		key
+	);
+	if let Some(value) = value {
+		endpoint += &format!("&custom_tag_value={}", value);
+	}
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn custom_tags(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/song/{}/tags/custom", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn put_custom_tag(path: &Path, key: &str, value: &str) -> Request<dto::CustomTag> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/song/{}/tags/custom", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::PUT)
+		.uri(&endpoint)
+		.body(dto::CustomTag {
+			key: key.to_owned(),
+			value: value.to_owned(),
+		})
+		.unwrap()
+}
+
+pub fn delete_custom_tag(path: &Path, key: &str) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/song/{}/tags/custom?key={}", url_encode(path.as_ref()), key);
+	Request::builder()
+		.method(Method::DELETE)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn random() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -233,6 +418,94 @@ pub fn thumbnail(path: &Path, size: Option<ThumbnailSize>, pad: Option<bool>) ->
 		.unwrap()
 }
 
+pub fn thumbnails_batch(
+	paths: &[&Path],
+	size: Option<ThumbnailSize>,
+) -> Request<dto::ThumbnailBatchInput> {
+	Request::builder()
+		.method(Method::POST)
+		.uri("/api/thumbnails/batch")
+		.body(dto::ThumbnailBatchInput {
+			paths: paths
+				.iter()
+				.map(|p| p.to_string_lossy().into_owned())
+				.collect(),
+			size,
+		})
+		.unwrap()
+}
+
+pub fn artwork_original(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/artwork/{}/original", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn put_artwork(path: &Path, image: Vec<u8>) -> Request<Vec<u8>> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/artwork/{}", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::PUT)
+		.uri(&endpoint)
+		.body(image)
+		.unwrap()
+}
+
+pub fn adjacent_albums(path: &Path, sort: Option<&str>) -> Request<()> {
+	let path = path.to_string_lossy();
+	let mut endpoint = format!("/api/album/{}/adjacent", url_encode(path.as_ref()));
+	if let Some(sort) = sort {
+		endpoint += &format!("?sort={}", sort);
+	}
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn favorites() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/favorites")
+		.body(())
+		.unwrap()
+}
+
+pub fn favorite_status(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/song/{}/favorite", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::GET)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn add_favorite(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/song/{}/favorite", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::PUT)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn remove_favorite(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/song/{}/favorite", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::DELETE)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
 pub fn playlists() -> Request<()> {
 	Request::builder()
 		.method(Method::GET)
@@ -279,6 +552,24 @@ pub fn lastfm_link_token() -> Request<()> {
 		.unwrap()
 }
 
+pub fn post_played(path: &Path) -> Request<()> {
+	let path = path.to_string_lossy();
+	let endpoint = format!("/api/song/{}/played", url_encode(path.as_ref()));
+	Request::builder()
+		.method(Method::POST)
+		.uri(&endpoint)
+		.body(())
+		.unwrap()
+}
+
+pub fn recently_played() -> Request<()> {
+	Request::builder()
+		.method(Method::GET)
+		.uri("/api/recently_played")
+		.body(())
+		.unwrap()
+}
+
 pub fn lastfm_now_playing(path: &Path) -> Request<()> {
 	let path = path.to_string_lossy();
 	let endpoint = format!("/api/lastfm/now_playing/{}", url_encode(path.as_ref()));
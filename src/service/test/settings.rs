@@ -1,5 +1,7 @@
+use http::header::{HeaderName, HeaderValue};
 use http::StatusCode;
 
+use crate::service::client_version::VERSION_HEADER;
 use crate::service::dto::{self, Settings};
 use crate::service::test::{protocol, ServiceType, TestService};
 use crate::test_name;
@@ -55,6 +57,20 @@ fn put_settings_requires_admin() {
 	assert_eq!(response.status(), StatusCode::FORBIDDEN);
 }
 
+#[test]
+fn put_settings_rejects_oversized_body() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_settings(dto::NewSettings {
+		album_art_pattern: Some("a".repeat(2 * 1024 * 1024)),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
 #[test]
 fn put_settings_golden_path() {
 	let mut service = ServiceType::new(&test_name!());
@@ -64,6 +80,37 @@ fn put_settings_golden_path() {
 	let request = protocol::put_settings(dto::NewSettings {
 		album_art_pattern: Some("test_pattern".to_owned()),
 		reindex_every_n_seconds: Some(31),
+		max_playlists_per_user: Some(50),
+		max_songs_per_playlist: Some(2000),
+		follow_symlinks: Some(true),
+		artwork_precedence: Some(dto::ArtworkPrecedence::EmbeddedFirst),
+		minimum_client_version: Some("7.0.0".to_owned()),
+		reject_unversioned_clients: Some(false),
+		max_concurrent_streams_per_user: Some(4),
+		infer_tags_from_path: Some(true),
+		reindex_on_startup: Some(true),
+		duplicate_policy: Some(dto::DuplicatePolicy::PreferFirstMount),
+		tag_parsing: Some(dto::TagParsing::Strict),
+		send_security_headers: Some(false),
+		assume_https_behind_proxy: Some(false),
+		default_page_size: Some(100),
+		max_page_size: Some(500),
+		partial_file_policy: Some(dto::PartialFilePolicy::IndexAsPending),
+		mime_overrides: Some(vec![dto::MimeOverride {
+			extension: "flac".to_owned(),
+			mime: "audio/x-flac".to_owned(),
+		}]),
+		trusted_proxies: Some(vec!["10.0.0.1".to_owned()]),
+		forwarded_ip_header: Some(dto::ForwardedIpHeader::XRealIp),
+		auth_log_format: Some(dto::AuthLogFormat::Json),
+		old_auth_secrets: Some(vec![
+			"YWFhYWFhYWFhYWFhYWFhYWFhYWFhYWFhYWFhYWFhYWE=".to_owned()
+		]),
+		num_traverser_threads: Some(2),
+		max_results_per_query: Some(500),
+		thumbnail_read_timeout_seconds: Some(5),
+		thumbnail_max_dimension: Some(800),
+		thumbnail_stored_max_dimension: Some(640),
 	});
 	let response = service.fetch(&request);
 	assert_eq!(response.status(), StatusCode::OK);
@@ -76,6 +123,204 @@ fn put_settings_golden_path() {
 		&Settings {
 			album_art_pattern: "test_pattern".to_owned(),
 			reindex_every_n_seconds: 31,
+			max_playlists_per_user: 50,
+			max_songs_per_playlist: 2000,
+			follow_symlinks: true,
+			artwork_precedence: dto::ArtworkPrecedence::EmbeddedFirst,
+			minimum_client_version: Some("7.0.0".to_owned()),
+			reject_unversioned_clients: false,
+			max_concurrent_streams_per_user: 4,
+			infer_tags_from_path: true,
+			reindex_on_startup: true,
+			duplicate_policy: dto::DuplicatePolicy::PreferFirstMount,
+			tag_parsing: dto::TagParsing::Strict,
+			send_security_headers: false,
+			assume_https_behind_proxy: false,
+			default_page_size: 100,
+			max_page_size: 500,
+			partial_file_policy: dto::PartialFilePolicy::IndexAsPending,
+			mime_overrides: vec![dto::MimeOverride {
+				extension: "flac".to_owned(),
+				mime: "audio/x-flac".to_owned(),
+			}],
+			trusted_proxies: vec!["10.0.0.1".to_owned()],
+			forwarded_ip_header: dto::ForwardedIpHeader::XRealIp,
+			auth_log_format: dto::AuthLogFormat::Json,
+			num_traverser_threads: 2,
+			max_results_per_query: 500,
+			thumbnail_read_timeout_seconds: 5,
+			thumbnail_max_dimension: 800,
+			thumbnail_stored_max_dimension: 640,
 		},
 	);
 }
+
+#[test]
+fn outdated_clients_are_rejected() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_settings(dto::NewSettings {
+		minimum_client_version: Some("5.2.0".to_owned()),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let mut request = protocol::version();
+	request.headers_mut().insert(
+		HeaderName::from_static(VERSION_HEADER),
+		HeaderValue::from_static("5.1.9"),
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UPGRADE_REQUIRED);
+
+	let mut request = protocol::version();
+	request.headers_mut().insert(
+		HeaderName::from_static(VERSION_HEADER),
+		HeaderValue::from_static("5.2.0"),
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	// Clients that do not declare a version are allowed by default
+	let request = protocol::version();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn unversioned_clients_can_be_rejected() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_settings(dto::NewSettings {
+		minimum_client_version: Some("5.2.0".to_owned()),
+		reject_unversioned_clients: Some(true),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::version();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UPGRADE_REQUIRED);
+}
+
+#[test]
+fn test_mount_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login();
+
+	let request = protocol::test_mount("test-data/small-collection");
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn test_mount_reports_valid_directory() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::test_mount("test-data/small-collection/Khemmis/Hunted");
+	let response = service.fetch_json::<_, dto::TestMountOutput>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let output = response.body();
+	assert!(output.exists);
+	assert!(output.is_directory);
+	assert!(output.is_readable);
+	assert_eq!(output.audio_files_found, 5);
+}
+
+#[test]
+fn test_mount_reports_missing_directory() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::test_mount("test-data/no-such-collection");
+	let response = service.fetch_json::<_, dto::TestMountOutput>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let output = response.body();
+	assert!(!output.exists);
+	assert!(!output.is_directory);
+	assert!(!output.is_readable);
+	assert_eq!(output.audio_files_found, 0);
+}
+
+#[test]
+fn test_mount_reports_file_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::test_mount("test-data/formats/sample.mp3");
+	let response = service.fetch_json::<_, dto::TestMountOutput>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let output = response.body();
+	assert!(output.exists);
+	assert!(!output.is_directory);
+	assert!(output.is_readable);
+	assert_eq!(output.audio_files_found, 0);
+}
+
+#[test]
+fn security_headers_are_absent_by_default() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+
+	let request = protocol::version();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response.headers().get("x-content-type-options").is_none());
+	assert!(response.headers().get("x-frame-options").is_none());
+	assert!(response.headers().get("referrer-policy").is_none());
+}
+
+#[test]
+fn security_headers_are_sent_when_enabled() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::put_settings(dto::NewSettings {
+		send_security_headers: Some(true),
+		..Default::default()
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+
+	let request = protocol::version();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		response.headers().get("x-content-type-options").unwrap(),
+		"nosniff"
+	);
+	assert_eq!(response.headers().get("x-frame-options").unwrap(), "DENY");
+	assert_eq!(
+		response.headers().get("referrer-policy").unwrap(),
+		"strict-origin-when-cross-origin"
+	);
+	// HSTS is reserved for requests that arrived over TLS
+	assert!(response
+		.headers()
+		.get("strict-transport-security")
+		.is_none());
+
+	let mut request = protocol::version();
+	request.headers_mut().insert(
+		HeaderName::from_static("x-forwarded-proto"),
+		HeaderValue::from_static("https"),
+	);
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(response
+		.headers()
+		.get("strict-transport-security")
+		.is_some());
+}
@@ -106,6 +106,115 @@ fn update_user_cannot_unadmin_self() {
 	assert_eq!(response.status(), StatusCode::CONFLICT);
 }
 
+#[test]
+fn admin_history_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::admin_history(TEST_USERNAME);
+
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	service.login();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn admin_history_records_promotions_and_demotions() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	for is_admin in [true, false] {
+		let request = protocol::update_user(
+			TEST_USERNAME,
+			dto::UserUpdate {
+				new_is_admin: Some(is_admin),
+				..Default::default()
+			},
+		);
+		let response = service.fetch(&request);
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	let request = protocol::admin_history(TEST_USERNAME);
+	let response = service.fetch_json::<_, Vec<dto::AdminChange>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	let history = response.body();
+	assert_eq!(history.len(), 2);
+	assert_eq!(history[0].actor.as_deref(), Some(TEST_USERNAME_ADMIN));
+	assert!(!history[0].old_is_admin);
+	assert!(history[0].new_is_admin);
+	assert!(history[1].old_is_admin);
+	assert!(!history[1].new_is_admin);
+}
+
+#[test]
+fn update_users_admin_requires_admin() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	let request = protocol::update_users_admin(dto::AdminUpdatesInput { updates: vec![] });
+
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	service.login();
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn update_users_admin_golden_path() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::update_users_admin(dto::AdminUpdatesInput {
+		updates: vec![
+			dto::AdminUpdate {
+				username: TEST_USERNAME.to_owned(),
+				is_admin: true,
+			},
+			dto::AdminUpdate {
+				username: "nobody".to_owned(),
+				is_admin: true,
+			},
+		],
+	});
+	let response = service.fetch_json::<_, Vec<dto::AdminUpdateResult>>(&request);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(
+		response.body(),
+		&vec![
+			dto::AdminUpdateResult {
+				username: TEST_USERNAME.to_owned(),
+				outcome: dto::AdminUpdateOutcome::Updated,
+			},
+			dto::AdminUpdateResult {
+				username: "nobody".to_owned(),
+				outcome: dto::AdminUpdateOutcome::NotFound,
+			},
+		]
+	);
+}
+
+#[test]
+fn update_users_admin_rejects_self_demotion() {
+	let mut service = ServiceType::new(&test_name!());
+	service.complete_initial_setup();
+	service.login_admin();
+
+	let request = protocol::update_users_admin(dto::AdminUpdatesInput {
+		updates: vec![dto::AdminUpdate {
+			username: TEST_USERNAME_ADMIN.to_owned(),
+			is_admin: false,
+		}],
+	});
+	let response = service.fetch(&request);
+	assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
 #[test]
 fn delete_user_requires_admin() {
 	let mut service = ServiceType::new(&test_name!());
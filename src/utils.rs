@@ -13,10 +13,11 @@ macro_rules! match_ignore_case {
 pub use crate::match_ignore_case;
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AudioFormat {
 	AIFF,
 	APE,
+	DSF,
 	FLAC,
 	MP3,
 	MP4,
@@ -39,9 +40,11 @@ pub fn get_audio_format(path: &Path) -> Option<AudioFormat> {
 		"aif" => Some(AudioFormat::AIFF),
 		"aiff" => Some(AudioFormat::AIFF),
 		"ape" => Some(AudioFormat::APE),
+		"dsf" => Some(AudioFormat::DSF),
 		"flac" => Some(AudioFormat::FLAC),
 		"mp3" => Some(AudioFormat::MP3),
 		"m4a" => Some(AudioFormat::MP4),
+		"m4b" => Some(AudioFormat::MP4),
 		"mpc" => Some(AudioFormat::MPC),
 		"ogg" => Some(AudioFormat::OGG),
 		"opus" => Some(AudioFormat::OPUS),
@@ -50,6 +53,66 @@ pub fn get_audio_format(path: &Path) -> Option<AudioFormat> {
 	}
 }
 
+// Canonical MIME type for each supported format. Some browsers refuse to play
+// audio served with a mismatched Content-Type.
+pub fn get_audio_mime(format: AudioFormat) -> &'static str {
+	match format {
+		AudioFormat::AIFF => "audio/aiff",
+		AudioFormat::APE => "audio/x-ape",
+		AudioFormat::DSF => "audio/x-dsf",
+		AudioFormat::FLAC => "audio/flac",
+		AudioFormat::MP3 => "audio/mpeg",
+		AudioFormat::MP4 => "audio/mp4",
+		AudioFormat::MPC => "audio/x-musepack",
+		AudioFormat::OGG => "audio/ogg",
+		AudioFormat::OPUS => "audio/opus",
+		AudioFormat::WAVE => "audio/wav",
+	}
+}
+
+// Virtual paths are serialized with forward slashes regardless of the host OS,
+// so clients see the same paths no matter where the server runs.
+pub fn path_to_forward_slashes<P: AsRef<Path>>(path: P) -> String {
+	let components: Vec<_> = path.as_ref().iter().map(|c| c.to_string_lossy()).collect();
+	components.join("/")
+}
+
+// Lowercases and strips diacritics so that "Björk" and "bjork" compare equal
+// when searching or sorting.
+pub fn normalize_unicode(text: &str) -> String {
+	use unicode_normalization::char::is_combining_mark;
+	use unicode_normalization::UnicodeNormalization;
+	text.nfkd()
+		.filter(|c| !is_combining_mark(*c))
+		.flat_map(char::to_lowercase)
+		.collect()
+}
+
+// DSF files embed a regular ID3v2 tag, but it lives in a metadata chunk whose
+// location is declared in the container header rather than at a fixed offset.
+pub fn read_dsf_id3_tag(path: &Path) -> Result<id3::Tag, id3::Error> {
+	use std::io::{Read, Seek, SeekFrom};
+
+	let mut file = std::fs::File::open(path)?;
+	let mut header = [0; 28];
+	file.read_exact(&mut header)?;
+	if &header[0..4] != b"DSD " {
+		return Err(id3::Error::new(
+			id3::ErrorKind::Parsing,
+			"Missing DSD chunk",
+		));
+	}
+	let metadata_offset = u64::from_le_bytes(header[20..28].try_into().unwrap());
+	if metadata_offset == 0 {
+		return Err(id3::Error::new(
+			id3::ErrorKind::NoTag,
+			"DSF file has no metadata chunk",
+		));
+	}
+	file.seek(SeekFrom::Start(metadata_offset))?;
+	id3::Tag::read_from(file)
+}
+
 #[test]
 fn can_guess_audio_format() {
 	assert_eq!(get_audio_format(Path::new("animals/🐷/my🐖file.jpg")), None);
@@ -61,6 +124,10 @@ fn can_guess_audio_format() {
 		get_audio_format(Path::new("animals/🐷/my🐖file.aiff")),
 		Some(AudioFormat::AIFF)
 	);
+	assert_eq!(
+		get_audio_format(Path::new("animals/🐷/my🐖file.dsf")),
+		Some(AudioFormat::DSF)
+	);
 	assert_eq!(
 		get_audio_format(Path::new("animals/🐷/my🐖file.flac")),
 		Some(AudioFormat::FLAC)
@@ -69,4 +136,31 @@ fn can_guess_audio_format() {
 		get_audio_format(Path::new("animals/🐷/my🐖file.wav")),
 		Some(AudioFormat::WAVE)
 	);
+	// Audiobooks use the same MP4 container as regular m4a files
+	assert_eq!(
+		get_audio_format(Path::new("animals/🐷/my🐖file.m4b")),
+		Some(AudioFormat::MP4)
+	);
+}
+
+#[test]
+fn can_normalize_unicode() {
+	assert_eq!(normalize_unicode("Björk"), "bjork");
+	assert_eq!(normalize_unicode("Étude"), "etude");
+	assert_eq!(normalize_unicode("bjork"), "bjork");
+	assert_eq!(normalize_unicode("ＴＥＳＴ"), "test");
+}
+
+#[test]
+fn maps_audio_formats_to_mime_types() {
+	assert_eq!(get_audio_mime(AudioFormat::AIFF), "audio/aiff");
+	assert_eq!(get_audio_mime(AudioFormat::APE), "audio/x-ape");
+	assert_eq!(get_audio_mime(AudioFormat::DSF), "audio/x-dsf");
+	assert_eq!(get_audio_mime(AudioFormat::FLAC), "audio/flac");
+	assert_eq!(get_audio_mime(AudioFormat::MP3), "audio/mpeg");
+	assert_eq!(get_audio_mime(AudioFormat::MP4), "audio/mp4");
+	assert_eq!(get_audio_mime(AudioFormat::MPC), "audio/x-musepack");
+	assert_eq!(get_audio_mime(AudioFormat::OGG), "audio/ogg");
+	assert_eq!(get_audio_mime(AudioFormat::OPUS), "audio/opus");
+	assert_eq!(get_audio_mime(AudioFormat::WAVE), "audio/wav");
 }